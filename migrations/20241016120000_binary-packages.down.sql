@@ -0,0 +1 @@
+DROP TABLE binary_packages;
@@ -0,0 +1,14 @@
+CREATE TABLE binary_packages (
+    id bigserial PRIMARY KEY,
+    chksum VARCHAR NOT NULL,
+    vendor VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    source_package VARCHAR NOT NULL,
+    source_version VARCHAR NOT NULL,
+    commit VARCHAR,
+    first_seen timestamptz NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX binary_packages_idx_chksum ON binary_packages (chksum);
+CREATE UNIQUE INDEX binary_packages_idx_uniq ON binary_packages (chksum, vendor, package, version);
@@ -0,0 +1,20 @@
+CREATE TABLE watchlists (
+    id bigserial PRIMARY KEY,
+    token VARCHAR NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT NOW(),
+    last_visit timestamptz
+);
+
+CREATE UNIQUE INDEX watchlists_idx_token ON watchlists (token);
+
+CREATE TABLE watchlist_entries (
+    watchlist_id bigint NOT NULL,
+    query VARCHAR NOT NULL,
+
+    CONSTRAINT fk_watchlist_id
+        FOREIGN KEY(watchlist_id)
+        REFERENCES watchlists(id)
+        ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX watchlist_entries_idx_uniq ON watchlist_entries (watchlist_id, query);
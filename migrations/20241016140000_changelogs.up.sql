@@ -0,0 +1,13 @@
+CREATE TABLE changelogs (
+    id bigserial PRIMARY KEY,
+    -- NULL if the changelog isn't specific to a vendor (e.g. debian/changelog)
+    vendor VARCHAR,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    author VARCHAR,
+    date VARCHAR,
+    text VARCHAR NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX changelogs_idx_uniq ON changelogs (package, version, COALESCE(vendor, ''));
@@ -0,0 +1,16 @@
+CREATE TABLE quarantine (
+    id bigserial PRIMARY KEY,
+    chksum VARCHAR NOT NULL,
+    url VARCHAR NOT NULL,
+    ref_chksum VARCHAR,
+    vendor VARCHAR,
+    package VARCHAR,
+    version VARCHAR,
+    reason VARCHAR NOT NULL,
+    rejected BOOLEAN NOT NULL DEFAULT false,
+    first_seen timestamptz NOT NULL DEFAULT NOW(),
+    UNIQUE (chksum, url)
+);
+
+CREATE INDEX quarantine_idx_chksum ON quarantine (chksum);
+CREATE INDEX quarantine_idx_ref_chksum ON quarantine (ref_chksum);
@@ -0,0 +1 @@
+DROP TABLE sbom_packages;
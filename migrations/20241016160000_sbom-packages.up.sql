@@ -0,0 +1,10 @@
+CREATE TABLE sbom_packages (
+    sbom_chksum VARCHAR NOT NULL,
+    strain VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    checksum VARCHAR
+);
+
+CREATE UNIQUE INDEX sbom_packages_idx_uniq ON sbom_packages (sbom_chksum, strain, name, version);
+CREATE INDEX sbom_packages_idx_name ON sbom_packages (strain, name);
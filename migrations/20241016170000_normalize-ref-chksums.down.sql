@@ -0,0 +1 @@
+-- normalized checksums can't be restored to their original formatting
@@ -0,0 +1,16 @@
+-- drop refs that only differ by the formatting of their checksum, prefer an already normalized one
+DELETE FROM refs
+WHERE id IN (
+    SELECT id FROM (
+        SELECT id, row_number() OVER (
+            PARTITION BY lower(trim(chksum)), vendor, package, version
+            ORDER BY (chksum = lower(trim(chksum))) DESC, id ASC
+        ) AS n
+        FROM refs
+    ) t
+    WHERE n > 1
+);
+
+UPDATE refs
+SET chksum = lower(trim(chksum))
+WHERE chksum <> lower(trim(chksum));
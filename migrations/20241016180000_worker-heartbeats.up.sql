@@ -0,0 +1,4 @@
+CREATE TABLE worker_heartbeats (
+    name VARCHAR PRIMARY KEY,
+    last_seen timestamptz NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE tasks
+ADD COLUMN next_attempt_at timestamptz;
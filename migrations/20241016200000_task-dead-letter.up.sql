@@ -0,0 +1,8 @@
+ALTER TABLE tasks
+ADD COLUMN failed_at timestamptz;
+
+UPDATE tasks
+SET failed_at = now()
+WHERE retries >= 5;
+
+CREATE INDEX tasks_idx_failed_at ON tasks (failed_at) WHERE failed_at IS NOT NULL;
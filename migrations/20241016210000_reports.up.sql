@@ -0,0 +1,15 @@
+CREATE TABLE reports (
+    id bigserial PRIMARY KEY,
+    chksum VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    vendor VARCHAR,
+    package VARCHAR,
+    version VARCHAR,
+    summary VARCHAR NOT NULL,
+    score INTEGER NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now(),
+    last_seen timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX reports_idx_uniq ON reports (chksum, kind);
+CREATE INDEX reports_idx_vendor_package ON reports (vendor, package);
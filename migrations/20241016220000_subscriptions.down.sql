@@ -0,0 +1,2 @@
+DROP INDEX refs_idx_first_seen;
+DROP TABLE subscriptions;
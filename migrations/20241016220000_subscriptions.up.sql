@@ -0,0 +1,20 @@
+CREATE TABLE subscriptions (
+    id bigserial PRIMARY KEY,
+    token VARCHAR NOT NULL,
+    target_kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    vendor VARCHAR,
+    package VARCHAR,
+    confirm_token VARCHAR NOT NULL,
+    unsubscribe_token VARCHAR NOT NULL,
+    confirmation_sent_at timestamptz,
+    confirmed_at timestamptz,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    last_notified timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX subscriptions_idx_token ON subscriptions (token);
+CREATE UNIQUE INDEX subscriptions_idx_confirm_token ON subscriptions (confirm_token);
+CREATE UNIQUE INDEX subscriptions_idx_unsubscribe_token ON subscriptions (unsubscribe_token);
+CREATE INDEX subscriptions_idx_target ON subscriptions (target);
+CREATE INDEX refs_idx_first_seen ON refs (first_seen);
@@ -0,0 +1,2 @@
+DROP INDEX refs_idx_package_trgm;
+DROP INDEX refs_idx_filename_trgm;
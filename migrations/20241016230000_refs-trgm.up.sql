@@ -0,0 +1,3 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX refs_idx_filename_trgm ON refs USING gin (filename gin_trgm_ops);
+CREATE INDEX refs_idx_package_trgm ON refs USING gin (package gin_trgm_ops);
@@ -0,0 +1,7 @@
+CREATE TABLE artifact_files (
+    chksum VARCHAR NOT NULL,
+    path VARCHAR NOT NULL,
+    PRIMARY KEY (chksum, path)
+);
+
+CREATE INDEX artifact_files_idx_path_trgm ON artifact_files USING gin (path gin_trgm_ops);
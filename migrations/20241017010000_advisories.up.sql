@@ -0,0 +1,8 @@
+CREATE TABLE advisories (
+    ecosystem VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    vulns JSONB NOT NULL,
+    last_checked timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (ecosystem, package, version)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE attestations (
+    id bigserial PRIMARY KEY,
+    chksum VARCHAR NOT NULL,
+    digest VARCHAR NOT NULL,
+    subject_name VARCHAR NOT NULL,
+    predicate_type VARCHAR NOT NULL,
+    builder_id VARCHAR,
+    build_type VARCHAR,
+    source_uri VARCHAR,
+    statement JSONB NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX attestations_idx_uniq ON attestations (chksum, digest);
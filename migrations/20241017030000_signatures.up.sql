@@ -0,0 +1,12 @@
+CREATE TABLE signatures (
+    id bigserial PRIMARY KEY,
+    chksum VARCHAR NOT NULL,
+    url VARCHAR NOT NULL,
+    signature_url VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    signer VARCHAR,
+    verified_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX signatures_idx_uniq ON signatures (chksum, signature_url);
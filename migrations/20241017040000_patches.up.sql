@@ -0,0 +1,16 @@
+CREATE TABLE patches (
+    id bigserial PRIMARY KEY,
+    vendor VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    filename VARCHAR NOT NULL,
+    chksum VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    -- NULL if the file is too large or not utf-8
+    content VARCHAR,
+    first_seen timestamptz NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX patches_idx_uniq ON patches (vendor, package, version, filename);
+CREATE INDEX patches_idx_package ON patches (package, version);
+CREATE INDEX patches_idx_chksum ON patches (chksum);
@@ -0,0 +1,7 @@
+-- file contents archived in blob storage, the object key is derived from the checksum
+CREATE TABLE blobs (
+    chksum VARCHAR PRIMARY KEY,
+    size BIGINT NOT NULL,
+    compressed_size BIGINT NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,10 @@
+-- files of a release tarball that are not in the git snapshot of the same version
+CREATE TABLE vcs_drift (
+    chksum VARCHAR PRIMARY KEY,
+    vcs_chksum VARCHAR NOT NULL,
+    categories JSONB NOT NULL,
+    anomalies JSONB NOT NULL,
+    score INTEGER NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now(),
+    last_seen timestamptz NOT NULL DEFAULT now()
+);
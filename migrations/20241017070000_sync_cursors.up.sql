@@ -0,0 +1,6 @@
+-- the position of syncs that follow a feed, so they can resume after a restart
+CREATE TABLE sync_cursors (
+    name VARCHAR PRIMARY KEY,
+    cursor VARCHAR NOT NULL,
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,15 @@
+-- the outcome of re-fetching the upstream url of a ref
+CREATE TABLE upstream_checks (
+    url VARCHAR PRIMARY KEY,
+    -- the checksum the url was expected to serve
+    chksum VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    http_status INTEGER,
+    -- the checksum the url served instead, if it was replaced
+    observed_chksum VARCHAR,
+    last_verified timestamptz NOT NULL DEFAULT now(),
+    gone_since timestamptz,
+    replaced_since timestamptz
+);
+CREATE INDEX upstream_checks_idx_chksum ON upstream_checks (chksum);
+CREATE INDEX upstream_checks_idx_last_verified ON upstream_checks (last_verified);
@@ -0,0 +1,2 @@
+-- aggregates of the file metadata, computed during ingestion
+ALTER TABLE artifacts ADD COLUMN totals JSON;
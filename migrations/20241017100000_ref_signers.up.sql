@@ -0,0 +1,10 @@
+-- the key a vendor expects the upstream signature of a ref to be made with
+CREATE TABLE ref_signers (
+    chksum VARCHAR NOT NULL,
+    vendor VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    fingerprint VARCHAR NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (chksum, vendor, package, version, fingerprint)
+);
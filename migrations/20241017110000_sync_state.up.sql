@@ -0,0 +1,8 @@
+-- the cache validators and digest of the upstream metadata a sync job processed last
+CREATE TABLE sync_state (
+    url VARCHAR PRIMARY KEY,
+    etag VARCHAR,
+    last_modified VARCHAR,
+    digest VARCHAR NOT NULL,
+    last_synced timestamptz NOT NULL DEFAULT now()
+);
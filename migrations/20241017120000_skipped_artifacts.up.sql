@@ -0,0 +1,8 @@
+-- artifacts we deliberately didn't download, e.g. because they exceed the size limit
+CREATE TABLE skipped_artifacts (
+    url VARCHAR PRIMARY KEY,
+    reason VARCHAR NOT NULL,
+    size BIGINT,
+    first_skipped timestamptz NOT NULL DEFAULT now(),
+    last_skipped timestamptz NOT NULL DEFAULT now()
+);
@@ -0,0 +1,10 @@
+-- the upstream project a package is built from, keyed by the normalized homepage url
+CREATE TABLE upstreams (
+    url VARCHAR NOT NULL,
+    vendor VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now(),
+    last_seen timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (url, vendor, package)
+);
+CREATE INDEX upstreams_idx_package ON upstreams (vendor, package);
@@ -0,0 +1,2 @@
+DROP INDEX artifact_files_idx_digest;
+ALTER TABLE artifact_files DROP COLUMN digest;
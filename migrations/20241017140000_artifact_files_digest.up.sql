@@ -0,0 +1,3 @@
+-- the digest of regular files, so artifacts can be looked up by the files they contain
+ALTER TABLE artifact_files ADD COLUMN digest VARCHAR;
+CREATE INDEX artifact_files_idx_digest ON artifact_files (digest);
@@ -0,0 +1 @@
+DROP TABLE reindex_checkpoints;
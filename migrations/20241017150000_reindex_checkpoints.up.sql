@@ -0,0 +1,9 @@
+-- progress of long running reindex jobs, so they can resume after a restart
+CREATE TABLE reindex_checkpoints (
+    name VARCHAR PRIMARY KEY,
+    started_at timestamptz NOT NULL,
+    last_imported timestamptz,
+    chksum VARCHAR,
+    processed BIGINT NOT NULL DEFAULT 0,
+    updated_at timestamptz NOT NULL DEFAULT now()
+);
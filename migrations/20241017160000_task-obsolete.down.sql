@@ -0,0 +1,5 @@
+DROP INDEX refs_idx_filename;
+
+ALTER TABLE tasks
+DROP COLUMN created_at,
+DROP COLUMN obsolete_at;
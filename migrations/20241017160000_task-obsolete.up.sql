@@ -0,0 +1,6 @@
+ALTER TABLE tasks
+ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+ADD COLUMN obsolete_at timestamptz;
+
+CREATE INDEX tasks_idx_obsolete_at ON tasks (obsolete_at) WHERE obsolete_at IS NOT NULL;
+CREATE INDEX refs_idx_filename ON refs (filename);
@@ -0,0 +1,3 @@
+ALTER TABLE sync_state
+DROP COLUMN snapshot_date,
+DROP COLUMN signer;
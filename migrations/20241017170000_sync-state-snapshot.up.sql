@@ -0,0 +1,4 @@
+-- when the upstream metadata was generated and who signed it, as announced by the metadata itself
+ALTER TABLE sync_state
+ADD COLUMN snapshot_date timestamptz,
+ADD COLUMN signer VARCHAR;
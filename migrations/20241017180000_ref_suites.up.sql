@@ -0,0 +1,13 @@
+-- the apt suites (e.g. `bookworm-security`) and components a ref was listed in
+CREATE TABLE ref_suites (
+    chksum VARCHAR NOT NULL,
+    vendor VARCHAR NOT NULL,
+    package VARCHAR NOT NULL,
+    version VARCHAR NOT NULL,
+    suite VARCHAR NOT NULL,
+    component VARCHAR NOT NULL,
+    first_seen timestamptz NOT NULL DEFAULT now(),
+    last_seen timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (chksum, vendor, package, version, suite, component)
+);
+CREATE INDEX ref_suites_idx_package ON ref_suites (vendor, package, version);
@@ -21,3 +21,144 @@ pub async fn run(args: &args::AddRef) -> Result<()> {
 
     Ok(())
 }
+
+fn print_ref(prefix: &str, r: &db::RefRecord) {
+    println!(
+        "{prefix} id={} vendor={:?} package={:?} version={:?} chksum={:?} filename={:?}",
+        r.id, r.vendor, r.package, r.version, r.chksum, r.filename
+    );
+}
+
+fn print_alias(prefix: &str, alias: &db::Alias) {
+    println!(
+        "{prefix} {} -> {} reason={:?}",
+        alias.alias_from,
+        alias.alias_to,
+        alias.reason.as_deref().unwrap_or("")
+    );
+}
+
+pub async fn run_refs(args: &args::Refs) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    match &args.subcommand {
+        args::RefsCmd::Rm(args) => {
+            let refs = db
+                .find_refs(
+                    &args.vendor,
+                    &args.package,
+                    args.version.as_deref(),
+                    args.chksum.as_deref(),
+                )
+                .await?;
+            let prefix = if args.dry_run {
+                "would delete"
+            } else {
+                "delete"
+            };
+            for r in &refs {
+                print_ref(prefix, r);
+            }
+            if !args.dry_run && !refs.is_empty() {
+                let ids = refs.iter().map(|r| r.id).collect::<Vec<_>>();
+                let num = db.delete_refs(&ids).await?;
+                info!("Deleted {num} refs");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run_aliases(args: &args::Aliases) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    match &args.subcommand {
+        args::AliasesCmd::Add(args) => {
+            if db.get_artifact(&args.alias_to).await?.is_none() {
+                return Err(Error::UnknownArtifact(args.alias_to.clone()));
+            }
+            let alias = db::Alias {
+                alias_from: args.alias_from.clone(),
+                alias_to: args.alias_to.clone(),
+                reason: args.reason.clone(),
+            };
+            if args.replace {
+                for old in db.get_aliases_from(&args.alias_from).await? {
+                    if old.alias_to != args.alias_to {
+                        print_alias(
+                            if args.dry_run {
+                                "would remove"
+                            } else {
+                                "remove"
+                            },
+                            &old,
+                        );
+                    }
+                }
+            }
+            print_alias(if args.dry_run { "would add" } else { "add" }, &alias);
+            if !args.dry_run {
+                if args.replace {
+                    db.delete_aliases(&args.alias_from, None).await?;
+                }
+                db.insert_alias_from_to(
+                    &alias.alias_from,
+                    &alias.alias_to,
+                    alias.reason.as_deref().unwrap_or("manual"),
+                )
+                .await?;
+            }
+        }
+        args::AliasesCmd::Rm(args) => {
+            let aliases = db.get_aliases_from(&args.alias_from).await?;
+            let aliases = aliases
+                .iter()
+                .filter(|a| args.to.as_ref().is_none_or(|to| *to == a.alias_to))
+                .collect::<Vec<_>>();
+            if aliases.is_empty() {
+                warn!("Alias not found: {:?}", args.alias_from);
+            }
+            for alias in &aliases {
+                print_alias(
+                    if args.dry_run {
+                        "would remove"
+                    } else {
+                        "remove"
+                    },
+                    alias,
+                );
+            }
+            if !args.dry_run && !aliases.is_empty() {
+                db.delete_aliases(&args.alias_from, args.to.as_deref())
+                    .await?;
+            }
+        }
+        args::AliasesCmd::Ls(args) => {
+            for alias in db.get_aliases_from(&args.chksum).await? {
+                print_alias("from", &alias);
+            }
+            for alias in db.get_aliases_to(&args.chksum).await? {
+                print_alias("to", &alias);
+            }
+        }
+        args::AliasesCmd::Mv(args) => {
+            if db.get_artifact(&args.to).await?.is_none() {
+                return Err(Error::UnknownArtifact(args.to.clone()));
+            }
+            for alias in db.get_aliases_to(&args.from).await? {
+                let moved = db::Alias {
+                    alias_to: args.to.clone(),
+                    ..alias
+                };
+                print_alias(if args.dry_run { "would move" } else { "move" }, &moved);
+            }
+            if !args.dry_run {
+                let num = db.move_aliases(&args.from, &args.to).await?;
+                info!("Moved {num} aliases from {:?} to {:?}", args.from, args.to);
+            }
+        }
+    }
+
+    Ok(())
+}
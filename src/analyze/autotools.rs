@@ -0,0 +1,463 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Inputs larger than this are not considered, generated configure scripts are usually <2MiB
+pub const MAX_INPUT_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Shell snippets that have no business in a build system, as seen in the xz-utils backdoor
+const SUSPICIOUS_SNIPPETS: &[(&str, &str)] = &[
+    ("grep -aErls", "searches binary files for a marker"),
+    ("| eval ", "evaluates the output of a pipeline"),
+    ("xz -d", "decompresses data"),
+    ("head -c", "extracts bytes from a file"),
+    ("tr \"\\t \\-_\"", "translates bytes"),
+    ("tests/files/", "reads from the test files"),
+];
+
+const SCORE_VERSION_MISMATCH: u32 = 20;
+const SCORE_PREREQ: u32 = 30;
+const SCORE_SERIAL_MISMATCH: u32 = 10;
+const SCORE_SUSPICIOUS_M4: u32 = 25;
+const SCORE_UNEXPLAINED_SNIPPET: u32 = 50;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Finding {
+    pub file: String,
+    pub message: String,
+    pub score: u32,
+}
+
+/// The result of comparing `configure` with the files it's generated from
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Analysis {
+    pub findings: Vec<Finding>,
+    pub score: u32,
+}
+
+impl Analysis {
+    fn push(&mut self, file: &str, message: String, score: u32) {
+        self.findings.push(Finding {
+            file: file.to_string(),
+            message,
+            score,
+        });
+        self.score += score;
+    }
+}
+
+/// The autotools files of a source tree, with paths relative to the top-level folder
+#[derive(Debug, Default)]
+pub struct Inputs {
+    pub configure: Option<String>,
+    pub configure_ac: Option<String>,
+    pub aclocal: Option<String>,
+    pub m4: BTreeMap<String, String>,
+}
+
+impl Inputs {
+    /// Returns the relative path if the content of this file is needed for the analysis
+    pub fn wants(path: &str) -> Option<&str> {
+        let path = path.strip_prefix("./").unwrap_or(path);
+        let (_, path) = path.split_once('/')?;
+        match path {
+            "configure" | "configure.ac" | "configure.in" | "aclocal.m4" => Some(path),
+            _ => {
+                let name = path.strip_prefix("m4/")?;
+                (!name.contains('/') && name.ends_with(".m4")).then_some(path)
+            }
+        }
+    }
+
+    pub fn insert(&mut self, path: &str, data: &[u8]) {
+        let data = String::from_utf8_lossy(data).into_owned();
+        match path {
+            "configure" => self.configure = Some(data),
+            "configure.ac" | "configure.in" => self.configure_ac = Some(data),
+            "aclocal.m4" => self.aclocal = Some(data),
+            _ => {
+                self.m4.insert(path.to_string(), data);
+            }
+        }
+    }
+
+    /// Check if the generated configure script could plausibly have been generated from the inputs
+    pub fn analyze(&self) -> Analysis {
+        let mut analysis = Analysis::default();
+        let (Some(configure), Some(configure_ac)) = (&self.configure, &self.configure_ac) else {
+            return analysis;
+        };
+        let generated = parse_generated_by(configure);
+
+        if let (Some(generated), Some(declared)) = (
+            generated
+                .as_ref()
+                .and_then(|g| g.package_version.as_deref()),
+            parse_ac_init(configure_ac),
+        ) {
+            if generated != declared {
+                analysis.push(
+                    "configure",
+                    format!(
+                        "configure was generated for version {generated:?}, but configure.ac declares {declared:?}"
+                    ),
+                    SCORE_VERSION_MISMATCH,
+                );
+            }
+        }
+
+        if let (Some(autoconf), Some(prereq)) = (
+            generated.as_ref().map(|g| g.autoconf.as_str()),
+            parse_ac_prereq(configure_ac),
+        ) {
+            if compare_versions(autoconf, &prereq) == Ordering::Less {
+                analysis.push(
+                    "configure",
+                    format!(
+                        "configure was generated by autoconf {autoconf}, but configure.ac requires {prereq}"
+                    ),
+                    SCORE_PREREQ,
+                );
+            }
+        }
+
+        if let Some(aclocal) = &self.aclocal {
+            let embedded = parse_embedded_serials(aclocal);
+            for (path, content) in &self.m4 {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                let (Some(shipped), Some(embedded)) = (parse_serial(content), embedded.get(name))
+                else {
+                    continue;
+                };
+                if shipped != *embedded {
+                    analysis.push(
+                        path,
+                        format!("{path} has serial {shipped}, but aclocal.m4 was generated from serial {embedded}"),
+                        SCORE_SERIAL_MISMATCH,
+                    );
+                }
+            }
+        }
+
+        for (path, content) in &self.m4 {
+            for (snippet, description) in SUSPICIOUS_SNIPPETS {
+                if content.contains(snippet) && !configure_ac.contains(snippet) {
+                    analysis.push(
+                        path,
+                        format!("{path} contains code that {description}: {snippet:?}"),
+                        SCORE_SUSPICIOUS_M4,
+                    );
+                }
+            }
+        }
+
+        let inputs = [Some(configure_ac), self.aclocal.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(self.m4.values())
+            .collect::<Vec<_>>();
+        for (snippet, description) in SUSPICIOUS_SNIPPETS {
+            if configure.contains(snippet) && !inputs.iter().any(|input| input.contains(snippet)) {
+                analysis.push(
+                    "configure",
+                    format!("configure contains code that {description}, but none of the inputs do: {snippet:?}"),
+                    SCORE_UNEXPLAINED_SNIPPET,
+                );
+            }
+        }
+
+        analysis
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Generated {
+    pub autoconf: String,
+    pub package_version: Option<String>,
+}
+
+/// Read the autoconf version and the package version from a generated configure script
+pub fn parse_generated_by(configure: &str) -> Option<Generated> {
+    let mut autoconf = None;
+    let mut package_version = None;
+    for line in configure.lines() {
+        if let Some(rest) = line.strip_prefix("# Generated by GNU Autoconf ") {
+            autoconf = rest.split_whitespace().next().map(String::from);
+        } else if let Some(value) = line.strip_prefix("PACKAGE_VERSION=") {
+            package_version = Some(value.trim_matches(['\'', '"']).to_string());
+            break;
+        }
+    }
+    Some(Generated {
+        autoconf: autoconf?,
+        package_version,
+    })
+}
+
+/// Split the arguments of an m4 macro call, quotes and nested parentheses are respected
+fn macro_args<'a>(txt: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let call = format!("{name}(");
+    let start = txt.match_indices(&call).map(|(idx, _)| idx).find(|idx| {
+        // skip mentions in comments
+        let line = txt[..*idx].rsplit('\n').next().unwrap_or_default();
+        !line.contains("dnl") && !line.contains('#')
+    })? + call.len();
+
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut quotes = 0;
+    let mut begin = start;
+    for (i, c) in txt[start..].char_indices() {
+        let i = start + i;
+        match c {
+            '[' => quotes += 1,
+            ']' => quotes -= 1,
+            '(' if quotes == 0 => depth += 1,
+            ')' if quotes == 0 && depth > 0 => depth -= 1,
+            ')' if quotes == 0 => {
+                args.push(&txt[begin..i]);
+                return Some(args);
+            }
+            ',' if quotes == 0 && depth == 0 => {
+                args.push(&txt[begin..i]);
+                begin = i + 1;
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Remove whitespace and one level of m4 quotes
+fn unquote(arg: &str) -> &str {
+    let arg = arg.trim();
+    arg.strip_prefix('[')
+        .and_then(|arg| arg.strip_suffix(']'))
+        .unwrap_or(arg)
+        .trim()
+}
+
+/// The version passed to AC_INIT, if it's a literal and not computed with m4_esyscmd or similar
+pub fn parse_ac_init(configure_ac: &str) -> Option<String> {
+    let args = macro_args(configure_ac, "AC_INIT")?;
+    let version = unquote(args.get(1)?);
+    if version.is_empty() || version.contains(['(', '[', '$']) {
+        None
+    } else {
+        Some(version.to_string())
+    }
+}
+
+pub fn parse_ac_prereq(configure_ac: &str) -> Option<String> {
+    let args = macro_args(configure_ac, "AC_PREREQ")?;
+    let version = unquote(args.first()?);
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Read the serial number of an m4 file, like `# serial 30` or `#serial 5`
+pub fn parse_serial(content: &str) -> Option<u64> {
+    content.lines().find_map(|line| {
+        let line = line.strip_prefix('#')?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["serial", serial, ..] | [_, "serial", serial, ..] => serial.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Serial numbers of m4 files that aclocal copied into aclocal.m4, keyed by filename
+///
+/// aclocal only copies files from the system search path, local files are `m4_include`d.
+pub fn parse_embedded_serials(aclocal: &str) -> BTreeMap<String, u64> {
+    let mut serials = BTreeMap::new();
+    for line in aclocal.lines() {
+        let Some(line) = line.strip_prefix('#') else {
+            continue;
+        };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if let [name, "serial", serial, ..] = words.as_slice() {
+            if let (true, Ok(serial)) = (name.ends_with(".m4"), serial.parse()) {
+                serials.entry(name.to_string()).or_insert(serial);
+            }
+        }
+    }
+    serials
+}
+
+/// Compare versions like `2.69` and `2.71c` by their numeric components
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |v: &str| {
+        v.split('.')
+            .map(|part| {
+                let digits =
+                    part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                part[..digits].parse::<u64>().unwrap_or(0)
+            })
+            .collect::<Vec<_>>()
+    };
+    numbers(a).cmp(&numbers(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIGURE_AC: &str = "dnl Process this file with autoconf
+AC_PREREQ([2.69])
+AC_INIT([XZ Utils], m4_esyscmd([/bin/sh build-aux/version.sh]),
+        [xz@tukaani.org], [xz], [https://tukaani.org/xz/])
+AC_CONFIG_MACRO_DIR([m4])
+";
+
+    const CONFIGURE: &str = "#! /bin/sh
+# Guess values for system-dependent variables and create Makefiles.
+# Generated by GNU Autoconf 2.72 for XZ Utils 5.6.1.
+#
+# Identity of this package.
+PACKAGE_NAME='XZ Utils'
+PACKAGE_TARNAME='xz'
+PACKAGE_VERSION='5.6.1'
+";
+
+    const BUILD_TO_HOST: &str = r##"# build-to-host.m4 serial 30
+dnl Copyright (C) 2023-2024 Free Software Foundation, Inc.
+  gl_am_configmake=`grep -aErls "#{4}[[:alnum:]]{5}#{4}$" $srcdir/ 2>/dev/null`
+  gl_path_map='tr "\t \-_" " \t_\-"'
+  gl_[$1]_config='sed \"r\n\" $gl_am_configmake | eval $gl_path_map | $gl_[$1]_prefix -d 2>/dev/null'
+"##;
+
+    #[test]
+    fn test_wants() {
+        assert_eq!(Inputs::wants("xz-5.6.1/configure"), Some("configure"));
+        assert_eq!(Inputs::wants("xz-5.6.1/configure.ac"), Some("configure.ac"));
+        assert_eq!(
+            Inputs::wants("xz-5.6.1/m4/build-to-host.m4"),
+            Some("m4/build-to-host.m4")
+        );
+        assert_eq!(Inputs::wants("xz-5.6.1/src/configure"), None);
+        assert_eq!(Inputs::wants("xz-5.6.1/m4/README"), None);
+        assert_eq!(Inputs::wants("configure"), None);
+    }
+
+    #[test]
+    fn test_parse_generated_by() {
+        assert_eq!(
+            parse_generated_by(CONFIGURE),
+            Some(Generated {
+                autoconf: "2.72".to_string(),
+                package_version: Some("5.6.1".to_string()),
+            })
+        );
+        assert_eq!(parse_generated_by("#!/bin/sh\necho hello\n"), None);
+    }
+
+    #[test]
+    fn test_parse_ac_init() {
+        assert_eq!(parse_ac_init(CONFIGURE_AC), None);
+        assert_eq!(
+            parse_ac_init("AC_INIT([GNU tar], [1.35], [bug-tar@gnu.org])\n").as_deref(),
+            Some("1.35")
+        );
+        assert_eq!(
+            parse_ac_init("AC_INIT(hello, 2.12)\n").as_deref(),
+            Some("2.12")
+        );
+        assert_eq!(parse_ac_prereq(CONFIGURE_AC).as_deref(), Some("2.69"));
+    }
+
+    #[test]
+    fn test_parse_serial() {
+        assert_eq!(parse_serial(BUILD_TO_HOST), Some(30));
+        assert_eq!(parse_serial("# serial 11 (pkg-config-0.29.1)\n"), Some(11));
+        assert_eq!(parse_serial("#serial 5\n"), Some(5));
+        assert_eq!(parse_serial("dnl no serial here\n"), None);
+
+        let aclocal = "# generated automatically by aclocal 1.16.5 -*- Autoconf -*-
+# pkg.m4 - Macros to locate and use pkg-config.   -*- Autoconf -*-
+# serial 12 (pkg-config-0.29.2)
+# build-to-host.m4 serial 3
+m4_include([m4/visibility.m4])
+";
+        assert_eq!(
+            parse_embedded_serials(aclocal),
+            BTreeMap::from([("build-to-host.m4".to_string(), 3)])
+        );
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("2.72", "2.69"), Ordering::Greater);
+        assert_eq!(compare_versions("2.71c", "2.71"), Ordering::Equal);
+        assert_eq!(compare_versions("2.9", "2.69"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_analyze_clean() {
+        let mut inputs = Inputs::default();
+        inputs.insert("configure", CONFIGURE.as_bytes());
+        inputs.insert("configure.ac", CONFIGURE_AC.as_bytes());
+        inputs.insert("m4/visibility.m4", b"# visibility.m4 serial 8\n");
+        assert_eq!(inputs.analyze(), Analysis::default());
+    }
+
+    #[test]
+    fn test_analyze_backdoor() {
+        let configure = format!(
+            "{CONFIGURE}gl_path_map='tr \"\\t \\-_\" \" \\t_\\-\"'\nsed \"r\\n\" $gl_am_configmake | eval $gl_path_map\n"
+        );
+        let mut inputs = Inputs::default();
+        inputs.insert("configure", configure.as_bytes());
+        inputs.insert("configure.ac", CONFIGURE_AC.as_bytes());
+        inputs.insert("aclocal.m4", b"# build-to-host.m4 serial 3\n");
+        inputs.insert("m4/build-to-host.m4", BUILD_TO_HOST.as_bytes());
+
+        let analysis = inputs.analyze();
+        assert_eq!(
+            analysis
+                .findings
+                .iter()
+                .map(|f| (f.file.as_str(), f.score))
+                .collect::<Vec<_>>(),
+            [
+                ("m4/build-to-host.m4", SCORE_SERIAL_MISMATCH),
+                ("m4/build-to-host.m4", SCORE_SUSPICIOUS_M4),
+                ("m4/build-to-host.m4", SCORE_SUSPICIOUS_M4),
+                ("m4/build-to-host.m4", SCORE_SUSPICIOUS_M4),
+            ]
+        );
+        assert_eq!(analysis.score, 85);
+
+        // the payload in configure can't be explained if the m4 file is missing
+        inputs.m4.clear();
+        let analysis = inputs.analyze();
+        assert_eq!(analysis.findings.len(), 2);
+        assert_eq!(analysis.score, 2 * SCORE_UNEXPLAINED_SNIPPET);
+    }
+
+    #[test]
+    fn test_analyze_version_mismatch() {
+        let mut inputs = Inputs::default();
+        inputs.insert("configure", CONFIGURE.as_bytes());
+        inputs.insert(
+            "configure.ac",
+            b"AC_PREREQ([2.73])\nAC_INIT([XZ Utils], [5.6.0])\n",
+        );
+        let analysis = inputs.analyze();
+        assert_eq!(
+            analysis.findings,
+            [
+                Finding {
+                    file: "configure".to_string(),
+                    message: "configure was generated for version \"5.6.1\", but configure.ac declares \"5.6.0\"".to_string(),
+                    score: SCORE_VERSION_MISMATCH,
+                },
+                Finding {
+                    file: "configure".to_string(),
+                    message: "configure was generated by autoconf 2.72, but configure.ac requires 2.73".to_string(),
+                    score: SCORE_PREREQ,
+                },
+            ]
+        );
+    }
+}
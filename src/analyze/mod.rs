@@ -0,0 +1,190 @@
+pub mod autotools;
+pub mod totals;
+
+use crate::ingest;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+pub fn detect_autotools(files: Option<&[ingest::tar::Entry]>) -> bool {
+    let Some(files) = files else {
+        return false;
+    };
+
+    let mut configure = HashSet::new();
+    let mut configure_ac = HashSet::new();
+
+    for file in files {
+        if let Some(folder) = file.path.strip_suffix("/configure") {
+            if configure_ac.contains(folder) {
+                return true;
+            }
+            configure.insert(folder);
+        }
+        if let Some(folder) = file.path.strip_suffix("/configure.ac") {
+            if configure.contains(folder) {
+                return true;
+            }
+            configure_ac.insert(folder);
+        }
+    }
+
+    false
+}
+
+/// Files that are only present in a release tarball, but not in the VCS snapshot
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Drift {
+    pub categories: BTreeMap<&'static str, Vec<String>>,
+    /// Files that are unusual to be generated during a release, e.g. object files
+    pub anomalies: Vec<String>,
+    pub score: u32,
+}
+
+/// Strip the top-level folder if all entries share one, like `foo-1.0/`
+fn strip_common_folder(files: &[ingest::tar::Entry]) -> Vec<&str> {
+    let prefix = files
+        .first()
+        .and_then(|f| f.path.split_once('/'))
+        .map(|(folder, _)| folder);
+    let shared = prefix.filter(|prefix| {
+        files.iter().all(|f| {
+            f.path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    });
+
+    files
+        .iter()
+        .filter(|f| f.digest.is_some())
+        .map(|f| match shared {
+            Some(prefix) => f.path[prefix.len()..].trim_start_matches('/'),
+            None => f.path.as_str(),
+        })
+        .collect()
+}
+
+/// Categorize a generated file by name, including its anomaly weight
+fn categorize(path: &str) -> (&'static str, u32) {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    let extension = filename.rsplit_once('.').map(|(_, ext)| ext);
+
+    match (filename, extension) {
+        (
+            "configure" | "config.guess" | "config.sub" | "install-sh" | "ltmain.sh" | "missing"
+            | "depcomp" | "compile" | "test-driver" | "ylwrap" | "Makefile.in" | "config.h.in",
+            _,
+        ) => ("autotools", 0),
+        (_, Some("m4")) => ("autotools", 0),
+        (_, Some("info" | "html" | "pdf" | "ps" | "1" | "3" | "5" | "7" | "8")) => {
+            ("documentation", 0)
+        }
+        (_, Some("gmo" | "mo" | "pot")) => ("translations", 0),
+        (_, Some("o" | "obj" | "a" | "lib" | "so" | "dll" | "dylib" | "exe" | "class")) => {
+            ("binary", 10)
+        }
+        (
+            _,
+            Some(
+                "zip" | "jar" | "tar" | "gz" | "tgz" | "xz" | "lzma" | "bz2" | "zst" | "lz" | "7z",
+            ),
+        ) => ("archive", 5),
+        _ => ("other", 0),
+    }
+}
+
+pub fn detect_drift(tarball: &[ingest::tar::Entry], vcs: &[ingest::tar::Entry]) -> Drift {
+    let vcs = strip_common_folder(vcs).into_iter().collect::<HashSet<_>>();
+
+    let mut drift = Drift::default();
+    for path in strip_common_folder(tarball) {
+        if vcs.contains(path) {
+            continue;
+        }
+
+        let (category, weight) = categorize(path);
+        drift
+            .categories
+            .entry(category)
+            .or_default()
+            .push(path.to_string());
+        if weight > 0 {
+            drift.anomalies.push(path.to_string());
+            drift.score += weight;
+        }
+    }
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::tar::{Entry, Metadata};
+
+    fn file(path: &str) -> Entry {
+        Entry {
+            path: path.to_string(),
+            digest: (!path.ends_with('/')).then(|| "sha256:00".to_string()),
+            metadata: Metadata {
+                mode: None,
+                links_to: None,
+                mtime: None,
+                uid: None,
+                username: None,
+                gid: None,
+                groupname: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_detect_drift() {
+        let tarball = [
+            file("xz-5.6.1/"),
+            file("xz-5.6.1/configure"),
+            file("xz-5.6.1/configure.ac"),
+            file("xz-5.6.1/m4/build-to-host.m4"),
+            file("xz-5.6.1/src/liblzma/common/common.c"),
+            file("xz-5.6.1/doc/man/txt/xz.txt"),
+            file("xz-5.6.1/po/de.gmo"),
+            file("xz-5.6.1/tests/files/bad-3-corrupt_lzma2.xz"),
+            file("xz-5.6.1/tests/files/good-large_compressed.lzma"),
+            file("xz-5.6.1/src/liblzma/liblzma_la-crc64-fast.o"),
+        ];
+        let vcs = [
+            file("configure.ac"),
+            file("src/liblzma/common/common.c"),
+            file("tests/files/bad-3-corrupt_lzma2.xz"),
+        ];
+
+        let drift = detect_drift(&tarball, &vcs);
+        assert_eq!(
+            drift,
+            Drift {
+                categories: [
+                    (
+                        "autotools",
+                        vec!["configure".to_string(), "m4/build-to-host.m4".to_string()]
+                    ),
+                    (
+                        "archive",
+                        vec!["tests/files/good-large_compressed.lzma".to_string()]
+                    ),
+                    (
+                        "binary",
+                        vec!["src/liblzma/liblzma_la-crc64-fast.o".to_string()]
+                    ),
+                    ("other", vec!["doc/man/txt/xz.txt".to_string()]),
+                    ("translations", vec!["po/de.gmo".to_string()]),
+                ]
+                .into_iter()
+                .collect(),
+                anomalies: vec![
+                    "tests/files/good-large_compressed.lzma".to_string(),
+                    "src/liblzma/liblzma_la-crc64-fast.o".to_string(),
+                ],
+                score: 15,
+            }
+        );
+    }
+}
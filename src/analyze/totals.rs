@@ -0,0 +1,213 @@
+use crate::ingest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Pax keys that describe the entry itself, anything else is worth a look
+const COMMON_PAX_KEYS: &[&str] = &[
+    "path",
+    "linkpath",
+    "size",
+    "uid",
+    "gid",
+    "uname",
+    "gname",
+    "mtime",
+    "atime",
+    "ctime",
+    "charset",
+    "hdrcharset",
+];
+/// How many distinct unusual pax keys are recorded
+const MAX_PAX_KEYS: usize = 16;
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+const S_IWOTH: u32 = 0o002;
+
+/// Aggregates of the file metadata of an archive, computed during ingestion
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Totals {
+    pub files: u64,
+    /// Uncompressed size of all regular files
+    pub total_size: u64,
+    /// Entries with the setuid or setgid bit
+    pub setuid: u64,
+    pub devices: u64,
+    pub world_writable: u64,
+    /// Absolute paths or paths containing `..`
+    pub unsafe_paths: u64,
+    /// Entries for a path that was already in the archive, the last one wins during extraction
+    pub duplicate_paths: u64,
+    /// Paths that only differ in case, they overwrite each other on case-insensitive filesystems
+    pub case_collisions: u64,
+    pub unusual_pax_keys: Vec<String>,
+}
+
+/// A path that would be written outside of the extraction directory
+pub fn is_unsafe_path(path: &str) -> bool {
+    path.starts_with('/') || path.split(['/', '\\']).any(|c| c == "..")
+}
+
+impl Totals {
+    /// Account for an entry, symlinks are ignored for the mode checks since they are always 0o777
+    pub fn add(&mut self, path: &str, mode: Option<u32>, size: Option<u64>, kind: EntryKind) {
+        if is_unsafe_path(path) {
+            self.unsafe_paths += 1;
+        }
+        match kind {
+            EntryKind::File => {
+                self.files += 1;
+                self.total_size += size.unwrap_or(0);
+            }
+            EntryKind::Device => self.devices += 1,
+            EntryKind::Symlink => return,
+            EntryKind::Other => (),
+        }
+        if let Some(mode) = mode {
+            if mode & (S_ISUID | S_ISGID) != 0 {
+                self.setuid += 1;
+            }
+            if mode & S_IWOTH != 0 {
+                self.world_writable += 1;
+            }
+        }
+    }
+
+    /// Record the key of a pax extended header, `comment` is expected in global headers of `git archive`
+    pub fn add_pax_key(&mut self, key: &str, global: bool) {
+        if COMMON_PAX_KEYS.contains(&key) || (global && key == "comment") {
+            return;
+        }
+        if self.unusual_pax_keys.len() < MAX_PAX_KEYS
+            && !self.unusual_pax_keys.iter().any(|k| k == key)
+        {
+            self.unusual_pax_keys.push(key.to_string());
+        }
+    }
+
+    /// Count paths that show up more than once, exactly or ignoring case
+    pub fn check_paths(&mut self, files: &[ingest::tar::Entry]) {
+        let mut seen = HashSet::new();
+        let mut seen_lowercase = HashSet::new();
+        for entry in files {
+            let path = entry.path.trim_start_matches("./").trim_end_matches('/');
+            if !seen.insert(path) {
+                self.duplicate_paths += 1;
+            } else if !seen_lowercase.insert(path.to_lowercase()) {
+                self.case_collisions += 1;
+            }
+        }
+    }
+
+    pub fn has_anomalies(&self) -> bool {
+        self.setuid > 0
+            || self.devices > 0
+            || self.world_writable > 0
+            || self.unsafe_paths > 0
+            || self.duplicate_paths > 0
+            || self.case_collisions > 0
+            || !self.unusual_pax_keys.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    File,
+    Device,
+    Symlink,
+    /// Directories, hardlinks and anything else
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totals() {
+        let mut totals = Totals::default();
+        totals.add("xz-5.6.3/", Some(0o755), None, EntryKind::Other);
+        totals.add(
+            "xz-5.6.3/configure",
+            Some(0o755),
+            Some(1000),
+            EntryKind::File,
+        );
+        totals.add("xz-5.6.3/README", Some(0o644), Some(24), EntryKind::File);
+        totals.add("xz-5.6.3/link", Some(0o777), None, EntryKind::Symlink);
+        assert!(!totals.has_anomalies());
+        assert_eq!(totals.files, 2);
+        assert_eq!(totals.total_size, 1024);
+
+        totals.add("xz-5.6.3/su", Some(0o4755), Some(1), EntryKind::File);
+        totals.add("xz-5.6.3/tmp/", Some(0o1777), None, EntryKind::Other);
+        totals.add("dev/null", Some(0o666), None, EntryKind::Device);
+        totals.add("../../etc/passwd", Some(0o644), Some(1), EntryKind::File);
+        assert_eq!(
+            totals,
+            Totals {
+                files: 4,
+                total_size: 1026,
+                setuid: 1,
+                devices: 1,
+                world_writable: 2,
+                unsafe_paths: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    fn entry(path: &str) -> ingest::tar::Entry {
+        ingest::tar::Entry {
+            path: path.to_string(),
+            digest: None,
+            metadata: ingest::tar::Metadata {
+                mode: None,
+                links_to: None,
+                mtime: None,
+                uid: None,
+                username: None,
+                gid: None,
+                groupname: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_check_paths() {
+        let mut totals = Totals::default();
+        totals.check_paths(&[
+            entry("foo-1.0/"),
+            entry("foo-1.0/README"),
+            entry("foo-1.0/readme"),
+            entry("foo-1.0/src/main.c"),
+            entry("./foo-1.0/src/main.c"),
+            entry("foo-1.0/src/"),
+        ]);
+        assert_eq!(totals.duplicate_paths, 1);
+        assert_eq!(totals.case_collisions, 1);
+    }
+
+    #[test]
+    fn test_pax_keys() {
+        let mut totals = Totals::default();
+        totals.add_pax_key("comment", true);
+        totals.add_pax_key("path", false);
+        totals.add_pax_key("mtime", false);
+        assert!(!totals.has_anomalies());
+
+        totals.add_pax_key("GNU.sparse.map", false);
+        totals.add_pax_key("GNU.sparse.map", false);
+        totals.add_pax_key("comment", false);
+        assert_eq!(totals.unusual_pax_keys, ["GNU.sparse.map", "comment"]);
+    }
+
+    #[test]
+    fn test_unsafe_path() {
+        assert!(is_unsafe_path("/etc/passwd"));
+        assert!(is_unsafe_path("foo/../../bar"));
+        assert!(!is_unsafe_path("./foo/bar"));
+        assert!(!is_unsafe_path("foo/..bar"));
+    }
+}
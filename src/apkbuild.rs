@@ -2,56 +2,59 @@ use crate::errors::*;
 use std::collections::HashMap;
 use yash_syntax::syntax::{self, Unquote, Value};
 
-/// Variables we keep track of for interpolation but nothing else
-const TRACKED_VARIABLES: &[&str] = &["_pkgname", "_pkgver", "_gitrev", "_commit", "url"];
-
 #[derive(Debug, Default, PartialEq)]
 pub struct Apkbuild {
     pub pkgname: Option<String>,
     pub pkgver: Option<String>,
-    pub extra: HashMap<&'static str, String>,
+    /// All other scalar assignments we could evaluate, e.g. `_commit` or `url`
+    pub extra: HashMap<String, String>,
 
     pub source: Vec<String>,
     pub sha512sums: Vec<String>,
 }
 
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
 impl Apkbuild {
+    fn get_var(&self, name: &str) -> Option<&String> {
+        match name {
+            "pkgname" => self.pkgname.as_ref(),
+            "pkgver" => self.pkgver.as_ref(),
+            _ => self.extra.get(name),
+        }
+    }
+
+    /// Expand `$var` and `${var}`, anything more elaborate (like `${var//./}`) is rejected
     pub fn resolve_vars(&self, mut text: &str) -> Result<String> {
         let mut out = String::new();
-
-        'outer: while !text.is_empty() {
-            if let Some((before, after)) = text.split_once('$') {
-                let vars = [
-                    ("pkgname", self.pkgname.as_ref()),
-                    ("pkgver", self.pkgver.as_ref()),
-                ]
-                .into_iter()
-                .chain(self.extra.iter().map(|(key, value)| (*key, Some(value))));
-
-                out.push_str(before);
-                for (name, value) in vars {
-                    if let Some(after) = after.strip_prefix(name) {
-                        let Some(value) = value else {
-                            return Err(Error::UnknownVariable(name.to_string()));
-                        };
-                        out.push_str(value);
-                        text = after;
-                        continue 'outer;
-                    }
-                }
-
-                return Err(Error::UnknownVariable(after.to_string()));
+        while let Some((before, after)) = text.split_once('$') {
+            out.push_str(before);
+            let (name, after) = if let Some(after) = after.strip_prefix('{') {
+                let Some((name, after)) = after.split_once('}') else {
+                    return Err(Error::InvalidPkgbuild("Missing closing }".to_string()));
+                };
+                (name, after)
             } else {
-                out.push_str(text);
-                break;
+                let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+                after.split_at(end)
+            };
+            if name.is_empty() || !name.chars().all(is_name) {
+                return Err(Error::UnknownVariable(name.to_string()));
             }
+            let Some(value) = self.get_var(name) else {
+                return Err(Error::UnknownVariable(name.to_string()));
+            };
+            out.push_str(value);
+            text = after;
         }
-
+        out.push_str(text);
         Ok(out)
     }
 
-    pub fn register_var(&mut self, key: &'static str, value: String) {
-        self.extra.insert(key, value);
+    pub fn register_var(&mut self, key: &str, value: String) {
+        self.extra.insert(key.to_string(), value);
     }
 }
 
@@ -103,11 +106,11 @@ pub fn parse(script: &str) -> Result<Apkbuild> {
                             .map(String::from)
                             .collect();
                     }
-                    _ => {
-                        if let Some(name) = TRACKED_VARIABLES.iter().find(|x| **x == name) {
-                            apkbuild.register_var(name, value?);
-                        }
-                    }
+                    _ => match value {
+                        Ok(value) => apkbuild.register_var(name, value),
+                        // only an error if something we care about references it later
+                        Err(err) => debug!("Failed to evaluate {name:?} in APKBUILD: {err:#}"),
+                    },
                 }
             }
         }
@@ -169,8 +172,15 @@ sha512sums="1aeecd8e8abb6f87fc54f88a8c25478f69d42d450af782e73c0fca7f051669a415c0
             pkgname: Some("cmatrix".to_string()),
             pkgver: Some("2.0".to_string()),
             extra: [
-                ("url", "https://github.com/abishekvashok/cmatrix".to_string()),
-            ].into_iter().collect(),
+                ("pkgrel", "2"),
+                ("pkgdesc", "Terminal based 'The Matrix' like implementation"),
+                ("url", "https://github.com/abishekvashok/cmatrix"),
+                ("arch", "all"),
+                ("license", "GPL-3.0-or-later"),
+                ("makedepends", "ncurses-dev kbd autoconf automake"),
+                ("subpackages", "cmatrix-doc"),
+                ("options", "!check"),
+            ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
 
             source: vec![
                 "https://github.com/abishekvashok/cmatrix/archive/v2.0.tar.gz".to_string(),
@@ -250,9 +260,17 @@ c652a87ad95f61901820adb61f3d1ceacedcb8aeaf9e89b2b728b7372eff67d9669eb363d5b2d2fb
                 pkgname: Some("7zip".to_string()),
                 pkgver: Some("23.01".to_string()),
                 extra: [
-                    ("_pkgver", "2301".to_string()),
-                    ("url", "https://7-zip.org/".to_string()),
-                ].into_iter().collect(),
+                    ("_pkgver", "2301"),
+                    ("pkgrel", "0"),
+                    ("pkgdesc", "File archiver with a high compression ratio"),
+                    ("url", "https://7-zip.org/"),
+                    ("arch", "all"),
+                    ("license", "LGPL-2.0-only"),
+                    ("subpackages", "7zip-doc"),
+                    ("provides", "7zip-virtual p7zip=23.01-r0"),
+                    ("replaces", "p7zip"),
+                    ("provider_priority", "100"),
+                ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
 
                 source: vec![
                     "https://7-zip.org/a/7z2301-src.tar.xz".to_string(),
@@ -269,4 +287,28 @@ c652a87ad95f61901820adb61f3d1ceacedcb8aeaf9e89b2b728b7372eff67d9669eb363d5b2d2fb
             }
         );
     }
+
+    #[test]
+    fn test_parse_commit_vars() {
+        init();
+
+        let data = r#"pkgname=foo
+pkgver=0_git20240101
+_commit=5f2a0b1c3d4e5f60718293a4b5c6d7e8f9012345
+_p=${pkgname}-${_commit}
+url="https://github.com/example/foo"
+source="$_p.tar.gz::$url/archive/$_commit.tar.gz"
+sha512sums="0000  foo.tar.gz"
+"#;
+        let apkbuild = parse(data).unwrap();
+        assert_eq!(
+            apkbuild.source,
+            ["https://github.com/example/foo/archive/5f2a0b1c3d4e5f60718293a4b5c6d7e8f9012345.tar.gz"]
+        );
+        assert_eq!(
+            apkbuild.resolve_vars("$_p").unwrap(),
+            "foo-5f2a0b1c3d4e5f60718293a4b5c6d7e8f9012345"
+        );
+        assert!(apkbuild.resolve_vars("${pkgver//_/}").is_err());
+    }
 }
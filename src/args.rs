@@ -1,5 +1,6 @@
 use crate::ingest;
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, Args as ClapArgs, Parser, Subcommand};
+#[cfg(feature = "web")]
 use std::net::SocketAddr;
 
 #[derive(Debug, Parser)]
@@ -14,14 +15,17 @@ pub struct Args {
 
 #[derive(Debug, Subcommand)]
 pub enum SubCommand {
+    #[cfg(feature = "web")]
     #[command(alias = "daemon")]
     Web(Web),
+    #[cfg(feature = "worker")]
     Worker(Worker),
     #[command(subcommand)]
     Plumbing(Plumbing),
 }
 
 /// Run the web server daemon
+#[cfg(feature = "web")]
 #[derive(Debug, Parser)]
 pub struct Web {
     #[arg(short = 'B', long, env)]
@@ -29,6 +33,7 @@ pub struct Web {
 }
 
 /// Run worker for background jobs
+#[cfg(feature = "worker")]
 #[derive(Debug, Parser)]
 pub struct Worker {
     /// Request through a proxy to evade rate limits
@@ -37,31 +42,108 @@ pub struct Worker {
     /// Path to use for temporary git clone operations
     #[arg(long, env = "WHATSRC_GIT_TMP")]
     pub git_tmp: String,
+    #[command(flatten)]
+    pub git_clone: GitClone,
+    /// Hold back artifacts fetched from urls starting with this prefix until they pass a policy check
+    #[arg(
+        long = "quarantine-url",
+        env = "WHATSRC_QUARANTINE_URLS",
+        value_delimiter = ','
+    )]
+    pub quarantine_urls: Vec<String>,
+    /// Only process tasks of this kind (e.g. `fetch`, `git`, `pacman-git-snapshot`)
+    #[arg(long, env = "WHATSRC_WORKER_ONLY", value_delimiter = ',')]
+    pub only: Vec<String>,
+    /// Never process tasks of this kind
+    #[arg(long, env = "WHATSRC_WORKER_EXCLUDE", value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    /// Number of tasks to process in parallel
+    #[arg(long, env = "WHATSRC_WORKER_CONCURRENCY", default_value = "1")]
+    pub concurrency: usize,
+    /// Minimum delay in milliseconds between two requests to the same host
+    #[arg(long, env = "WHATSRC_HOST_DELAY", default_value = "500")]
+    pub host_delay: u64,
+    /// Name this worker reports its heartbeat as, shown on the status page
+    #[arg(long, env = "WHATSRC_WORKER_NAME", default_value = "worker")]
+    pub name: String,
+    /// Deliver subscription alerts from this worker, only one worker should have this enabled
+    #[arg(long, env = "WHATSRC_WORKER_NOTIFY")]
+    pub notify: bool,
+    /// Periodically compare release tarballs with the git snapshot of the same version
+    #[arg(long, env = "WHATSRC_WORKER_COMPARE_VCS")]
+    pub compare_vcs: bool,
+    /// Periodically re-fetch the upstream urls of refs to detect vanished or replaced artifacts
+    #[arg(long, env = "WHATSRC_WORKER_VERIFY_UPSTREAM")]
+    pub verify_upstream: bool,
+    /// Periodically drop queued tasks for versions that have been superseded
+    #[arg(long, env = "WHATSRC_WORKER_PRUNE_TASKS")]
+    pub prune_tasks: bool,
+    /// Keyring used by gpgv to verify pgp signatures of release artifacts
+    #[arg(long, env = "WHATSRC_PGP_KEYRING")]
+    pub pgp_keyring: Option<String>,
+    /// Seconds to wait for running tasks after SIGTERM, before they are aborted and retried later
+    #[arg(long, env = "WHATSRC_WORKER_SHUTDOWN_TIMEOUT", default_value = "300")]
+    pub shutdown_timeout: u64,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Plumbing {
     IngestTar(IngestTar),
     IngestGit(IngestGit),
+    IngestHg(IngestHg),
+    IngestFossil(IngestFossil),
     IngestPacmanSnapshot(IngestPacmanSnapshot),
     IngestRpm(IngestRpm),
     IngestWolfi(IngestWolfi),
     IngestVoid(IngestVoid),
     IngestSbom(IngestSbom),
+    IngestApk(IngestApk),
+    IngestDeb(IngestDeb),
+    IngestDsc(IngestDsc),
+    IngestZip(IngestZip),
+    IngestAttestation(IngestAttestation),
+    VerifySignature(VerifySignature),
     ParsePkgbuild(ParsePkgbuild),
     SyncAlpine(SyncAlpine),
     SyncApt(SyncApt),
     SyncPacman(SyncPacman),
     SyncLiveBootstrap(SyncLiveBootstrap),
     SyncRpm(SyncRpm),
+    SyncObs(SyncObs),
+    SyncKoji(SyncKoji),
     SyncGentoo(SyncGentoo),
     SyncHomebrew(SyncHomebrew),
     SyncGuix(SyncGuix),
     SyncVoid(SyncVoid),
     SyncYocto(SyncYocto),
+    SyncBuildroot(SyncBuildroot),
+    SyncChimera(SyncChimera),
+    SyncSpack(SyncSpack),
+    SyncTermux(SyncTermux),
+    SyncPypi(SyncPypi),
+    SyncNpmRegistry(SyncNpmRegistry),
+    SyncGoproxy(SyncGoproxy),
+    SyncConan(SyncConan),
+    SyncOsv(SyncOsv),
+    SyncMsys2(SyncMsys2),
+    SyncVcpkg(SyncVcpkg),
     AddRef(AddRef),
+    #[command(name = "ref")]
+    Refs(Refs),
+    #[command(name = "alias")]
+    Aliases(Aliases),
     ReindexUrl(ReindexUrl),
+    #[command(alias = "reindex-sboms")]
     ReindexSbom(ReindexSbom),
+    ReindexPackages(ReindexPackages),
+    ReindexFiles(ReindexFiles),
+    ExportVsa(ExportVsa),
+    Scrub(Scrub),
+    DedupRefs(DedupRefs),
+    ReviewQuarantine(ReviewQuarantine),
+    Requeue(Requeue),
+    Tasks(Tasks),
+    GenerateReports(GenerateReports),
 }
 
 /// Ingest a .tar into the archive
@@ -72,16 +154,70 @@ pub struct IngestTar {
     pub file: Option<String>,
 }
 
+/// Ingest a .zip into the archive
+#[derive(Debug, Parser)]
+pub struct IngestZip {
+    pub file: Option<String>,
+}
+
+/// Record the build provenance of an artifact from an in-toto statement, DSSE envelope or sigstore bundle
+#[derive(Debug, Parser)]
+pub struct IngestAttestation {
+    pub file: String,
+}
+
+/// Queue the verification of a detached pgp signature or cosign bundle
+#[derive(Debug, Parser)]
+pub struct VerifySignature {
+    /// The signed file, derived from the signature url if omitted
+    #[arg(long)]
+    pub url: Option<String>,
+    pub signature_url: String,
+}
+
+/// How git repositories are fetched for snapshots, only the requested revision is fetched
+#[derive(Debug, Clone, Default, ClapArgs)]
+pub struct GitClone {
+    /// Keep fetched repositories in the git workdir, so the next snapshot of the same url reuses them
+    #[arg(long = "git-cache", env = "WHATSRC_GIT_CACHE")]
+    pub cache: bool,
+    /// Partial clone filter, e.g. `blob:limit=1m`, filtered blobs are fetched on demand by `git archive`
+    #[arg(long = "git-filter", env = "WHATSRC_GIT_FILTER")]
+    pub filter: Option<String>,
+}
+
 /// Create a `git archive` of a git ref
 #[derive(Debug, Parser)]
 pub struct IngestGit {
     /// The directory to clone into
     #[arg(long)]
     pub tmp: String,
+    #[command(flatten)]
+    pub git_clone: GitClone,
     /// The url to clone from, including tag information
     pub git: ingest::git::GitUrl,
 }
 
+/// Create an `hg archive` of a mercurial revision
+#[derive(Debug, Parser)]
+pub struct IngestHg {
+    /// The directory to clone into
+    #[arg(long)]
+    pub tmp: String,
+    /// The url to clone from, including the revision (`hg+https://...#rev=...`)
+    pub hg: ingest::hg::HgUrl,
+}
+
+/// Create a `fossil tarball` of a fossil check-in
+#[derive(Debug, Parser)]
+pub struct IngestFossil {
+    /// The directory to clone into
+    #[arg(long)]
+    pub tmp: String,
+    /// The url to clone from, including the check-in (`fossil+https://...#rev=...`)
+    pub fossil: ingest::fossil::FossilUrl,
+}
+
 /// Ingest a pacman git .tar.gz
 #[derive(Debug, Parser)]
 pub struct IngestPacmanSnapshot {
@@ -151,6 +287,33 @@ pub struct IngestSbom {
     pub file: String,
 }
 
+/// Link an .apk binary package to its source package
+#[derive(Debug, Parser)]
+pub struct IngestApk {
+    #[arg(long)]
+    pub vendor: String,
+    pub file: Option<String>,
+}
+
+/// Link a .deb binary package to its source package
+#[derive(Debug, Parser)]
+pub struct IngestDeb {
+    #[arg(long)]
+    pub vendor: String,
+    pub file: Option<String>,
+}
+
+/// Ingest a Debian source package (.dsc) along with the patches of its debian tarball
+#[derive(Debug, Parser)]
+pub struct IngestDsc {
+    #[arg(long)]
+    pub vendor: String,
+    /// Download the .dsc and the files it references, instead of reading them from disk
+    #[arg(long)]
+    pub fetch: bool,
+    pub file: String,
+}
+
 /// Attempt parsing a PKGBUILD
 #[derive(Debug, Parser)]
 pub struct ParsePkgbuild {}
@@ -160,8 +323,12 @@ pub struct ParsePkgbuild {}
 pub struct SyncAlpine {
     #[arg(long)]
     pub vendor: String,
-    #[arg(long)]
-    pub repo: Option<String>,
+    /// The repositories to import, e.g. `main`, `community` or `testing`
+    #[arg(long = "repo", value_delimiter = ',')]
+    pub repos: Vec<String>,
+    /// The architectures to import, `file` is then the url of the mirror (e.g. `.../alpine/edge`)
+    #[arg(long = "arch", value_delimiter = ',')]
+    pub arches: Vec<String>,
     #[arg(long)]
     pub fetch: bool,
     pub file: String,
@@ -173,14 +340,26 @@ pub struct SyncApt {
     #[arg(long)]
     pub vendor: String,
     /// The release names to import, e.g. `sid`, `stable` or `stable-security`
-    #[arg(short, long = "release", required = true)]
+    #[arg(short, long = "release", required = true, value_delimiter = ',')]
     pub releases: Vec<String>,
     /// The suite name to import, e.g. `main`, `contrib` or `non-free`
-    #[arg(long = "suite", default_value = "main")]
+    #[arg(long = "suite", default_value = "main", value_delimiter = ',')]
     pub suites: Vec<String>,
+    /// The pockets of each release to import, e.g. `release`, `security` or `updates`
+    #[arg(long = "pocket", default_value = "release", value_delimiter = ',')]
+    pub pockets: Vec<String>,
+    /// Only trust a signed InRelease file, verified with this keyring (e.g. `debian-archive-keyring.gpg`)
+    #[arg(long, env = "WHATSRC_APT_KEYRING")]
+    pub keyring: Option<String>,
     /// Queue a task even if artifact is already known
     #[arg(short = 'R', long)]
     pub reindex: bool,
+    /// Also import the .dsc of each package, to track the patches applied by the vendor
+    #[arg(long)]
+    pub patches: bool,
+    /// Process the Sources index even if it didn't change since the last sync
+    #[arg(long)]
+    pub force: bool,
     pub url: String,
 }
 
@@ -212,6 +391,44 @@ pub struct SyncLiveBootstrap {
 pub struct SyncRpm {
     #[arg(long)]
     pub vendor: String,
+    /// Process the primary metadata even if it didn't change since the last sync
+    #[arg(long)]
+    pub force: bool,
+    pub url: String,
+}
+
+/// Start an import of recent source builds from a Koji build system
+#[derive(Debug, Parser)]
+pub struct SyncKoji {
+    #[arg(long)]
+    pub vendor: String,
+    /// The url of the Koji hub api
+    #[arg(long, default_value = "https://koji.fedoraproject.org/kojihub")]
+    pub url: String,
+    /// The url the build results are downloaded from
+    #[arg(long, default_value = "https://kojipkgs.fedoraproject.org")]
+    pub topurl: String,
+    /// Import the latest builds of this tag (e.g. `epel9` or a side tag), instead of all recent builds
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// Import the builds that completed within this many days
+    #[arg(long, default_value = "7")]
+    pub days: u64,
+    /// The maximum number of builds to request
+    #[arg(long, default_value = "1000")]
+    pub limit: i64,
+}
+
+/// Start an import of a software vendor (Open Build Service)
+#[derive(Debug, Parser)]
+pub struct SyncObs {
+    #[arg(long)]
+    pub vendor: String,
+    /// The project to import packages from
+    #[arg(long, default_value = "openSUSE:Factory")]
+    pub project: String,
+    /// The base url of the OBS api, the public routes don't need authentication
+    #[arg(long, default_value = "https://api.opensuse.org/public")]
     pub url: String,
 }
 
@@ -232,6 +449,9 @@ pub struct SyncHomebrew {
     pub vendor: String,
     #[arg(long)]
     pub fetch: bool,
+    /// The file is the cask index (cask.json) instead of the formulae index
+    #[arg(long)]
+    pub casks: bool,
     pub file: String,
 }
 
@@ -265,6 +485,138 @@ pub struct SyncYocto {
     pub file: String,
 }
 
+/// Start an import of a software vendor (buildroot)
+#[derive(Debug, Parser)]
+pub struct SyncBuildroot {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    pub file: String,
+}
+
+/// Start an import of a software vendor (chimera)
+#[derive(Debug, Parser)]
+pub struct SyncChimera {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    pub file: String,
+}
+
+/// Start an import of a software vendor (spack)
+#[derive(Debug, Parser)]
+pub struct SyncSpack {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    pub file: String,
+}
+
+/// Start an import of a software vendor (termux)
+#[derive(Debug, Parser)]
+pub struct SyncTermux {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    pub file: String,
+}
+
+/// Start an import of a software vendor (pypi)
+#[derive(Debug, Parser)]
+pub struct SyncPypi {
+    /// The package index to import from
+    #[arg(long, default_value = "https://pypi.org")]
+    pub url: String,
+    /// Only import these projects, instead of walking the whole simple index
+    #[arg(long = "package")]
+    pub packages: Vec<String>,
+    /// Also queue the sdists for download, by default only their checksums are recorded
+    #[arg(long)]
+    pub download: bool,
+}
+
+/// Follow the changes feed of the npm registry, runs until interrupted
+#[derive(Debug, Parser)]
+pub struct SyncNpmRegistry {
+    /// The replication endpoint to read the changes feed from
+    #[arg(long, default_value = "https://replicate.npmjs.com/registry")]
+    pub url: String,
+    /// The registry to download package metadata from
+    #[arg(long, default_value = "https://registry.npmjs.org")]
+    pub registry: String,
+    /// Start at this sequence number instead of the last stored position
+    #[arg(long)]
+    pub since: Option<String>,
+    /// How many changes to request at once
+    #[arg(long, default_value = "1000")]
+    pub limit: usize,
+    /// Also queue the tarballs for download, by default only their checksums are recorded
+    #[arg(long)]
+    pub download: bool,
+}
+
+/// Follow the index of the Go module proxy, runs until interrupted
+#[derive(Debug, Parser)]
+pub struct SyncGoproxy {
+    /// The module index to read new versions from
+    #[arg(long, default_value = "https://index.golang.org/index")]
+    pub url: String,
+    /// Start at this timestamp instead of the last stored position
+    #[arg(long)]
+    pub since: Option<String>,
+    /// How many modules to request at once
+    #[arg(long, default_value = "2000")]
+    pub limit: usize,
+}
+
+/// Start an import of a software vendor (conan)
+#[derive(Debug, Parser)]
+pub struct SyncConan {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    /// A .tar.gz snapshot of the conan-center-index git repository
+    pub file: String,
+}
+
+/// Look up known vulnerabilities of packages on osv.dev
+#[derive(Debug, Parser)]
+pub struct SyncOsv {
+    /// Only check packages of this vendor
+    #[arg(long)]
+    pub vendor: Option<String>,
+    /// Upper limit of packages to check
+    #[arg(long, default_value = "1000")]
+    pub limit: i64,
+}
+
+/// Start an import of a software vendor (msys2)
+#[derive(Debug, Parser)]
+pub struct SyncMsys2 {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    /// The srcinfo.json.gz cache of a packages repository
+    pub file: String,
+}
+
+/// Start an import of a software vendor (vcpkg)
+#[derive(Debug, Parser)]
+pub struct SyncVcpkg {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub fetch: bool,
+    /// A .tar.gz snapshot of the vcpkg git repository
+    pub file: String,
+}
+
 /// This command should merge into Ingest eventually
 #[derive(Debug, Parser)]
 pub struct AddRef {
@@ -278,6 +630,95 @@ pub struct AddRef {
     pub filename: Option<String>,
 }
 
+/// Manage the refs of packages
+#[derive(Debug, Parser)]
+pub struct Refs {
+    #[command(subcommand)]
+    pub subcommand: RefsCmd,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RefsCmd {
+    Rm(RefsRm),
+}
+
+/// Delete bogus refs of a package
+#[derive(Debug, Parser)]
+pub struct RefsRm {
+    #[arg(long)]
+    pub vendor: String,
+    #[arg(long)]
+    pub package: String,
+    /// Only delete the refs of this version
+    #[arg(long)]
+    pub version: Option<String>,
+    /// Only delete the refs pointing to this artifact
+    #[arg(long)]
+    pub chksum: Option<String>,
+    /// Only print the refs that would be deleted
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+/// Manage the aliases of artifacts
+#[derive(Debug, Parser)]
+pub struct Aliases {
+    #[command(subcommand)]
+    pub subcommand: AliasesCmd,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AliasesCmd {
+    Add(AliasesAdd),
+    Rm(AliasesRm),
+    Ls(AliasesLs),
+    Mv(AliasesMv),
+}
+
+/// Add an alias to an artifact
+#[derive(Debug, Parser)]
+pub struct AliasesAdd {
+    pub alias_from: String,
+    /// The canonical digest of the artifact
+    pub alias_to: String,
+    #[arg(long)]
+    pub reason: Option<String>,
+    /// Remove other aliases of `alias_from` so it only points to `alias_to`
+    #[arg(long)]
+    pub replace: bool,
+    /// Only print what would change
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+/// Remove an alias
+#[derive(Debug, Parser)]
+pub struct AliasesRm {
+    pub alias_from: String,
+    /// Only remove the alias pointing to this artifact
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Only print what would change
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+/// List the aliases from and to a digest
+#[derive(Debug, Parser)]
+pub struct AliasesLs {
+    pub chksum: String,
+}
+
+/// Re-point all aliases of an artifact to a different canonical digest
+#[derive(Debug, Parser)]
+pub struct AliasesMv {
+    pub from: String,
+    pub to: String,
+    /// Only print what would change
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
 /// Requeue all known urls
 #[derive(Debug, Parser)]
 pub struct ReindexUrl {
@@ -290,15 +731,162 @@ pub struct ReindexUrl {
     /// Only reindex items that haven't been imported the last X days
     #[arg(long)]
     pub age: Option<i64>,
+    #[command(flatten)]
+    pub filter_artifacts: ReindexFilter,
 }
 
-/// Reindex all known sboms
+/// Select the artifacts of a reindex job
+#[derive(Debug, Clone, ClapArgs)]
+pub struct ReindexFilter {
+    /// Only artifacts referenced by this vendor
+    #[arg(long)]
+    pub vendor: Option<String>,
+    /// Only artifacts first seen since this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Only artifacts with a checksum starting with this prefix, e.g. `sha256:00`
+    #[arg(long)]
+    pub chksum_prefix: Option<String>,
+    /// Store progress under this name and resume from it if the job was interrupted
+    #[arg(long)]
+    pub checkpoint: Option<String>,
+}
+
+/// Parse all known sboms again and refresh their packages, e.g. after a parser fix
 #[derive(Debug, Parser)]
 pub struct ReindexSbom {
-    /// Only queue sboms of this strain
+    /// Only reindex sboms of this strain
     #[arg(long)]
     pub strain: Option<String>,
-    /// Upper limit of tasks to schedule
+    /// Upper limit of sboms to reindex
     #[arg(long)]
     pub limit: Option<usize>,
 }
+
+/// Rebuild the index of file paths used by `path:` searches
+#[derive(Debug, Parser)]
+pub struct ReindexFiles {
+    /// Upper limit of artifacts to index
+    #[arg(long)]
+    pub limit: Option<usize>,
+    #[command(flatten)]
+    pub filter_artifacts: ReindexFilter,
+}
+
+/// Requeue the artifacts and sboms of specific packages, e.g. after an advisory
+#[derive(Debug, Parser)]
+pub struct ReindexPackages {
+    /// Read additional package names from this file, one per line
+    #[arg(long)]
+    pub file: Option<String>,
+    /// Upper limit of refs to consider, per package
+    #[arg(long, default_value = "500")]
+    pub limit: usize,
+    /// The package names, as used by any of the vendors
+    pub packages: Vec<String>,
+}
+
+/// Export a SLSA verification summary attestation for an artifact
+#[derive(Debug, Parser)]
+pub struct ExportVsa {
+    /// The identifier to use for the verifier and policy
+    #[arg(long, default_value = crate::vsa::DEFAULT_VERIFIER_ID)]
+    pub verifier_id: String,
+    /// The checksum of the artifact, or any of its aliases
+    pub chksum: String,
+}
+
+/// Verify stored data and archived blobs against their recorded digests
+#[derive(Debug, Parser)]
+pub struct Scrub {
+    /// Upper limit of items to check, per kind of data
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+/// Find and merge refs that only differ by version string formatting
+#[derive(Debug, Parser)]
+pub struct DedupRefs {
+    /// Only check refs of this vendor
+    #[arg(long)]
+    pub vendor: Option<String>,
+    /// Merge the duplicates instead of only reporting them
+    #[arg(long)]
+    pub apply: bool,
+}
+
+/// List artifacts held back by the quarantine policy, or approve/reject one
+#[derive(Debug, Parser)]
+pub struct ReviewQuarantine {
+    /// Publish the refs of this artifact
+    #[arg(long, group = "action")]
+    pub approve: Option<String>,
+    /// Keep this artifact hidden and stop listing it
+    #[arg(long, group = "action")]
+    pub reject: Option<String>,
+    /// Run the automated policy check again and release the artifacts that pass
+    #[arg(long, group = "action")]
+    pub recheck: bool,
+    /// Also list rejected artifacts
+    #[arg(long)]
+    pub all: bool,
+}
+
+/// Reset the error counter of failed tasks so they are retried
+#[derive(Debug, Parser)]
+pub struct Requeue {
+    /// Only requeue tasks with keys starting with this prefix, e.g. `source-rpm:*`
+    pub prefix: String,
+}
+
+/// Inspect and retry tasks that exceeded the retry limit
+#[derive(Debug, Parser)]
+pub struct Tasks {
+    #[command(subcommand)]
+    pub subcommand: TasksCmd,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TasksCmd {
+    ListFailed(TasksListFailed),
+    Retry(TasksRetry),
+    Prune(TasksPrune),
+}
+
+/// List the most recently failed tasks
+#[derive(Debug, Parser)]
+pub struct TasksListFailed {
+    /// Maximum number of tasks to list
+    #[arg(long, default_value = "100")]
+    pub limit: i64,
+}
+
+/// Move a failed task back into the queue
+#[derive(Debug, Parser)]
+pub struct TasksRetry {
+    /// The ids of the tasks, as shown by list-failed
+    #[arg(required = true)]
+    pub ids: Vec<i64>,
+}
+
+/// Drop tasks for versions that have been superseded by a newer one
+#[derive(Debug, Parser)]
+pub struct TasksPrune {
+    /// Also delete tasks that have been failed for this many days
+    #[arg(long)]
+    pub failed_days: Option<i64>,
+    /// Only list the tasks that would be marked as obsolete, without changing anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+/// Run the detection pass that produces the findings listed on /reports
+#[derive(Debug, Parser)]
+pub struct GenerateReports {
+    /// Only check release tarballs of this vendor (skips the lockfile checks)
+    #[arg(long)]
+    pub vendor: Option<String>,
+    /// Maximum number of artifacts to check per detection
+    #[arg(long, default_value = "1000")]
+    pub limit: i64,
+}
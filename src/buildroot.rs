@@ -0,0 +1,275 @@
+use crate::errors::*;
+use std::collections::HashMap;
+
+/// Nesting limit for variable expansion, protects against self-referencing variables
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub struct Artifact {
+    pub version: String,
+    pub url: String,
+    pub commit: Option<String>,
+    pub chksum: Option<String>,
+}
+
+/// The variable prefix buildroot derives from a package name, `xz-utils` becomes `XZ_UTILS`
+pub fn variable_prefix(package: &str) -> String {
+    package.to_uppercase().replace('-', "_")
+}
+
+/// Collect the variable assignments of a `.mk` file, the first assignment wins
+///
+/// Later assignments are usually inside of `ifeq` blocks for non-default configurations.
+pub fn parse_variables(mk: &str) -> HashMap<&str, &str> {
+    let mut vars = HashMap::new();
+    for line in mk.lines() {
+        if line.starts_with(|c: char| c.is_whitespace()) || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let Some(key) = key
+            .strip_suffix(':')
+            .or_else(|| (!key.ends_with(['+', '?'])).then_some(key))
+        else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            continue;
+        }
+        vars.entry(key).or_insert(value.trim());
+    }
+    vars
+}
+
+fn expand_call(vars: &HashMap<&str, &str>, call: &str, depth: usize) -> Result<String> {
+    let mut args = call.split(',').map(str::trim);
+    match args.next() {
+        Some("qstrip") => {
+            let value = args.collect::<Vec<_>>().join(",");
+            let value = expand(vars, &value, depth + 1)?;
+            Ok(value.trim().trim_matches('"').to_string())
+        }
+        Some("github") => {
+            let (Some(owner), Some(repo), Some(version)) = (args.next(), args.next(), args.next())
+            else {
+                return Err(Error::UnknownVariable(format!("call {call}")));
+            };
+            let owner = expand(vars, owner, depth + 1)?;
+            let repo = expand(vars, repo, depth + 1)?;
+            let version = expand(vars, version, depth + 1)?;
+            Ok(format!(
+                "https://github.com/{owner}/{repo}/archive/{version}"
+            ))
+        }
+        _ => Err(Error::UnknownVariable(format!("call {call}"))),
+    }
+}
+
+/// Expand `$(VAR)` references and the `github`/`qstrip` helper functions
+pub fn expand(vars: &HashMap<&str, &str>, mut text: &str, depth: usize) -> Result<String> {
+    if depth > MAX_DEPTH {
+        return Err(Error::UnknownVariable(text.to_string()));
+    }
+
+    let mut out = String::new();
+    while let Some((before, after)) = text.split_once("$(") {
+        out.push_str(before);
+
+        // find the matching closing parenthesis
+        let mut level = 1;
+        let Some(end) = after.find(|c| {
+            match c {
+                '(' => level += 1,
+                ')' => level -= 1,
+                _ => (),
+            }
+            level == 0
+        }) else {
+            return Err(Error::UnknownVariable(after.to_string()));
+        };
+        let name = &after[..end];
+        text = &after[end + 1..];
+
+        if let Some(call) = name.strip_prefix("call ") {
+            out.push_str(&expand_call(vars, call, depth)?);
+        } else {
+            let name = expand(vars, name, depth + 1)?;
+            let Some(value) = vars.get(name.as_str()) else {
+                return Err(Error::UnknownVariable(name));
+            };
+            out.push_str(&expand(vars, value, depth + 1)?);
+        }
+    }
+    out.push_str(text);
+
+    Ok(out)
+}
+
+/// Parse a `.hash` file into a map of filename to digest (`sha256:...`)
+pub fn parse_hashes(hash: &str) -> HashMap<&str, String> {
+    let mut hashes = HashMap::new();
+    for line in hash.lines() {
+        let line = line.split_once('#').map(|x| x.0).unwrap_or(line);
+        let mut parts = line.split_whitespace();
+        let (Some(algo), Some(digest), Some(filename)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        if matches!(algo, "sha256" | "sha512") {
+            hashes
+                .entry(filename)
+                .or_insert_with(|| format!("{algo}:{digest}"));
+        }
+    }
+    hashes
+}
+
+/// Resolve the upstream source of a package from its `.mk` and `.hash` file
+pub fn parse(package: &str, mk: &str, hash: &str) -> Result<Option<Artifact>> {
+    let prefix = variable_prefix(package);
+    let mut vars = parse_variables(mk);
+    vars.insert("PKG", &prefix);
+
+    let var = |name: &str| vars.get(format!("{prefix}_{name}").as_str()).copied();
+
+    let Some(version) = var("VERSION") else {
+        return Ok(None);
+    };
+    let version = expand(&vars, version, 0)?;
+
+    let Some(site) = var("SITE") else {
+        return Ok(None);
+    };
+    let site = expand(&vars, site, 0)?;
+
+    match var("SITE_METHOD") {
+        Some("git") => {
+            let is_commit = version.len() == 40 && version.bytes().all(|b| b.is_ascii_hexdigit());
+            if !is_commit {
+                return Ok(None);
+            }
+            let commit = version.to_lowercase();
+            Ok(Some(Artifact {
+                url: format!("git+{site}#commit={commit}"),
+                version,
+                commit: Some(commit),
+                chksum: None,
+            }))
+        }
+        Some(_) => Ok(None),
+        None => {
+            let source = match var("SOURCE") {
+                Some(source) => expand(&vars, source, 0)?,
+                None => format!("{package}-{version}.tar.gz"),
+            };
+
+            let Some(chksum) = parse_hashes(hash).remove(source.as_str()) else {
+                return Ok(None);
+            };
+
+            Ok(Some(Artifact {
+                url: format!("{}/{source}", site.trim_end_matches('/')),
+                version,
+                commit: None,
+                chksum: Some(chksum),
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xz() {
+        let mk = r#"################################################################################
+#
+# xz
+#
+################################################################################
+
+XZ_VERSION = 5.4.6
+XZ_SOURCE = xz-$(XZ_VERSION).tar.bz2
+XZ_SITE = https://github.com/tukaani-project/xz/releases/download/v$(XZ_VERSION)
+XZ_INSTALL_STAGING = YES
+XZ_CONF_ENV = ac_cv_prog_cc_c99='-std=gnu99'
+XZ_LICENSE = Public Domain, BSD-0-Clause, GPL-2.0+, GPL-3.0+, LGPL-2.1+
+XZ_LICENSE_FILES = COPYING COPYING.0BSD COPYING.GPLv2 COPYING.GPLv3 COPYING.LGPLv2.1
+XZ_CPE_ID_VENDOR = tukaani
+
+ifeq ($(BR2_PACKAGE_LIBICONV),y)
+XZ_DEPENDENCIES += libiconv
+endif
+
+$(eval $(autotools-package))
+$(eval $(host-autotools-package))
+"#;
+        let hash = r#"# Locally calculated after checking pgp signature
+sha256  913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49  xz-5.4.6.tar.bz2
+
+# Hash for license files
+sha256  72d7ef9c98d2da4d1a30a9d9b0d0b18c3ed1c1d3bb4d93a4b17ac0d2a2bb7b14  COPYING
+"#;
+        let artifact = parse("xz", mk, hash).unwrap();
+        assert_eq!(
+            artifact,
+            Some(Artifact {
+                version: "5.4.6".to_string(),
+                url: "https://github.com/tukaani-project/xz/releases/download/v5.4.6/xz-5.4.6.tar.bz2".to_string(),
+                commit: None,
+                chksum: Some("sha256:913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_github_helper() {
+        let mk = r#"LIBSPNG_VERSION = 0.7.4
+LIBSPNG_SITE = $(call github,randy408,libspng,v$(LIBSPNG_VERSION))
+LIBSPNG_LICENSE = BSD-2-Clause
+"#;
+        let hash = "sha256  47ec02be6c0a6323044600a9221b049f63e1953faf816903e7383d4dc4234487  libspng-0.7.4.tar.gz\n";
+        let artifact = parse("libspng", mk, hash).unwrap();
+        assert_eq!(
+            artifact,
+            Some(Artifact {
+                version: "0.7.4".to_string(),
+                url: "https://github.com/randy408/libspng/archive/v0.7.4/libspng-0.7.4.tar.gz"
+                    .to_string(),
+                commit: None,
+                chksum: Some(
+                    "sha256:47ec02be6c0a6323044600a9221b049f63e1953faf816903e7383d4dc4234487"
+                        .to_string()
+                ),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_git_commit() {
+        let mk = r#"LIBCAMERA_SITE = https://git.libcamera.org/libcamera/libcamera.git
+LIBCAMERA_VERSION = 9ff01da2a7e7a8cb45f0e6dd2e02b6d8b3ee07e0
+LIBCAMERA_SITE_METHOD = git
+"#;
+        let artifact = parse("libcamera", mk, "").unwrap();
+        assert_eq!(
+            artifact,
+            Some(Artifact {
+                version: "9ff01da2a7e7a8cb45f0e6dd2e02b6d8b3ee07e0".to_string(),
+                url: "git+https://git.libcamera.org/libcamera/libcamera.git#commit=9ff01da2a7e7a8cb45f0e6dd2e02b6d8b3ee07e0".to_string(),
+                commit: Some("9ff01da2a7e7a8cb45f0e6dd2e02b6d8b3ee07e0".to_string()),
+                chksum: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_variable() {
+        let mk = "FOO_VERSION = $(BAR_VERSION)\nFOO_SITE = https://example.com\n";
+        assert!(parse("foo", mk, "").is_err());
+    }
+}
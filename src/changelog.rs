@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Entry {
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub text: String,
+}
+
+/// Parse the most recent entry of a `debian/changelog`
+pub fn parse_debian(txt: &str) -> Option<Entry> {
+    let mut lines = txt.lines().skip_while(|line| line.trim().is_empty());
+
+    // xz-utils (5.6.1-1) unstable; urgency=medium
+    let header = lines.next()?;
+    let (package, rest) = header.split_once(" (")?;
+    let (version, _) = rest.split_once(')')?;
+
+    let mut entry = Entry {
+        package: Some(package.to_string()),
+        version: Some(version.to_string()),
+        ..Default::default()
+    };
+
+    let mut text = Vec::new();
+    for line in lines {
+        // -- Sebastian Andrzej Siewior <sebastian@breakpoint.cc>  Wed, 27 Mar 2024 22:53:21 +0100
+        if let Some(trailer) = line.strip_prefix(" -- ") {
+            if let Some((author, date)) = trailer.split_once("  ") {
+                entry.author = Some(author.to_string());
+                entry.date = Some(date.trim().to_string());
+            } else {
+                entry.author = Some(trailer.trim().to_string());
+            }
+            break;
+        }
+        text.push(line);
+    }
+
+    entry.text = text.join("\n").trim_matches('\n').to_string();
+    Some(entry)
+}
+
+/// Parse the most recent entry of the `%changelog` section of an rpm .spec file
+pub fn parse_rpm_spec(txt: &str) -> Option<Entry> {
+    let mut lines = txt
+        .lines()
+        .skip_while(|line| line.trim() != "%changelog")
+        .skip(1)
+        .skip_while(|line| line.trim().is_empty());
+
+    // * Wed Mar 27 2024 Richard W.M. Jones <rjones@redhat.com> - 5.6.1-1
+    let header = lines.next()?.strip_prefix('*')?.trim();
+    let (header, version) = match header.rsplit_once(" - ") {
+        Some((header, version)) => (header, Some(version.trim().to_string())),
+        None => (header, None),
+    };
+
+    // the date is always made up of 4 words, e.g. `Wed Mar 27 2024`
+    let words = header.splitn(5, ' ').collect::<Vec<_>>();
+    let (date, author) = if words.len() == 5 {
+        (Some(words[..4].join(" ")), Some(words[4].to_string()))
+    } else {
+        (None, Some(header.to_string()))
+    };
+
+    let mut text = Vec::new();
+    for line in lines {
+        if line.starts_with('*') || line.starts_with('%') {
+            break;
+        }
+        text.push(line);
+    }
+
+    Some(Entry {
+        package: None,
+        version,
+        author,
+        date,
+        text: text.join("\n").trim_matches('\n').to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_debian() {
+        let data = r#"xz-utils (5.6.1+really5.4.5-1) unstable; urgency=medium
+
+  * Temporarily revert to 5.4.5.
+
+ -- Salvatore Bonaccorso <carnil@debian.org>  Thu, 28 Mar 2024 22:35:08 +0100
+
+xz-utils (5.6.1-1) unstable; urgency=medium
+
+  * Non-maintainer upload.
+  * Import 5.6.1
+
+ -- Sebastian Andrzej Siewior <sebastian@breakpoint.cc>  Wed, 27 Mar 2024 22:53:21 +0100
+"#;
+        let entry = parse_debian(data).unwrap();
+        assert_eq!(
+            entry,
+            Entry {
+                package: Some("xz-utils".to_string()),
+                version: Some("5.6.1+really5.4.5-1".to_string()),
+                author: Some("Salvatore Bonaccorso <carnil@debian.org>".to_string()),
+                date: Some("Thu, 28 Mar 2024 22:35:08 +0100".to_string()),
+                text: "  * Temporarily revert to 5.4.5.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_spec() {
+        let data = r#"Name:           xz
+Version:        5.4.6
+Release:        3%{?dist}
+
+%files
+%license COPYING
+
+%changelog
+* Fri Mar 29 2024 Richard W.M. Jones <rjones@redhat.com> - 5.4.6-3
+- Revert to 5.4.6
+- https://www.openwall.com/lists/oss-security/2024/03/29/4
+
+* Wed Mar 27 2024 Richard W.M. Jones <rjones@redhat.com> - 5.6.1-1
+- New upstream version 5.6.1
+"#;
+        let entry = parse_rpm_spec(data).unwrap();
+        assert_eq!(
+            entry,
+            Entry {
+                package: None,
+                version: Some("5.4.6-3".to_string()),
+                author: Some("Richard W.M. Jones <rjones@redhat.com>".to_string()),
+                date: Some("Fri Mar 29 2024".to_string()),
+                text:
+                    "- Revert to 5.4.6\n- https://www.openwall.com/lists/oss-security/2024/03/29/4"
+                        .to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_spec_without_changelog() {
+        assert_eq!(parse_rpm_spec("Name: xz\nVersion: 5.4.6\n"), None);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::errors::*;
+use std::collections::HashMap;
+
+/// Mirror placeholders, as defined in `src/cbuild/core/template.py` of cports
+const SITES: &[(&str, &str)] = &[
+    ("$(FREEDESKTOP_SITE)", "https://freedesktop.org/software"),
+    ("$(GNOME_SITE)", "https://download.gnome.org/sources"),
+    ("$(GNU_SITE)", "https://ftp.gnu.org/gnu"),
+    ("$(KDE_SITE)", "https://download.kde.org/stable"),
+    ("$(KERNEL_SITE)", "https://www.kernel.org/pub/linux"),
+    (
+        "$(NONGNU_SITE)",
+        "https://download.savannah.nongnu.org/releases",
+    ),
+    (
+        "$(PYPI_SITE)",
+        "https://files.pythonhosted.org/packages/source",
+    ),
+    (
+        "$(SOURCEFORGE_SITE)",
+        "https://downloads.sourceforge.net/sourceforge",
+    ),
+    ("$(XORG_SITE)", "https://www.x.org/releases/individual"),
+];
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Str(String),
+    List(Vec<Value>),
+    /// Numbers, booleans and anything we don't evaluate
+    Other(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Template {
+    pub pkgname: String,
+    pub pkgver: String,
+    pub pkgrel: String,
+    pub sources: Vec<String>,
+    pub sha256: Vec<String>,
+}
+
+impl Template {
+    /// The version, as it shows up in the apk repository
+    pub fn version(&self) -> String {
+        format!("{}-r{}", self.pkgver, self.pkgrel)
+    }
+
+    /// Pair the urls with their checksum, like cbuild does
+    pub fn artifacts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sources
+            .iter()
+            .map(String::as_str)
+            .zip(self.sha256.iter().map(String::as_str))
+    }
+}
+
+/// Expand `{pkgver}` references of an f-string with the variables assigned so far
+fn interpolate(vars: &HashMap<String, String>, mut text: &str) -> Result<String> {
+    let mut out = String::new();
+    while let Some(idx) = text.find(['{', '}']) {
+        out.push_str(&text[..idx]);
+        let rest = &text[idx..];
+        if let Some(rest) = rest.strip_prefix("{{") {
+            out.push('{');
+            text = rest;
+        } else if let Some(rest) = rest.strip_prefix("}}") {
+            out.push('}');
+            text = rest;
+        } else if let Some(rest) = rest.strip_prefix('{') {
+            let Some((name, rest)) = rest.split_once('}') else {
+                return Err(Error::InvalidChimeraTemplate(text.to_string()));
+            };
+            let Some(value) = vars.get(name) else {
+                return Err(Error::UnknownVariable(name.to_string()));
+            };
+            out.push_str(value);
+            text = rest;
+        } else {
+            return Err(Error::InvalidChimeraTemplate(text.to_string()));
+        }
+    }
+    out.push_str(text);
+    Ok(out)
+}
+
+/// Parse a python literal, returns the value and the remaining input
+fn parse_value<'a>(vars: &HashMap<String, String>, txt: &'a str) -> Result<(Value, &'a str)> {
+    let txt = txt.trim_start();
+    if let Some(mut rest) = txt.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::List(items), rest));
+            }
+            let (item, after) = parse_value(vars, rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+            if rest.trim_start().is_empty() {
+                return Err(Error::InvalidChimeraTemplate(
+                    "Unterminated list".to_string(),
+                ));
+            }
+        }
+    }
+
+    let (format, rest) = match txt.strip_prefix('f') {
+        Some(rest) if rest.starts_with(['"', '\'']) => (true, rest),
+        _ => (false, txt),
+    };
+    if let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let rest = &rest[1..];
+        let Some(end) = rest.find(quote) else {
+            return Err(Error::InvalidChimeraTemplate(txt.to_string()));
+        };
+        let text = &rest[..end];
+        let text = if format {
+            interpolate(vars, text)?
+        } else {
+            text.to_string()
+        };
+        return Ok((Value::Str(text), &rest[end + 1..]));
+    }
+
+    let end = txt.find([',', ']']).unwrap_or(txt.len());
+    Ok((Value::Other(txt[..end].trim().to_string()), &txt[end..]))
+}
+
+fn expand_sites(url: &str) -> String {
+    let mut url = url.to_string();
+    for (placeholder, site) in SITES {
+        url = url.replace(placeholder, site);
+    }
+    url
+}
+
+fn strings(value: Value) -> Vec<String> {
+    match value {
+        Value::Str(s) => vec![s],
+        Value::List(items) => items.into_iter().flat_map(strings).collect(),
+        Value::Other(_) => Vec::new(),
+    }
+}
+
+/// Strip comments, `#` inside of strings is kept
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
+
+/// Read the top-level assignments of a cports `template.py`
+pub fn parse(script: &str) -> Result<Template> {
+    let mut vars = HashMap::new();
+    let mut template = Template::default();
+
+    let mut lines = script.lines();
+    while let Some(line) = lines.next() {
+        // functions and hooks follow after the metadata
+        if line.starts_with("def ") || line.starts_with('@') {
+            break;
+        }
+        if line.starts_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let Some((key, value)) = strip_comment(line).split_once(" = ") else {
+            continue;
+        };
+        let key = key.trim();
+        if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+
+        // lists may span multiple lines
+        let mut value = value.to_string();
+        while value.matches('[').count() > value.matches(']').count() {
+            let Some(line) = lines.next() else {
+                break;
+            };
+            value.push(' ');
+            value.push_str(strip_comment(line));
+        }
+
+        let value = match parse_value(&vars, &value) {
+            Ok((value, _)) => value,
+            Err(err) => {
+                debug!("Failed to evaluate {key:?} in template: {err:#}");
+                continue;
+            }
+        };
+        match (key, value) {
+            ("source", value) => {
+                template.sources = strings(value)
+                    .into_iter()
+                    .map(|url| {
+                        // `url>filename` renames the download
+                        let url = url.split_once('>').map(|x| x.0).unwrap_or(&url);
+                        expand_sites(url)
+                    })
+                    .collect();
+            }
+            ("sha256", value) => template.sha256 = strings(value),
+            (key, Value::Str(value) | Value::Other(value)) => {
+                match key {
+                    "pkgname" => template.pkgname = value.clone(),
+                    "pkgver" => template.pkgver = value.clone(),
+                    "pkgrel" => template.pkgrel = value.clone(),
+                    _ => (),
+                }
+                vars.insert(key.to_string(), value);
+            }
+            _ => (),
+        }
+    }
+
+    if template.pkgname.is_empty() || template.pkgver.is_empty() {
+        return Err(Error::InvalidChimeraTemplate(
+            "Missing pkgname or pkgver".to_string(),
+        ));
+    }
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let script = r#"pkgname = "xz"
+pkgver = "5.6.3"
+pkgrel = 0
+build_style = "gnu_configure"
+configure_args = ["--disable-nls"]
+hostmakedepends = ["pkgconf"]
+pkgdesc = "XZ compression utilities"
+license = "0BSD"
+url = "https://tukaani.org/xz"
+source = f"https://github.com/tukaani-project/xz/releases/download/v{pkgver}/xz-{pkgver}.tar.gz"
+sha256 = "b1d45295d3f71f25a4c9101bd7c8d16cb56348bbef3bbc738da0351e17c73317"
+
+
+@subpackage("liblzma")
+def _(self):
+    source = "not this"
+"#;
+        let template = parse(script).unwrap();
+        assert_eq!(
+            template,
+            Template {
+                pkgname: "xz".to_string(),
+                pkgver: "5.6.3".to_string(),
+                pkgrel: "0".to_string(),
+                sources: vec![
+                    "https://github.com/tukaani-project/xz/releases/download/v5.6.3/xz-5.6.3.tar.gz"
+                        .to_string()
+                ],
+                sha256: vec![
+                    "b1d45295d3f71f25a4c9101bd7c8d16cb56348bbef3bbc738da0351e17c73317".to_string()
+                ],
+            }
+        );
+        assert_eq!(template.version(), "5.6.3-r0");
+    }
+
+    #[test]
+    fn test_parse_multiple_sources() {
+        let script = r#"pkgname = "gcc"
+pkgver = "14.2.1"
+_patchver = "20241012"
+pkgrel = 3
+_commit = "9fbf9c4b0d0d"
+source = [
+    f"$(GNU_SITE)/gcc/gcc-{pkgver}/gcc-{pkgver}.tar.xz",
+    # the patches are versioned separately
+    f"https://github.com/chimera-linux/gcc-patches/archive/{_commit}.tar.gz>patches-{_patchver}.tar.gz",
+]
+source_paths = [".", "patches"]
+sha256 = [
+    "a7b39bc69cbf9e25826c5a60ab26477001f7c08d85cec04bc0e29cabed6f3cc9",
+    "1f9a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8",
+]
+"#;
+        let template = parse(script).unwrap();
+        assert_eq!(template.version(), "14.2.1-r3");
+        assert_eq!(
+            template.artifacts().collect::<Vec<_>>(),
+            [
+                (
+                    "https://ftp.gnu.org/gnu/gcc/gcc-14.2.1/gcc-14.2.1.tar.xz",
+                    "a7b39bc69cbf9e25826c5a60ab26477001f7c08d85cec04bc0e29cabed6f3cc9"
+                ),
+                (
+                    "https://github.com/chimera-linux/gcc-patches/archive/9fbf9c4b0d0d.tar.gz",
+                    "1f9a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_expression() {
+        let script = r#"pkgname = "foo"
+pkgver = "1.2.3"
+pkgrel = 0
+source = f"https://example.com/foo-{pkgver[:-2]}.tar.gz"
+sha256 = "00"
+"#;
+        let template = parse(script).unwrap();
+        assert_eq!(template.sources, Vec::<String>::new());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::errors::*;
 use blake2::Blake2b512;
 use digest::Digest;
 use sha2::{Sha256, Sha512};
@@ -68,3 +69,62 @@ pub fn sha256(data: &[u8]) -> String {
     sha256.update(data);
     format!("sha256:{}", hex::encode(sha256.finalize()))
 }
+
+/// Validate a `family:hexdigest` checksum, returns it with a lowercase digest
+pub fn normalize(chksum: &str) -> Result<String> {
+    let Some((family, digest)) = chksum.trim().split_once(':') else {
+        return Err(Error::InvalidChecksum(chksum.to_string()));
+    };
+
+    let family = family.to_ascii_lowercase();
+    let lengths: &[usize] = match family.as_str() {
+        "sha256" => &[64],
+        "sha512" | "blake2b" => &[128],
+        // sha1 and sha256 object format
+        "git" => &[40, 64],
+        "hg" => &[40],
+        // sha1 and sha3-256 artifact hashes
+        "fossil" => &[40, 64],
+        _ => return Err(Error::UnknownChecksumFamily(family)),
+    };
+
+    if !lengths.contains(&digest.len()) || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::InvalidChecksum(chksum.to_string()));
+    }
+
+    Ok(format!("{family}:{}", digest.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_checksum() {
+        assert_eq!(
+            normalize("sha256:56D9FC4585DA4F39BBC5C8EC953FB7962188FA5ED70B2DD5A19DC82DF997BA5E")
+                .unwrap(),
+            "sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e"
+        );
+        assert_eq!(
+            normalize(" git:7747534db4576db43eced4356859ef400351ca28\n").unwrap(),
+            "git:7747534db4576db43eced4356859ef400351ca28"
+        );
+        assert!(matches!(
+            normalize("sha256:56d9fc4585da4f39"),
+            Err(Error::InvalidChecksum(_))
+        ));
+        assert!(matches!(
+            normalize("sha256:zzd9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e"),
+            Err(Error::InvalidChecksum(_))
+        ));
+        assert!(matches!(
+            normalize("md5:e6a600fd5e1d9cbde2d983680233ad02"),
+            Err(Error::UnknownChecksumFamily(_))
+        ));
+        assert!(matches!(
+            normalize("56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e"),
+            Err(Error::InvalidChecksum(_))
+        ));
+    }
+}
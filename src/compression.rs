@@ -1,13 +1,35 @@
-use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder};
+use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
 use std::pin::Pin;
 use std::task::Poll;
-use tokio::io::{self, AsyncBufRead, AsyncRead, ReadBuf};
+use tokio::io::{self, AsyncBufRead, AsyncBufReadExt, AsyncRead, ReadBuf};
+
+/// Detect the compression of a stream from its first bytes, `None` means uncompressed
+pub fn detect(magic: &[u8]) -> Option<&'static str> {
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Some("gz")
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some("xz")
+    } else if magic.starts_with(b"BZh") {
+        Some("bz2")
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some("zst")
+    } else {
+        None
+    }
+}
+
+/// Peek at the start of a buffered stream to detect its compression, without consuming any data
+pub async fn sniff<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<&'static str>> {
+    let buf = reader.fill_buf().await?;
+    Ok(detect(buf))
+}
 
 pub enum Decompressor<R> {
     Plain(R),
     Gz(GzipDecoder<R>),
     Xz(XzDecoder<R>),
     Bz2(BzDecoder<R>),
+    Zstd(ZstdDecoder<R>),
 }
 
 impl<R: AsyncBufRead> Decompressor<R> {
@@ -25,12 +47,17 @@ impl<R: AsyncBufRead> Decompressor<R> {
         Decompressor::Bz2(decoder)
     }
 
+    pub fn zstd(reader: R) -> Self {
+        Decompressor::Zstd(ZstdDecoder::new(reader))
+    }
+
     pub fn into_inner(self) -> R {
         match self {
             Decompressor::Plain(r) => r,
             Decompressor::Gz(r) => r.into_inner(),
             Decompressor::Xz(r) => r.into_inner(),
             Decompressor::Bz2(r) => r.into_inner(),
+            Decompressor::Zstd(r) => r.into_inner(),
         }
     }
 }
@@ -46,6 +73,57 @@ impl<R: AsyncBufRead + Unpin> AsyncRead for Decompressor<R> {
             Decompressor::Gz(r) => Pin::new(r).poll_read(cx, buf),
             Decompressor::Xz(r) => Pin::new(r).poll_read(cx, buf),
             Decompressor::Bz2(r) => Pin::new(r).poll_read(cx, buf),
+            Decompressor::Zstd(r) => Pin::new(r).poll_read(cx, buf),
         }
     }
 }
+
+/// Enter a span on every read, so the time spent in the decompressor shows up in traces
+pub struct Traced<R> {
+    reader: R,
+    span: tracing::Span,
+}
+
+impl<R> Traced<R> {
+    pub fn new(reader: R, span: tracing::Span) -> Self {
+        Traced { reader, span }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Traced<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let _enter = this.span.enter();
+        Pin::new(&mut this.reader).poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(detect(&[0x1f, 0x8b, 0x08, 0x00]), Some("gz"));
+        assert_eq!(detect(b"\xfd7zXZ\x00\x00\x04"), Some("xz"));
+        assert_eq!(detect(b"BZh91AY&SY"), Some("bz2"));
+        assert_eq!(detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x04]), Some("zst"));
+        assert_eq!(detect(b"foo-1.0/\0\0\0\0"), None);
+        assert_eq!(detect(b""), None);
+    }
+
+    #[tokio::test]
+    async fn test_sniff_does_not_consume() {
+        let mut reader = io::BufReader::new(&[0x1f, 0x8b, 0x08, 0x00][..]);
+        assert_eq!(sniff(&mut reader).await.unwrap(), Some("gz"));
+        assert_eq!(reader.buffer(), [0x1f, 0x8b, 0x08, 0x00]);
+    }
+}
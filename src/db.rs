@@ -1,20 +1,38 @@
+use crate::analyze::totals::Totals;
+use crate::changelog;
 use crate::chksums;
 use crate::chksums::Checksums;
 use crate::errors::*;
 use crate::ingest;
+use crate::purl::Purl;
+use crate::reports;
 use crate::sbom;
 use futures::Stream;
 use futures::TryStreamExt;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use sqlx::postgres::{PgPoolOptions, Postgres};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, Postgres};
 use sqlx::types::chrono::{DateTime, Utc};
-use sqlx::Pool;
 use sqlx::Row;
+use sqlx::{Pool, Transaction};
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::io::{Read, Write};
+use std::str::FromStr;
+use std::time::Duration;
 
 const RETRY_LIMIT: i64 = 5;
+/// Rows per multi-row INSERT, keeps the bind arrays at a reasonable size
+const INSERT_BATCH_SIZE: usize = 1000;
+
+// sqlx currently does not support just putting `migrations` here
+static MIGRATOR: Migrator = sqlx::migrate!("db/migrations");
+
+/// The version of the most recent migration this binary ships with
+pub fn latest_migration_version() -> Option<i64> {
+    MIGRATOR.iter().map(|m| m.version).max()
+}
 
 fn compress_json<W: Write, T: Serialize + ?Sized>(writer: W, obj: &T) -> Result<()> {
     let mut writer = lz4_flex::frame::FrameEncoder::new(writer);
@@ -29,52 +47,256 @@ fn decompress_json<R: Read, T: DeserializeOwned>(reader: R) -> Result<T> {
     Ok(obj)
 }
 
+/// Trigram indexes can't narrow down a substring search on shorter terms
+const SUBSTRING_SEARCH_MIN_LEN: usize = 3;
+
+/// Whether a substring search has enough characters in a row to use the trigram indexes
+fn is_substring_searchable(search: &str) -> bool {
+    search
+        .split('*')
+        .any(|part| part.chars().count() >= SUBSTRING_SEARCH_MIN_LEN)
+}
+
+/// Escape the wildcards of a `LIKE` pattern, so user input matches literally
+fn escape_like(txt: &str) -> String {
+    let mut out = String::with_capacity(txt.len());
+    for c in txt.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+async fn replace_artifact_files(
+    tx: &mut Transaction<'_, Postgres>,
+    chksum: &str,
+    files: &[ingest::tar::Entry],
+) -> Result<()> {
+    let paths = files
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect::<Vec<_>>();
+    let digests = files
+        .iter()
+        .map(|entry| entry.digest.as_deref())
+        .collect::<Vec<_>>();
+
+    sqlx::query(
+        "DELETE FROM artifact_files
+        WHERE chksum = $1",
+    )
+    .bind(chksum)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO artifact_files (chksum, path, digest)
+        SELECT $1, f.path, f.digest FROM UNNEST($2::varchar[], $3::varchar[]) AS f(path, digest)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(chksum)
+    .bind(&paths)
+    .bind(&digests)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+const DEFAULT_APPLICATION_NAME: &str = "what-the-src";
+
+/// Tuning of the connection pool, the web frontend and workers have very different workloads
+///
+/// Configured with `WHATSRC_DB_MAX_CONNECTIONS`, `WHATSRC_DB_ACQUIRE_TIMEOUT` and
+/// `WHATSRC_DB_STATEMENT_TIMEOUT` (both in seconds) and `WHATSRC_DB_APPLICATION_NAME`.
+#[derive(Debug, PartialEq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub acquire_timeout: Option<Duration>,
+    pub statement_timeout: Option<Duration>,
+    pub application_name: Option<String>,
+}
+
+impl PoolConfig {
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|key| {
+            env::var(key)
+                .ok()
+                .filter(|value: &String| !value.is_empty())
+        })
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(var: F) -> Result<Self> {
+        let seconds = |key, err| {
+            var(key)
+                .map(|v| v.parse().map(Duration::from_secs).map_err(|_| err))
+                .transpose()
+        };
+        Ok(PoolConfig {
+            max_connections: var("WHATSRC_DB_MAX_CONNECTIONS")
+                .map(|v| v.parse())
+                .transpose()
+                .map_err(|_| {
+                    Error::InvalidDatabaseConfig("WHATSRC_DB_MAX_CONNECTIONS is not a number")
+                })?
+                .unwrap_or(DEFAULT_MAX_CONNECTIONS),
+            acquire_timeout: seconds(
+                "WHATSRC_DB_ACQUIRE_TIMEOUT",
+                Error::InvalidDatabaseConfig(
+                    "WHATSRC_DB_ACQUIRE_TIMEOUT is not a number of seconds",
+                ),
+            )?,
+            statement_timeout: seconds(
+                "WHATSRC_DB_STATEMENT_TIMEOUT",
+                Error::InvalidDatabaseConfig(
+                    "WHATSRC_DB_STATEMENT_TIMEOUT is not a number of seconds",
+                ),
+            )?,
+            application_name: var("WHATSRC_DB_APPLICATION_NAME"),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Client {
     pool: Pool<Postgres>,
+    /// Used for the read-only queries of the web frontend, same as `pool` unless a replica is configured
+    read_pool: Pool<Postgres>,
+}
+
+async fn connect(database_url: &str, config: &PoolConfig) -> Result<Pool<Postgres>> {
+    let mut options = PgConnectOptions::from_str(database_url)?;
+    // an application_name from the url takes precedence over the default
+    if let Some(name) = &config.application_name {
+        options = options.application_name(name);
+    } else if options.get_application_name().is_none() {
+        options = options.application_name(DEFAULT_APPLICATION_NAME);
+    }
+    if let Some(timeout) = config.statement_timeout {
+        options = options.options([("statement_timeout", format!("{}s", timeout.as_secs()))]);
+    }
+
+    let mut pool = PgPoolOptions::new().max_connections(config.max_connections);
+    if let Some(timeout) = config.acquire_timeout {
+        pool = pool.acquire_timeout(timeout);
+    }
+    let pool = pool.connect_with(options).await?;
+    Ok(pool)
 }
 
 impl Client {
     pub async fn create() -> Result<Self> {
         let database_url = env::var("DATABASE_URL").unwrap();
+        let config = PoolConfig::from_env()?;
 
         debug!("Connecting to database...");
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
-            .await?;
+        let pool = connect(&database_url, &config).await?;
 
-        // sqlx currently does not support just putting `migrations` here
-        sqlx::migrate!("db/migrations").run(&pool).await?;
+        MIGRATOR.run(&pool).await?;
         debug!("Database has been setup");
 
-        Ok(Client { pool })
+        Ok(Client {
+            read_pool: pool.clone(),
+            pool,
+        })
+    }
+
+    /// Send search, artifact and stats queries to `DATABASE_READ_URL`, if configured
+    ///
+    /// Writes always go to the primary, this is only meant for the web frontend.
+    pub async fn with_read_replica(mut self) -> Result<Self> {
+        let Some(database_url) = env::var("DATABASE_READ_URL")
+            .ok()
+            .filter(|value| !value.is_empty())
+        else {
+            return Ok(self);
+        };
+        let config = PoolConfig::from_env()?;
+
+        debug!("Connecting to read replica...");
+        self.read_pool = connect(&database_url, &config).await?;
+        Ok(self)
     }
 
-    pub async fn insert_artifact(&self, chksum: &str, files: &[ingest::tar::Entry]) -> Result<()> {
+    /// Write everything we learned about an archive in one transaction
+    ///
+    /// The artifact only becomes visible together with its aliases and sbom refs, if the worker
+    /// dies halfway through the task is retried and nothing is left behind.
+    #[tracing::instrument(skip_all, fields(chksum = %import.chksum, files = import.files.len()))]
+    pub async fn insert_import(&self, import: &Import<'_>) -> Result<()> {
         let mut buf = Vec::new();
-        compress_json(&mut buf, files)?;
+        compress_json(&mut buf, import.files)?;
+        let totals = serde_json::to_value(import.totals)?;
 
-        let _result = sqlx::query(
-            "INSERT INTO artifacts (chksum, last_imported, files_compressed)
-            VALUES ($1, now(), $2)
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO artifacts (chksum, last_imported, files_compressed, totals)
+            VALUES ($1, now(), $2, $3)
             ON CONFLICT (chksum) DO UPDATE SET
             last_imported = EXCLUDED.last_imported,
             files = null,
-            files_compressed = EXCLUDED.files_compressed
+            files_compressed = EXCLUDED.files_compressed,
+            totals = EXCLUDED.totals
             ",
         )
-        .bind(chksum)
+        .bind(&import.chksum)
         .bind(&buf)
-        .execute(&self.pool)
+        .bind(&totals)
+        .execute(&mut *tx)
         .await?;
+
+        replace_artifact_files(&mut tx, &import.chksum, import.files).await?;
+
+        for (alias_from, reason) in &import.aliases {
+            sqlx::query(
+                "INSERT INTO aliases (alias_from, alias_to, reason)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (alias_from, alias_to) DO UPDATE SET
+                reason = COALESCE(EXCLUDED.reason, aliases.reason)",
+            )
+            .bind(alias_from)
+            .bind(&import.chksum)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for sbom in import.sbom_refs {
+            sqlx::query(
+                "INSERT INTO sbom_refs (from_archive, sbom_strain, sbom_chksum, path)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(&import.chksum)
+            .bind(sbom.strain)
+            .bind(&sbom.chksum)
+            .bind(&sbom.path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace the file paths and digests that are searchable for this artifact
+    #[tracing::instrument(skip_all, fields(chksum = %chksum, files = files.len()))]
+    pub async fn insert_artifact_files(
+        &self,
+        chksum: &str,
+        files: &[ingest::tar::Entry],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        replace_artifact_files(&mut tx, chksum, files).await?;
+        tx.commit().await?;
         Ok(())
     }
 
     pub async fn get_artifact(&self, chksum: &str) -> Result<Option<Artifact>> {
         let result = sqlx::query_as::<_, Artifact>("SELECT * FROM artifacts WHERE chksum = $1")
             .bind(chksum)
-            .fetch_optional(&self.pool)
+            .fetch_optional(&self.read_pool)
             .await?;
         Ok(result)
     }
@@ -99,21 +321,71 @@ impl Client {
         Ok(())
     }
 
-    pub async fn register_chksums_aliases(
-        &self,
-        chksums: &Checksums,
-        canonical: &str,
-        label: &str,
-    ) -> Result<()> {
-        if chksums.sha256 != canonical {
-            self.insert_alias_from_to(&chksums.sha256, canonical, &format!("sha256({label})"))
-                .await?;
+    /// Delete aliases of `alias_from`, either all of them or only the one pointing to `alias_to`
+    pub async fn delete_aliases(&self, alias_from: &str, alias_to: Option<&str>) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM aliases
+            WHERE alias_from = $1
+            AND ($2::varchar IS NULL OR alias_to = $2)",
+        )
+        .bind(alias_from)
+        .bind(alias_to)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn get_aliases_from(&self, alias_from: &str) -> Result<Vec<Alias>> {
+        let mut result = sqlx::query_as::<_, Alias>(
+            "SELECT *
+            FROM aliases
+            WHERE alias_from = $1
+            ORDER BY alias_to ASC",
+        )
+        .bind(alias_from)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
         }
-        self.insert_alias_from_to(&chksums.sha512, canonical, &format!("sha512({label})"))
-            .await?;
-        self.insert_alias_from_to(&chksums.blake2b, canonical, &format!("blake2b({label})"))
-            .await?;
-        Ok(())
+        Ok(rows)
+    }
+
+    /// Point all aliases of `from` to `to` instead, an alias from `to` to `from` is dropped
+    pub async fn move_aliases(&self, from: &str, to: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        // aliases that already exist for the new target
+        sqlx::query(
+            "DELETE FROM aliases a
+            WHERE a.alias_to = $1
+            AND EXISTS (
+                SELECT 1 FROM aliases b
+                WHERE b.alias_from = a.alias_from AND b.alias_to = $2
+            )",
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+        let result = sqlx::query(
+            "UPDATE aliases
+            SET alias_to = $2
+            WHERE alias_to = $1 AND alias_from != $2",
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM aliases
+            WHERE alias_to = $1",
+        )
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected())
     }
 
     pub async fn get_artifact_alias(&self, chksum: &str) -> Result<Option<Alias>> {
@@ -123,19 +395,20 @@ impl Client {
             WHERE alias_from = $1",
         )
         .bind(chksum)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
         Ok(result)
     }
 
-    pub async fn resolve_artifact(&self, chksum: &str) -> Result<Option<Artifact>> {
-        let result = sqlx::query_as::<_, Artifact>(
-            "SELECT a.*
+    /// Resolve a checksum to the canonical checksum of an ingested artifact, without loading its files
+    pub async fn resolve_artifact_chksum(&self, chksum: &str) -> Result<Option<String>> {
+        let result = sqlx::query_scalar(
+            "SELECT a.chksum
             FROM artifacts a
             LEFT JOIN aliases x ON x.alias_to = a.chksum
             WHERE x.alias_from = $1
             UNION ALL
-            SELECT a.*
+            SELECT a.chksum
             FROM artifacts a
             WHERE a.chksum = $1",
         )
@@ -145,127 +418,2097 @@ impl Client {
         Ok(result)
     }
 
-    pub async fn insert_ref(&self, obj: &Ref) -> Result<()> {
+    pub async fn insert_attestation(&self, obj: &NewAttestation) -> Result<()> {
         let _result = sqlx::query(
-            "INSERT INTO refs (chksum, vendor, package, version, filename, last_seen)
-            VALUES ($1, $2, $3, $4, $5, now())
-            ON CONFLICT (chksum, vendor, package, version) DO UPDATE SET
-            last_seen = EXCLUDED.last_seen,
-            filename = COALESCE(EXCLUDED.filename, refs.filename)",
+            "INSERT INTO attestations (chksum, digest, subject_name, predicate_type, builder_id, build_type, source_uri, statement)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING",
         )
         .bind(&obj.chksum)
-        .bind(&obj.vendor)
-        .bind(&obj.package)
-        .bind(&obj.version)
-        .bind(&obj.filename)
+        .bind(&obj.digest)
+        .bind(&obj.subject_name)
+        .bind(&obj.predicate_type)
+        .bind(&obj.builder_id)
+        .bind(&obj.build_type)
+        .bind(&obj.source_uri)
+        .bind(&obj.statement)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    pub async fn get_ref(
-        &self,
-        chksum: &str,
-        vendor: &str,
-        package: &str,
-        version: &str,
-    ) -> Result<Option<Ref>> {
-        let result = sqlx::query_as(
+    pub async fn get_attestations(&self, chksum: &str) -> Result<Vec<Attestation>> {
+        let mut result = sqlx::query_as::<_, Attestation>(
             "SELECT *
-            FROM refs
+            FROM attestations
             WHERE chksum = $1
-            AND vendor = $2
-            AND package = $3
-            AND version = $4",
+            ORDER BY first_seen ASC",
         )
         .bind(chksum)
-        .bind(vendor)
-        .bind(package)
-        .bind(version)
-        .fetch_optional(&self.pool)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Record the signing key a vendor lists for the upstream signature of a ref
+    pub async fn insert_ref_signer(&self, r: &Ref, fingerprint: &str) -> Result<()> {
+        let chksum = chksums::normalize(&r.chksum)?;
+        let _result = sqlx::query(
+            "INSERT INTO ref_signers (chksum, vendor, package, version, fingerprint)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(&chksum)
+        .bind(&r.vendor)
+        .bind(&r.package)
+        .bind(&r.version)
+        .bind(fingerprint)
+        .execute(&self.pool)
         .await?;
-        Ok(result)
+        Ok(())
     }
 
-    pub async fn get_named_ref(
-        &self,
-        vendor: &str,
-        package: &str,
-        version: &str,
-    ) -> Result<Option<Ref>> {
-        let result = sqlx::query_as(
+    pub async fn get_ref_signers(&self, chksums: &[String]) -> Result<Vec<RefSigner>> {
+        let mut result = sqlx::query_as::<_, RefSigner>(
             "SELECT *
-            FROM refs
-            WHERE vendor = $1
-            AND package = $2
-            AND version = $3",
+            FROM ref_signers
+            WHERE chksum = ANY($1)
+            ORDER BY vendor ASC, fingerprint ASC",
         )
-        .bind(vendor)
-        .bind(package)
-        .bind(version)
-        .fetch_optional(&self.pool)
+        .bind(chksums)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_signature(&self, obj: &NewSignature) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO signatures (chksum, url, signature_url, kind, status, signer)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (chksum, signature_url) DO UPDATE
+            SET url = EXCLUDED.url,
+                kind = EXCLUDED.kind,
+                status = EXCLUDED.status,
+                signer = EXCLUDED.signer,
+                verified_at = now()",
+        )
+        .bind(&obj.chksum)
+        .bind(&obj.url)
+        .bind(&obj.signature_url)
+        .bind(&obj.kind)
+        .bind(&obj.status)
+        .bind(&obj.signer)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_signatures(&self, chksum: &str) -> Result<Vec<Signature>> {
+        let mut result = sqlx::query_as::<_, Signature>(
+            "SELECT *
+            FROM signatures
+            WHERE chksum = $1
+            ORDER BY verified_at ASC",
+        )
+        .bind(chksum)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn has_blob(&self, chksum: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1
+                FROM blobs
+                WHERE chksum = $1
+            )",
+        )
+        .bind(chksum)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(exists)
+    }
+
+    /// Number and total size of the files that have their content stored
+    pub async fn get_blob(&self, chksum: &str) -> Result<Option<Blob>> {
+        let blob = sqlx::query_as::<_, Blob>(
+            "SELECT *
+            FROM blobs
+            WHERE chksum = $1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(blob)
+    }
+
+    pub fn get_all_blobs(&self) -> impl Stream<Item = Result<Blob>> {
+        let pool = self.pool.clone();
+        async_stream::stream! {
+            let mut result = sqlx::query_as::<_, Blob>(
+                "SELECT *
+                FROM blobs
+                ORDER BY first_seen ASC",
+            )
+            .fetch(&pool);
+
+            while let Some(row) = result.try_next().await? {
+                yield Ok(row);
+            }
+        }
+    }
+
+    pub async fn get_blobs_size(&self, chksums: &[String]) -> Result<(i64, i64)> {
+        let result = sqlx::query_as(
+            "SELECT count(*), coalesce(sum(size), 0)::bigint
+            FROM blobs
+            WHERE chksum = ANY($1)",
+        )
+        .bind(chksums)
+        .fetch_one(&self.read_pool)
         .await?;
         Ok(result)
     }
 
-    pub async fn bump_named_refs(&self, vendor: &str, package: &str, version: &str) -> Result<()> {
+    pub async fn insert_blob(&self, chksum: &str, size: i64, compressed_size: i64) -> Result<()> {
         let _result = sqlx::query(
-            "UPDATE refs
-            SET last_seen = now()
-            WHERE vendor = $1
-            AND package = $2
-            AND version = $3",
+            "INSERT INTO blobs (chksum, size, compressed_size)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING",
         )
-        .bind(vendor)
-        .bind(package)
-        .bind(version)
+        .bind(chksum)
+        .bind(size)
+        .bind(compressed_size)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    pub async fn get_all_refs_for(&self, chksum: &str) -> Result<Vec<RefView>> {
-        let mut result = sqlx::query_as::<_, Ref>(
+    pub async fn get_aliases_to(&self, chksum: &str) -> Result<Vec<Alias>> {
+        let mut result = sqlx::query_as::<_, Alias>(
             "SELECT *
-            FROM (
-                SELECT refs.*
-                FROM refs
-                WHERE chksum = $1
+            FROM aliases
+            WHERE alias_to = $1
+            ORDER BY alias_from ASC",
+        )
+        .bind(chksum)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn resolve_artifact(&self, chksum: &str) -> Result<Option<Artifact>> {
+        let result = sqlx::query_as::<_, Artifact>(
+            "SELECT a.*
+            FROM artifacts a
+            LEFT JOIN aliases x ON x.alias_to = a.chksum
+            WHERE x.alias_from = $1
+            UNION ALL
+            SELECT a.*
+            FROM artifacts a
+            WHERE a.chksum = $1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Resolve many checksums at once, returns the artifact each known checksum points to
+    pub async fn resolve_artifacts_bulk(
+        &self,
+        chksums: &[String],
+    ) -> Result<HashMap<String, String>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            "SELECT x.alias_from, a.chksum
+            FROM artifacts a
+            JOIN aliases x ON x.alias_to = a.chksum
+            WHERE x.alias_from = ANY($1)
+            UNION ALL
+            SELECT a.chksum, a.chksum
+            FROM artifacts a
+            WHERE a.chksum = ANY($1)",
+        )
+        .bind(chksums)
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Find a `git archive` snapshot of the same vendor/package/version as this artifact
+    pub async fn get_vcs_snapshot_for(&self, chksum: &str) -> Result<Option<Artifact>> {
+        let result = sqlx::query_as::<_, Artifact>(
+            "SELECT a.*
+            FROM refs r1
+            JOIN refs r2 ON r2.vendor = r1.vendor
+                AND r2.package = r1.package
+                AND r2.version = r1.version
+            JOIN aliases x ON x.alias_from = r2.chksum
+            JOIN artifacts a ON a.chksum = x.alias_to
+            WHERE x.reason = 'git-archive'
+            AND a.chksum <> $1
+            AND r1.chksum IN (
+                SELECT $1
                 UNION
-                SELECT refs.*
-                FROM refs
-                LEFT JOIN aliases x ON x.alias_from = refs.chksum
-                WHERE x.alias_to = $1
+                SELECT alias_from FROM aliases WHERE alias_to = $1
+            )
+            LIMIT 1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Insert or bump a ref, a new checksum for a file we already know of is reported
+    pub async fn insert_ref(&self, obj: &Ref) -> Result<()> {
+        self.insert_refs(std::slice::from_ref(obj)).await
+    }
+
+    /// Insert many refs with one query per batch, refs that show up twice are only inserted once
+    #[tracing::instrument(skip_all, fields(refs = refs.len()))]
+    pub async fn insert_refs(&self, refs: &[Ref]) -> Result<()> {
+        let mut seen = HashSet::new();
+        let mut unique = Vec::new();
+        for obj in refs {
+            let chksum = chksums::normalize(&obj.chksum)?;
+            if seen.insert((chksum.clone(), &obj.vendor, &obj.package, &obj.version)) {
+                unique.push(Ref {
+                    chksum,
+                    ..obj.clone()
+                });
+            }
+        }
+
+        for batch in unique.chunks(INSERT_BATCH_SIZE) {
+            let inserted: Vec<(String, String, String, String, bool)> = sqlx::query_as(
+                "INSERT INTO refs (chksum, vendor, package, version, filename, last_seen)
+                SELECT chksum, vendor, package, version, filename, now()
+                FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::text[])
+                    WITH ORDINALITY AS t(chksum, vendor, package, version, filename, idx)
+                ORDER BY idx
+                ON CONFLICT (chksum, vendor, package, version) DO UPDATE SET
+                last_seen = EXCLUDED.last_seen,
+                filename = COALESCE(EXCLUDED.filename, refs.filename)
+                RETURNING chksum, vendor, package, version, (xmax = 0) AS inserted",
+            )
+            .bind(batch.iter().map(|r| r.chksum.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.vendor.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.package.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.version.as_str()).collect::<Vec<_>>())
+            .bind(
+                batch
+                    .iter()
+                    .map(|r| r.filename.as_deref())
+                    .collect::<Vec<_>>(),
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let inserted = inserted
+                .into_iter()
+                .filter(|row| row.4)
+                .map(|(chksum, vendor, package, version, _)| (chksum, vendor, package, version))
+                .collect::<HashSet<_>>();
+            for obj in batch {
+                let Some(filename) = obj.filename.as_deref() else {
+                    continue;
+                };
+                let key = (
+                    obj.chksum.clone(),
+                    obj.vendor.clone(),
+                    obj.package.clone(),
+                    obj.version.clone(),
+                );
+                if inserted.contains(&key) {
+                    self.check_checksum_change(obj, filename).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_checksum_change(&self, obj: &Ref, filename: &str) -> Result<()> {
+        let chksum = &obj.chksum;
+        if let Some(previous) = self.get_previous_chksum(obj, chksum, filename).await? {
+            warn!(
+                "Checksum of {filename:?} changed for vendor={:?} package={:?} version={:?}: {previous:?} -> {chksum:?}",
+                obj.vendor, obj.package, obj.version
+            );
+            self.insert_report(&reports::checksum_change(obj, &previous, chksum, filename))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Another checksum that was recorded for the same file of this vendor/package/version
+    ///
+    /// Versions often have multiple artifacts, so only refs with the same filename are compared.
+    async fn get_previous_chksum(
+        &self,
+        obj: &Ref,
+        chksum: &str,
+        filename: &str,
+    ) -> Result<Option<String>> {
+        let previous = sqlx::query_scalar(
+            "SELECT chksum
+            FROM refs
+            WHERE vendor = $1
+            AND package = $2
+            AND version = $3
+            AND filename = $4
+            AND chksum <> $5
+            ORDER BY last_seen DESC
+            LIMIT 1",
+        )
+        .bind(&obj.vendor)
+        .bind(&obj.package)
+        .bind(&obj.version)
+        .bind(filename)
+        .bind(chksum)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(previous)
+    }
+
+    pub async fn get_ref(
+        &self,
+        chksum: &str,
+        vendor: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<Ref>> {
+        let result = sqlx::query_as(
+            "SELECT *
+            FROM refs
+            WHERE chksum = $1
+            AND vendor = $2
+            AND package = $3
+            AND version = $4",
+        )
+        .bind(chksum)
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn get_named_ref(
+        &self,
+        vendor: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<Ref>> {
+        let result = sqlx::query_as(
+            "SELECT *
+            FROM refs
+            WHERE vendor = $1
+            AND package = $2
+            AND version = $3",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Mark the refs of a package as seen and report if this package still needs to be imported
+    ///
+    /// The `revision` is what the import is tracked by, usually the version, but some vendors
+    /// track the commit of their build instructions instead.
+    pub async fn refresh_package(
+        &self,
+        vendor: &str,
+        package: &str,
+        version: &str,
+        revision: &str,
+    ) -> Result<bool> {
+        let changed = sqlx::query_scalar(
+            "WITH bumped AS (
+                UPDATE refs
+                SET last_seen = now()
+                WHERE vendor = $1
+                AND package = $2
+                AND version = $3
+            )
+            SELECT NOT EXISTS (
+                SELECT 1
+                FROM packages
+                WHERE vendor = $1
+                AND package = $2
+                AND version = $4
+            )",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .bind(revision)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(changed)
+    }
+
+    /// Like `refresh_package` for many packages at once, with the version also used as revision
+    ///
+    /// Returns one bool per `(package, version)`, in the same order.
+    pub async fn refresh_packages(
+        &self,
+        vendor: &str,
+        packages: &[(String, String)],
+    ) -> Result<Vec<bool>> {
+        let mut changed = Vec::with_capacity(packages.len());
+        for batch in packages.chunks(INSERT_BATCH_SIZE) {
+            let result: Vec<bool> = sqlx::query_scalar(
+                "WITH input AS (
+                    SELECT *
+                    FROM UNNEST($2::text[], $3::text[]) WITH ORDINALITY AS t(package, version, idx)
+                ), bumped AS (
+                    UPDATE refs r
+                    SET last_seen = now()
+                    FROM input i
+                    WHERE r.vendor = $1
+                    AND r.package = i.package
+                    AND r.version = i.version
+                )
+                SELECT NOT EXISTS (
+                    SELECT 1
+                    FROM packages p
+                    WHERE p.vendor = $1
+                    AND p.package = i.package
+                    AND p.version = i.version
+                )
+                FROM input i
+                ORDER BY i.idx",
+            )
+            .bind(vendor)
+            .bind(batch.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, v)| v.as_str()).collect::<Vec<_>>())
+            .fetch_all(&self.pool)
+            .await?;
+            changed.extend(result);
+        }
+        Ok(changed)
+    }
+
+    pub async fn find_refs(
+        &self,
+        vendor: &str,
+        package: &str,
+        version: Option<&str>,
+        chksum: Option<&str>,
+    ) -> Result<Vec<RefRecord>> {
+        let mut result = sqlx::query_as::<_, RefRecord>(
+            "SELECT *
+            FROM refs
+            WHERE vendor = $1 AND package = $2
+            AND ($3::varchar IS NULL OR version = $3)
+            AND ($4::varchar IS NULL OR chksum = $4)
+            ORDER BY id ASC",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .bind(chksum)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn delete_refs(&self, ids: &[i64]) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM refs
+            WHERE id = ANY($1)",
+        )
+        .bind(ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn get_all_refs_for(&self, chksum: &str) -> Result<Vec<RefView>> {
+        self.get_all_refs_for_at(chksum, None).await
+    }
+
+    /// Only return refs that were already known at the given point in time
+    pub async fn get_all_refs_for_at(
+        &self,
+        chksum: &str,
+        at: Option<&DateTime<Utc>>,
+    ) -> Result<Vec<RefView>> {
+        let mut result = sqlx::query_as::<_, Ref>(
+            "SELECT *
+            FROM (
+                SELECT refs.*
+                FROM refs
+                WHERE chksum = $1
+                UNION
+                SELECT refs.*
+                FROM refs
+                LEFT JOIN aliases x ON x.alias_from = refs.chksum
+                WHERE x.alias_to = $1
             ) t
+            WHERE $2::timestamptz IS NULL OR first_seen <= $2
             ORDER BY vendor ASC
             ",
         )
-        .bind(chksum)
-        .fetch(&self.pool);
+        .bind(chksum)
+        .bind(at)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row.into());
+        }
+        Ok(rows)
+    }
+
+    /// Like `get_all_refs_for`, but for many artifacts at once
+    pub async fn get_all_refs_for_bulk(&self, chksums: &[String]) -> Result<Vec<ResolvedRef>> {
+        let mut result = sqlx::query_as::<_, ResolvedRef>(
+            "SELECT refs.*, refs.chksum AS artifact
+            FROM refs
+            WHERE chksum = ANY($1)
+            UNION
+            SELECT refs.*, x.alias_to AS artifact
+            FROM refs
+            JOIN aliases x ON x.alias_from = refs.chksum
+            WHERE x.alias_to = ANY($1)
+            ORDER BY vendor ASC",
+        )
+        .bind(chksums)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Stream all refs that share vendor, package and chksum with at least one other ref
+    ///
+    /// Rows are grouped and the most recently seen ref of each group comes first.
+    pub fn get_ref_duplicate_candidates(
+        &self,
+        vendor: Option<&str>,
+    ) -> impl Stream<Item = Result<RefRecord>> {
+        let pool = self.pool.clone();
+        let vendor = vendor.map(String::from);
+        async_stream::stream! {
+            let mut result = sqlx::query_as::<_, RefRecord>(
+                "SELECT refs.*
+                FROM refs
+                JOIN (
+                    SELECT vendor, package, chksum
+                    FROM refs
+                    WHERE $1::varchar IS NULL OR vendor = $1
+                    GROUP BY vendor, package, chksum
+                    HAVING COUNT(*) > 1
+                ) dup USING (vendor, package, chksum)
+                ORDER BY vendor, package, chksum, last_seen DESC, id DESC",
+            )
+            .bind(vendor)
+            .fetch(&pool);
+
+            while let Some(row) = result.try_next().await? {
+                yield Ok(row);
+            }
+        }
+    }
+
+    /// Merge a ref into another one, keeping the earliest `first_seen` and any known filename
+    pub async fn merge_ref_into(&self, from: &RefRecord, into: &RefRecord) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE refs
+            SET first_seen = LEAST(first_seen, $2),
+            filename = COALESCE(filename, $3)
+            WHERE id = $1",
+        )
+        .bind(into.id)
+        .bind(from.first_seen)
+        .bind(&from.filename)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM refs
+            WHERE id = $1",
+        )
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub fn get_all_artifacts_by_age(&self) -> impl Stream<Item = Result<Artifact>> {
+        let pool = self.pool.clone();
+        async_stream::stream! {
+            let mut result = sqlx::query_as::<_, Artifact>(
+                "SELECT *
+                FROM artifacts
+                ORDER BY last_imported ASC",
+            )
+            .fetch(&pool);
+
+            while let Some(row) = result.try_next().await? {
+                yield Ok(row);
+            }
+        }
+    }
+
+    /// The next artifacts after `cursor`, ordered like `get_all_artifacts_by_age`
+    pub async fn get_artifacts_batch(
+        &self,
+        filter: &ArtifactFilter,
+        cursor: Option<&(DateTime<Utc>, String)>,
+        limit: usize,
+    ) -> Result<Vec<Artifact>> {
+        let mut result = sqlx::query_as::<_, Artifact>(
+            "SELECT a.*
+            FROM artifacts a
+            WHERE ($1::timestamptz IS NULL OR (a.last_imported, a.chksum) > ($1, $2))
+            AND a.last_imported < $3
+            AND ($8::bigint IS NULL OR a.last_imported < now() - $8 * interval '1 day')
+            AND ($4::timestamptz IS NULL OR a.first_seen >= $4)
+            AND ($5::varchar IS NULL OR a.chksum LIKE $5 || '%')
+            AND ($6::varchar IS NULL OR EXISTS (
+                SELECT 1
+                FROM refs r
+                LEFT JOIN aliases x ON x.alias_from = r.chksum
+                WHERE r.vendor = $6
+                AND (r.chksum = a.chksum OR x.alias_to = a.chksum)
+            ))
+            ORDER BY a.last_imported ASC, a.chksum ASC
+            LIMIT $7",
+        )
+        .bind(cursor.map(|(last_imported, _)| last_imported))
+        .bind(cursor.map(|(_, chksum)| chksum))
+        .bind(filter.imported_before)
+        .bind(filter.since)
+        .bind(filter.chksum_prefix.as_deref().map(escape_like))
+        .bind(&filter.vendor)
+        .bind(limit as i64)
+        .bind(filter.age)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn get_reindex_checkpoint(&self, name: &str) -> Result<Option<ReindexCheckpoint>> {
+        let result = sqlx::query_as::<_, ReindexCheckpoint>(
+            "SELECT name, started_at, last_imported, chksum, processed
+            FROM reindex_checkpoints
+            WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn insert_reindex_checkpoint(&self, obj: &ReindexCheckpoint) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO reindex_checkpoints (name, started_at, last_imported, chksum, processed, updated_at)
+            VALUES ($1, $2, $3, $4, $5, now())
+            ON CONFLICT (name) DO UPDATE SET
+            started_at = EXCLUDED.started_at,
+            last_imported = EXCLUDED.last_imported,
+            chksum = EXCLUDED.chksum,
+            processed = EXCLUDED.processed,
+            updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&obj.name)
+        .bind(obj.started_at)
+        .bind(obj.last_imported)
+        .bind(&obj.chksum)
+        .bind(obj.processed)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_reindex_checkpoint(&self, name: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "DELETE FROM reindex_checkpoints
+            WHERE name = $1",
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_binary_package(&self, pkg: &BinaryPackage) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO binary_packages (chksum, vendor, package, version, source_package, source_version, commit)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(&pkg.chksum)
+        .bind(&pkg.vendor)
+        .bind(&pkg.package)
+        .bind(&pkg.version)
+        .bind(&pkg.source_package)
+        .bind(&pkg.source_version)
+        .bind(&pkg.commit)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_binary_package(&self, chksum: &str) -> Result<Option<BinaryPackage>> {
+        let result = sqlx::query_as::<_, BinaryPackage>(
+            "SELECT *
+            FROM binary_packages
+            WHERE chksum = $1
+            ORDER BY id ASC
+            LIMIT 1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn get_all_named_refs(
+        &self,
+        vendor: &str,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<RefView>> {
+        let mut result = sqlx::query_as::<_, Ref>(
+            "SELECT *
+            FROM refs
+            WHERE vendor = $1
+            AND package = $2
+            AND version = $3
+            ORDER BY id ASC",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row.into());
+        }
+        Ok(rows)
+    }
+
+    /// Pick a random artifact, weighted towards recently imported ones
+    ///
+    /// This picks a random point in time and walks the `last_imported` index from there,
+    /// instead of sorting the whole table.
+    pub async fn get_random_artifact(&self) -> Result<Option<String>> {
+        // squaring moves the sample closer to the most recent imports
+        let r = fastrand::f64();
+        let result = sqlx::query_scalar(
+            "WITH range AS (
+                SELECT MIN(last_imported) AS oldest, MAX(last_imported) AS newest
+                FROM artifacts
+                WHERE last_imported > to_timestamp(0)
+            )
+            SELECT chksum
+            FROM artifacts a
+            WHERE last_imported >= (SELECT newest - (newest - oldest) * $1 FROM range)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = a.chksum)
+            ORDER BY last_imported ASC
+            LIMIT 1",
+        )
+        .bind(r * r)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// The most recently imported artifacts, along with one of the refs pointing to them
+    pub async fn get_recent_artifacts(&self, limit: i64) -> Result<Vec<RecentArtifact>> {
+        let result = sqlx::query_as::<_, RecentArtifact>(
+            "SELECT a.chksum, a.last_imported, r.vendor, r.package, r.version
+            FROM artifacts a
+            LEFT JOIN LATERAL (
+                SELECT vendor, package, version
+                FROM refs
+                WHERE refs.chksum = a.chksum
+                ORDER BY first_seen DESC
+                LIMIT 1
+            ) r ON true
+            WHERE NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = a.chksum)
+            ORDER BY a.last_imported DESC
+            LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn get_recent_reports(&self, limit: i64) -> Result<Vec<Report>> {
+        let result = sqlx::query_as::<_, Report>(
+            "SELECT *
+            FROM reports
+            ORDER BY first_seen DESC
+            LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn create_watchlist(&self, token: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO watchlists (token)
+            VALUES ($1)",
+        )
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Reads from the primary, the page is loaded right after the watchlist is created or changed
+    pub async fn get_watchlist(&self, token: &str) -> Result<Option<Watchlist>> {
+        let result = sqlx::query_as::<_, Watchlist>(
+            "SELECT *
+            FROM watchlists
+            WHERE token = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn bump_watchlist_visit(&self, watchlist: &Watchlist) -> Result<()> {
+        let _result = sqlx::query(
+            "UPDATE watchlists
+            SET last_visit = now()
+            WHERE id = $1",
+        )
+        .bind(watchlist.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Reads from the primary, see `get_watchlist`
+    pub async fn get_watchlist_entries(&self, watchlist: &Watchlist) -> Result<Vec<String>> {
+        let mut result = sqlx::query_scalar(
+            "SELECT query
+            FROM watchlist_entries
+            WHERE watchlist_id = $1
+            ORDER BY query ASC",
+        )
+        .bind(watchlist.id)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_watchlist_entry(&self, watchlist: &Watchlist, query: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO watchlist_entries (watchlist_id, query)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(watchlist.id)
+        .bind(query)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_watchlist_entry(&self, watchlist: &Watchlist, query: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "DELETE FROM watchlist_entries
+            WHERE watchlist_id = $1
+            AND query = $2",
+        )
+        .bind(watchlist.id)
+        .bind(query)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Return the refs of a package or an artifact (including its aliases)
+    /// Refs of a package across all vendors, with the artifact they resolve to
+    ///
+    /// Vendor specific suffixes like `-1` or `-r0` are accepted, the caller is expected
+    /// to check the upstream version exactly.
+    pub async fn get_refs_for_upstream_version(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<ResolvedRef>> {
+        let mut result = sqlx::query_as::<_, ResolvedRef>(
+            "SELECT r.chksum, r.vendor, r.package, r.version, r.filename,
+                COALESCE(x.alias_to, r.chksum) AS artifact
+            FROM refs r
+            LEFT JOIN aliases x ON x.alias_from = r.chksum
+            WHERE r.package = $1
+            AND (r.version = $2 OR r.version LIKE $2 || '-%' OR r.version LIKE '%:' || $2 || '%')
+            ORDER BY r.vendor ASC, r.version ASC",
+        )
+        .bind(package)
+        .bind(version)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// All refs of a package of one vendor, sorted by the time they were last seen
+    pub async fn get_package_versions(
+        &self,
+        vendor: &str,
+        package: &str,
+    ) -> Result<Vec<RefRecord>> {
+        let mut result = sqlx::query_as::<_, RefRecord>(
+            "SELECT *
+            FROM refs
+            WHERE vendor = $1 AND package = $2
+            ORDER BY last_seen DESC, id DESC",
+        )
+        .bind(vendor)
+        .bind(package)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn get_watched_refs(&self, query: &str, limit: usize) -> Result<Vec<RefRecord>> {
+        let mut result = sqlx::query_as::<_, RefRecord>(
+            "SELECT *
+            FROM (
+                SELECT refs.*
+                FROM refs
+                WHERE package = $1 OR chksum = $1
+                UNION
+                SELECT refs.*
+                FROM refs
+                LEFT JOIN aliases x ON x.alias_from = refs.chksum
+                WHERE x.alias_to = $1
+            ) t
+            ORDER BY first_seen DESC
+            LIMIT $2",
+        )
+        .bind(query)
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Reports on an artifact or package of a watchlist entry, most recent first
+    pub async fn get_watched_reports(&self, query: &str, limit: usize) -> Result<Vec<Report>> {
+        let mut result = sqlx::query_as::<_, Report>(
+            "SELECT *
+            FROM reports
+            WHERE package = $1
+            OR chksum = $1
+            OR chksum IN (SELECT alias_from FROM aliases WHERE alias_to = $1)
+            ORDER BY first_seen DESC
+            LIMIT $2",
+        )
+        .bind(query)
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_subscription(&self, obj: &NewSubscription) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO subscriptions (token, confirm_token, unsubscribe_token, target_kind, target, vendor, package, confirmed_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, CASE WHEN $8 THEN now() END)",
+        )
+        .bind(&obj.token)
+        .bind(&obj.confirm_token)
+        .bind(&obj.unsubscribe_token)
+        .bind(obj.target_kind)
+        .bind(&obj.target)
+        .bind(&obj.vendor)
+        .bind(&obj.package)
+        .bind(obj.confirmed)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Confirm a subscription, notifications only cover what happens after this point
+    pub async fn confirm_subscription(&self, confirm_token: &str) -> Result<Option<Subscription>> {
+        let result = sqlx::query_as::<_, Subscription>(
+            "UPDATE subscriptions
+            SET confirmed_at = coalesce(confirmed_at, now()),
+            last_notified = CASE WHEN confirmed_at IS NULL THEN now() ELSE last_notified END
+            WHERE confirm_token = $1
+            RETURNING *",
+        )
+        .bind(confirm_token)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Unconfirmed subscriptions that still need a confirmation mail
+    ///
+    /// Only one confirmation mail per address and day is sent, additional subscriptions are
+    /// picked up once the previous one has been confirmed or expired.
+    pub async fn get_pending_confirmations(&self) -> Result<Vec<Subscription>> {
+        let result = sqlx::query_as::<_, Subscription>(
+            "SELECT DISTINCT ON (s.target) s.*
+            FROM subscriptions s
+            WHERE s.confirmed_at IS NULL
+            AND s.confirmation_sent_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM subscriptions x
+                WHERE x.target = s.target
+                AND x.confirmation_sent_at > now() - interval '1 day'
+            )
+            ORDER BY s.target, s.id ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn bump_confirmation_sent(&self, subscription: &Subscription) -> Result<()> {
+        let _result = sqlx::query(
+            "UPDATE subscriptions
+            SET confirmation_sent_at = now()
+            WHERE id = $1",
+        )
+        .bind(subscription.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove subscriptions that have not been confirmed within a week
+    pub async fn delete_expired_subscriptions(&self) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM subscriptions
+            WHERE confirmed_at IS NULL
+            AND created_at < now() - interval '7 days'",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_subscription_by_unsubscribe_token(&self, token: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM subscriptions
+            WHERE unsubscribe_token = $1",
+        )
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Reads from the primary, the page is loaded right after the subscription is created or confirmed
+    pub async fn get_subscription(&self, token: &str) -> Result<Option<Subscription>> {
+        let result = sqlx::query_as::<_, Subscription>(
+            "SELECT *
+            FROM subscriptions
+            WHERE token = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Reads from the primary, see `get_subscription`
+    pub async fn get_subscription_by_confirm_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<Subscription>> {
+        let result = sqlx::query_as::<_, Subscription>(
+            "SELECT *
+            FROM subscriptions
+            WHERE confirm_token = $1",
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn get_subscriptions(&self) -> Result<Vec<Subscription>> {
+        let result = sqlx::query_as::<_, Subscription>(
+            "SELECT *
+            FROM subscriptions
+            WHERE confirmed_at IS NOT NULL
+            ORDER BY id ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn delete_subscription(&self, token: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "DELETE FROM subscriptions
+            WHERE token = $1",
+        )
+        .bind(token)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn bump_subscription_notified(
+        &self,
+        subscription: &Subscription,
+        until: &DateTime<Utc>,
+    ) -> Result<()> {
+        let _result = sqlx::query(
+            "UPDATE subscriptions
+            SET last_notified = $2
+            WHERE id = $1",
+        )
+        .bind(subscription.id)
+        .bind(until)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Refs matching a subscription that were added in the given time window
+    pub async fn get_new_refs_for(
+        &self,
+        subscription: &Subscription,
+        until: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<RefRecord>> {
+        let result = sqlx::query_as::<_, RefRecord>(
+            "SELECT *
+            FROM refs
+            WHERE first_seen > $1 AND first_seen <= $2
+            AND ($3::varchar IS NULL OR vendor = $3)
+            AND ($4::varchar IS NULL OR package = $4)
+            ORDER BY first_seen ASC
+            LIMIT $5",
+        )
+        .bind(subscription.last_notified)
+        .bind(until)
+        .bind(&subscription.vendor)
+        .bind(&subscription.package)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Reports matching a subscription that were created in the given time window
+    pub async fn get_new_reports_for(
+        &self,
+        subscription: &Subscription,
+        until: &DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Report>> {
+        let result = sqlx::query_as::<_, Report>(
+            "SELECT *
+            FROM reports
+            WHERE first_seen > $1 AND first_seen <= $2
+            AND ($3::varchar IS NULL OR vendor = $3)
+            AND ($4::varchar IS NULL OR package = $4)
+            ORDER BY first_seen ASC
+            LIMIT $5",
+        )
+        .bind(subscription.last_notified)
+        .bind(until)
+        .bind(&subscription.vendor)
+        .bind(&subscription.package)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn insert_report(&self, obj: &NewReport) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO reports (chksum, kind, vendor, package, version, summary, score)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (chksum, kind) DO UPDATE SET
+            vendor = EXCLUDED.vendor,
+            package = EXCLUDED.package,
+            version = EXCLUDED.version,
+            summary = EXCLUDED.summary,
+            score = EXCLUDED.score,
+            last_seen = now()",
+        )
+        .bind(&obj.chksum)
+        .bind(obj.kind)
+        .bind(&obj.vendor)
+        .bind(&obj.package)
+        .bind(&obj.version)
+        .bind(&obj.summary)
+        .bind(obj.score)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_vcs_drift(&self, obj: &NewVcsDrift) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO vcs_drift (chksum, vcs_chksum, categories, anomalies, score)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (chksum) DO UPDATE SET
+            vcs_chksum = EXCLUDED.vcs_chksum,
+            categories = EXCLUDED.categories,
+            anomalies = EXCLUDED.anomalies,
+            score = EXCLUDED.score,
+            last_seen = now()",
+        )
+        .bind(&obj.chksum)
+        .bind(&obj.vcs_chksum)
+        .bind(&obj.categories)
+        .bind(&obj.anomalies)
+        .bind(obj.score)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_vcs_drift(&self, chksum: &str) -> Result<Option<VcsDrift>> {
+        let result = sqlx::query_as::<_, VcsDrift>(
+            "SELECT *
+            FROM vcs_drift
+            WHERE chksum = $1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Upstream urls that haven't been verified recently, never verified ones first
+    ///
+    /// The checksum is taken from the most recently seen ref of the url.
+    pub async fn get_upstream_check_candidates(
+        &self,
+        reverify_after_days: i32,
+        limit: i64,
+    ) -> Result<Vec<(String, String)>> {
+        let mut result = sqlx::query(
+            "SELECT url, chksum
+            FROM (
+                SELECT DISTINCT ON (r.filename) r.filename AS url, r.chksum, u.last_verified
+                FROM refs r
+                LEFT JOIN upstream_checks u ON u.url = r.filename
+                WHERE (r.filename LIKE 'https://%' OR r.filename LIKE 'http://%')
+                AND (u.last_verified IS NULL OR u.last_verified < now() - make_interval(days => $1))
+                ORDER BY r.filename, r.last_seen DESC
+            ) x
+            ORDER BY last_verified ASC NULLS FIRST
+            LIMIT $2",
+        )
+        .bind(reverify_after_days)
+        .bind(limit)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push((row.get(0), row.get(1)));
+        }
+        Ok(rows)
+    }
+
+    pub async fn get_sync_state(&self, url: &str) -> Result<Option<SyncState>> {
+        let result = sqlx::query_as::<_, SyncState>(
+            "SELECT url, etag, last_modified, digest, snapshot_date, signer
+            FROM sync_state
+            WHERE url = $1",
+        )
+        .bind(url)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Record the processed metadata, the snapshot date and signer are kept if they aren't known
+    pub async fn insert_sync_state(&self, obj: &SyncState) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO sync_state (url, etag, last_modified, digest, snapshot_date, signer, last_synced)
+            VALUES ($1, $2, $3, $4, $5, $6, now())
+            ON CONFLICT (url) DO UPDATE SET
+            etag = EXCLUDED.etag,
+            last_modified = EXCLUDED.last_modified,
+            digest = EXCLUDED.digest,
+            snapshot_date = COALESCE(EXCLUDED.snapshot_date, sync_state.snapshot_date),
+            signer = COALESCE(EXCLUDED.signer, sync_state.signer),
+            last_synced = EXCLUDED.last_synced",
+        )
+        .bind(&obj.url)
+        .bind(&obj.etag)
+        .bind(&obj.last_modified)
+        .bind(&obj.digest)
+        .bind(obj.snapshot_date)
+        .bind(&obj.signer)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the outcome of a check, `gone_since` and `replaced_since` keep their first occurrence
+    ///
+    /// Inconclusive checks (`error`) don't clear a previous finding.
+    pub async fn insert_upstream_check(&self, obj: &NewUpstreamCheck) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO upstream_checks (url, chksum, status, http_status, observed_chksum, last_verified, gone_since, replaced_since)
+            VALUES ($1, $2, $3, $4, $5, now(),
+                CASE WHEN $3 = 'gone' THEN now() END,
+                CASE WHEN $3 = 'replaced' THEN now() END)
+            ON CONFLICT (url) DO UPDATE SET
+            chksum = EXCLUDED.chksum,
+            status = CASE WHEN EXCLUDED.status = 'error' THEN upstream_checks.status ELSE EXCLUDED.status END,
+            http_status = EXCLUDED.http_status,
+            observed_chksum = CASE
+                WHEN EXCLUDED.status = 'error' THEN upstream_checks.observed_chksum
+                ELSE EXCLUDED.observed_chksum
+            END,
+            last_verified = EXCLUDED.last_verified,
+            gone_since = CASE
+                WHEN EXCLUDED.status = 'gone' THEN COALESCE(upstream_checks.gone_since, now())
+                WHEN EXCLUDED.status = 'error' THEN upstream_checks.gone_since
+            END,
+            replaced_since = CASE
+                WHEN EXCLUDED.status = 'replaced' THEN COALESCE(upstream_checks.replaced_since, now())
+                WHEN EXCLUDED.status = 'error' THEN upstream_checks.replaced_since
+            END",
+        )
+        .bind(&obj.url)
+        .bind(&obj.chksum)
+        .bind(obj.status)
+        .bind(obj.http_status)
+        .bind(&obj.observed_chksum)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Upstream urls of these checksums that vanished or serve different content
+    pub async fn get_upstream_warnings(&self, chksums: &[String]) -> Result<Vec<UpstreamCheck>> {
+        let mut result = sqlx::query_as::<_, UpstreamCheck>(
+            "SELECT *
+            FROM upstream_checks
+            WHERE chksum = ANY($1)
+            AND status IN ('gone', 'replaced')
+            ORDER BY url ASC",
+        )
+        .bind(chksums)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Remove a finding that no longer applies
+    pub async fn delete_report(&self, chksum: &str, kind: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "DELETE FROM reports
+            WHERE chksum = $1 AND kind = $2",
+        )
+        .bind(chksum)
+        .bind(kind)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_reports(
+        &self,
+        vendor: Option<&str>,
+        package: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Report>> {
+        let mut result = sqlx::query_as::<_, Report>(
+            "SELECT *
+            FROM reports
+            WHERE ($1::varchar IS NULL OR vendor = $1)
+            AND ($2::varchar IS NULL OR package = $2)
+            ORDER BY score DESC, last_seen DESC
+            LIMIT $3",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(limit)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Release tarballs of packages that also reference a VCS snapshot of the same version
+    ///
+    /// With `only_new`, tarballs that have already been compared are skipped.
+    pub async fn get_drift_candidates(
+        &self,
+        vendor: Option<&str>,
+        only_new: bool,
+        limit: i64,
+    ) -> Result<Vec<(Ref, String)>> {
+        let mut result = sqlx::query(
+            "SELECT DISTINCT r1.chksum, r1.vendor, r1.package, r1.version, r1.filename, x.alias_to
+            FROM refs r1
+            JOIN refs r2 ON r2.vendor = r1.vendor
+                AND r2.package = r1.package
+                AND r2.version = r1.version
+            JOIN aliases x ON x.alias_from = r2.chksum
+            WHERE x.reason = 'git-archive'
+            AND r1.chksum <> r2.chksum
+            AND r1.chksum NOT IN (SELECT alias_from FROM aliases WHERE reason = 'git-archive')
+            AND ($1::varchar IS NULL OR r1.vendor = $1)
+            AND (NOT $2 OR NOT EXISTS (
+                SELECT 1 FROM vcs_drift d
+                WHERE d.chksum = r1.chksum
+                OR d.chksum IN (SELECT alias_to FROM aliases WHERE alias_from = r1.chksum)
+            ))
+            LIMIT $3",
+        )
+        .bind(vendor)
+        .bind(only_new)
+        .bind(limit)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            let r = Ref {
+                chksum: row.get(0),
+                vendor: row.get(1),
+                package: row.get(2),
+                version: row.get(3),
+                filename: row.get(4),
+            };
+            rows.push((r, row.get(5)));
+        }
+        Ok(rows)
+    }
+
+    /// Lockfiles of the given strains, along with the archive they were found in
+    ///
+    /// Returns all lockfiles of the next `limit` archives after `cursor`, ordered by archive.
+    pub async fn get_sboms_in_archives(
+        &self,
+        strains: &[&str],
+        cursor: &str,
+        limit: i64,
+    ) -> Result<Vec<(Sbom, String)>> {
+        let mut result = sqlx::query(
+            "WITH archives AS (
+                SELECT DISTINCT from_archive
+                FROM sbom_refs
+                WHERE sbom_strain = ANY($1)
+                AND from_archive > $2
+                ORDER BY from_archive ASC
+                LIMIT $3
+            )
+            SELECT s.chksum, s.strain, s.data, r.from_archive
+            FROM archives a
+            JOIN sbom_refs r ON r.from_archive = a.from_archive
+            JOIN sboms s ON s.chksum = r.sbom_chksum AND s.strain = r.sbom_strain
+            WHERE s.strain = ANY($1)
+            ORDER BY r.from_archive ASC",
+        )
+        .bind(strains)
+        .bind(cursor)
+        .bind(limit)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            let sbom = Sbom {
+                chksum: row.get(0),
+                strain: row.get(1),
+                data: row.get(2),
+            };
+            rows.push((sbom, row.get(3)));
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_quarantine(&self, obj: &NewQuarantine) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO quarantine (chksum, url, ref_chksum, vendor, package, version, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (chksum, url) DO NOTHING",
+        )
+        .bind(&obj.chksum)
+        .bind(&obj.url)
+        .bind(obj.success_ref.as_ref().map(|r| &r.chksum))
+        .bind(obj.success_ref.as_ref().map(|r| &r.vendor))
+        .bind(obj.success_ref.as_ref().map(|r| &r.package))
+        .bind(obj.success_ref.as_ref().map(|r| &r.version))
+        .bind(&obj.reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record an artifact we didn't download, `size` is the announced size if we know it
+    pub async fn insert_skipped_artifact(
+        &self,
+        url: &str,
+        reason: &str,
+        size: Option<i64>,
+    ) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO skipped_artifacts (url, reason, size)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (url) DO UPDATE SET
+            reason = EXCLUDED.reason,
+            size = COALESCE(EXCLUDED.size, skipped_artifacts.size),
+            last_skipped = now()",
+        )
+        .bind(url)
+        .bind(reason)
+        .bind(size)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Check if an artifact is held back from public listing
+    pub async fn is_quarantined(&self, chksum: &str) -> Result<bool> {
+        let result = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM quarantine WHERE chksum = $1
+            )",
+        )
+        .bind(chksum)
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Whether a lockfile was only found in quarantined archives
+    pub async fn is_sbom_quarantined(&self, sbom: &Sbom) -> Result<bool> {
+        let result = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM sbom_refs
+                WHERE sbom_strain = $1 AND sbom_chksum = $2
+            ) AND NOT EXISTS (
+                SELECT 1 FROM sbom_refs
+                WHERE sbom_strain = $1 AND sbom_chksum = $2
+                AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = sbom_refs.from_archive)
+            )",
+        )
+        .bind(&sbom.strain)
+        .bind(&sbom.chksum)
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    pub async fn get_quarantined(&self, include_rejected: bool) -> Result<Vec<Quarantine>> {
+        let mut result = sqlx::query_as::<_, Quarantine>(
+            "SELECT *
+            FROM quarantine
+            WHERE $1 OR NOT rejected
+            ORDER BY id ASC",
+        )
+        .bind(include_rejected)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Publish the refs that were held back for an artifact and lift the quarantine
+    pub async fn release_quarantine(&self, chksum: &str) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut rows = Vec::new();
+        {
+            let mut result = sqlx::query_as::<_, Quarantine>(
+                "DELETE FROM quarantine
+                WHERE chksum = $1
+                RETURNING *",
+            )
+            .bind(chksum)
+            .fetch(&mut *tx);
+            while let Some(row) = result.try_next().await? {
+                rows.push(row);
+            }
+        }
+
+        for row in &rows {
+            let Some(r) = row.success_ref() else { continue };
+            sqlx::query(
+                "INSERT INTO refs (chksum, vendor, package, version, filename, last_seen)
+                VALUES ($1, $2, $3, $4, $5, now())
+                ON CONFLICT (chksum, vendor, package, version) DO UPDATE SET
+                last_seen = EXCLUDED.last_seen,
+                filename = COALESCE(EXCLUDED.filename, refs.filename)",
+            )
+            .bind(&r.chksum)
+            .bind(&r.vendor)
+            .bind(&r.package)
+            .bind(&r.version)
+            .bind(&r.filename)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(rows.len())
+    }
+
+    /// Keep the artifact hidden, but stop listing it as pending review
+    pub async fn reject_quarantine(&self, chksum: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "UPDATE quarantine
+            SET rejected = true
+            WHERE chksum = $1",
+        )
+        .bind(chksum)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_refs_for_package(
+        &self,
+        package: &str,
+        version: Option<&str>,
+        vendor: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<RefView>> {
+        let mut result = sqlx::query_as::<_, Ref>(
+            "SELECT *
+            FROM refs
+            WHERE package = $1
+            AND ($2::varchar IS NULL OR version = $2)
+            AND ($3::varchar IS NULL OR vendor = $3)
+            ORDER BY id DESC
+            LIMIT $4",
+        )
+        .bind(package)
+        .bind(version)
+        .bind(vendor)
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row.into());
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_changelog(
+        &self,
+        vendor: Option<&str>,
+        package: &str,
+        version: &str,
+        entry: &changelog::Entry,
+    ) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO changelogs (vendor, package, version, author, date, text)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (package, version, COALESCE(vendor, '')) DO NOTHING",
+        )
+        .bind(vendor)
+        .bind(package)
+        .bind(version)
+        .bind(&entry.author)
+        .bind(&entry.date)
+        .bind(&entry.text)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Return all changelog entries that match the package and version of any of the refs
+    pub async fn get_changelogs_for_refs(&self, refs: &[RefView]) -> Result<Vec<Changelog>> {
+        let packages = refs.iter().map(|r| r.package.as_str()).collect::<Vec<_>>();
+        let versions = refs.iter().map(|r| r.version.as_str()).collect::<Vec<_>>();
+
+        let mut result = sqlx::query_as::<_, Changelog>(
+            "SELECT *
+            FROM changelogs
+            WHERE (package, version) IN (
+                SELECT * FROM UNNEST($1::varchar[], $2::varchar[])
+            )",
+        )
+        .bind(packages)
+        .bind(versions)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_patch(&self, obj: &NewPatch) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO patches (vendor, package, version, filename, chksum, size, content)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (vendor, package, version, filename) DO UPDATE
+            SET chksum = EXCLUDED.chksum,
+                size = EXCLUDED.size,
+                content = EXCLUDED.content",
+        )
+        .bind(&obj.vendor)
+        .bind(&obj.package)
+        .bind(&obj.version)
+        .bind(&obj.filename)
+        .bind(&obj.chksum)
+        .bind(obj.size)
+        .bind(&obj.content)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Return the patches and spec files of all source packages the refs belong to
+    pub async fn get_patches_for_refs(&self, refs: &[RefView]) -> Result<Vec<PatchRef>> {
+        let vendors = refs.iter().map(|r| r.vendor.as_str()).collect::<Vec<_>>();
+        let packages = refs.iter().map(|r| r.package.as_str()).collect::<Vec<_>>();
+        let versions = refs.iter().map(|r| r.version.as_str()).collect::<Vec<_>>();
+
+        let mut result = sqlx::query_as::<_, PatchRef>(
+            "SELECT vendor, package, version, filename, chksum, size
+            FROM patches
+            WHERE (vendor, package, version) IN (
+                SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[])
+            )
+            ORDER BY filename ASC",
+        )
+        .bind(vendors)
+        .bind(packages)
+        .bind(versions)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Record the apt suite and component the refs were listed in
+    pub async fn insert_ref_suites(
+        &self,
+        refs: &[Ref],
+        suite: &str,
+        component: &str,
+    ) -> Result<()> {
+        for batch in refs.chunks(INSERT_BATCH_SIZE) {
+            sqlx::query(
+                "INSERT INTO ref_suites (chksum, vendor, package, version, suite, component)
+                SELECT chksum, vendor, package, version, $5, $6
+                FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[])
+                    AS t(chksum, vendor, package, version)
+                ON CONFLICT (chksum, vendor, package, version, suite, component) DO UPDATE SET
+                last_seen = now()",
+            )
+            .bind(batch.iter().map(|r| r.chksum.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.vendor.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.package.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.version.as_str()).collect::<Vec<_>>())
+            .bind(suite)
+            .bind(component)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_ref_suites_for_refs(&self, refs: &[RefView]) -> Result<Vec<RefSuite>> {
+        let chksums = refs.iter().map(|r| r.chksum.as_str()).collect::<Vec<_>>();
+        let vendors = refs.iter().map(|r| r.vendor.as_str()).collect::<Vec<_>>();
+        let packages = refs.iter().map(|r| r.package.as_str()).collect::<Vec<_>>();
+        let versions = refs.iter().map(|r| r.version.as_str()).collect::<Vec<_>>();
+
+        let mut result = sqlx::query_as::<_, RefSuite>(
+            "SELECT chksum, vendor, package, version, suite, component
+            FROM ref_suites
+            WHERE (chksum, vendor, package, version) IN (
+                SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[], $4::varchar[])
+            )
+            ORDER BY suite ASC, component ASC",
+        )
+        .bind(chksums)
+        .bind(vendors)
+        .bind(packages)
+        .bind(versions)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_upstream(&self, url: &str, vendor: &str, package: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO upstreams (url, vendor, package)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (url, vendor, package) DO UPDATE SET
+            last_seen = now()",
+        )
+        .bind(url)
+        .bind(vendor)
+        .bind(package)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_upstreams_for_package(
+        &self,
+        vendor: &str,
+        package: &str,
+    ) -> Result<Vec<String>> {
+        let urls = sqlx::query_scalar(
+            "SELECT url
+            FROM upstreams
+            WHERE vendor = $1 AND package = $2
+            ORDER BY last_seen DESC",
+        )
+        .bind(vendor)
+        .bind(package)
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(urls)
+    }
+
+    /// All refs of packages that are built from this upstream, most recently seen first
+    pub async fn get_upstream_refs(&self, url: &str, limit: usize) -> Result<Vec<RefRecord>> {
+        let mut result = sqlx::query_as::<_, RefRecord>(
+            "SELECT refs.*
+            FROM upstreams
+            JOIN refs ON refs.vendor = upstreams.vendor AND refs.package = upstreams.package
+            WHERE upstreams.url = $1
+            ORDER BY refs.last_seen DESC, refs.id DESC
+            LIMIT $2",
+        )
+        .bind(url)
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn has_patches(&self, vendor: &str, package: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1
+                FROM patches
+                WHERE vendor = $1 AND package = $2
+            )",
+        )
+        .bind(vendor)
+        .bind(package)
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(exists)
+    }
+
+    /// Return the patches of all versions of a package, most recently seen version first
+    pub async fn get_package_patches(&self, vendor: &str, package: &str) -> Result<Vec<PatchRef>> {
+        let mut result = sqlx::query_as::<_, PatchRef>(
+            "SELECT vendor, package, version, filename, chksum, size
+            FROM patches
+            WHERE vendor = $1 AND package = $2
+            ORDER BY MAX(first_seen) OVER (PARTITION BY version) DESC, version ASC, filename ASC",
+        )
+        .bind(vendor)
+        .bind(package)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Return all source packages that contain a file with this checksum
+    pub async fn get_patch_refs(&self, chksum: &str) -> Result<Vec<PatchRef>> {
+        let mut result = sqlx::query_as::<_, PatchRef>(
+            "SELECT vendor, package, version, filename, chksum, size
+            FROM patches
+            WHERE chksum = $1
+            ORDER BY vendor ASC, package ASC, version ASC",
+        )
+        .bind(chksum)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn get_patch_content(&self, chksum: &str) -> Result<Option<String>> {
+        let result: Option<(String,)> = sqlx::query_as(
+            "SELECT content
+            FROM patches
+            WHERE chksum = $1
+            AND content IS NOT NULL
+            LIMIT 1",
+        )
+        .bind(chksum)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(result.map(|(content,)| content))
+    }
+
+    /// Packages of vendors tracked by OSV that weren't checked for advisories recently
+    pub async fn get_osv_candidates(
+        &self,
+        ecosystems: &[(&str, &str)],
+        limit: i64,
+    ) -> Result<Vec<(String, String, String)>> {
+        let vendors = ecosystems.iter().map(|e| e.0).collect::<Vec<_>>();
+        let ecosystems = ecosystems.iter().map(|e| e.1).collect::<Vec<_>>();
+
+        let mut result = sqlx::query_as(
+            "SELECT DISTINCT e.ecosystem, r.package, r.version
+            FROM refs r
+            JOIN UNNEST($1::varchar[], $2::varchar[]) AS e(vendor, ecosystem)
+                ON e.vendor = r.vendor
+            LEFT JOIN advisories a ON a.ecosystem = e.ecosystem
+                AND a.package = r.package
+                AND a.version = r.version
+            WHERE a.last_checked IS NULL OR a.last_checked < now() - interval '7 days'
+            LIMIT $3",
+        )
+        .bind(vendors)
+        .bind(ecosystems)
+        .bind(limit)
+        .fetch(&self.pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    pub async fn insert_advisories<T: Serialize>(
+        &self,
+        ecosystem: &str,
+        package: &str,
+        version: &str,
+        vulns: &T,
+    ) -> Result<()> {
+        let vulns = serde_json::to_value(vulns)?;
+        let _result = sqlx::query(
+            "INSERT INTO advisories (ecosystem, package, version, vulns, last_checked)
+            VALUES ($1, $2, $3, $4, now())
+            ON CONFLICT (ecosystem, package, version) DO UPDATE SET
+            vulns = EXCLUDED.vulns,
+            last_checked = EXCLUDED.last_checked",
+        )
+        .bind(ecosystem)
+        .bind(package)
+        .bind(version)
+        .bind(vulns)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Cached advisories, looked up by `(ecosystem, package, version)`
+    pub async fn get_advisories(&self, keys: &[(&str, &str, &str)]) -> Result<Vec<Advisory>> {
+        let ecosystems = keys.iter().map(|k| k.0).collect::<Vec<_>>();
+        let packages = keys.iter().map(|k| k.1).collect::<Vec<_>>();
+        let versions = keys.iter().map(|k| k.2).collect::<Vec<_>>();
+
+        let mut result = sqlx::query_as::<_, Advisory>(
+            "SELECT *
+            FROM advisories
+            WHERE (ecosystem, package, version) IN (
+                SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[])
+            )",
+        )
+        .bind(ecosystems)
+        .bind(packages)
+        .bind(versions)
+        .fetch(&self.read_pool);
 
         let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
-            rows.push(row.into());
+            rows.push(row);
         }
         Ok(rows)
     }
 
-    pub fn get_all_artifacts_by_age(&self) -> impl Stream<Item = Result<Artifact>> {
-        let pool = self.pool.clone();
-        async_stream::stream! {
-            let mut result = sqlx::query_as::<_, Artifact>(
-                "SELECT *
-                FROM artifacts
-                ORDER BY last_imported ASC",
-            )
-            .fetch(&pool);
-
-            while let Some(row) = result.try_next().await? {
-                yield Ok(row);
-            }
-        }
-    }
-
     pub async fn insert_task(&self, task: &Task) -> Result<()> {
         let _result = sqlx::query(
             "INSERT INTO tasks(key, data)
@@ -279,34 +2522,223 @@ impl Client {
         Ok(())
     }
 
+    /// Insert many tasks with one query per batch, tasks that are already queued are skipped
+    pub async fn insert_tasks(&self, tasks: &[Task]) -> Result<()> {
+        for batch in tasks.chunks(INSERT_BATCH_SIZE) {
+            let _result = sqlx::query(
+                "INSERT INTO tasks(key, data)
+                SELECT key, data::json
+                FROM UNNEST($1::text[], $2::jsonb[]) AS t(key, data)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(batch.iter().map(|t| t.key.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|t| &t.data).collect::<Vec<_>>())
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
     pub async fn bump_task_error_counter(&self, task: &Task, error: &str) -> Result<()> {
         let _result = sqlx::query(
             "UPDATE tasks
             SET retries = retries + 1,
-            error = $2
+            error = $2,
+            next_attempt_at = now() + interval '1 minute' * power(2, retries),
+            failed_at = CASE WHEN retries + 1 >= $3 THEN now() END
             WHERE id = $1",
         )
         .bind(task.id)
         .bind(error)
+        .bind(RETRY_LIMIT)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    pub async fn get_random_task(&self) -> Result<Option<Task>> {
+    /// Pick a task that is due, `running` are the ids of tasks that are already in progress
+    pub async fn get_random_task(
+        &self,
+        filter: &TaskFilter,
+        running: &[i64],
+    ) -> Result<Option<Task>> {
         let result = sqlx::query_as(
             "SELECT *
                 FROM tasks
                 WHERE retries < $1
+                AND obsolete_at IS NULL
+                AND (next_attempt_at IS NULL OR next_attempt_at <= now())
+                AND NOT (id = ANY($2))
+                AND (cardinality($3::varchar[]) = 0 OR split_part(key, ':', 1) = ANY($3))
+                AND NOT (split_part(key, ':', 1) = ANY($4))
                 ORDER BY RANDOM()
                 LIMIT 1",
         )
         .bind(RETRY_LIMIT)
+        .bind(running)
+        .bind(&filter.only)
+        .bind(&filter.exclude)
         .fetch_optional(&self.pool)
         .await?;
         Ok(result)
     }
 
+    /// Reset the error counter of all tasks with the given key prefix, so they are picked up again
+    pub async fn requeue_tasks(&self, prefix: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE tasks
+            SET retries = 0,
+            error = NULL,
+            next_attempt_at = NULL,
+            failed_at = NULL,
+            obsolete_at = NULL
+            WHERE starts_with(key, $1)
+            AND (retries > 0 OR next_attempt_at IS NOT NULL OR obsolete_at IS NOT NULL)",
+        )
+        .bind(prefix)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Tasks that exceeded the retry limit and are no longer attempted
+    pub async fn get_failed_tasks(&self, limit: i64) -> Result<Vec<Task>> {
+        let result = sqlx::query_as(
+            "SELECT *
+            FROM tasks
+            WHERE failed_at IS NOT NULL
+            ORDER BY failed_at DESC
+            LIMIT $1",
+        )
+        .bind(limit)
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Queued tasks for a version that has been superseded
+    ///
+    /// A version is superseded once a different version of the same package showed up, and the
+    /// old version hasn't been seen by a sync since. This covers tasks that name their package,
+    /// either directly or through `success_ref`, and tasks for a url if every ref of that url has
+    /// been superseded.
+    pub async fn get_superseded_tasks(&self) -> Result<Vec<Task>> {
+        let result = sqlx::query_as::<_, Task>(
+            "SELECT t.*
+            FROM tasks t
+            WHERE t.obsolete_at IS NULL AND t.failed_at IS NULL
+            AND (
+                t.id IN (
+                    SELECT s.id
+                    FROM (
+                        SELECT id, created_at,
+                            CASE WHEN json_typeof(body->'success_ref') = 'object'
+                                THEN body->'success_ref'
+                                ELSE body
+                            END AS r
+                        FROM (
+                            SELECT id, created_at, (SELECT value FROM json_each(data) LIMIT 1) AS body
+                            FROM tasks
+                            WHERE obsolete_at IS NULL AND failed_at IS NULL
+                        ) x
+                    ) s
+                    WHERE EXISTS (
+                        SELECT 1
+                        FROM refs n
+                        WHERE n.vendor = s.r->>'vendor'
+                        AND n.package = s.r->>'package'
+                        AND n.version != s.r->>'version'
+                        AND n.first_seen > s.created_at
+                        AND NOT EXISTS (
+                            SELECT 1
+                            FROM refs o
+                            WHERE o.vendor = n.vendor
+                            AND o.package = n.package
+                            AND o.version = s.r->>'version'
+                            AND o.last_seen >= n.first_seen
+                        )
+                    )
+                )
+                OR (
+                    EXISTS (
+                        SELECT 1
+                        FROM refs r
+                        WHERE r.filename = substr(t.key, strpos(t.key, ':') + 1)
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1
+                        FROM refs r
+                        WHERE r.filename = substr(t.key, strpos(t.key, ':') + 1)
+                        AND NOT EXISTS (
+                            SELECT 1
+                            FROM refs n
+                            WHERE n.vendor = r.vendor
+                            AND n.package = r.package
+                            AND n.version != r.version
+                            AND n.first_seen > r.last_seen
+                        )
+                    )
+                )
+            )
+            ORDER BY t.id ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Mark queued tasks as obsolete if the version they were created for has been superseded
+    ///
+    /// Returns the number of tasks marked, see `get_superseded_tasks`.
+    pub async fn mark_superseded_tasks(&self) -> Result<u64> {
+        let ids = self
+            .get_superseded_tasks()
+            .await?
+            .into_iter()
+            .map(|task| task.id)
+            .collect::<Vec<_>>();
+        let result = sqlx::query(
+            "UPDATE tasks
+            SET obsolete_at = now()
+            WHERE id = ANY($1)
+            AND obsolete_at IS NULL AND failed_at IS NULL",
+        )
+        .bind(&ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete obsolete tasks, and failed tasks that have been in the dead-letter queue for this many days
+    pub async fn prune_tasks(&self, failed_days: Option<i64>) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM tasks
+            WHERE obsolete_at IS NOT NULL
+            OR ($1::bigint IS NOT NULL AND failed_at < now() - $1 * interval '1 day')",
+        )
+        .bind(failed_days)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Move a failed task out of the dead-letter queue, returns false if there's no such task
+    pub async fn retry_task(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE tasks
+            SET retries = 0,
+            error = NULL,
+            next_attempt_at = NULL,
+            failed_at = NULL,
+            obsolete_at = NULL
+            WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn delete_task(&self, task: &Task) -> Result<()> {
         let _result = sqlx::query(
             "DELETE FROM tasks
@@ -332,28 +2764,12 @@ impl Client {
         Ok(())
     }
 
-    pub async fn get_package(
+    pub async fn search(
         &self,
-        vendor: &str,
-        package: &str,
-        version: &str,
-    ) -> Result<Option<Package>> {
-        let result = sqlx::query_as(
-            "SELECT *
-            FROM packages
-            WHERE vendor = $1
-            AND package = $2
-            AND version = $3",
-        )
-        .bind(vendor)
-        .bind(package)
-        .bind(version)
-        .fetch_optional(&self.pool)
-        .await?;
-        Ok(result)
-    }
-
-    pub async fn search(&self, search: &str, limit: usize) -> Result<Vec<RefView>> {
+        search: &str,
+        at: Option<&DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<RefView>> {
         let exact = search.strip_suffix('%').unwrap_or(search);
 
         // Search for exact matches first
@@ -361,12 +2777,15 @@ impl Client {
             "SELECT *
             FROM refs
             WHERE package = $1
+            AND ($3::timestamptz IS NULL OR first_seen <= $3)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = refs.chksum OR q.ref_chksum = refs.chksum)
             ORDER BY id DESC
             LIMIT $2",
         )
         .bind(exact)
         .bind(limit as i64)
-        .fetch(&self.pool);
+        .bind(at)
+        .fetch(&self.read_pool);
 
         let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
@@ -378,18 +2797,142 @@ impl Client {
             "SELECT *
             FROM refs
             WHERE package LIKE $3 AND package != $1
+            AND ($4::timestamptz IS NULL OR first_seen <= $4)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = refs.chksum OR q.ref_chksum = refs.chksum)
             ORDER BY id DESC
             LIMIT $2",
         )
         .bind(exact)
         .bind(limit as i64)
         .bind(search)
-        .fetch(&self.pool);
+        .bind(at)
+        .fetch(&self.read_pool);
+
+        while let Some(row) = result.try_next().await? {
+            rows.push(row.into());
+        }
+
+        Ok(rows)
+    }
+
+    /// Search for refs of a specific version, vendor specific revisions are accepted
+    pub async fn search_version(
+        &self,
+        package: &str,
+        version: &str,
+        at: Option<&DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<RefView>> {
+        let mut result = sqlx::query_as::<_, Ref>(
+            "SELECT *
+            FROM refs
+            WHERE package = $1
+            AND (version = $2 OR version LIKE $3 || '-%')
+            AND ($5::timestamptz IS NULL OR first_seen <= $5)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = refs.chksum OR q.ref_chksum = refs.chksum)
+            ORDER BY id DESC
+            LIMIT $4",
+        )
+        .bind(package)
+        .bind(version)
+        .bind(escape_like(version))
+        .bind(limit as i64)
+        .bind(at)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row.into());
+        }
+        Ok(rows)
+    }
+
+    /// Substring search on package names and filenames, backed by trigram indexes
+    ///
+    /// A `*` in the search term matches anything. Terms without at least 3 characters
+    /// in a row would scan the whole table and don't return any results.
+    pub async fn search_substring(
+        &self,
+        search: &str,
+        at: Option<&DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<RefView>> {
+        if !is_substring_searchable(search) {
+            return Ok(Vec::new());
+        }
+        let pattern = format!("%{}%", escape_like(search).replace('*', "%"));
+        let mut result = sqlx::query_as::<_, Ref>(
+            "SELECT *
+            FROM refs
+            WHERE (filename ILIKE $1 OR package ILIKE $1)
+            AND ($3::timestamptz IS NULL OR first_seen <= $3)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = refs.chksum OR q.ref_chksum = refs.chksum)
+            ORDER BY id DESC
+            LIMIT $2",
+        )
+        .bind(pattern)
+        .bind(limit as i64)
+        .bind(at)
+        .fetch(&self.read_pool);
 
+        let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
             rows.push(row.into());
         }
+        Ok(rows)
+    }
+
+    /// Find artifacts containing a file, matching either the full path or the path below the top-level directory
+    pub async fn search_path(&self, path: &str, limit: usize) -> Result<Vec<FileMatch>> {
+        let mut result = sqlx::query_as::<_, FileMatch>(
+            "SELECT f.chksum, f.path, r.vendor, r.package, r.version
+            FROM artifact_files f
+            LEFT JOIN LATERAL (
+                SELECT vendor, package, version
+                FROM refs
+                WHERE refs.chksum = f.chksum
+                ORDER BY first_seen DESC
+                LIMIT 1
+            ) r ON true
+            WHERE (f.path = $1 OR f.path LIKE '%/' || $2)
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = f.chksum)
+            ORDER BY f.chksum ASC
+            LIMIT $3",
+        )
+        .bind(path)
+        .bind(escape_like(path))
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
+
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    /// Find the artifacts that contain a file with this digest
+    pub async fn get_files_by_digest(
+        &self,
+        digest: &str,
+        limit: usize,
+    ) -> Result<Vec<FileDigestMatch>> {
+        let mut result = sqlx::query_as::<_, FileDigestMatch>(
+            "SELECT f.chksum, f.path
+            FROM artifact_files f
+            WHERE f.digest = $1
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = f.chksum)
+            ORDER BY f.chksum ASC, f.path ASC
+            LIMIT $2",
+        )
+        .bind(digest)
+        .bind(limit as i64)
+        .fetch(&self.read_pool);
 
+        let mut rows = Vec::new();
+        while let Some(row) = result.try_next().await? {
+            rows.push(row);
+        }
         Ok(rows)
     }
 
@@ -408,10 +2951,58 @@ impl Client {
         Ok(chksum)
     }
 
+    /// Record the packages listed in an sbom for aggregate statistics, replacing the rows of an
+    /// earlier run so packages of an older parser don't linger
+    pub async fn insert_sbom_packages(
+        &self,
+        sbom_chksum: &str,
+        strain: &str,
+        packages: &[sbom::Package],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "DELETE FROM sbom_packages
+            WHERE sbom_chksum = $1 AND strain = $2",
+        )
+        .bind(sbom_chksum)
+        .bind(strain)
+        .execute(&mut *tx)
+        .await?;
+        for pkg in packages {
+            sqlx::query(
+                "INSERT INTO sbom_packages (sbom_chksum, strain, name, version, checksum)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(sbom_chksum)
+            .bind(strain)
+            .bind(&pkg.name)
+            .bind(&pkg.version)
+            .bind(&pkg.checksum)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Forget the packages of an sbom that can't be parsed anymore
+    pub async fn delete_sbom_packages(&self, sbom_chksum: &str, strain: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "DELETE FROM sbom_packages
+            WHERE sbom_chksum = $1 AND strain = $2",
+        )
+        .bind(sbom_chksum)
+        .bind(strain)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn get_sbom(&self, chksum: &str) -> Result<Option<Sbom>> {
         let result = sqlx::query_as::<_, Sbom>("SELECT * FROM sboms WHERE chksum = $1")
             .bind(chksum)
-            .fetch_optional(&self.pool)
+            .fetch_optional(&self.read_pool)
             .await?;
         Ok(result)
     }
@@ -441,27 +3032,6 @@ impl Client {
         Ok(result)
     }
 
-    pub async fn insert_sbom_ref(
-        &self,
-        archive_digest: &str,
-        sbom_strain: &str,
-        sbom_digest: &str,
-        path: &str,
-    ) -> Result<()> {
-        let _result = sqlx::query(
-            "INSERT INTO sbom_refs (from_archive, sbom_strain, sbom_chksum, path)
-            VALUES ($1, $2, $3, $4)
-            ON CONFLICT DO NOTHING",
-        )
-        .bind(archive_digest)
-        .bind(sbom_strain)
-        .bind(sbom_digest)
-        .bind(path)
-        .execute(&self.pool)
-        .await?;
-        Ok(())
-    }
-
     pub async fn get_sbom_refs_for_archive(&self, archive_digest: &str) -> Result<Vec<SbomRef>> {
         let mut result = sqlx::query_as::<_, SbomRef>(
             "SELECT *
@@ -470,7 +3040,7 @@ impl Client {
             ORDER BY path ASC",
         )
         .bind(archive_digest)
-        .fetch(&self.pool);
+        .fetch(&self.read_pool);
 
         let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
@@ -484,11 +3054,12 @@ impl Client {
             "SELECT *
             FROM sbom_refs
             WHERE sbom_strain = $1 AND sbom_chksum = $2
+            AND NOT EXISTS (SELECT 1 FROM quarantine q WHERE q.chksum = sbom_refs.from_archive)
             ORDER BY from_archive ASC, path ASC",
         )
         .bind(&sbom.strain)
         .bind(&sbom.chksum)
-        .fetch(&self.pool);
+        .fetch(&self.read_pool);
 
         let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
@@ -498,7 +3069,9 @@ impl Client {
     }
 
     pub async fn get_stats(&self, sql: &str, param: Option<i64>) -> Result<Vec<(String, i64)>> {
-        let mut result = sqlx::query(sql).bind(param.unwrap_or(0)).fetch(&self.pool);
+        let mut result = sqlx::query(sql)
+            .bind(param.unwrap_or(0))
+            .fetch(&self.read_pool);
 
         let mut rows = Vec::new();
         while let Some(row) = result.try_next().await? {
@@ -553,6 +3126,19 @@ impl Client {
         .await
     }
 
+    pub async fn stats_failed_tasks(&self, limit: i64) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT coalesce(error, ''), count(*) num
+            FROM tasks
+            WHERE failed_at IS NOT NULL
+            GROUP BY error
+            ORDER BY num DESC
+            LIMIT $1",
+            Some(limit),
+        )
+        .await
+    }
+
     pub async fn stats_aliases_with_reason(&self) -> Result<Vec<(String, i64)>> {
         self.get_stats(
             "select '%', floor(100.0*(select count(*) from aliases where reason is not null)/(select count(*) from aliases))::bigint as percent",
@@ -563,12 +3149,148 @@ impl Client {
 
     pub async fn stats_compressed_artifacts(&self) -> Result<Vec<(String, i64)>> {
         self.get_stats(
-            "select '%', floor(100.0*(select count(*) from artifacts where files_compressed is not null)/(select count(*) from artifacts))::bigint as percent",
+            "select '%', floor(100.0*(select count(*) from artifacts where files_compressed is not null)/(select count(*) from artifacts))::bigint as percent",
+            None,
+        )
+        .await
+    }
+
+    pub async fn stats_sbom_strains(&self) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT strain, count(*)
+            FROM sboms
+            GROUP BY strain
+            ORDER BY strain",
+            None,
+        )
+        .await
+    }
+
+    pub async fn stats_sbom_top_dependencies(&self, limit: i64) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT strain || ' ' || name, count(DISTINCT sbom_chksum) num
+            FROM sbom_packages
+            GROUP BY strain, name
+            ORDER BY num DESC, strain, name
+            LIMIT $1",
+            Some(limit),
+        )
+        .await
+    }
+
+    pub async fn stats_sbom_packages_with_checksum(&self) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT strain, floor(100.0 * count(checksum) / count(*))::bigint
+            FROM sbom_packages
+            GROUP BY strain
+            ORDER BY strain",
+            None,
+        )
+        .await
+    }
+
+    /// Share of packages whose checksum points to an artifact we have imported
+    pub async fn stats_sbom_packages_resolvable(&self) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT strain, floor(100.0 * count(*) FILTER (
+                WHERE EXISTS (SELECT 1 FROM artifacts a WHERE a.chksum = p.checksum)
+                OR EXISTS (SELECT 1 FROM aliases x WHERE x.alias_from = p.checksum)
+            ) / count(*))::bigint
+            FROM sbom_packages p
+            GROUP BY strain
+            ORDER BY strain",
+            None,
+        )
+        .await
+    }
+
+    pub async fn heartbeat_worker(&self, name: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO worker_heartbeats (name, last_seen)
+            VALUES ($1, now())
+            ON CONFLICT (name) DO UPDATE SET
+            last_seen = EXCLUDED.last_seen",
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_sync_cursor(&self, name: &str) -> Result<Option<String>> {
+        let cursor = sqlx::query_scalar(
+            "SELECT cursor
+            FROM sync_cursors
+            WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(cursor)
+    }
+
+    pub async fn set_sync_cursor(&self, name: &str, cursor: &str) -> Result<()> {
+        let _result = sqlx::query(
+            "INSERT INTO sync_cursors (name, cursor, updated_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (name) DO UPDATE SET
+            cursor = EXCLUDED.cursor,
+            updated_at = EXCLUDED.updated_at",
+        )
+        .bind(name)
+        .bind(cursor)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn status_migration_version(&self) -> Result<Option<i64>> {
+        let version = sqlx::query_scalar(
+            "SELECT max(version)
+            FROM _sqlx_migrations
+            WHERE success",
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(version)
+    }
+
+    /// The most recent time a ref of each vendor was seen by a sync
+    pub async fn status_last_sync(&self) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let result = sqlx::query_as(
+            "SELECT vendor, max(last_seen)
+            FROM refs
+            GROUP BY vendor
+            ORDER BY vendor",
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(result)
+    }
+
+    /// Seconds since each worker last reported in
+    pub async fn status_worker_heartbeats(&self) -> Result<Vec<(String, i64)>> {
+        self.get_stats(
+            "SELECT name, extract(epoch FROM now() - last_seen)::bigint
+            FROM worker_heartbeats
+            ORDER BY name",
             None,
         )
         .await
     }
 
+    pub async fn status_task_backlog(&self) -> Result<i64> {
+        let num = sqlx::query_scalar(
+            "SELECT count(*)
+            FROM tasks
+            WHERE retries < $1",
+        )
+        .bind(RETRY_LIMIT)
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(num)
+    }
+
     pub async fn dangling_artifacts(&self) -> Result<Vec<String>> {
         let mut result = sqlx::query(
             "select * from (
@@ -590,6 +3312,48 @@ impl Client {
     }
 }
 
+/// The database rows of an ingested archive, see `Client::insert_import`
+#[derive(Debug)]
+pub struct Import<'a> {
+    pub chksum: String,
+    pub files: &'a [ingest::tar::Entry],
+    pub totals: &'a Totals,
+    /// Other checksums that point to this artifact, with the reason
+    pub aliases: Vec<(String, String)>,
+    pub sbom_refs: &'a [sbom::Ref],
+}
+
+impl<'a> Import<'a> {
+    pub fn new(
+        chksum: &str,
+        files: &'a [ingest::tar::Entry],
+        totals: &'a Totals,
+        sbom_refs: &'a [sbom::Ref],
+    ) -> Self {
+        Import {
+            chksum: chksum.to_string(),
+            files,
+            totals,
+            aliases: Vec::new(),
+            sbom_refs,
+        }
+    }
+
+    pub fn add_alias(&mut self, alias_from: &str, reason: &str) {
+        self.aliases
+            .push((alias_from.to_string(), reason.to_string()));
+    }
+
+    /// Alias all digests of `chksums`, the canonical one is skipped
+    pub fn add_chksums_aliases(&mut self, chksums: &Checksums, label: &str) {
+        if chksums.sha256 != self.chksum {
+            self.add_alias(&chksums.sha256, &format!("sha256({label})"));
+        }
+        self.add_alias(&chksums.sha512, &format!("sha512({label})"));
+        self.add_alias(&chksums.blake2b, &format!("blake2b({label})"));
+    }
+}
+
 #[derive(sqlx::FromRow, Debug, Serialize)]
 pub struct Artifact {
     pub chksum: String,
@@ -601,9 +3365,20 @@ pub struct Artifact {
     pub files: Option<serde_json::Value>,
     #[serde(skip)]
     pub files_compressed: Option<Vec<u8>>,
+    pub totals: Option<serde_json::Value>,
 }
 
 impl Artifact {
+    /// Only artifacts ingested since the totals were introduced have them
+    pub fn get_totals(&self) -> Result<Option<Totals>> {
+        let totals = self
+            .totals
+            .clone()
+            .map(serde_json::from_value)
+            .transpose()?;
+        Ok(totals)
+    }
+
     pub fn get_files(&self) -> Result<Option<Vec<ingest::tar::Entry>>> {
         if let Some(files) = &self.files {
             let files = serde_json::from_value(files.clone())?;
@@ -639,7 +3414,7 @@ pub struct SbomRef {
     pub path: String,
 }
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
 pub struct Ref {
     pub chksum: String,
     pub vendor: String,
@@ -648,6 +3423,413 @@ pub struct Ref {
     pub filename: Option<String>,
 }
 
+#[derive(sqlx::FromRow, Debug)]
+pub struct ResolvedRef {
+    #[sqlx(flatten)]
+    pub r: Ref,
+    pub artifact: String,
+}
+
+/// A binary package, linked to the source package it was built from
+#[derive(sqlx::FromRow, Debug, PartialEq, Serialize)]
+pub struct BinaryPackage {
+    pub chksum: String,
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub source_package: String,
+    pub source_version: String,
+    pub commit: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct RefRecord {
+    pub id: i64,
+    pub chksum: String,
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub filename: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl From<RefRecord> for RefView {
+    fn from(r: RefRecord) -> Self {
+        Ref {
+            chksum: r.chksum,
+            vendor: r.vendor,
+            package: r.package,
+            version: r.version,
+            filename: r.filename,
+        }
+        .into()
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct Changelog {
+    pub vendor: Option<String>,
+    pub package: String,
+    pub version: String,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    pub text: String,
+}
+
+impl Changelog {
+    pub fn matches(&self, r: &RefView) -> bool {
+        self.package == r.package
+            && self.version == r.version
+            && self
+                .vendor
+                .as_ref()
+                .is_none_or(|vendor| *vendor == r.vendor)
+    }
+}
+
+/// A patch or spec file shipped in a source package, e.g. a source rpm
+#[derive(Debug)]
+pub struct NewPatch {
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub filename: String,
+    pub chksum: String,
+    pub size: i64,
+    pub content: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct PatchRef {
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub filename: String,
+    pub chksum: String,
+    pub size: i64,
+}
+
+impl PatchRef {
+    pub fn matches(&self, r: &RefView) -> bool {
+        self.vendor == r.vendor && self.package == r.package && self.version == r.version
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct RefSuite {
+    pub chksum: String,
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub suite: String,
+    pub component: String,
+}
+
+impl RefSuite {
+    pub fn matches(&self, r: &RefView) -> bool {
+        self.chksum == r.chksum
+            && self.vendor == r.vendor
+            && self.package == r.package
+            && self.version == r.version
+    }
+}
+
+/// Build provenance for an artifact, the subject of the statement has been matched to it
+#[derive(Debug)]
+pub struct NewAttestation {
+    pub chksum: String,
+    pub digest: String,
+    pub subject_name: String,
+    pub predicate_type: String,
+    pub builder_id: Option<String>,
+    pub build_type: Option<String>,
+    pub source_uri: Option<String>,
+    pub statement: serde_json::Value,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct Attestation {
+    #[serde(skip)]
+    pub id: i64,
+    pub chksum: String,
+    pub digest: String,
+    pub subject_name: String,
+    pub predicate_type: String,
+    pub builder_id: Option<String>,
+    pub build_type: Option<String>,
+    pub source_uri: Option<String>,
+    #[serde(skip)]
+    pub statement: serde_json::Value,
+    #[serde(skip)]
+    pub first_seen: DateTime<Utc>,
+}
+
+/// The outcome of verifying a detached signature of a release artifact
+#[derive(Debug)]
+pub struct NewSignature {
+    pub chksum: String,
+    pub url: String,
+    pub signature_url: String,
+    pub kind: String,
+    pub status: String,
+    pub signer: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct Signature {
+    #[serde(skip)]
+    pub id: i64,
+    pub chksum: String,
+    pub url: String,
+    pub signature_url: String,
+    pub kind: String,
+    pub status: String,
+    pub signer: Option<String>,
+    #[serde(skip)]
+    pub verified_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Blob {
+    pub chksum: String,
+    pub size: i64,
+    pub compressed_size: i64,
+    pub first_seen: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Advisory {
+    pub ecosystem: String,
+    pub package: String,
+    pub version: String,
+    pub vulns: serde_json::Value,
+    pub last_checked: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct NewSubscription {
+    pub token: String,
+    pub confirm_token: String,
+    pub unsubscribe_token: String,
+    pub target_kind: &'static str,
+    pub target: String,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    /// Webhooks are active right away, email addresses need to be confirmed first
+    pub confirmed: bool,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct Subscription {
+    #[serde(skip)]
+    pub id: i64,
+    pub token: String,
+    #[serde(skip)]
+    pub confirm_token: String,
+    #[serde(skip)]
+    pub unsubscribe_token: String,
+    pub target_kind: String,
+    pub target: String,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    #[serde(skip)]
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub last_notified: DateTime<Utc>,
+    #[serde(skip)]
+    pub confirmation_sent_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// A finding of the detection pass, there's at most one per artifact and kind
+#[derive(Debug)]
+pub struct NewReport {
+    pub chksum: String,
+    pub kind: &'static str,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub summary: String,
+    pub score: i32,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct FileMatch {
+    pub chksum: String,
+    pub path: String,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct FileDigestMatch {
+    pub chksum: String,
+    pub path: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct RecentArtifact {
+    pub chksum: String,
+    pub last_imported: DateTime<Utc>,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct Report {
+    pub id: i64,
+    pub chksum: String,
+    pub kind: String,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub summary: String,
+    pub score: i32,
+    #[serde(skip)]
+    pub first_seen: DateTime<Utc>,
+    #[serde(skip)]
+    pub last_seen: DateTime<Utc>,
+}
+
+/// The files of a release tarball that are not in the `git archive` snapshot
+#[derive(Debug)]
+pub struct NewVcsDrift {
+    pub chksum: String,
+    pub vcs_chksum: String,
+    pub categories: serde_json::Value,
+    pub anomalies: serde_json::Value,
+    pub score: i32,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct VcsDrift {
+    pub chksum: String,
+    pub vcs_chksum: String,
+    pub categories: serde_json::Value,
+    pub anomalies: serde_json::Value,
+    pub score: i32,
+    #[serde(skip)]
+    pub first_seen: DateTime<Utc>,
+    #[serde(skip)]
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow, Debug, Serialize)]
+pub struct RefSigner {
+    pub chksum: String,
+    pub vendor: String,
+    pub package: String,
+    pub version: String,
+    pub fingerprint: String,
+    #[serde(skip)]
+    pub first_seen: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct NewUpstreamCheck {
+    pub url: String,
+    pub chksum: String,
+    pub status: &'static str,
+    pub http_status: Option<i32>,
+    pub observed_chksum: Option<String>,
+}
+
+/// Which artifacts a reindex job should visit
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtifactFilter {
+    /// Artifacts imported at or after this point are skipped, e.g. because the job re-imported them
+    pub imported_before: DateTime<Utc>,
+    /// Only artifacts that haven't been imported in this many days
+    pub age: Option<i64>,
+    /// Only artifacts first seen at or after this point
+    pub since: Option<DateTime<Utc>>,
+    pub chksum_prefix: Option<String>,
+    /// Only artifacts referenced by this vendor, directly or through an alias
+    pub vendor: Option<String>,
+}
+
+/// How far a reindex job got, `last_imported` and `chksum` are the last artifact it processed
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct ReindexCheckpoint {
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub last_imported: Option<DateTime<Utc>>,
+    pub chksum: Option<String>,
+    pub processed: i64,
+}
+
+/// What we know about upstream metadata from the previous sync
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct SyncState {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub digest: String,
+    /// When the metadata was generated upstream, e.g. the `Date` of an apt Release file
+    pub snapshot_date: Option<DateTime<Utc>>,
+    /// Fingerprint of the key that signed the metadata
+    pub signer: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct UpstreamCheck {
+    pub url: String,
+    pub chksum: String,
+    pub status: String,
+    pub http_status: Option<i32>,
+    pub observed_chksum: Option<String>,
+    pub last_verified: DateTime<Utc>,
+    pub gone_since: Option<DateTime<Utc>>,
+    pub replaced_since: Option<DateTime<Utc>>,
+}
+
+/// An artifact that was fetched from an untrusted url, along with the ref it would publish
+#[derive(Debug)]
+pub struct NewQuarantine {
+    pub chksum: String,
+    pub url: String,
+    pub success_ref: Option<Ref>,
+    pub reason: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Quarantine {
+    pub id: i64,
+    pub chksum: String,
+    pub url: String,
+    pub ref_chksum: Option<String>,
+    pub vendor: Option<String>,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub reason: String,
+    pub rejected: bool,
+    pub first_seen: DateTime<Utc>,
+}
+
+impl Quarantine {
+    pub fn success_ref(&self) -> Option<Ref> {
+        Some(Ref {
+            chksum: self.ref_chksum.clone()?,
+            vendor: self.vendor.clone()?,
+            package: self.package.clone()?,
+            version: self.version.clone()?,
+            filename: Some(self.url.clone()),
+        })
+    }
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Watchlist {
+    pub id: i64,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+    pub last_visit: Option<DateTime<Utc>>,
+}
+
 #[derive(sqlx::FromRow, Debug, Serialize)]
 pub struct RefView {
     pub chksum: String,
@@ -657,6 +3839,7 @@ pub struct RefView {
     pub version: String,
     pub filename: Option<String>,
     pub href: Option<String>,
+    pub purl: Option<String>,
 }
 
 impl From<Ref> for RefView {
@@ -730,6 +3913,13 @@ impl From<Ref> for RefView {
                 );
                 (Cow::Borrowed("Void Linux"), Some(href))
             }
+            "buildroot" => {
+                let href = format!(
+                    "https://gitlab.com/buildroot.org/buildroot/-/tree/master/package/{}",
+                    r.package
+                );
+                (Cow::Borrowed("Buildroot"), Some(href))
+            }
             "yocto" => {
                 let href = format!(
                     "https://layers.openembedded.org/layerindex/branch/master/recipes/?q={}",
@@ -737,9 +3927,41 @@ impl From<Ref> for RefView {
                 );
                 (Cow::Borrowed("Yocto Project"), Some(href))
             }
+            "msys2" => {
+                let href = format!("https://packages.msys2.org/base/{}", r.package);
+                (Cow::Borrowed("MSYS2"), Some(href))
+            }
+            "conan" => {
+                let href = format!(
+                    "https://conan.io/center/recipes/{}?version={}",
+                    r.package, r.version
+                );
+                (Cow::Borrowed("ConanCenter"), Some(href))
+            }
+            "registry.npmjs.org" => {
+                let href = format!(
+                    "https://www.npmjs.com/package/{}/v/{}",
+                    r.package, r.version
+                );
+                (Cow::Borrowed("npm"), Some(href))
+            }
+            "proxy.golang.org" => {
+                let href = format!("https://pkg.go.dev/{}@{}", r.package, r.version);
+                (Cow::Borrowed("Go module proxy"), Some(href))
+            }
+            "pypi" => {
+                let href = format!("https://pypi.org/project/{}/{}/", r.package, r.version);
+                (Cow::Borrowed("PyPI"), Some(href))
+            }
+            "vcpkg" => {
+                let href = format!("https://vcpkg.io/en/package/{}", r.package);
+                (Cow::Borrowed("vcpkg"), Some(href))
+            }
             other => (Cow::Owned(other.to_owned()), None),
         };
 
+        let purl = Purl::for_ref(&r.vendor, &r.package, &r.version).map(|p| p.to_string());
+
         RefView {
             chksum: r.chksum,
             vendor: r.vendor,
@@ -748,10 +3970,18 @@ impl From<Ref> for RefView {
             version: r.version,
             filename: r.filename,
             href,
+            purl,
         }
     }
 }
 
+/// Restrict which kind of tasks a worker picks up, the kind is the prefix of the task key
+#[derive(Debug, Default, PartialEq)]
+pub struct TaskFilter {
+    pub only: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
 #[derive(sqlx::FromRow, Debug, Serialize)]
 pub struct Task {
     pub id: i64,
@@ -759,6 +3989,8 @@ pub struct Task {
     pub data: serde_json::Value,
     pub retries: i16,
     pub error: Option<String>,
+    #[serde(skip)]
+    pub failed_at: Option<DateTime<Utc>>,
 }
 
 impl Task {
@@ -770,6 +4002,7 @@ impl Task {
             data,
             retries: 0,
             error: None,
+            failed_at: None,
         })
     }
 
@@ -815,12 +4048,50 @@ pub enum TaskData {
     },
     GitSnapshot {
         url: String,
+        /// Take precedence over the `#tag=` or `#commit=` of the url
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
+    },
+    HgSnapshot {
+        url: String,
+        /// Takes precedence over the `#rev=` of the url
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+    },
+    FossilSnapshot {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
     },
     IndexSbom {
         // support old task format
         strain: Option<String>,
         chksum: String,
     },
+    VerifySignature {
+        url: String,
+        signature_url: String,
+    },
+    DebianSource {
+        vendor: String,
+        package: String,
+        version: String,
+        url: String,
+    },
+    ObsPackage {
+        vendor: String,
+        url: String,
+        project: String,
+        package: String,
+        spec: String,
+        srcmd5: String,
+    },
+    GoModule {
+        path: String,
+        version: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -864,4 +4135,80 @@ mod tests {
         let decompressed = decompress_json::<_, BTreeMap<String, String>>(&buf[..]).unwrap();
         assert_eq!(obj, decompressed);
     }
+
+    #[test]
+    fn test_is_substring_searchable() {
+        assert!(!is_substring_searchable(""));
+        assert!(!is_substring_searchable("a"));
+        assert!(!is_substring_searchable("xz"));
+        assert!(!is_substring_searchable("a*b*c"));
+        assert!(!is_substring_searchable("**"));
+        assert!(is_substring_searchable("lib"));
+        assert!(is_substring_searchable("xz*utils"));
+        assert!(is_substring_searchable("*zst*"));
+    }
+
+    #[test]
+    fn test_import_aliases() {
+        let inner = Checksums {
+            sha256: "sha256:aaaa".to_string(),
+            sha512: "sha512:bbbb".to_string(),
+            blake2b: "blake2b:cccc".to_string(),
+        };
+        let outer = Checksums {
+            sha256: "sha256:dddd".to_string(),
+            sha512: "sha512:eeee".to_string(),
+            blake2b: "blake2b:ffff".to_string(),
+        };
+        let totals = Totals::default();
+        let mut import = Import::new(&inner.sha256, &[], &totals, &[]);
+        import.add_chksums_aliases(&inner, "tar");
+        import.add_chksums_aliases(&outer, "gz(tar)");
+        assert_eq!(
+            import.aliases,
+            [
+                ("sha512:bbbb".to_string(), "sha512(tar)".to_string()),
+                ("blake2b:cccc".to_string(), "blake2b(tar)".to_string()),
+                ("sha256:dddd".to_string(), "sha256(gz(tar))".to_string()),
+                ("sha512:eeee".to_string(), "sha512(gz(tar))".to_string()),
+                ("blake2b:ffff".to_string(), "blake2b(gz(tar))".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("xz-5.6.1.tar.*"), "xz-5.6.1.tar.*");
+        assert_eq!(escape_like("python_dateutil"), "python\\_dateutil");
+        assert_eq!(escape_like("100%\\"), "100\\%\\\\");
+    }
+    #[test]
+    fn test_pool_config() {
+        let config = PoolConfig::from_lookup(|_| None).unwrap();
+        assert_eq!(
+            config,
+            PoolConfig {
+                max_connections: 5,
+                acquire_timeout: None,
+                statement_timeout: None,
+                application_name: None,
+            }
+        );
+
+        let config = PoolConfig::from_lookup(|key| match key {
+            "WHATSRC_DB_MAX_CONNECTIONS" => Some("32".to_string()),
+            "WHATSRC_DB_STATEMENT_TIMEOUT" => Some("10".to_string()),
+            "WHATSRC_DB_APPLICATION_NAME" => Some("what-the-src-web".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(config.max_connections, 32);
+        assert_eq!(config.statement_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.application_name.as_deref(), Some("what-the-src-web"));
+
+        assert!(PoolConfig::from_lookup(|key| {
+            (key == "WHATSRC_DB_ACQUIRE_TIMEOUT").then(|| "5s".to_string())
+        })
+        .is_err());
+    }
 }
@@ -0,0 +1,210 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use futures::StreamExt;
+
+/// Split a version string into the upstream version and the package release, if any
+///
+/// This removes the epoch (`1:1.2.3`) for all vendors and splits off the package
+/// release (`1.2.3-1`, `1.2.3-r0`, `1.2.3_1`) for vendors that are known to append one.
+pub fn split_version<'a>(vendor: &str, version: &'a str) -> (&'a str, Option<&'a str>) {
+    let version = match version.split_once(':') {
+        Some((epoch, rest)) if !epoch.is_empty() && epoch.bytes().all(|b| b.is_ascii_digit()) => {
+            rest
+        }
+        _ => version,
+    };
+
+    let release = match vendor {
+        "alpine" | "gentoo" => version
+            .rsplit_once("-r")
+            .filter(|(_, rel)| !rel.is_empty() && rel.bytes().all(|b| b.is_ascii_digit())),
+        "void" => version
+            .rsplit_once('_')
+            .filter(|(_, rel)| !rel.is_empty() && rel.bytes().all(|b| b.is_ascii_digit())),
+        "archlinux" | "debian" | "ubuntu" | "kali" | "fedora" | "opensuse" | "homebrew" => {
+            version.rsplit_once('-')
+        }
+        _ => None,
+    };
+    match release {
+        Some((version, release)) => (version, Some(release)),
+        None => (version, None),
+    }
+}
+
+/// Find refs that are trivially formatted variants of another ref in the same group
+///
+/// All refs in the group are expected to share vendor, package and chksum. Only refs
+/// that differ by their epoch, or lack a package release, are merged into a ref of the
+/// same upstream version. Two refs with different package releases are separate
+/// releases of the vendor and are always kept. Within a release the most recently
+/// seen ref comes first and is kept, since it's what the vendor currently publishes.
+/// Returns pairs of `(duplicate, canonical)`.
+pub fn find_duplicates(group: &[db::RefRecord]) -> Vec<(&db::RefRecord, &db::RefRecord)> {
+    let versions = group
+        .iter()
+        .map(|r| split_version(&r.vendor, &r.version))
+        .collect::<Vec<_>>();
+    let mut canonical = vec![None; group.len()];
+
+    // epoch-only variants, with or without a release, go into the most recent ref
+    for idx in 0..group.len() {
+        canonical[idx] =
+            (0..idx).find(|&other| canonical[other].is_none() && versions[other] == versions[idx]);
+    }
+
+    // refs without release go into the most recent ref that has one
+    for idx in 0..group.len() {
+        let (upstream, release) = versions[idx];
+        if release.is_some() || canonical[idx].is_some() {
+            continue;
+        }
+        canonical[idx] = (0..group.len()).find(|&other| {
+            canonical[other].is_none()
+                && versions[other].0 == upstream
+                && versions[other].1.is_some()
+        });
+    }
+
+    // point variants of variants to the ref that's kept
+    (0..group.len())
+        .filter_map(|idx| {
+            let mut target = canonical[idx]?;
+            while let Some(next) = canonical[target] {
+                target = next;
+            }
+            Some((&group[idx], &group[target]))
+        })
+        .collect()
+}
+
+async fn process_group(db: &db::Client, group: &[db::RefRecord], apply: bool) -> Result<usize> {
+    let duplicates = find_duplicates(group);
+    for (dup, canonical) in &duplicates {
+        info!(
+            "Found duplicate ref: vendor={:?} package={:?} chksum={:?} version={:?} canonical={:?}",
+            dup.vendor, dup.package, dup.chksum, dup.version, canonical.version
+        );
+        if apply {
+            db.merge_ref_into(dup, canonical).await?;
+        }
+    }
+    Ok(duplicates.len())
+}
+
+pub async fn run(args: &args::DedupRefs) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let mut found = 0;
+    let mut group = Vec::<db::RefRecord>::new();
+
+    let stream = db.get_ref_duplicate_candidates(args.vendor.as_deref());
+    tokio::pin!(stream);
+    while let Some(r) = stream.next().await {
+        let r = r?;
+
+        if let Some(prev) = group.first() {
+            if (&prev.vendor, &prev.package, &prev.chksum) != (&r.vendor, &r.package, &r.chksum) {
+                found += process_group(&db, &group, args.apply).await?;
+                group.clear();
+            }
+        }
+        group.push(r);
+    }
+    found += process_group(&db, &group, args.apply).await?;
+
+    if args.apply {
+        info!("Merged {found} duplicate refs");
+    } else {
+        info!("Found {found} duplicate refs, use --apply to merge them");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::chrono::DateTime;
+
+    fn record(id: i64, vendor: &str, version: &str) -> db::RefRecord {
+        db::RefRecord {
+            id,
+            chksum: "sha256:55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0"
+                .to_string(),
+            vendor: vendor.to_string(),
+            package: "xz".to_string(),
+            version: version.to_string(),
+            filename: None,
+            first_seen: DateTime::UNIX_EPOCH,
+            last_seen: DateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_split_version() {
+        assert_eq!(split_version("debian", "1:1.2.3-1"), ("1.2.3", Some("1")));
+        assert_eq!(split_version("debian", "1.2.3"), ("1.2.3", None));
+        assert_eq!(
+            split_version("archlinux", "2:5.6.1-2"),
+            ("5.6.1", Some("2"))
+        );
+        assert_eq!(split_version("alpine", "5.6.1-r0"), ("5.6.1", Some("0")));
+        assert_eq!(split_version("alpine", "5.6.1-rc2"), ("5.6.1-rc2", None));
+        assert_eq!(split_version("void", "5.6.1_1"), ("5.6.1", Some("1")));
+        assert_eq!(
+            split_version("live-bootstrap", "5.6.1-2"),
+            ("5.6.1-2", None)
+        );
+        assert_eq!(split_version("guix", "a:5.6.1"), ("a:5.6.1", None));
+    }
+
+    fn find(group: &[db::RefRecord]) -> Vec<(i64, i64)> {
+        find_duplicates(group)
+            .into_iter()
+            .map(|(dup, canonical)| (dup.id, canonical.id))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let group = [
+            record(3, "debian", "5.6.1-1"),
+            record(2, "debian", "5.6.1"),
+            record(1, "debian", "1:5.6.1"),
+            record(4, "debian", "5.6.0-1"),
+        ];
+        assert_eq!(find(&group), vec![(2, 3), (1, 3)]);
+    }
+
+    #[test]
+    fn test_find_duplicates_keeps_releases() {
+        let group = [
+            record(1, "debian", "5.6.1-1"),
+            record(2, "debian", "5.6.1-2"),
+        ];
+        assert_eq!(find(&group), vec![]);
+
+        let group = [
+            record(1, "alpine", "5.6.1-r0"),
+            record(2, "alpine", "5.6.1-r1"),
+        ];
+        assert_eq!(find(&group), vec![]);
+
+        let group = [record(1, "void", "5.6.1_1"), record(2, "void", "5.6.1_2")];
+        assert_eq!(find(&group), vec![]);
+    }
+
+    #[test]
+    fn test_find_duplicates_prefers_release() {
+        // the revision-less variant is the most recent, but the release is what's kept
+        let group = [
+            record(1, "debian", "5.6.1"),
+            record(2, "debian", "1:5.6.1-2"),
+            record(3, "debian", "5.6.1-2"),
+            record(4, "debian", "5.6.1-1"),
+        ];
+        assert_eq!(find(&group), vec![(1, 2), (3, 2)]);
+    }
+}
@@ -4,6 +4,7 @@ use std::process::ExitStatus;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[cfg(feature = "web")]
     #[error(transparent)]
     TemplateError(#[from] handlebars::TemplateError),
     #[error(transparent)]
@@ -12,6 +13,7 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Migrate(#[from] sqlx::migrate::MigrateError),
+    #[cfg(feature = "web")]
     #[error(transparent)]
     RenderError(#[from] handlebars::RenderError),
     #[error(transparent)]
@@ -19,6 +21,8 @@ pub enum Error {
     #[error(transparent)]
     Xml(#[from] serde_xml_rs::Error),
     #[error(transparent)]
+    XmlReader(#[from] xml::reader::Error),
+    #[error(transparent)]
     Toml(#[from] toml::de::Error),
     #[error(transparent)]
     Plist(#[from] plist::Error),
@@ -45,11 +49,26 @@ pub enum Error {
     #[error(transparent)]
     Regex(#[from] regex::Error),
     #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[cfg(feature = "web")]
+    #[error(transparent)]
     InvalidUri(#[from] warp::http::uri::InvalidUri),
+    #[cfg(feature = "web")]
     #[error(transparent)]
     SerdeUrl(#[from] serde_urlencoded::ser::Error),
+    #[cfg(feature = "web")]
+    #[error(transparent)]
+    Getrandom(#[from] getrandom::Error),
+    #[cfg(feature = "otel")]
+    #[error(transparent)]
+    OtlpExporter(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[cfg(feature = "otel")]
+    #[error(transparent)]
+    TracingInit(#[from] tracing_subscriber::util::TryInitError),
     #[error(transparent)]
     JoinError(#[from] tokio::task::JoinError),
+    #[error(transparent)]
+    InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
     #[error("Child process has exited with error: {0}")]
     ChildExit(std::process::ExitStatus),
     #[error("Parser encountered invalid data")]
@@ -72,12 +91,32 @@ pub enum Error {
     GitFetchError(ExitStatus),
     #[error("Failed to parse git rev-parse output")]
     GitRevParseError(String),
+    #[error("Unknown version control reference string: {0:?}")]
+    UnknownVcsRef(String),
+    #[error("Version control url is missing a revision: {0:?}")]
+    MissingVcsRef(String),
+    #[error("Invalid version control revision: {0:?}")]
+    InvalidVcsRef(String),
+    #[error("Version control url needs to use https: {0:?}")]
+    InsecureVcsUrl(String),
+    #[error("Error in version control operation")]
+    VcsError(ExitStatus),
+    #[error("Timeout of version control clone operation")]
+    VcsFetchTimeout,
+    #[error("Failed to parse version control revision: {0:?}")]
+    VcsRevParseError(String),
+    #[error("Git reference resolved to commit {actual:?}, expected {expected:?}")]
+    GitCommitMismatch { expected: String, actual: String },
     #[error("Failed to determine filename for Sources index")]
     AptIndexMissingSources,
+    #[error("Failed to verify signature of InRelease file: {0}")]
+    AptInvalidSignature(&'static str),
     #[error("Unknown sbom strain: {0:?}")]
     UnknownSbomStrain(String),
     #[error("Task is missing mandatory repo field")]
     AlpineMissingRepo,
+    #[error("Syncing multiple repositories requires --arch, the file is used as mirror url")]
+    AlpineMultipleRepos,
     #[error("APKINDEX is missing mandatory field: {0:?}")]
     ApkMissingField(&'static str),
     #[error("Unrecognized apk vendor: {0:?}")]
@@ -86,11 +125,60 @@ pub enum Error {
     WolfiMissingChecksum(ingest::wolfi::Step),
     #[error("Unrecognized substitute in wolfi package: {0:?}")]
     WolfiUnknownSubstitute(String),
+    #[error("Artifact not found: {0:?}")]
+    UnknownArtifact(String),
+    #[error("Invalid point in time, expected YYYY-MM-DD or RFC 3339: {0:?}")]
+    InvalidTimestamp(String),
+    #[error("Binary package is missing mandatory field: {0:?}")]
+    BinaryPackageMissingField(&'static str),
+    #[error("Scrub detected {0} corrupted entries")]
+    ScrubFailed(usize),
     #[error("String is poisoned, failed to interpolate: {0:?}")]
     YoctoPoisonedStr(String),
+    #[error("Too many nested includes in bitbake recipe: {0:?}")]
+    YoctoIncludeDepth(String),
+    #[error("Invalid checksum: {0:?}")]
+    InvalidChecksum(String),
+    #[error("Unknown checksum family: {0:?}")]
+    UnknownChecksumFamily(String),
+    #[error("Unsupported attestation format: {0:?}")]
+    UnsupportedAttestation(String),
+    #[error("Invalid package url: {0:?}")]
+    InvalidPurl(String),
+    #[error("Missing query parameter: {0}")]
+    MissingQueryParameter(&'static str),
+    #[error("Invalid subscription: {0}")]
+    InvalidSubscription(&'static str),
+    #[error("Failed to deliver notification: {0}")]
+    NotificationFailed(String),
+    #[error("Unsupported signature format: {0:?}")]
+    UnsupportedSignature(String),
+    #[error("Signed file is not a known artifact (yet): {0:?}")]
+    UnknownSignedArtifact(String),
+    #[error("Debian source control file is missing mandatory field: {0:?}")]
+    DscMissingField(&'static str),
+    #[error("RPM spec file is missing mandatory field: {0:?}")]
+    RpmSpecMissingField(&'static str),
+    #[error("Invalid Chimera Linux template: {0}")]
+    InvalidChimeraTemplate(String),
+    #[error("Koji api returned an error: {0}")]
+    KojiFault(String),
+    #[error("Invalid XML-RPC response: {0}")]
+    InvalidXmlRpc(String),
+    #[error("Checksum mismatch, expected {expected:?} but got {actual:?}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("Invalid blob storage configuration: {0}")]
+    InvalidStorageConfig(&'static str),
+    #[error("Invalid database configuration: {0}")]
+    InvalidDatabaseConfig(&'static str),
+    #[error("Invalid download size limit: {0:?}")]
+    InvalidSizeLimit(String),
+    #[error("Download of {size} bytes exceeds the size limit of {limit} bytes")]
+    DownloadTooLarge { size: u64, limit: u64 },
 }
 
 // TODO: consider fixing this
+#[cfg(feature = "web")]
 impl warp::reject::Reject for Error {}
 
 pub type Result<T> = std::result::Result<T, Error>;
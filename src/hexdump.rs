@@ -0,0 +1,115 @@
+use std::fmt::Write as _;
+
+/// Members up to this size are shown as hex dump, if their content is archived
+pub const MAX_SIZE: u64 = 64 * 1024;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Magic bytes at the start of a file, along with a description of the file type
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x7fELF", "ELF binary"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O binary (64-bit)"),
+    (b"\xce\xfa\xed\xfe", "Mach-O binary (32-bit)"),
+    (
+        b"\xca\xfe\xba\xbe",
+        "Java class file or Mach-O universal binary",
+    ),
+    (b"MZ", "DOS/PE executable"),
+    (b"\0asm", "WebAssembly module"),
+    (b"!<arch>\n", "ar archive"),
+    (b"\x1f\x8b", "gzip compressed data"),
+    (b"\xfd7zXZ\0", "xz compressed data"),
+    (b"BZh", "bzip2 compressed data"),
+    (b"\x28\xb5\x2f\xfd", "zstd compressed data"),
+    (b"\x04\x22\x4d\x18", "lz4 compressed data"),
+    (b"LZIP", "lzip compressed data"),
+    (b"PK\x03\x04", "zip archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7-zip archive"),
+    (b"\xed\xab\xee\xdb", "rpm package"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"%PDF-", "PDF document"),
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"-----BEGIN PGP ", "OpenPGP armored data"),
+    (b"#!", "script"),
+];
+
+/// Identify the type of a file by its magic bytes
+pub fn detect_type(data: &[u8]) -> Option<&'static str> {
+    if let Some((_, description)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(description);
+    }
+    // tar has its magic in the header, not at the start of the file
+    if data.get(257..262) == Some(b"ustar") {
+        return Some("tar archive");
+    }
+    None
+}
+
+/// Format data like `hexdump -C`, with offset, hex and ascii columns
+pub fn format(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (idx, line) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(out, "{:08x} ", idx * BYTES_PER_LINE).ok();
+        for i in 0..BYTES_PER_LINE {
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match line.get(i) {
+                Some(b) => write!(out, "{b:02x} ").ok(),
+                None => write!(out, "   ").ok(),
+            };
+        }
+        out.push_str(" |");
+        for b in line {
+            let c = if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            };
+            out.push(c);
+        }
+        out.push_str("|\n");
+    }
+    writeln!(out, "{:08x}", data.len()).ok();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let data = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0>\0hello";
+        assert_eq!(
+            format(data),
+            "\
+00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|
+00000010  03 00 3e 00 68 65 6c 6c  6f                       |..>.hello|
+00000019
+"
+        );
+        assert_eq!(format(b""), "00000000\n");
+    }
+
+    #[test]
+    fn test_detect_type() {
+        assert_eq!(detect_type(b"\x7fELF\x02\x01\x01"), Some("ELF binary"));
+        assert_eq!(
+            detect_type(b"\xfd7zXZ\0\0\x04\xe6\xd6\xb4\x46"),
+            Some("xz compressed data")
+        );
+        assert_eq!(detect_type(b"#!/bin/sh\nexit 0\n"), Some("script"));
+
+        let mut tar = vec![0; 512];
+        tar[..9].copy_from_slice(b"configure");
+        tar[257..263].copy_from_slice(b"ustar\0");
+        assert_eq!(detect_type(&tar), Some("tar archive"));
+
+        assert_eq!(detect_type(b"AC_INIT([xz], [5.6.1])\n"), None);
+        assert_eq!(detect_type(b""), None);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::apkbuild;
 use crate::db::{self, Task, TaskData};
 use crate::errors::*;
+use crate::upstream;
 use crate::utils;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
@@ -17,6 +18,10 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
     info!("Parsing APKBUILD");
     let apkbuild = apkbuild::parse(&buf)?;
 
+    if let Some(url) = apkbuild.extra.get("url") {
+        upstream::insert(db, url, vendor, package).await?;
+    }
+
     for i in 0..apkbuild.source.len() {
         let Some(url) = apkbuild.source.get(i) else {
             continue;
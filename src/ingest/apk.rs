@@ -0,0 +1,141 @@
+use crate::args;
+use crate::chksums::Hasher;
+use crate::db;
+use crate::errors::*;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use tokio::fs::File;
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tokio_tar::Archive;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PkgInfo {
+    pub pkgname: Option<String>,
+    pub pkgver: Option<String>,
+    pub origin: Option<String>,
+    pub commit: Option<String>,
+}
+
+pub fn parse_pkginfo(txt: &str) -> PkgInfo {
+    let mut info = PkgInfo::default();
+    for line in txt.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = Some(value.to_string());
+        match key {
+            "pkgname" => info.pkgname = value,
+            "pkgver" => info.pkgver = value,
+            "origin" => info.origin = value,
+            "commit" => info.commit = value,
+            _ => (),
+        }
+    }
+    info
+}
+
+/// Read the .PKGINFO of an .apk and link it to the source package it was built from
+pub async fn stream_data<R: AsyncRead + Unpin>(
+    reader: R,
+    vendor: &str,
+) -> Result<db::BinaryPackage> {
+    let reader = io::BufReader::new(Hasher::new(reader));
+    // an .apk consists of multiple concatenated gzip streams (signature, control, data)
+    let mut reader = GzipDecoder::new(reader);
+    reader.multiple_members(true);
+
+    let mut pkginfo = None;
+    {
+        let mut tar = Archive::new(&mut reader);
+        let mut entries = tar.entries()?;
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry?;
+            let path = entry.path()?;
+            debug!("Found entry in .apk: {path:?}");
+            if path.to_str() == Some(".PKGINFO") {
+                let mut buf = String::new();
+                entry.read_to_string(&mut buf).await?;
+                pkginfo = Some(parse_pkginfo(&buf));
+                break;
+            }
+        }
+    }
+    let pkginfo = pkginfo.ok_or(Error::BinaryPackageMissingField(".PKGINFO"))?;
+
+    // consume the remaining data so the checksum covers the whole file
+    let mut reader = reader.into_inner();
+    io::copy(&mut reader, &mut io::sink()).await?;
+    let (_, digests) = reader.into_inner().digests();
+
+    let package = pkginfo
+        .pkgname
+        .ok_or(Error::BinaryPackageMissingField("pkgname"))?;
+    let version = pkginfo
+        .pkgver
+        .ok_or(Error::BinaryPackageMissingField("pkgver"))?;
+    let source_package = pkginfo.origin.unwrap_or_else(|| package.clone());
+
+    Ok(db::BinaryPackage {
+        chksum: digests.sha256,
+        vendor: vendor.to_string(),
+        package,
+        source_version: version.clone(),
+        version,
+        source_package,
+        commit: pkginfo.commit,
+    })
+}
+
+pub async fn run(args: &args::IngestApk) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let input: Box<dyn AsyncRead + Unpin> = if let Some(path) = &args.file {
+        Box::new(File::open(path).await?)
+    } else {
+        Box::new(io::stdin())
+    };
+
+    let pkg = stream_data(input, &args.vendor).await?;
+    super::insert_binary_package(&db, &pkg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pkginfo() {
+        let data = r#"# Generated by abuild 3.13.0-r3
+# using fakeroot version 1.32.1
+# Tue Apr  2 10:46:53 UTC 2024
+pkgname = xz-libs
+pkgver = 5.6.1-r2
+pkgdesc = Library for XZ and LZMA compressed files
+url = https://tukaani.org/xz
+builddate = 1712054813
+packager = Buildozer <alpine-devel@lists.alpinelinux.org>
+size = 180224
+arch = x86_64
+origin = xz
+commit = 02a0a0e3e5a5f7bb8cb0ff4e6e4e5aa0a6d3aec6
+maintainer = Natanael Copa <ncopa@alpinelinux.org>
+license = 0BSD
+depend = so:libc.musl-x86_64.so.1
+provides = so:liblzma.so.5=5.6.1
+datahash = 1de0d3c6ab6f5f6e0a47d8d3b04f18a46c5b8af1f67e4cb4ef5c7d6b0f55e32c
+"#;
+        let pkginfo = parse_pkginfo(data);
+        assert_eq!(
+            pkginfo,
+            PkgInfo {
+                pkgname: Some("xz-libs".to_string()),
+                pkgver: Some("5.6.1-r2".to_string()),
+                origin: Some("xz".to_string()),
+                commit: Some("02a0a0e3e5a5f7bb8cb0ff4e6e4e5aa0a6d3aec6".to_string()),
+            }
+        );
+    }
+}
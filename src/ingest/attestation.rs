@@ -0,0 +1,262 @@
+use crate::args;
+use crate::chksums;
+use crate::db;
+use crate::errors::*;
+use data_encoding::BASE64;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tokio::fs;
+
+pub const STATEMENT_TYPE_V1: &str = "https://in-toto.io/Statement/v1";
+pub const STATEMENT_TYPE_V01: &str = "https://in-toto.io/Statement/v0.1";
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// A sigstore bundle, as published by npm and GitHub artifact attestations
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    dsse_envelope: Envelope,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    payload_type: String,
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Document {
+    Bundle(Bundle),
+    Envelope(Envelope),
+    Statement(Statement),
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    #[serde(default)]
+    pub predicate: serde_json::Value,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Subject {
+    #[serde(default)]
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+impl Subject {
+    /// The digests of this subject, in the format used by the artifacts table
+    pub fn checksums(&self) -> impl Iterator<Item = String> + '_ {
+        self.digest
+            .iter()
+            .filter(|(family, _)| matches!(family.as_str(), "sha256" | "sha512"))
+            .map(|(family, value)| format!("{family}:{}", value.to_lowercase()))
+    }
+}
+
+/// The parts of a SLSA provenance predicate that are shown on the artifact page
+#[derive(Debug, Default, PartialEq)]
+pub struct Provenance {
+    pub builder_id: Option<String>,
+    pub build_type: Option<String>,
+    pub source_uri: Option<String>,
+}
+
+fn json_str(value: &serde_json::Value, pointer: &str) -> Option<String> {
+    value.pointer(pointer)?.as_str().map(String::from)
+}
+
+impl Provenance {
+    /// Supports SLSA provenance v0.2 and v1
+    pub fn from_predicate(predicate: &serde_json::Value) -> Self {
+        if predicate.get("buildDefinition").is_some() {
+            let source_uri = json_str(
+                predicate,
+                "/buildDefinition/externalParameters/workflow/repository",
+            )
+            .or_else(|| json_str(predicate, "/buildDefinition/resolvedDependencies/0/uri"));
+            Provenance {
+                builder_id: json_str(predicate, "/runDetails/builder/id"),
+                build_type: json_str(predicate, "/buildDefinition/buildType"),
+                source_uri,
+            }
+        } else {
+            Provenance {
+                builder_id: json_str(predicate, "/builder/id"),
+                build_type: json_str(predicate, "/buildType"),
+                source_uri: json_str(predicate, "/invocation/configSource/uri"),
+            }
+        }
+    }
+}
+
+/// Extract the in-toto statement, along with the raw payload it was decoded from
+///
+/// Signatures of envelopes are not verified, the statement is only trusted as far as
+/// its subject matches an artifact we've already ingested.
+pub fn parse(data: &[u8]) -> Result<(Statement, Vec<u8>)> {
+    let envelope = match serde_json::from_slice::<Document>(data)? {
+        Document::Bundle(bundle) => bundle.dsse_envelope,
+        Document::Envelope(envelope) => envelope,
+        Document::Statement(statement) => return Ok((statement, data.to_vec())),
+    };
+    if envelope.payload_type != PAYLOAD_TYPE {
+        return Err(Error::UnsupportedAttestation(envelope.payload_type));
+    }
+    let payload = BASE64.decode(envelope.payload.as_bytes())?;
+    let statement = serde_json::from_slice::<Statement>(&payload)?;
+    Ok((statement, payload))
+}
+
+pub async fn ingest(db: &db::Client, data: &[u8]) -> Result<usize> {
+    let (statement, payload) = parse(data)?;
+    if !matches!(
+        statement.statement_type.as_str(),
+        STATEMENT_TYPE_V1 | STATEMENT_TYPE_V01
+    ) {
+        return Err(Error::UnsupportedAttestation(statement.statement_type));
+    }
+
+    let digest = chksums::sha256(&payload);
+    let provenance = Provenance::from_predicate(&statement.predicate);
+    let raw = serde_json::from_slice::<serde_json::Value>(&payload)?;
+    let mut verified = 0;
+    for subject in &statement.subject {
+        for chksum in subject.checksums() {
+            let Some(artifact) = db.resolve_artifact_chksum(&chksum).await? else {
+                debug!("Subject is not a known artifact: {chksum:?}");
+                continue;
+            };
+            info!(
+                "Recording provenance for {artifact:?} (subject={:?}, builder={:?})",
+                subject.name, provenance.builder_id
+            );
+            db.insert_attestation(&db::NewAttestation {
+                chksum: artifact,
+                digest: digest.clone(),
+                subject_name: subject.name.clone(),
+                predicate_type: statement.predicate_type.clone(),
+                builder_id: provenance.builder_id.clone(),
+                build_type: provenance.build_type.clone(),
+                source_uri: provenance.source_uri.clone(),
+                statement: raw.clone(),
+            })
+            .await?;
+            verified += 1;
+        }
+    }
+
+    Ok(verified)
+}
+
+pub async fn run(args: &args::IngestAttestation) -> Result<()> {
+    let db = db::Client::create().await?;
+    let data = fs::read(&args.file).await?;
+    let verified = ingest(&db, &data).await?;
+    if verified == 0 {
+        warn!("None of the attestation subjects matched a known artifact");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"{
+  "_type": "https://in-toto.io/Statement/v1",
+  "subject": [
+    {
+      "name": "pkg:npm/sigstore@2.3.1",
+      "digest": {
+        "sha512": "8E13C4D8C3B8F5E4E9B6F0F1B4C3B1F6D4B0C2C0A2E8D5D8A1F6B3E7C1D9A0F2E4B6C8D0A2B4C6D8E0F2A4B6C8D0E2F4A6B8C0D2E4F6A8B0C2D4E6F8A0B2C4"
+      }
+    }
+  ],
+  "predicateType": "https://slsa.dev/provenance/v1",
+  "predicate": {
+    "buildDefinition": {
+      "buildType": "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1",
+      "externalParameters": {
+        "workflow": {
+          "ref": "refs/heads/main",
+          "repository": "https://github.com/sigstore/sigstore-js",
+          "path": ".github/workflows/release.yml"
+        }
+      }
+    },
+    "runDetails": {
+      "builder": {
+        "id": "https://github.com/actions/runner/github-hosted"
+      }
+    }
+  }
+}"#;
+
+    #[test]
+    fn test_parse_statement() {
+        let (statement, payload) = parse(STATEMENT.as_bytes()).unwrap();
+        assert_eq!(payload, STATEMENT.as_bytes());
+        assert_eq!(statement.predicate_type, "https://slsa.dev/provenance/v1");
+        assert_eq!(
+            statement.subject[0].checksums().collect::<Vec<_>>(),
+            ["sha512:8e13c4d8c3b8f5e4e9b6f0f1b4c3b1f6d4b0c2c0a2e8d5d8a1f6b3e7c1d9a0f2e4b6c8d0a2b4c6d8e0f2a4b6c8d0e2f4a6b8c0d2e4f6a8b0c2d4e6f8a0b2c4"]
+        );
+        assert_eq!(
+            Provenance::from_predicate(&statement.predicate),
+            Provenance {
+                builder_id: Some("https://github.com/actions/runner/github-hosted".to_string()),
+                build_type: Some(
+                    "https://slsa-framework.github.io/github-actions-buildtypes/workflow/v1"
+                        .to_string()
+                ),
+                source_uri: Some("https://github.com/sigstore/sigstore-js".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let bundle = serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle+json;version=0.2",
+            "dsseEnvelope": {
+                "payload": BASE64.encode(STATEMENT.as_bytes()),
+                "payloadType": PAYLOAD_TYPE,
+                "signatures": [{"sig": "MEUCIQ==", "keyid": ""}]
+            }
+        });
+        let (statement, payload) = parse(bundle.to_string().as_bytes()).unwrap();
+        assert_eq!(payload, STATEMENT.as_bytes());
+        assert_eq!(statement.subject[0].name, "pkg:npm/sigstore@2.3.1");
+    }
+
+    #[test]
+    fn test_provenance_v02() {
+        let predicate = serde_json::json!({
+            "builder": {"id": "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml@refs/tags/v1.9.0"},
+            "buildType": "https://github.com/slsa-framework/slsa-github-generator/generic@v1",
+            "invocation": {
+                "configSource": {
+                    "uri": "git+https://github.com/example/project@refs/tags/v1.0.0",
+                    "digest": {"sha1": "6c2c0ba0bd3b2e49da6ac1f1d0f4ab3b7bb4d1e2"}
+                }
+            }
+        });
+        let provenance = Provenance::from_predicate(&predicate);
+        assert_eq!(
+            provenance.source_uri.as_deref(),
+            Some("git+https://github.com/example/project@refs/tags/v1.0.0")
+        );
+        assert_eq!(
+            provenance.build_type.as_deref(),
+            Some("https://github.com/slsa-framework/slsa-github-generator/generic@v1")
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::args;
+use crate::chksums::Hasher;
+use crate::compression::Decompressor;
+use crate::db;
+use crate::errors::*;
+use futures::StreamExt;
+use tokio::fs::File;
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tokio_tar::Archive;
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER_LEN: usize = 60;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Control {
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub source: Option<String>,
+    pub source_version: Option<String>,
+}
+
+pub fn parse_control(txt: &str) -> Control {
+    let mut control = Control::default();
+    for line in txt.lines() {
+        // skip continuation lines of multi-line fields
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Package" => control.package = Some(value.to_string()),
+            "Version" => control.version = Some(value.to_string()),
+            "Source" => {
+                // the source version is only listed if it differs, e.g. `xz-utils (5.6.1-1)`
+                if let Some((name, version)) = value.split_once(" (") {
+                    control.source = Some(name.to_string());
+                    control.source_version = version.strip_suffix(')').map(String::from);
+                } else {
+                    control.source = Some(value.to_string());
+                }
+            }
+            _ => (),
+        }
+    }
+    control
+}
+
+async fn read_control<R: AsyncRead + Unpin>(reader: R, name: &str) -> Result<Option<Control>> {
+    let reader = io::BufReader::new(reader);
+    let mut reader = match name.strip_prefix("control.tar") {
+        Some(".gz") => Decompressor::gz(reader),
+        Some(".xz") => Decompressor::xz(reader),
+        Some(".zst") => Decompressor::zstd(reader),
+        Some("") => Decompressor::Plain(reader),
+        _ => return Ok(None),
+    };
+
+    let mut tar = Archive::new(&mut reader);
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        let path = entry.path()?;
+        debug!("Found entry in control.tar: {path:?}");
+        if matches!(path.to_str(), Some("./control" | "control")) {
+            let mut buf = String::new();
+            entry.read_to_string(&mut buf).await?;
+            return Ok(Some(parse_control(&buf)));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the control file of a .deb and link it to the source package it was built from
+pub async fn stream_data<R: AsyncRead + Unpin>(
+    reader: R,
+    vendor: &str,
+) -> Result<db::BinaryPackage> {
+    let mut reader = Hasher::new(reader);
+
+    let mut magic = [0; AR_MAGIC.len()];
+    reader.read_exact(&mut magic).await?;
+    if magic != AR_MAGIC {
+        return Err(Error::InvalidData);
+    }
+
+    let mut control = None;
+    loop {
+        let mut header = [0; AR_HEADER_LEN];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let name = String::from_utf8_lossy(&header[..16]);
+        let name = name.trim_end().trim_end_matches('/').to_string();
+        let size = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse::<u64>().ok())
+            .ok_or(Error::InvalidData)?;
+        debug!("Found member in .deb: name={name:?} size={size}");
+
+        let mut member = (&mut reader).take(size);
+        if control.is_none() && name.starts_with("control.tar") {
+            control = read_control(&mut member, &name).await?;
+        }
+        io::copy(&mut member, &mut io::sink()).await?;
+
+        // members are aligned to an even offset
+        if size % 2 == 1 {
+            io::copy(&mut (&mut reader).take(1), &mut io::sink()).await?;
+        }
+    }
+    let (_, digests) = reader.digests();
+
+    let control = control.ok_or(Error::BinaryPackageMissingField("control"))?;
+    let package = control
+        .package
+        .ok_or(Error::BinaryPackageMissingField("Package"))?;
+    let version = control
+        .version
+        .ok_or(Error::BinaryPackageMissingField("Version"))?;
+
+    Ok(db::BinaryPackage {
+        chksum: digests.sha256,
+        vendor: vendor.to_string(),
+        source_package: control.source.unwrap_or_else(|| package.clone()),
+        source_version: control.source_version.unwrap_or_else(|| version.clone()),
+        package,
+        version,
+        commit: None,
+    })
+}
+
+pub async fn run(args: &args::IngestDeb) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let input: Box<dyn AsyncRead + Unpin> = if let Some(path) = &args.file {
+        Box::new(File::open(path).await?)
+    } else {
+        Box::new(io::stdin())
+    };
+
+    let pkg = stream_data(input, &args.vendor).await?;
+    super::insert_binary_package(&db, &pkg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_control() {
+        let data = r#"Package: liblzma5
+Source: xz-utils
+Version: 5.6.1+really5.4.5-1
+Architecture: amd64
+Maintainer: Sebastian Andrzej Siewior <sebastian@breakpoint.cc>
+Installed-Size: 401
+Depends: libc6 (>= 2.34)
+Section: libs
+Priority: optional
+Multi-Arch: same
+Homepage: https://tukaani.org/xz/
+Description: XZ-format compression library
+ XZ is the successor to the Lempel-Ziv/Markov-chain Algorithm
+ compression format.
+"#;
+        let control = parse_control(data);
+        assert_eq!(
+            control,
+            Control {
+                package: Some("liblzma5".to_string()),
+                version: Some("5.6.1+really5.4.5-1".to_string()),
+                source: Some("xz-utils".to_string()),
+                source_version: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_control_binnmu() {
+        let data = r#"Package: libfoo1
+Source: foo (1.2-3)
+Version: 1.2-3+b1
+Architecture: amd64
+"#;
+        let control = parse_control(data);
+        assert_eq!(
+            control,
+            Control {
+                package: Some("libfoo1".to_string()),
+                version: Some("1.2-3+b1".to_string()),
+                source: Some("foo".to_string()),
+                source_version: Some("1.2-3".to_string()),
+            }
+        );
+    }
+}
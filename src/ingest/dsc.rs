@@ -0,0 +1,364 @@
+use crate::args;
+use crate::chksums;
+use crate::compression::Decompressor;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::ingest;
+use crate::utils::{self, HttpClient};
+use futures::StreamExt;
+use std::collections::HashMap;
+use tokio::fs::File;
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tokio_tar::Archive;
+
+const PATCHES_DIR: &str = "debian/patches/";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Dsc {
+    pub source: String,
+    pub version: String,
+    pub format: Option<String>,
+    pub files: Vec<DscFile>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DscFile {
+    pub sha256: String,
+    pub size: u64,
+    pub filename: String,
+}
+
+impl DscFile {
+    pub fn chksum(&self) -> String {
+        format!("sha256:{}", self.sha256)
+    }
+
+    fn compression(&self) -> Option<&'static str> {
+        let (_, ext) = self.filename.rsplit_once(".tar")?;
+        match ext {
+            ".gz" => Some("gz"),
+            ".xz" => Some("xz"),
+            ".bz2" => Some("bz2"),
+            ".zst" => Some("zst"),
+            _ => None,
+        }
+    }
+}
+
+impl Dsc {
+    /// Parse a Debian source control file, the pgp signature is ignored if present
+    pub fn parse(txt: &str) -> Result<Self> {
+        let mut lines = txt.lines().peekable();
+        if lines.peek() == Some(&"-----BEGIN PGP SIGNED MESSAGE-----") {
+            // skip the armor headers, they end with an empty line
+            for line in lines.by_ref() {
+                if line.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        let mut source = None;
+        let mut version = None;
+        let mut format = None;
+        let mut files = Vec::new();
+        let mut in_checksums_sha256_section = false;
+        for line in lines {
+            if line == "-----BEGIN PGP SIGNATURE-----" {
+                break;
+            }
+
+            if let Some(line) = line.strip_prefix(' ') {
+                if !in_checksums_sha256_section {
+                    continue;
+                }
+                let mut fields = line.split_whitespace();
+                let (Some(sha256), Some(size), Some(filename)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let Ok(size) = size.parse() else {
+                    continue;
+                };
+                files.push(DscFile {
+                    sha256: sha256.to_lowercase(),
+                    size,
+                    filename: filename.to_string(),
+                });
+                continue;
+            }
+
+            in_checksums_sha256_section = false;
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "Source" => source = Some(value.to_string()),
+                "Version" => version = Some(value.to_string()),
+                "Format" => format = Some(value.to_string()),
+                "Checksums-Sha256" => in_checksums_sha256_section = true,
+                _ => (),
+            }
+        }
+
+        Ok(Dsc {
+            source: source.ok_or(Error::DscMissingField("Source"))?,
+            version: version.ok_or(Error::DscMissingField("Version"))?,
+            format,
+            files,
+        })
+    }
+
+    /// The upstream tarballs, including additional components like `.orig-docs.tar.xz`
+    pub fn orig_tarballs(&self) -> impl Iterator<Item = &DscFile> {
+        self.files.iter().filter(|f| {
+            f.compression().is_some()
+                && (f.filename.contains(".orig.tar.") || f.filename.contains(".orig-"))
+        })
+    }
+
+    /// The `debian/` directory of a `3.0 (quilt)` source package
+    pub fn debian_tarball(&self) -> Option<&DscFile> {
+        self.files
+            .iter()
+            .find(|f| f.filename.contains(".debian.tar."))
+    }
+}
+
+/// The patches listed in a quilt series file, in the order they are applied
+pub fn parse_series(txt: &str) -> Vec<&str> {
+    txt.lines()
+        .map(|line| line.split_once('#').map(|x| x.0).unwrap_or(line))
+        // patches can be followed by options like `-p1`
+        .filter_map(|line| line.split_whitespace().next())
+        .collect()
+}
+
+/// Read all files in `debian/patches/` from the debian tarball
+pub async fn read_patches<R: AsyncRead + Unpin>(
+    reader: R,
+    compression: Option<&str>,
+) -> Result<HashMap<String, Vec<u8>>> {
+    let reader = io::BufReader::new(reader);
+    let mut reader = match compression {
+        Some("gz") => Decompressor::gz(reader),
+        Some("xz") => Decompressor::xz(reader),
+        Some("bz2") => Decompressor::bz2(reader),
+        Some("zst") => Decompressor::zstd(reader),
+        _ => Decompressor::Plain(reader),
+    };
+
+    let mut patches = HashMap::new();
+    let mut tar = Archive::new(&mut reader);
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?;
+        let Some(path) = path.to_str() else {
+            continue;
+        };
+        let path = path.strip_prefix("./").unwrap_or(path);
+        let Some(name) = path.strip_prefix(PATCHES_DIR) else {
+            continue;
+        };
+        let name = name.to_string();
+        debug!("Found file in debian/patches: {name:?}");
+
+        let mut buf = Vec::new();
+        entry.read_to_end(&mut buf).await?;
+        patches.insert(name, buf);
+    }
+    Ok(patches)
+}
+
+async fn open(
+    http: Option<&HttpClient>,
+    location: &str,
+) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+    if let Some(http) = http {
+        http.fetch(location).await
+    } else {
+        let file = File::open(location).await?;
+        Ok(Box::new(file))
+    }
+}
+
+/// Import a source package, `location` is either a url or a path, depending on `http`
+pub async fn ingest(
+    db: &db::Client,
+    http: Option<&HttpClient>,
+    vendor: &str,
+    location: &str,
+) -> Result<()> {
+    let mut buf = String::new();
+    open(http, location).await?.read_to_string(&mut buf).await?;
+    let dsc = Dsc::parse(&buf)?;
+    info!(
+        "Parsed source package: package={:?} version={:?} format={:?}",
+        dsc.source, dsc.version, dsc.format
+    );
+
+    // the referenced files are located next to the .dsc
+    let base = location.rsplit_once('/').map(|x| x.0).unwrap_or(".");
+
+    for file in dsc.orig_tarballs() {
+        let r = db::Ref {
+            chksum: file.chksum(),
+            vendor: vendor.to_string(),
+            package: dsc.source.clone(),
+            version: dsc.version.clone(),
+            filename: Some(file.filename.clone()),
+        };
+        info!("insert: {r:?}");
+        db.insert_ref(&r).await?;
+
+        if db.resolve_artifact(&r.chksum).await?.is_some() {
+            continue;
+        }
+        let path = format!("{base}/{}", file.filename);
+        if http.is_some() {
+            db.insert_task(&Task::new(
+                format!("fetch:{path}"),
+                &TaskData::FetchTar {
+                    url: path,
+                    compression: None,
+                    success_ref: None,
+                },
+            )?)
+            .await?;
+        } else {
+            let reader = open(http, &path).await?;
+            ingest::tar::stream_data(Some(db), reader, file.compression()).await?;
+        }
+    }
+
+    let Some(file) = dsc.debian_tarball() else {
+        info!("Source package has no debian tarball, no patches to import");
+        return Ok(());
+    };
+    let path = format!("{base}/{}", file.filename);
+    info!("Reading debian tarball: {path:?}");
+    let mut data = Vec::new();
+    open(http, &path).await?.read_to_end(&mut data).await?;
+    let chksum = chksums::sha256(&data);
+    if chksum != file.chksum() {
+        return Err(Error::ChecksumMismatch {
+            expected: file.chksum(),
+            actual: chksum,
+        });
+    }
+    let mut patches = read_patches(&data[..], file.compression()).await?;
+
+    let Some(series) = patches.remove("series") else {
+        info!("Source package has no quilt series");
+        return Ok(());
+    };
+    let series = String::from_utf8_lossy(&series).into_owned();
+    let mut applied = parse_series(&series)
+        .into_iter()
+        .filter_map(|name| Some((name.to_string(), patches.remove(name)?)))
+        .collect::<Vec<_>>();
+    applied.push(("series".to_string(), series.into_bytes()));
+
+    for (name, data) in applied {
+        let size = data.len() as i64;
+        let patch = db::NewPatch {
+            vendor: vendor.to_string(),
+            package: dsc.source.clone(),
+            version: dsc.version.clone(),
+            filename: format!("{PATCHES_DIR}{name}"),
+            chksum: chksums::sha256(&data),
+            size,
+            content: String::from_utf8(data).ok(),
+        };
+        info!(
+            "Inserting patch: package={:?} version={:?} filename={:?}",
+            patch.package, patch.version, patch.filename
+        );
+        db.insert_patch(&patch).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::IngestDsc) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = if args.fetch {
+        Some(utils::http_client(None)?)
+    } else {
+        None
+    };
+    ingest(&db, http.as_ref(), &args.vendor, &args.file).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dsc() {
+        let txt = "-----BEGIN PGP SIGNED MESSAGE-----
+Hash: SHA512
+
+Format: 3.0 (quilt)
+Source: xz-utils
+Binary: liblzma-dev, liblzma5, xz-utils, xzdec, liblzma-dev-doc
+Architecture: any all
+Version: 5.6.2-2
+Maintainer: Sebastian Andrzej Siewior <sebastian@breakpoint.cc>
+Checksums-Sha1:
+ 0ac8a0c5e1a3a8bde4c2e1b0ef4c2d8a3e1e1f3f 1853096 xz-utils_5.6.2.orig.tar.xz
+Checksums-Sha256:
+ A9DB3BB3D64E248A0FAE963F8FB6BA851A26BA1822E504DC0EFD18A80C626CAF 1853096 xz-utils_5.6.2.orig.tar.xz
+ 1d2a1d8b2e4c1f6b0f5e1d1c7b6a9e8f3c2d4b5a6e7f8091a2b3c4d5e6f7a8b9 833 xz-utils_5.6.2.orig.tar.xz.asc
+ 3f4e5d6c7b8a9f0e1d2c3b4a5f6e7d8c9b0a1f2e3d4c5b6a7f8e9d0c1b2a3f4e 26860 xz-utils_5.6.2-2.debian.tar.xz
+Files:
+ bbf73fb28425cebb854328599f85c4cf 1853096 xz-utils_5.6.2.orig.tar.xz
+
+-----BEGIN PGP SIGNATURE-----
+
+iQIzBAEBCgAdFiEE
+-----END PGP SIGNATURE-----
+";
+        let dsc = Dsc::parse(txt).unwrap();
+        assert_eq!(dsc.source, "xz-utils");
+        assert_eq!(dsc.version, "5.6.2-2");
+        assert_eq!(dsc.format.as_deref(), Some("3.0 (quilt)"));
+        assert_eq!(
+            dsc.orig_tarballs().collect::<Vec<_>>(),
+            [&DscFile {
+                sha256: "a9db3bb3d64e248a0fae963f8fb6ba851a26ba1822e504dc0efd18a80c626caf"
+                    .to_string(),
+                size: 1853096,
+                filename: "xz-utils_5.6.2.orig.tar.xz".to_string(),
+            }]
+        );
+        assert_eq!(
+            dsc.debian_tarball().map(|f| f.filename.as_str()),
+            Some("xz-utils_5.6.2-2.debian.tar.xz")
+        );
+    }
+
+    #[test]
+    fn test_parse_series() {
+        let series = "# applied in this order
+debian/0001-fix-build.patch
+0002-cve-2024-3094.patch -p1
+
+0003-disable-tests.patch # flaky
+";
+        assert_eq!(
+            parse_series(series),
+            [
+                "debian/0001-fix-build.patch",
+                "0002-cve-2024-3094.patch",
+                "0003-disable-tests.patch"
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::ingest;
+use fd_lock::RwLock;
+use std::str::FromStr;
+use tokio::fs;
+use tokio::io;
+use tokio::process;
+use tokio::time;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FossilUrl {
+    url: String,
+    /// A check-in hash or tag
+    rev: Option<String>,
+}
+
+impl FromStr for FossilUrl {
+    type Err = Error;
+
+    fn from_str(full_url: &str) -> Result<FossilUrl> {
+        let url = full_url.strip_prefix("fossil+").unwrap_or(full_url);
+        if !url.starts_with("https://") {
+            return Err(Error::InsecureVcsUrl(full_url.to_string()));
+        }
+
+        let (url, info) = url
+            .rsplit_once('#')
+            .map(|(url, info)| (url, Some(info)))
+            .unwrap_or((url, None));
+
+        let mut fossil = FossilUrl {
+            url: url.to_string(),
+            ..Default::default()
+        };
+
+        if let Some(info) = info {
+            match info.split_once('=') {
+                Some(("rev", value)) if ingest::is_valid_vcs_rev(value) => {
+                    fossil.rev = Some(value.to_string())
+                }
+                Some(("rev", value)) => return Err(Error::InvalidVcsRef(value.to_string())),
+                _ => return Err(Error::UnknownVcsRef(info.to_string())),
+            }
+        }
+
+        Ok(fossil)
+    }
+}
+
+impl FossilUrl {
+    /// Set the check-in to snapshot, overriding what the url says
+    pub fn pinned(mut self, rev: Option<String>) -> Self {
+        if rev.is_some() {
+            self.rev = rev;
+        }
+        self
+    }
+}
+
+/// Find the full check-in hash in the output of `fossil info`
+fn parse_info_hash(output: &str) -> Option<&str> {
+    output.lines().find_map(|line| {
+        let value = line.strip_prefix("hash:")?;
+        value.split_whitespace().next()
+    })
+}
+
+pub async fn take_snapshot(db: &db::Client, fossil: &FossilUrl, tmp: &str) -> Result<()> {
+    fs::create_dir_all(tmp).await?;
+    let dir = fs::File::open(tmp).await?;
+    info!("Getting lock on filesystem fossil workdir...");
+    let mut lock = RwLock::new(dir.into_std().await);
+    let _lock = lock.write();
+    debug!("Acquired lock");
+
+    let Some(rev) = &fossil.rev else {
+        return Err(Error::MissingVcsRef(fossil.url.clone()));
+    };
+    // the revision may also come from a pin, which isn't checked when parsing the url
+    if !ingest::is_valid_vcs_rev(rev) {
+        return Err(Error::InvalidVcsRef(rev.clone()));
+    }
+
+    let path = format!("{}/fossil", tmp.strip_suffix('/').unwrap_or(tmp));
+    if fs::metadata(&path).await.is_ok() {
+        debug!("Running cleanup of temporary fossil repository");
+        fs::remove_dir_all(&path).await?;
+    }
+    fs::create_dir_all(&path).await?;
+    let repo = format!("{path}/repo.fossil");
+    let tarball = format!("{path}/snapshot.tar.gz");
+
+    info!("Cloning fossil repository from {:?}", fossil.url);
+    let child = process::Command::new("fossil")
+        .args(["clone", "--", &fossil.url, &repo])
+        .status();
+    let Ok(status) = time::timeout(ingest::git::CLONE_TIMEOUT, child).await else {
+        return Err(Error::VcsFetchTimeout);
+    };
+    let status = status?;
+    if !status.success() {
+        return Err(Error::VcsError(status));
+    }
+
+    let output = process::Command::new("fossil")
+        .args(["info", "-R", &repo, "--", rev])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::VcsError(output.status));
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    let Some(hash) = parse_info_hash(&output) else {
+        return Err(Error::VcsRevParseError(output.into_owned()));
+    };
+    info!("Resolved fossil check-in {rev:?} to hash: {hash:?}");
+
+    info!("Taking `fossil tarball` snapshot of {hash:?}");
+    let status = process::Command::new("fossil")
+        .args(["tarball", "-R", &repo, "--", hash, &tarball])
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(Error::VcsError(status));
+    }
+
+    let file = fs::File::open(&tarball).await?;
+    let reader = io::BufReader::new(file);
+    let summary = ingest::tar::stream_data(Some(db), reader, Some("gz")).await?;
+
+    db.insert_alias_from_to(
+        &format!("fossil:{hash}"),
+        &summary.inner_digests.sha256,
+        "fossil-tarball",
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn run(args: &args::IngestFossil) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    take_snapshot(&db, &args.fossil, &args.tmp).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fossil_url() {
+        let url = "fossil+https://sqlite.org/src#rev=version-3.46.1";
+        let fossil = url.parse::<FossilUrl>().unwrap();
+        assert_eq!(
+            fossil,
+            FossilUrl {
+                url: "https://sqlite.org/src".to_string(),
+                rev: Some("version-3.46.1".to_string()),
+            }
+        );
+        assert!("fossil+https://sqlite.org/src#rev=-Rother.fossil"
+            .parse::<FossilUrl>()
+            .is_err());
+        assert!("fossil+http://sqlite.org/src#rev=version-3.46.1"
+            .parse::<FossilUrl>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_info_hash() {
+        let output = "hash:         c9c2ab54ba1f5f46360f1b4f35d849cd3f080e6fc2b6c60e91b16c63f69a1e33 2024-08-13 09:16:08 UTC
+parent:       4e57a7bc6e12b9e8dd8b7fee8b8c6fd3e9ec6b3c6e80fd8b06dfb4b4b0d6ef5e 2024-08-12 18:47:27 UTC
+tags:         trunk, release, version-3.46.1
+comment:      Version 3.46.1 (user: drh)
+";
+        assert_eq!(
+            parse_info_hash(output),
+            Some("c9c2ab54ba1f5f46360f1b4f35d849cd3f080e6fc2b6c60e91b16c63f69a1e33")
+        );
+        assert_eq!(parse_info_hash("tags: trunk\n"), None);
+    }
+}
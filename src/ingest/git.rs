@@ -1,4 +1,5 @@
 use crate::args;
+use crate::chksums;
 use crate::db;
 use crate::errors::*;
 use crate::ingest;
@@ -49,43 +50,51 @@ impl FromStr for GitUrl {
     }
 }
 
-pub async fn take_snapshot(db: &db::Client, git: &GitUrl, tmp: &str) -> Result<()> {
-    fs::create_dir_all(tmp).await?;
-    let dir = fs::File::open(tmp).await?;
-    info!("Getting lock on filesystem git workdir...");
-    let mut lock = RwLock::new(dir.into_std().await);
-    let _lock = lock.write();
-    debug!("Acquired lock");
+impl GitUrl {
+    /// Set the tag or commit to snapshot, overriding what the url says
+    ///
+    /// With both, the tag is fetched and has to point to the commit.
+    pub fn pinned(mut self, tag: Option<String>, commit: Option<String>) -> Self {
+        if tag.is_some() {
+            self.tag = tag;
+        }
+        if commit.is_some() {
+            self.commit = commit;
+        }
+        self
+    }
 
-    let reference = if let Some(tag) = &git.tag {
-        tag
-    } else if let Some(commit) = &git.commit {
-        commit
-    } else {
-        return Err(Error::InvalidGitRef(git.clone()));
-    };
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
 
-    let path = format!("{}/git", tmp.strip_suffix('/').unwrap_or(tmp));
-    if fs::metadata(&path).await.is_ok() {
-        debug!("Running cleanup of temporary git repository");
-        fs::remove_dir_all(&path).await?;
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
     }
+}
 
-    // Checking out a single commit occupies 40GB disk
-    if [
-        "https://chromium.googlesource.com/chromium/src.git",
-        "https://github.com/chromium/chromium.git",
-    ]
-    .contains(&git.url.as_str())
-    {
-        info!("Detected chromium, skipping 🤡: {:?}", git.url);
-        return Ok(());
+/// The directory a cached clone of this url is kept in
+fn cache_path(tmp: &str, url: &str) -> String {
+    let digest = chksums::sha256(url.as_bytes());
+    let digest = digest.strip_prefix("sha256:").unwrap_or(&digest);
+    format!("{tmp}/cache/{digest}")
+}
+
+async fn git_config(path: &str, key: &str, value: &str) -> Result<()> {
+    let status = process::Command::new("git")
+        .args(["-C", path, "config", key, value])
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(Error::GitError(status));
     }
+    Ok(())
+}
 
-    // run git clone
+async fn init_repository(path: &str, git: &GitUrl, opts: &args::GitClone) -> Result<()> {
     info!("Setting up git repository");
     let status = process::Command::new("git")
-        .args(["init", "-qb", "main", &path])
+        .args(["init", "-qb", "main", path])
         .status()
         .await?;
     if !status.success() {
@@ -102,20 +111,106 @@ pub async fn take_snapshot(db: &db::Client, git: &GitUrl, tmp: &str) -> Result<(
 
     debug!("Adding git remote: {:?}", git.url);
     let status = process::Command::new("git")
-        .args(["-C", &path, "remote", "add", "origin", &git.url])
+        .args(["-C", path, "remote", "add", "origin", &git.url])
         .status()
         .await?;
     if !status.success() {
         return Err(Error::GitError(status));
     }
 
+    // `git archive` needs to know where to get filtered objects from
+    if let Some(filter) = &opts.filter {
+        git_config(path, "remote.origin.promisor", "true").await?;
+        git_config(path, "remote.origin.partialclonefilter", filter).await?;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(db, tmp, opts))]
+pub async fn take_snapshot(
+    db: &db::Client,
+    git: &GitUrl,
+    tmp: &str,
+    opts: &args::GitClone,
+) -> Result<()> {
+    fs::create_dir_all(tmp).await?;
+    let dir = fs::File::open(tmp).await?;
+    info!("Getting lock on filesystem git workdir...");
+    let mut lock = RwLock::new(dir.into_std().await);
+    let _lock = lock.write();
+    debug!("Acquired lock");
+
+    let reference = if let Some(tag) = &git.tag {
+        tag
+    } else if let Some(commit) = &git.commit {
+        commit
+    } else {
+        return Err(Error::InvalidGitRef(git.clone()));
+    };
+
+    // Checking out a single commit occupies 40GB disk
+    if [
+        "https://chromium.googlesource.com/chromium/src.git",
+        "https://github.com/chromium/chromium.git",
+    ]
+    .contains(&git.url.as_str())
+    {
+        info!("Detected chromium, skipping 🤡: {:?}", git.url);
+        return Ok(());
+    }
+
+    let tmp = tmp.strip_suffix('/').unwrap_or(tmp);
+    let path = if opts.cache {
+        cache_path(tmp, &git.url)
+    } else {
+        format!("{tmp}/git")
+    };
+
+    if opts.cache && fs::metadata(&path).await.is_ok() {
+        info!("Reusing cached git repository: {path:?}");
+    } else {
+        if fs::metadata(&path).await.is_ok() {
+            debug!("Running cleanup of temporary git repository");
+            fs::remove_dir_all(&path).await?;
+        }
+        init_repository(&path, git, opts).await?;
+    }
+
+    let result = snapshot_at(db, &path, git, reference, opts).await;
+    let git_failed = matches!(
+        result,
+        Err(Error::GitError(_) | Error::GitFetchError(_) | Error::GitFetchTimeout)
+    );
+    if git_failed && opts.cache {
+        // don't keep a repository around that may be in a broken state
+        debug!("Removing cached git repository after error: {path:?}");
+        fs::remove_dir_all(&path).await.ok();
+    }
+    result
+}
+
+async fn snapshot_at(
+    db: &db::Client,
+    path: &str,
+    git: &GitUrl,
+    reference: &str,
+    opts: &args::GitClone,
+) -> Result<()> {
     info!(
         "Fetching git VCS tree-ish reference from {:?}: {:?}",
         git.url, reference
     );
-    let child = process::Command::new("git")
-        .args(["-C", &path, "fetch", "origin", reference])
-        .status();
+    let mut fetch = vec!["-C", path, "fetch", "--depth", "1"];
+    let filter = opts
+        .filter
+        .as_ref()
+        .map(|filter| format!("--filter={filter}"));
+    if let Some(filter) = &filter {
+        fetch.push(filter);
+    }
+    fetch.extend(["origin", reference]);
+    let child = process::Command::new("git").args(fetch).status();
     let Ok(status) = time::timeout(CLONE_TIMEOUT, child).await else {
         return Err(Error::GitFetchTimeout);
     };
@@ -126,7 +221,7 @@ pub async fn take_snapshot(db: &db::Client, git: &GitUrl, tmp: &str) -> Result<(
 
     info!("Resolving FETCH_HEAD git ref");
     let output = process::Command::new("git")
-        .args(["-C", &path, "rev-list", "-n1", "FETCH_HEAD"])
+        .args(["-C", path, "rev-list", "-n1", "FETCH_HEAD"])
         .output()
         .await?;
     if !output.status.success() {
@@ -137,12 +232,20 @@ pub async fn take_snapshot(db: &db::Client, git: &GitUrl, tmp: &str) -> Result<(
         return Err(Error::GitRevParseError(output));
     };
     info!("Resolved ref FETCH_HEAD to git commit: {commit:?}");
+    if let Some(expected) = &git.commit {
+        if !commit.eq_ignore_ascii_case(expected) {
+            return Err(Error::GitCommitMismatch {
+                expected: expected.clone(),
+                actual: commit,
+            });
+        }
+    }
 
     info!("Taking `git archive` snapshot of FETCH_HEAD");
     let mut child = process::Command::new("git")
         .args([
             "-C",
-            &path,
+            path,
             "-c",
             "core.abbrev=no",
             "archive",
@@ -174,7 +277,7 @@ pub async fn take_snapshot(db: &db::Client, git: &GitUrl, tmp: &str) -> Result<(
 pub async fn run(args: &args::IngestGit) -> Result<()> {
     let db = db::Client::create().await?;
 
-    take_snapshot(&db, &args.git, &args.tmp).await?;
+    take_snapshot(&db, &args.git, &args.tmp, &args.git_clone).await?;
 
     Ok(())
 }
@@ -211,6 +314,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cache_path() {
+        let path = cache_path("/tmp/whatsrc", "https://github.com/llvm/llvm-project.git");
+        assert!(path.starts_with("/tmp/whatsrc/cache/"));
+        assert_eq!(path.len(), "/tmp/whatsrc/cache/".len() + 64);
+        assert_ne!(
+            path,
+            cache_path("/tmp/whatsrc", "https://github.com/curl/curl.git")
+        );
+    }
+
+    #[test]
+    fn pin_git_url() {
+        let url = "git+https://github.com/wolfi-dev/os.git#tag=v1.0.0";
+        let git = url.parse::<GitUrl>().unwrap().pinned(
+            None,
+            Some("91e5c5e1baf31e19f6d3af3b0b6b81f849ce81da".to_string()),
+        );
+        assert_eq!(git.tag(), Some("v1.0.0"));
+        assert_eq!(
+            git.commit(),
+            Some("91e5c5e1baf31e19f6d3af3b0b6b81f849ce81da")
+        );
+
+        let git = git.pinned(Some("v1.0.1".to_string()), None);
+        assert_eq!(git.tag(), Some("v1.0.1"));
+    }
+
     #[test]
     fn parse_git_url_commit() {
         let url = "git+https://github.com/rapid7/metasploit-framework.git?signed#commit=77fb7ae14f17fd7f4851bca87e0c28c704797591";
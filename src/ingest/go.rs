@@ -0,0 +1,91 @@
+use crate::ingest::tar::Entry;
+use data_encoding::BASE64;
+use sha2::{Digest, Sha256};
+
+/// The `<module>@<version>/` prefix that all files of a module zip share
+fn module_prefix(path: &str) -> Option<&str> {
+    let at = path.find('@')?;
+    let end = at + path[at..].find('/')?;
+    let version = &path[at + 1..end];
+    version.starts_with('v').then(|| &path[..=end])
+}
+
+/// Calculate the `h1:` hash of a Go module, as recorded in go.sum
+///
+/// This is the base64 encoded sha256 of a `sha256sum` style listing of the files, sorted by path.
+/// Returns None if the archive isn't laid out like a module zip.
+pub fn module_dirhash(files: &[Entry]) -> Option<String> {
+    let mut files = files
+        .iter()
+        .filter_map(|entry| {
+            let digest = entry.digest.as_deref()?.strip_prefix("sha256:")?;
+            Some((entry.path.as_str(), digest))
+        })
+        .collect::<Vec<_>>();
+    let prefix = module_prefix(files.first()?.0)?;
+    if !files.iter().all(|(path, _)| path.starts_with(prefix)) {
+        return None;
+    }
+    files.sort();
+
+    let mut sha256 = Sha256::new();
+    for (path, digest) in files {
+        sha256.update(format!("{digest}  {path}\n"));
+    }
+    Some(format!("h1:{}", BASE64.encode(&sha256.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest;
+    use ::zip::write::SimpleFileOptions;
+    use std::io::{Cursor, Write};
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ::zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        for (path, data) in files {
+            zip.start_file(*path, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_module_prefix() {
+        assert_eq!(
+            module_prefix("example.com/foo@v1.0.0/go.mod"),
+            Some("example.com/foo@v1.0.0/")
+        );
+        assert_eq!(
+            module_prefix("github.com/!burnt!sushi/toml@v1.4.0/decode.go"),
+            Some("github.com/!burnt!sushi/toml@v1.4.0/")
+        );
+        assert_eq!(module_prefix("foo-1.0/go.mod"), None);
+        assert_eq!(module_prefix("foo-1.0/@types/index.d.ts"), None);
+    }
+
+    #[tokio::test]
+    async fn test_module_dirhash() {
+        let data = zip(&[
+            ("example.com/foo@v1.0.0/go.mod", b"module example.com/foo\n"),
+            ("example.com/foo@v1.0.0/foo.go", b"package foo\n"),
+        ]);
+        let summary = ingest::zip::stream_data(None, &data[..]).await.unwrap();
+        assert_eq!(
+            module_dirhash(&summary.files).as_deref(),
+            Some("h1:s6dv4hTuhzo+y48ELWnzDuH1i3oN2H48y2W7tKyOlxo=")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_a_module() {
+        let data = zip(&[
+            ("example.com/foo@v1.0.0/go.mod", b"module example.com/foo\n"),
+            ("README.md", b"hello world\n"),
+        ]);
+        let summary = ingest::zip::stream_data(None, &data[..]).await.unwrap();
+        assert_eq!(module_dirhash(&summary.files), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::ingest;
+use fd_lock::RwLock;
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::fs;
+use tokio::process;
+use tokio::time;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HgUrl {
+    url: String,
+    /// A changeset id or tag
+    rev: Option<String>,
+}
+
+impl FromStr for HgUrl {
+    type Err = Error;
+
+    fn from_str(full_url: &str) -> Result<HgUrl> {
+        let url = full_url.strip_prefix("hg+").unwrap_or(full_url);
+        if !url.starts_with("https://") {
+            return Err(Error::InsecureVcsUrl(full_url.to_string()));
+        }
+
+        let (url, info) = url
+            .rsplit_once('#')
+            .map(|(url, info)| (url, Some(info)))
+            .unwrap_or((url, None));
+
+        let mut hg = HgUrl {
+            url: url.to_string(),
+            ..Default::default()
+        };
+
+        if let Some(info) = info {
+            match info.split_once('=') {
+                Some(("rev", value)) if ingest::is_valid_vcs_rev(value) => {
+                    hg.rev = Some(value.to_string())
+                }
+                Some(("rev", value)) => return Err(Error::InvalidVcsRef(value.to_string())),
+                _ => return Err(Error::UnknownVcsRef(info.to_string())),
+            }
+        }
+
+        Ok(hg)
+    }
+}
+
+impl HgUrl {
+    /// Set the revision to snapshot, overriding what the url says
+    pub fn pinned(mut self, rev: Option<String>) -> Self {
+        if rev.is_some() {
+            self.rev = rev;
+        }
+        self
+    }
+}
+
+pub async fn take_snapshot(db: &db::Client, hg: &HgUrl, tmp: &str) -> Result<()> {
+    fs::create_dir_all(tmp).await?;
+    let dir = fs::File::open(tmp).await?;
+    info!("Getting lock on filesystem hg workdir...");
+    let mut lock = RwLock::new(dir.into_std().await);
+    let _lock = lock.write();
+    debug!("Acquired lock");
+
+    let Some(rev) = &hg.rev else {
+        return Err(Error::MissingVcsRef(hg.url.clone()));
+    };
+    // the revision may also come from a pin, which isn't checked when parsing the url
+    if !ingest::is_valid_vcs_rev(rev) {
+        return Err(Error::InvalidVcsRef(rev.clone()));
+    }
+
+    let path = format!("{}/hg", tmp.strip_suffix('/').unwrap_or(tmp));
+    if fs::metadata(&path).await.is_ok() {
+        debug!("Running cleanup of temporary hg repository");
+        fs::remove_dir_all(&path).await?;
+    }
+
+    info!("Cloning hg repository from {:?}: {rev:?}", hg.url);
+    let child = process::Command::new("hg")
+        .args(["clone", "--noupdate", "-r", rev, "--", &hg.url, &path])
+        .status();
+    let Ok(status) = time::timeout(ingest::git::CLONE_TIMEOUT, child).await else {
+        return Err(Error::VcsFetchTimeout);
+    };
+    let status = status?;
+    if !status.success() {
+        return Err(Error::VcsError(status));
+    }
+
+    let output = process::Command::new("hg")
+        .args(["-R", &path, "log", "-r", rev, "--template", "{node}"])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::VcsError(output.status));
+    }
+    let node = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    info!("Resolved hg revision {rev:?} to changeset: {node:?}");
+
+    info!("Taking `hg archive` snapshot of {node:?}");
+    let mut child = process::Command::new("hg")
+        .args([
+            "-R",
+            &path,
+            "--config",
+            "ui.archivemeta=false",
+            "archive",
+            "-r",
+            &node,
+            "-t",
+            "tar",
+            "-",
+        ])
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let stdout = child.stdout.take().unwrap();
+    let summary = ingest::tar::stream_data(Some(db), stdout, None).await?;
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(Error::VcsError(status));
+    }
+
+    db.insert_alias_from_to(
+        &format!("hg:{node}"),
+        &summary.inner_digests.sha256,
+        "hg-archive",
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn run(args: &args::IngestHg) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    take_snapshot(&db, &args.hg, &args.tmp).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hg_url() {
+        let url = "hg+https://hg.mozilla.org/projects/nspr#rev=NSPR_4_35_RTM";
+        let hg = url.parse::<HgUrl>().unwrap();
+        assert_eq!(
+            hg,
+            HgUrl {
+                url: "https://hg.mozilla.org/projects/nspr".to_string(),
+                rev: Some("NSPR_4_35_RTM".to_string()),
+            }
+        );
+        assert!("hg+https://hg.mozilla.org/projects/nspr#branch=default"
+            .parse::<HgUrl>()
+            .is_err());
+        assert!(
+            "hg+https://hg.mozilla.org/projects/nspr#rev=--config=hooks.pre-archive=id"
+                .parse::<HgUrl>()
+                .is_err()
+        );
+        assert!("hg+https://hg.mozilla.org/projects/nspr#rev=file('*')"
+            .parse::<HgUrl>()
+            .is_err());
+        assert!("hg+ssh://hg.mozilla.org/projects/nspr#rev=NSPR_4_35_RTM"
+            .parse::<HgUrl>()
+            .is_err());
+    }
+}
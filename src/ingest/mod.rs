@@ -1,7 +1,52 @@
 pub mod alpine;
+pub mod apk;
+pub mod attestation;
+pub mod deb;
+pub mod dsc;
+pub mod fossil;
 pub mod git;
+pub mod go;
+pub mod hg;
 pub mod pacman;
 pub mod rpm;
 pub mod tar;
 pub mod void;
 pub mod wolfi;
+pub mod zip;
+
+use crate::db;
+use crate::errors::*;
+
+/// Whether a hg or fossil revision is a hash or tag name, and can't be mistaken for an option
+///
+/// Revset and other expressions are rejected too, the revision is passed on to the vcs as-is.
+pub fn is_valid_vcs_rev(rev: &str) -> bool {
+    rev.len() <= 255
+        && rev.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && rev
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+}
+
+/// Record a binary package and log the source refs it can be traced back to
+pub async fn insert_binary_package(db: &db::Client, pkg: &db::BinaryPackage) -> Result<()> {
+    info!("Inserting binary package: {pkg:?}");
+    db.insert_binary_package(pkg).await?;
+
+    let refs = db
+        .get_all_named_refs(&pkg.vendor, &pkg.source_package, &pkg.source_version)
+        .await?;
+    if refs.is_empty() {
+        warn!(
+            "Source package is not known yet: vendor={:?} package={:?} version={:?}",
+            pkg.vendor, pkg.source_package, pkg.source_version
+        );
+    }
+    for r in refs {
+        info!(
+            "Built from: chksum={:?} filename={:?}",
+            r.chksum, r.filename
+        );
+    }
+    Ok(())
+}
@@ -3,10 +3,12 @@ use crate::db;
 use crate::errors::*;
 use crate::pkgbuild;
 use crate::sbom;
+use crate::signature;
 use crate::utils;
 use async_compression::tokio::bufread::GzipDecoder;
 use futures::StreamExt;
 use srcinfo::Srcinfo;
+use std::collections::HashSet;
 use tokio::io::{self, AsyncRead, AsyncReadExt};
 use tokio_tar::Archive;
 
@@ -17,6 +19,13 @@ pub struct Snapshot {
 }
 
 impl Snapshot {
+    pub fn from_srcinfo(srcinfo: String) -> Snapshot {
+        Snapshot {
+            pkgbuild: String::new(),
+            srcinfo: Some(srcinfo),
+        }
+    }
+
     pub async fn parse_from_tgz<R: AsyncRead + Unpin>(
         db: &db::Client,
         reader: R,
@@ -74,9 +83,9 @@ impl Snapshot {
         })
     }
 
-    fn get_from_archvec(vec: &[srcinfo::ArchVec]) -> &[String] {
+    fn get_from_archvec<'a>(vec: &'a [srcinfo::ArchVec], arch: Option<&str>) -> &'a [String] {
         vec.iter()
-            .find(|x| x.arch.is_none())
+            .find(|x| x.arch.as_deref() == arch)
             .map(|e| &e.vec[..])
             .unwrap_or(&[])
     }
@@ -118,41 +127,80 @@ impl Snapshot {
         v.filter(|v| *v != "SKIP").cloned()
     }
 
+    /// Fingerprints of the keys that are allowed to sign the sources
+    pub fn valid_pgp_keys(&self) -> Result<Vec<String>> {
+        let keys = if let Some(srcinfo) = &self.srcinfo {
+            Srcinfo::parse_buf(srcinfo.as_bytes())?.base.valid_pgp_keys
+        } else {
+            pkgbuild::parse(self.pkgbuild.as_bytes())?.validpgpkeys
+        };
+        Ok(keys
+            .iter()
+            .filter_map(|key| signature::normalize_fingerprint(key))
+            .collect())
+    }
+
     pub fn source_entries(&self) -> Result<Vec<SourceEntry>> {
         if let Some(srcinfo) = &self.srcinfo {
             let srcinfo = Srcinfo::parse_buf(srcinfo.as_bytes())?;
-            let sources = Self::get_from_archvec(&srcinfo.base.source);
-            let sha256sums = Self::get_from_archvec(&srcinfo.base.sha256sums);
-            let sha512sums = Self::get_from_archvec(&srcinfo.base.sha512sums);
-            let b2sums = Self::get_from_archvec(&srcinfo.base.b2sums);
-
-            Ok(Self::source_entries_from_lists(
-                sources.len(),
-                sources,
-                sha256sums,
-                sha512sums,
-                b2sums,
-            ))
+            for pkg in &srcinfo.pkgs {
+                debug!(
+                    "Found split package {:?} of pkgbase {:?}",
+                    pkg.pkgname, srcinfo.base.pkgbase
+                );
+            }
+
+            // the generic `source` array, followed by `source_<arch>` arrays
+            let mut arches = vec![None];
+            for source in &srcinfo.base.source {
+                if let Some(arch) = source.arch.as_deref() {
+                    arches.push(Some(arch));
+                }
+            }
+
+            let mut out = Vec::new();
+            for arch in arches {
+                let sources = Self::get_from_archvec(&srcinfo.base.source, arch);
+                let sha256sums = Self::get_from_archvec(&srcinfo.base.sha256sums, arch);
+                let sha512sums = Self::get_from_archvec(&srcinfo.base.sha512sums, arch);
+                let b2sums = Self::get_from_archvec(&srcinfo.base.b2sums, arch);
+
+                out.extend(Self::source_entries_from_lists(
+                    sources.len(),
+                    sources,
+                    sha256sums,
+                    sha512sums,
+                    b2sums,
+                ));
+            }
+            Ok(out)
         } else {
             let pkgbuild = pkgbuild::parse(self.pkgbuild.as_bytes())?;
 
-            let max = [
-                pkgbuild.sha256sums.len(),
-                pkgbuild.sha512sums.len(),
-                pkgbuild.b2sums.len(),
-            ]
+            let mut out =
+                Self::sum_entries(&pkgbuild.sha256sums, &pkgbuild.sha512sums, &pkgbuild.b2sums);
+            for sums in pkgbuild.arch.values() {
+                out.extend(Self::sum_entries(
+                    &sums.sha256sums,
+                    &sums.sha512sums,
+                    &sums.b2sums,
+                ));
+            }
+            Ok(out)
+        }
+    }
+
+    /// Source entries without urls, we can't evaluate the `source` array of a PKGBUILD
+    fn sum_entries(
+        sha256sums: &[String],
+        sha512sums: &[String],
+        b2sums: &[String],
+    ) -> Vec<SourceEntry> {
+        let max = [sha256sums.len(), sha512sums.len(), b2sums.len()]
             .into_iter()
             .max()
             .unwrap_or(0);
-
-            Ok(Self::source_entries_from_lists(
-                max,
-                &[],
-                &pkgbuild.sha256sums,
-                &pkgbuild.sha512sums,
-                &pkgbuild.b2sums,
-            ))
-        }
+        Self::source_entries_from_lists(max, &[], sha256sums, sha512sums, b2sums)
     }
 }
 
@@ -187,8 +235,39 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
     if prefer_pkgbuild {
         snapshot.srcinfo = None;
     }
+    import_snapshot(db, &snapshot, vendor, package, version).await
+}
+
+pub async fn import_snapshot(
+    db: &db::Client,
+    snapshot: &Snapshot,
+    vendor: &str,
+    package: &str,
+    version: &str,
+) -> Result<()> {
+    let entries = snapshot.source_entries()?;
+    let mut signed = HashSet::new();
+    for entry in &entries {
+        let Some(signature_url) = &entry.url else {
+            continue;
+        };
+        // detached signatures are usually listed right next to the file they sign
+        let Some((_, url)) = signature::detect(signature_url) else {
+            continue;
+        };
+        if entries.iter().any(|e| e.url.as_deref() == Some(url)) {
+            db.insert_task(&signature::task_for(url, signature_url)?)
+                .await?;
+            signed.insert(url.to_string());
+        }
+    }
+    let valid_pgp_keys = if signed.is_empty() {
+        Vec::new()
+    } else {
+        snapshot.valid_pgp_keys()?
+    };
 
-    for entry in snapshot.source_entries()? {
+    for entry in entries {
         debug!("Found source entry: {entry:?}");
         let Some(chksum) = entry.preferred_chksum() else {
             continue;
@@ -210,6 +289,12 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
         };
         info!("insert: {r:?}");
         db.insert_ref(&r).await?;
+
+        if r.filename.as_ref().is_some_and(|url| signed.contains(url)) {
+            for fingerprint in &valid_pgp_keys {
+                db.insert_ref_signer(&r, fingerprint).await?;
+            }
+        }
     }
 
     Ok(())
@@ -231,3 +316,60 @@ pub async fn run(args: &args::IngestPacmanSnapshot) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arch_specific_sources() {
+        let srcinfo = "pkgbase = foo
+	pkgver = 1.0
+	pkgrel = 1
+	arch = x86_64
+	arch = aarch64
+	source = https://example.com/foo-1.0.tar.gz
+	source_x86_64 = https://example.com/foo-1.0-x86_64.tar.gz
+	source_aarch64 = https://example.com/foo-1.0-aarch64.tar.gz
+	sha256sums = 7a1258a5dfc48c54cea1092adddb6bcfb1fcf19c7272c0a6a9e1d2d7daee6e12
+	sha256sums_x86_64 = f9a4925f7d7bb7de54e17cd9ad7c584dfae88ad182d943b79cf403425000f128
+	sha256sums_aarch64 = 56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e
+
+pkgname = foo
+
+pkgname = foo-docs
+";
+        let snapshot = Snapshot::from_srcinfo(srcinfo.to_string());
+        let entries = snapshot
+            .source_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.url.clone().unwrap(),
+                    entry.preferred_chksum().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                (
+                    "https://example.com/foo-1.0.tar.gz".to_string(),
+                    "sha256:7a1258a5dfc48c54cea1092adddb6bcfb1fcf19c7272c0a6a9e1d2d7daee6e12"
+                        .to_string()
+                ),
+                (
+                    "https://example.com/foo-1.0-x86_64.tar.gz".to_string(),
+                    "sha256:f9a4925f7d7bb7de54e17cd9ad7c584dfae88ad182d943b79cf403425000f128"
+                        .to_string()
+                ),
+                (
+                    "https://example.com/foo-1.0-aarch64.tar.gz".to_string(),
+                    "sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,15 +1,29 @@
 use crate::args;
+use crate::changelog;
+use crate::chksums;
 use crate::db;
 use crate::errors::*;
 use crate::ingest;
+use crate::signature;
 use crate::utils;
 use futures::StreamExt;
+use std::collections::HashSet;
 use std::process::Stdio;
 use std::sync::Arc;
-use tokio::io::{self, AsyncRead};
+use tokio::io::{self, AsyncRead, AsyncReadExt};
 use tokio::process::Command;
 use tokio_tar::{Archive, EntryType};
+use tracing::Instrument;
 
+/// Patches and spec files larger than this are recorded without their content
+const MAX_PATCH_SIZE: usize = 1024 * 1024;
+
+/// Files of a source rpm that describe the changes of the distribution to upstream
+pub fn is_patch(filename: &str) -> bool {
+    filename.ends_with(".spec") || filename.ends_with(".patch") || filename.ends_with(".diff")
+}
+
+#[tracing::instrument(skip_all)]
 pub async fn read_routine<R: AsyncRead + Unpin>(
     db: &db::Client,
     reader: R,
@@ -20,6 +34,11 @@ pub async fn read_routine<R: AsyncRead + Unpin>(
     let mut tar = Archive::new(reader);
     let mut entries = tar.entries()?;
 
+    // filenames of the source rpm, to pair tarballs with their signature and keyring
+    let mut filenames = HashSet::new();
+    let mut fingerprints = Vec::new();
+    let mut refs = Vec::new();
+
     while let Some(entry) = entries.next().await {
         let entry = entry?;
         let filename = {
@@ -39,6 +58,43 @@ pub async fn read_routine<R: AsyncRead + Unpin>(
 
             filename.to_string()
         };
+        fingerprints.extend(signature::fingerprint_from_keyring_filename(&filename));
+        filenames.insert(filename.clone());
+
+        if is_patch(&filename) {
+            let mut entry = entry;
+            let mut buf = Vec::new();
+            entry.read_to_end(&mut buf).await?;
+
+            let chksum = chksums::sha256(&buf);
+            let size = buf.len();
+            let content = if size <= MAX_PATCH_SIZE {
+                String::from_utf8(buf).ok()
+            } else {
+                None
+            };
+
+            if filename.ends_with(".spec") {
+                if let Some(changelog) = content.as_deref().and_then(changelog::parse_rpm_spec) {
+                    info!("Inserting changelog: package={package:?} version={version:?}");
+                    db.insert_changelog(Some(&vendor), &package, &version, &changelog)
+                        .await?;
+                }
+            }
+
+            info!("Inserting patch: package={package:?} version={version:?} filename={filename:?} chksum={chksum:?}");
+            db.insert_patch(&db::NewPatch {
+                vendor: vendor.to_string(),
+                package: package.to_string(),
+                version: version.to_string(),
+                filename,
+                chksum,
+                size: size as i64,
+                content,
+            })
+            .await?;
+            continue;
+        }
 
         // TODO: find a better solution for this, can we just autodetect all regardless of file name?
         let compression = if filename.ends_with(".tar.gz")
@@ -50,6 +106,8 @@ pub async fn read_routine<R: AsyncRead + Unpin>(
             Some("xz")
         } else if filename.ends_with(".tar.bz2") {
             Some("bz2")
+        } else if filename.ends_with(".tar.zst") {
+            Some("zst")
         } else if filename.ends_with(".tar") {
             None
         } else {
@@ -73,10 +131,28 @@ pub async fn read_routine<R: AsyncRead + Unpin>(
         };
         info!("insert ref: {r:?}");
         db.insert_ref(&r).await?;
+        refs.push(r);
+    }
+
+    for r in &refs {
+        let Some(filename) = &r.filename else {
+            continue;
+        };
+        let is_signed = filenames.iter().any(|sig| {
+            signature::detect(sig).is_some_and(|(kind, signed)| {
+                kind == signature::KIND_PGP && signed == filename.as_str()
+            })
+        });
+        if is_signed {
+            for fingerprint in &fingerprints {
+                db.insert_ref_signer(r, fingerprint).await?;
+            }
+        }
     }
     Ok(())
 }
 
+#[tracing::instrument(skip(db, reader))]
 pub async fn stream_data<R: AsyncRead + Unpin>(
     db: Arc<db::Client>,
     mut reader: R,
@@ -98,8 +174,9 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
     };
 
     let stdout = child.stdout.take().unwrap();
-    let reader =
-        tokio::spawn(async move { read_routine(&db, stdout, vendor, package, version).await });
+    let reader = tokio::spawn(
+        async move { read_routine(&db, stdout, vendor, package, version).await }.in_current_span(),
+    );
 
     let (reader, writer) = tokio::join!(reader, writer);
     debug!("Sent {} bytes to child process", writer?);
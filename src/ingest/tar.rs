@@ -1,13 +1,21 @@
+use crate::analyze::autotools;
+use crate::analyze::totals::{EntryKind, Totals};
 use crate::args;
+use crate::changelog;
 use crate::chksums::{Checksums, Hasher};
-use crate::compression::Decompressor;
+use crate::compression::{Decompressor, Traced};
 use crate::db;
 use crate::errors::*;
+use crate::ingest::go;
+use crate::reports;
 use crate::sbom;
+use crate::storage::Storage;
+use crate::utils;
 use digest::Digest;
 use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::str;
 use tokio::fs::File;
 use tokio::io::{self, AsyncRead, AsyncReadExt};
 use tokio_tar::{Archive, EntryType};
@@ -92,6 +100,35 @@ pub struct TarSummary {
     pub sbom_refs: Vec<sbom::Ref>,
 }
 
+/// Insert a dependency lockfile found in an archive and schedule it for indexing
+pub async fn register_sbom(
+    db: &db::Client,
+    strain: &str,
+    data: String,
+    path: &str,
+) -> Result<sbom::Ref> {
+    let sbom = sbom::Sbom::new(strain, data)?;
+    let chksum = db.insert_sbom(&sbom).await?;
+    let strain = sbom.strain();
+    db.insert_task(&db::Task::new(
+        format!("sbom:{strain}:{chksum}"),
+        &db::TaskData::IndexSbom {
+            strain: Some(strain.to_string()),
+            chksum: chksum.clone(),
+        },
+    )?)
+    .await?;
+    Ok(sbom::Ref {
+        strain,
+        chksum,
+        path: path.to_string(),
+    })
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(compression = tracing::field::Empty, files = tracing::field::Empty)
+)]
 pub async fn stream_data<R: AsyncRead + Unpin>(
     db: Option<&db::Client>,
     reader: R,
@@ -103,29 +140,72 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
         Some("gz") => (Decompressor::gz(reader), "gz(tar)"),
         Some("xz") => (Decompressor::xz(reader), "xz(tar)"),
         Some("bz2") => (Decompressor::bz2(reader), "bz2(tar)"),
+        Some("zst") => (Decompressor::zstd(reader), "zst(tar)"),
         None => (Decompressor::Plain(reader), "tar"),
         unknown => panic!("Unknown compression algorithm: {unknown:?}"),
     };
+    tracing::Span::current().record("compression", outer_label);
+    let reader = Traced::new(reader, tracing::info_span!("decompress"));
     let reader = Hasher::new(reader);
 
+    // Archive the content of files too, if blob storage is configured
+    let storage = match (db, Storage::from_env()?) {
+        (Some(db), Some(storage)) => Some((db, storage, utils::http_client(None)?)),
+        _ => None,
+    };
+
     // Open archive
     let mut tar = Archive::new(reader);
     let mut files = Vec::new();
     let mut sbom_refs = Vec::new();
+    let mut autotools = autotools::Inputs::default();
+    let mut totals = Totals::default();
     {
         let mut entries = tar.entries()?;
         while let Some(entry) = entries.next().await {
             let mut entry = entry?;
+            let global = entry.header().entry_type() == EntryType::XGlobalHeader;
+            if let Some(pax) = entry.pax_extensions().await? {
+                for ext in pax {
+                    if let Ok(key) = ext?.key() {
+                        totals.add_pax_key(key, global);
+                    }
+                }
+            }
             let Some((metadata, is_file)) = Metadata::from_tar_header(&entry)? else {
                 continue;
             };
 
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                _ if is_file => EntryKind::File,
+                EntryType::Char | EntryType::Block => EntryKind::Device,
+                EntryType::Symlink => EntryKind::Symlink,
+                _ => EntryKind::Other,
+            };
+            let (mode, size) = (header.mode().ok(), header.size().ok());
+
             let path = entry.path()?;
             let filename = path.file_name().and_then(|f| f.to_str()).map(String::from);
             let path = path.to_string_lossy().into_owned();
+            totals.add(&path, mode, size, kind);
 
             let digest = if is_file {
                 let sbom = sbom::detect_from_filename(filename.as_deref());
+                let is_changelog = path == "debian/changelog";
+                let autotools_input = autotools::Inputs::wants(&path).filter(|_| {
+                    db.is_some()
+                        && entry
+                            .header()
+                            .size()
+                            .is_ok_and(|size| size <= autotools::MAX_INPUT_SIZE)
+                });
+                let archive = storage.as_ref().is_some_and(|(_, storage, _)| {
+                    entry
+                        .header()
+                        .size()
+                        .is_ok_and(|size| size <= storage.max_blob_size)
+                });
 
                 let mut buf = [0; 4096];
                 let mut data = Vec::<u8>::new();
@@ -137,33 +217,41 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
                     }
                     let buf = &buf[..n];
                     sha256.update(buf);
-                    if sbom.is_some() {
+                    if sbom.is_some() || is_changelog || archive || autotools_input.is_some() {
                         data.extend(buf);
                     }
                 }
 
                 let digest = format!("sha256:{}", hex::encode(sha256.finalize()));
 
-                if let Some(sbom) = sbom {
+                if let Some(input) = autotools_input {
+                    autotools.insert(input, &data);
+                }
+
+                if let Some((db, storage, http)) = storage.as_ref().filter(|_| archive) {
+                    if let Err(err) = storage.store_blob(db, http, &digest, &data).await {
+                        warn!("Failed to archive content of {path:?}: {err:#}");
+                    }
+                }
+
+                if is_changelog {
+                    if let (Some(db), Ok(data)) = (db, str::from_utf8(&data)) {
+                        if let Some(entry) = changelog::parse_debian(data) {
+                            if let (Some(package), Some(version)) = (&entry.package, &entry.version)
+                            {
+                                info!(
+                                    "Inserting changelog: package={package:?} version={version:?}"
+                                );
+                                db.insert_changelog(None, package, version, &entry).await?;
+                            }
+                        }
+                    }
+                } else if let Some(sbom) = sbom {
                     if let Ok(data) = String::from_utf8(data) {
                         if let Some(db) = db {
-                            let sbom = sbom::Sbom::new(sbom, data)?;
-                            let chksum = db.insert_sbom(&sbom).await?;
-                            let strain = sbom.strain();
-                            info!("Inserted sbom {strain:?}: {digest:?}");
-                            sbom_refs.push(sbom::Ref {
-                                strain,
-                                chksum: chksum.clone(),
-                                path: path.clone(),
-                            });
-                            db.insert_task(&db::Task::new(
-                                format!("sbom:{strain}:{chksum}"),
-                                &db::TaskData::IndexSbom {
-                                    strain: Some(strain.to_string()),
-                                    chksum,
-                                },
-                            )?)
-                            .await?;
+                            let sbom_ref = register_sbom(db, sbom, data, &path).await?;
+                            info!("Inserted sbom {:?}: {digest:?}", sbom_ref.strain);
+                            sbom_refs.push(sbom_ref);
                         }
                     }
                 }
@@ -193,22 +281,28 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
     // Determine hashes
     let (reader, inner_digests) = reader.digests();
     info!("Found digest for inner .tar: {inner_digests:?}");
-    let reader = reader.into_inner().into_inner();
+    let reader = reader.into_inner().into_inner().into_inner();
 
     let (_stream, outer_digests) = reader.digests();
     info!("Found digests for outer compressed tar: {outer_digests:?}");
 
+    totals.check_paths(&files);
+    tracing::Span::current().record("files", files.len());
+
     if let Some(db) = db {
         // Insert into database
-        db.insert_artifact(&inner_digests.sha256, &files).await?;
-        db.register_chksums_aliases(&inner_digests, &inner_digests.sha256, "tar")
-            .await?;
-        db.register_chksums_aliases(&outer_digests, &inner_digests.sha256, outer_label)
-            .await?;
-
-        for sbom in &sbom_refs {
-            db.insert_sbom_ref(&inner_digests.sha256, sbom.strain, &sbom.chksum, &sbom.path)
-                .await?;
+        let chksum = &inner_digests.sha256;
+        let mut import = db::Import::new(chksum, &files, &totals, &sbom_refs);
+        import.add_chksums_aliases(&inner_digests, "tar");
+        import.add_chksums_aliases(&outer_digests, outer_label);
+        if let Some(h1) = go::module_dirhash(&files) {
+            import.add_alias(&h1, "go-dirhash");
+        }
+        db.insert_import(&import).await?;
+
+        let analysis = autotools.analyze();
+        if autotools.configure.is_some() {
+            reports::check_autotools(db, &inner_digests.sha256, &analysis).await?;
         }
     }
 
@@ -296,28 +390,28 @@ mod tests {
         );
     }
 
+    const FOO_TAR_GZ: &[u8] = &[
+        0x1f, 0x8b, 0x8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0xed, 0xd5, 0xed, 0xa, 0x82, 0x30,
+        0x14, 0x6, 0xe0, 0xfd, 0xee, 0x2a, 0x76, 0x3, 0xd9, 0xbe, 0x77, 0x23, 0xfd, 0xf, 0x21,
+        0xcd, 0x91, 0x29, 0xa8, 0xfd, 0xe8, 0xee, 0x9b, 0xd4, 0x20, 0xfa, 0x10, 0x2, 0xa7, 0x94,
+        0xef, 0x83, 0x30, 0x50, 0xc1, 0xc3, 0xde, 0x9d, 0x63, 0x5e, 0xd7, 0x6b, 0x9e, 0xb0, 0xd,
+        0x89, 0x88, 0x79, 0x56, 0xeb, 0x7e, 0xe5, 0x56, 0xb3, 0xc7, 0x35, 0x20, 0x5c, 0x19, 0xee,
+        0x6f, 0x5a, 0x61, 0x2c, 0x61, 0x9c, 0x19, 0x2b, 0x9, 0xd5, 0x31, 0x8b, 0xa, 0xce, 0x6d,
+        0x97, 0x36, 0x94, 0xfa, 0x35, 0x6b, 0x86, 0xdf, 0x1b, 0x7e, 0xfe, 0xa3, 0xf2, 0x7b, 0xfe,
+        0x75, 0xe3, 0xe, 0xae, 0x4a, 0xcb, 0x5d, 0xee, 0xca, 0x6c, 0xe4, 0x6f, 0xf4, 0x1, 0x1b,
+        0xa5, 0x3e, 0xe7, 0x2f, 0xe5, 0x53, 0xfe, 0x52, 0x71, 0x4d, 0x28, 0x1b, 0xb9, 0x8e, 0xb7,
+        0x16, 0x9e, 0xff, 0xb6, 0x70, 0x2d, 0xf5, 0x57, 0x57, 0x64, 0x34, 0x9c, 0x1, 0xda, 0x9f,
+        0x81, 0x64, 0x35, 0x77, 0x69, 0x30, 0x81, 0xd0, 0xff, 0x45, 0xda, 0xec, 0x4b, 0x57, 0x1d,
+        0x67, 0xe9, 0xff, 0x97, 0xf9, 0x6f, 0x5, 0xf3, 0xf3, 0x9f, 0x4f, 0x31, 0x9c, 0x16, 0xde,
+        0xff, 0x61, 0x8b, 0xdb, 0xcb, 0x29, 0x56, 0xfc, 0xb7, 0xff, 0xbf, 0xb5, 0x5f, 0xe4, 0x6f,
+        0x98, 0x30, 0x84, 0x8a, 0x98, 0xb9, 0x7, 0xb, 0xcf, 0x1f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xfe, 0xc3, 0x15, 0xdc, 0x23, 0xbf, 0x4f, 0x0,
+        0x28, 0x0, 0x0,
+    ];
+
     #[tokio::test]
     async fn test_ingest_tar() {
-        let data = [
-            0x1f, 0x8b, 0x8, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3, 0xed, 0xd5, 0xed, 0xa, 0x82, 0x30,
-            0x14, 0x6, 0xe0, 0xfd, 0xee, 0x2a, 0x76, 0x3, 0xd9, 0xbe, 0x77, 0x23, 0xfd, 0xf, 0x21,
-            0xcd, 0x91, 0x29, 0xa8, 0xfd, 0xe8, 0xee, 0x9b, 0xd4, 0x20, 0xfa, 0x10, 0x2, 0xa7,
-            0x94, 0xef, 0x83, 0x30, 0x50, 0xc1, 0xc3, 0xde, 0x9d, 0x63, 0x5e, 0xd7, 0x6b, 0x9e,
-            0xb0, 0xd, 0x89, 0x88, 0x79, 0x56, 0xeb, 0x7e, 0xe5, 0x56, 0xb3, 0xc7, 0x35, 0x20,
-            0x5c, 0x19, 0xee, 0x6f, 0x5a, 0x61, 0x2c, 0x61, 0x9c, 0x19, 0x2b, 0x9, 0xd5, 0x31,
-            0x8b, 0xa, 0xce, 0x6d, 0x97, 0x36, 0x94, 0xfa, 0x35, 0x6b, 0x86, 0xdf, 0x1b, 0x7e,
-            0xfe, 0xa3, 0xf2, 0x7b, 0xfe, 0x75, 0xe3, 0xe, 0xae, 0x4a, 0xcb, 0x5d, 0xee, 0xca,
-            0x6c, 0xe4, 0x6f, 0xf4, 0x1, 0x1b, 0xa5, 0x3e, 0xe7, 0x2f, 0xe5, 0x53, 0xfe, 0x52,
-            0x71, 0x4d, 0x28, 0x1b, 0xb9, 0x8e, 0xb7, 0x16, 0x9e, 0xff, 0xb6, 0x70, 0x2d, 0xf5,
-            0x57, 0x57, 0x64, 0x34, 0x9c, 0x1, 0xda, 0x9f, 0x81, 0x64, 0x35, 0x77, 0x69, 0x30,
-            0x81, 0xd0, 0xff, 0x45, 0xda, 0xec, 0x4b, 0x57, 0x1d, 0x67, 0xe9, 0xff, 0x97, 0xf9,
-            0x6f, 0x5, 0xf3, 0xf3, 0x9f, 0x4f, 0x31, 0x9c, 0x16, 0xde, 0xff, 0x61, 0x8b, 0xdb,
-            0xcb, 0x29, 0x56, 0xfc, 0xb7, 0xff, 0xbf, 0xb5, 0x5f, 0xe4, 0x6f, 0x98, 0x30, 0x84,
-            0x8a, 0x98, 0xb9, 0x7, 0xb, 0xcf, 0x1f, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
-            0x0, 0x0, 0x0, 0x0, 0x0, 0xfe, 0xc3, 0x15, 0xdc, 0x23, 0xbf, 0x4f, 0x0, 0x28, 0x0, 0x0,
-        ];
-
-        let summary = stream_data(None, &data[..], Some("gz")).await.unwrap();
+        let summary = stream_data(None, FOO_TAR_GZ, Some("gz")).await.unwrap();
         assert_eq!(summary, TarSummary {
             inner_digests: Checksums {
                 sha256: "sha256:55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0".to_string(),
@@ -386,4 +480,20 @@ mod tests {
             sbom_refs: vec![],
         });
     }
+
+    #[tokio::test]
+    async fn test_ingest_tar_zst() {
+        use async_compression::tokio::bufread::{GzipDecoder, ZstdEncoder};
+
+        let mut data = Vec::new();
+        let tar = io::BufReader::new(GzipDecoder::new(FOO_TAR_GZ));
+        ZstdEncoder::new(tar).read_to_end(&mut data).await.unwrap();
+
+        let summary = stream_data(None, &data[..], Some("zst")).await.unwrap();
+        assert_eq!(
+            summary.inner_digests.sha256,
+            "sha256:55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0"
+        );
+        assert_eq!(summary.files.len(), 4);
+    }
 }
@@ -150,7 +150,7 @@ pub async fn stream_data<R: AsyncRead + Unpin>(
         debug!("source={source:?}");
         let url = source.url;
 
-        let Some(task) = utils::task_for_url(&url) else {
+        let Some(task) = utils::task_for_ref(&url, &source.chksum) else {
             continue;
         };
         db.insert_task(&task).await?;
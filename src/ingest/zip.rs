@@ -0,0 +1,359 @@
+use crate::analyze::totals::{EntryKind, Totals};
+use crate::args;
+use crate::changelog;
+use crate::chksums::{Checksums, Hasher};
+use crate::db;
+use crate::errors::*;
+use crate::ingest::go;
+use crate::ingest::tar::{self, Entry, LinksTo, Metadata};
+use crate::sbom;
+use crate::storage::Storage;
+use crate::utils::{self, SizeLimits};
+use digest::Digest;
+use sha2::Sha256;
+use std::io::{Cursor, Read};
+use tokio::fs::File;
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Unix file type bits as stored in the external attributes of a zip entry
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFBLK: u32 = 0o060000;
+
+#[derive(Debug, PartialEq)]
+pub struct ZipSummary {
+    pub outer_digests: Checksums,
+    pub files: Vec<Entry>,
+    pub sbom_refs: Vec<sbom::Ref>,
+}
+
+/// Lockfiles and changelogs larger than this are not parsed
+const MAX_TEXT_SIZE: u64 = 16 * 1024 * 1024;
+/// Symlink targets are short, anything longer is cut off
+const MAX_LINK_SIZE: u64 = 4096;
+
+/// A member whose content is needed after reading the zip
+enum Member {
+    Sbom {
+        strain: &'static str,
+        path: String,
+        data: String,
+    },
+    Changelog(String),
+    Blob {
+        digest: String,
+        data: Vec<u8>,
+    },
+}
+
+/// How much of each member is read, and which members are kept in memory
+struct ReadLimits {
+    /// Upper limit of the uncompressed size of a single member
+    member: Option<u64>,
+    /// Keep lockfiles and changelogs
+    text: bool,
+    /// Keep files up to this size for blob storage
+    blob: Option<u64>,
+}
+
+fn metadata_from_mode(mode: Option<u32>, links_to: Option<LinksTo>) -> Metadata {
+    Metadata {
+        mode: mode.map(|mode| format!("0o{:o}", mode & !S_IFMT)),
+        links_to,
+        mtime: None,
+        uid: None,
+        username: None,
+        gid: None,
+        groupname: None,
+    }
+}
+
+/// Hash a member, the content is only buffered if `buffer` is set and it fits in `buffer`
+fn read_member<R: Read>(
+    mut reader: R,
+    limit: Option<u64>,
+    buffer: Option<u64>,
+) -> Result<(String, Option<Vec<u8>>)> {
+    let mut buf = [0; 4096];
+    let mut data = buffer.map(|_| Vec::new());
+    let mut sha256 = Sha256::new();
+    let mut size = 0;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        size += n as u64;
+        if let Some(limit) = limit.filter(|limit| size > *limit) {
+            return Err(Error::DownloadTooLarge { size, limit });
+        }
+        let buf = &buf[..n];
+        sha256.update(buf);
+        if buffer.is_some_and(|max| size > max) {
+            data = None;
+        } else if let Some(data) = &mut data {
+            data.extend(buf);
+        }
+    }
+    let digest = format!("sha256:{}", hex::encode(sha256.finalize()));
+    Ok((digest, data))
+}
+
+fn read_entries(
+    data: Vec<u8>,
+    limits: ReadLimits,
+    tx: mpsc::Sender<Member>,
+) -> Result<(Vec<Entry>, Totals)> {
+    let mut archive = ::zip::ZipArchive::new(Cursor::new(data))?;
+    let mut files = Vec::new();
+    let mut totals = Totals::default();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let path = file.name().to_string();
+        let mode = file.unix_mode();
+        let is_symlink = mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK);
+
+        let kind = match mode.map(|mode| mode & S_IFMT) {
+            _ if file.is_dir() => EntryKind::Other,
+            Some(S_IFLNK) => EntryKind::Symlink,
+            Some(S_IFCHR | S_IFBLK) => EntryKind::Device,
+            _ => EntryKind::File,
+        };
+        totals.add(&path, mode, Some(file.size()), kind);
+
+        let (digest, links_to) = if file.is_dir() {
+            (None, None)
+        } else if is_symlink {
+            let mut data = Vec::new();
+            file.by_ref().take(MAX_LINK_SIZE).read_to_end(&mut data)?;
+            let target = String::from_utf8_lossy(&data).into_owned();
+            (None, Some(LinksTo::Symbolic(target)))
+        } else {
+            let filename = path.rsplit('/').next();
+            let sbom = sbom::detect_from_filename(filename).filter(|_| limits.text);
+            let is_changelog = limits.text && path == "debian/changelog";
+            let blob = limits.blob.filter(|max| file.size() <= *max);
+
+            let buffer = if sbom.is_some() || is_changelog {
+                Some(blob.unwrap_or(0).max(MAX_TEXT_SIZE))
+            } else {
+                blob
+            };
+            let (digest, data) = read_member(&mut file, limits.member, buffer)?;
+
+            if let Some(data) = data {
+                let is_text =
+                    (sbom.is_some() || is_changelog) && data.len() as u64 <= MAX_TEXT_SIZE;
+                let is_blob = blob.is_some_and(|max| data.len() as u64 <= max);
+                let (text, blob) = match (is_text, is_blob) {
+                    (true, true) => (Some(data.clone()), Some(data)),
+                    (true, false) => (Some(data), None),
+                    (false, true) => (None, Some(data)),
+                    (false, false) => (None, None),
+                };
+                let text = text.and_then(|data| String::from_utf8(data).ok());
+                let mut members = Vec::new();
+                match (sbom, text) {
+                    (Some(strain), Some(data)) => members.push(Member::Sbom {
+                        strain,
+                        path: path.clone(),
+                        data,
+                    }),
+                    (None, Some(data)) => members.push(Member::Changelog(data)),
+                    _ => (),
+                }
+                if let Some(data) = blob {
+                    members.push(Member::Blob {
+                        digest: digest.clone(),
+                        data,
+                    });
+                }
+                for member in members {
+                    // the receiver only goes away if processing failed, the error is reported there
+                    if tx.blocking_send(member).is_err() {
+                        return Ok((files, totals));
+                    }
+                }
+            }
+
+            (Some(digest), None)
+        };
+
+        let entry = Entry {
+            path,
+            digest,
+            metadata: metadata_from_mode(mode, links_to),
+        };
+        debug!("Found entry={entry:?}");
+        files.push(entry);
+    }
+
+    totals.check_paths(&files);
+    Ok((files, totals))
+}
+
+pub async fn stream_data<R: AsyncRead + Unpin>(
+    db: Option<&db::Client>,
+    reader: R,
+) -> Result<ZipSummary> {
+    // The central directory is at the end of the file, buffer the whole archive
+    let mut reader = Hasher::new(reader);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).await?;
+
+    let (_stream, outer_digests) = reader.digests();
+    info!("Found digests for zip: {outer_digests:?}");
+
+    // Archive the content of files too, if blob storage is configured
+    let storage = match (db, Storage::from_env()?) {
+        (Some(db), Some(storage)) => Some((db, storage, utils::http_client(None)?)),
+        _ => None,
+    };
+    let limits = ReadLimits {
+        member: SizeLimits::from_env()?.default,
+        text: db.is_some(),
+        blob: storage
+            .as_ref()
+            .map(|(_, storage, _)| storage.max_blob_size),
+    };
+
+    // Members are handed over one by one, so only one of them is held in memory at a time
+    let (tx, mut rx) = mpsc::channel(1);
+    let task = tokio::task::spawn_blocking(move || read_entries(data, limits, tx));
+
+    let mut sbom_refs = Vec::new();
+    while let Some(member) = rx.recv().await {
+        match member {
+            Member::Sbom { strain, path, data } => {
+                if let Some(db) = db {
+                    let sbom_ref = tar::register_sbom(db, strain, data, &path).await?;
+                    info!("Inserted sbom {:?}: {:?}", sbom_ref.strain, sbom_ref.chksum);
+                    sbom_refs.push(sbom_ref);
+                }
+            }
+            Member::Changelog(data) => {
+                if let (Some(db), Some(entry)) = (db, changelog::parse_debian(&data)) {
+                    if let (Some(package), Some(version)) = (&entry.package, &entry.version) {
+                        info!("Inserting changelog: package={package:?} version={version:?}");
+                        db.insert_changelog(None, package, version, &entry).await?;
+                    }
+                }
+            }
+            Member::Blob { digest, data } => {
+                if let Some((db, storage, http)) = &storage {
+                    if let Err(err) = storage.store_blob(db, http, &digest, &data).await {
+                        warn!("Failed to archive content of {digest:?}: {err:#}");
+                    }
+                }
+            }
+        }
+    }
+    let (files, totals) = task.await??;
+
+    if let Some(db) = db {
+        // Insert into database
+        let mut import = db::Import::new(&outer_digests.sha256, &files, &totals, &sbom_refs);
+        import.add_chksums_aliases(&outer_digests, "zip");
+        if let Some(h1) = go::module_dirhash(&files) {
+            import.add_alias(&h1, "go-dirhash");
+        }
+        db.insert_import(&import).await?;
+    }
+
+    Ok(ZipSummary {
+        outer_digests,
+        files,
+        sbom_refs,
+    })
+}
+
+pub async fn run(args: &args::IngestZip) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let input: Box<dyn AsyncRead + Unpin> = if let Some(path) = &args.file {
+        Box::new(File::open(path).await?)
+    } else {
+        Box::new(io::stdin())
+    };
+
+    stream_data(Some(&db), input).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chksums;
+    use ::zip::write::SimpleFileOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_member() {
+        let hello = "sha256:a948904f2f0f479b8f8197694b30184b0d2ed1c1cd2a1ec0fb85d299a192a447";
+        let (digest, data) = read_member(&b"hello world\n"[..], None, None).unwrap();
+        assert_eq!((digest.as_str(), data), (hello, None));
+
+        let (digest, data) = read_member(&b"hello world\n"[..], Some(12), Some(12)).unwrap();
+        assert_eq!(
+            (digest.as_str(), data.as_deref()),
+            (hello, Some(&b"hello world\n"[..]))
+        );
+
+        // too large to buffer, but still hashed
+        let (digest, data) = read_member(&b"hello world\n"[..], None, Some(4)).unwrap();
+        assert_eq!((digest.as_str(), data), (hello, None));
+
+        let err = read_member(&b"hello world\n"[..], Some(4), None).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DownloadTooLarge { size: 12, limit: 4 }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_zip() {
+        let mut zip = ::zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().unix_permissions(0o755);
+        zip.add_directory("foo-1.0/", options).unwrap();
+        let options = SimpleFileOptions::default().unix_permissions(0o644);
+        zip.start_file("foo-1.0/original_file", options).unwrap();
+        zip.write_all(b"hello world\n").unwrap();
+        zip.add_symlink("foo-1.0/symlink_file", "original_file", options)
+            .unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let summary = stream_data(None, &data[..]).await.unwrap();
+        assert_eq!(summary.outer_digests.sha256, chksums::sha256(&data));
+        assert_eq!(
+            summary.files,
+            vec![
+                Entry {
+                    path: "foo-1.0/".to_string(),
+                    digest: None,
+                    metadata: metadata_from_mode(Some(0o40755), None),
+                },
+                Entry {
+                    path: "foo-1.0/original_file".to_string(),
+                    digest: Some(
+                        "sha256:a948904f2f0f479b8f8197694b30184b0d2ed1c1cd2a1ec0fb85d299a192a447"
+                            .to_string()
+                    ),
+                    metadata: metadata_from_mode(Some(0o100644), None),
+                },
+                Entry {
+                    path: "foo-1.0/symlink_file".to_string(),
+                    digest: None,
+                    metadata: metadata_from_mode(
+                        Some(0o120644),
+                        Some(LinksTo::Symbolic("original_file".to_string()))
+                    ),
+                },
+            ]
+        );
+        assert_eq!(summary.files[0].metadata.mode.as_deref(), Some("0o755"));
+    }
+}
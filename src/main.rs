@@ -1,19 +1,44 @@
+#![recursion_limit = "256"]
+
 pub mod alias;
+pub mod analyze;
 pub mod apkbuild;
 pub mod apt;
 pub mod args;
+pub mod buildroot;
+pub mod changelog;
+pub mod chimera;
 pub mod chksums;
 pub mod compression;
 pub mod db;
+pub mod dedup;
 pub mod errors;
+pub mod hexdump;
 pub mod ingest;
+pub mod notify;
+pub mod osv;
 pub mod pkgbuild;
+pub mod purl;
+pub mod quarantine;
 pub mod reindex;
+pub mod reports;
+pub mod rpm_spec;
 pub mod sbom;
+pub mod scrub;
+pub mod signature;
+pub mod spack;
+pub mod storage;
 pub mod sync;
+pub mod tasks;
+pub mod telemetry;
+pub mod termux;
+pub mod upstream;
 pub mod utils;
 pub mod void_template;
+pub mod vsa;
+#[cfg(feature = "web")]
 pub mod web;
+#[cfg(feature = "worker")]
 pub mod worker;
 pub mod yocto;
 
@@ -36,12 +61,17 @@ async fn main() -> Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or(log_level)).init();
 
     dotenvy::dotenv().ok();
+    let _telemetry = telemetry::init()?;
 
     match args.subcommand {
+        #[cfg(feature = "web")]
         SubCommand::Web(args) => web::run(&args).await,
+        #[cfg(feature = "worker")]
         SubCommand::Worker(args) => worker::run(&args).await,
         SubCommand::Plumbing(Plumbing::IngestTar(args)) => ingest::tar::run(&args).await,
         SubCommand::Plumbing(Plumbing::IngestGit(args)) => ingest::git::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestHg(args)) => ingest::hg::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestFossil(args)) => ingest::fossil::run(&args).await,
         SubCommand::Plumbing(Plumbing::IngestPacmanSnapshot(args)) => {
             ingest::pacman::run(&args).await
         }
@@ -49,6 +79,14 @@ async fn main() -> Result<()> {
         SubCommand::Plumbing(Plumbing::IngestWolfi(args)) => ingest::wolfi::run(&args).await,
         SubCommand::Plumbing(Plumbing::IngestVoid(args)) => ingest::void::run(&args).await,
         SubCommand::Plumbing(Plumbing::IngestSbom(args)) => sbom::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestApk(args)) => ingest::apk::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestDeb(args)) => ingest::deb::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestDsc(args)) => ingest::dsc::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestZip(args)) => ingest::zip::run(&args).await,
+        SubCommand::Plumbing(Plumbing::IngestAttestation(args)) => {
+            ingest::attestation::run(&args).await
+        }
+        SubCommand::Plumbing(Plumbing::VerifySignature(args)) => signature::run(&args).await,
         SubCommand::Plumbing(Plumbing::ParsePkgbuild(_args)) => {
             let mut bytes = Vec::new();
             let mut stdin = io::stdin();
@@ -65,13 +103,39 @@ async fn main() -> Result<()> {
             sync::live_bootstrap::run(&args).await
         }
         SubCommand::Plumbing(Plumbing::SyncRpm(args)) => sync::rpm::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncObs(args)) => sync::obs::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncKoji(args)) => sync::koji::run(&args).await,
         SubCommand::Plumbing(Plumbing::SyncGentoo(args)) => sync::gentoo::run(&args).await,
         SubCommand::Plumbing(Plumbing::SyncHomebrew(args)) => sync::homebrew::run(&args).await,
         SubCommand::Plumbing(Plumbing::SyncGuix(args)) => sync::guix::run(&args).await,
         SubCommand::Plumbing(Plumbing::SyncVoid(args)) => sync::void::run(&args).await,
         SubCommand::Plumbing(Plumbing::SyncYocto(args)) => sync::yocto::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncBuildroot(args)) => sync::buildroot::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncChimera(args)) => sync::chimera::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncSpack(args)) => sync::spack::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncTermux(args)) => sync::termux::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncPypi(args)) => sync::pypi::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncNpmRegistry(args)) => {
+            sync::npm_registry::run(&args).await
+        }
+        SubCommand::Plumbing(Plumbing::SyncGoproxy(args)) => sync::goproxy::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncConan(args)) => sync::conan::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncOsv(args)) => osv::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncMsys2(args)) => sync::msys2::run(&args).await,
+        SubCommand::Plumbing(Plumbing::SyncVcpkg(args)) => sync::vcpkg::run(&args).await,
         SubCommand::Plumbing(Plumbing::AddRef(args)) => alias::run(&args).await,
+        SubCommand::Plumbing(Plumbing::Refs(args)) => alias::run_refs(&args).await,
+        SubCommand::Plumbing(Plumbing::Aliases(args)) => alias::run_aliases(&args).await,
         SubCommand::Plumbing(Plumbing::ReindexUrl(args)) => reindex::run_url(&args).await,
         SubCommand::Plumbing(Plumbing::ReindexSbom(args)) => reindex::run_sbom(&args).await,
+        SubCommand::Plumbing(Plumbing::ReindexPackages(args)) => reindex::run_packages(&args).await,
+        SubCommand::Plumbing(Plumbing::ReindexFiles(args)) => reindex::run_files(&args).await,
+        SubCommand::Plumbing(Plumbing::ExportVsa(args)) => vsa::run(&args).await,
+        SubCommand::Plumbing(Plumbing::Scrub(args)) => scrub::run(&args).await,
+        SubCommand::Plumbing(Plumbing::DedupRefs(args)) => dedup::run(&args).await,
+        SubCommand::Plumbing(Plumbing::ReviewQuarantine(args)) => quarantine::run(&args).await,
+        SubCommand::Plumbing(Plumbing::Requeue(args)) => tasks::run_requeue(&args).await,
+        SubCommand::Plumbing(Plumbing::Tasks(args)) => tasks::run(&args).await,
+        SubCommand::Plumbing(Plumbing::GenerateReports(args)) => reports::run(&args).await,
     }
 }
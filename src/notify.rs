@@ -0,0 +1,512 @@
+use crate::db::{self, RefView, Report, Subscription};
+use crate::errors::*;
+use crate::utils::{self, HttpIdentity};
+use serde::Serialize;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::env;
+use std::fmt::Write as _;
+use std::net::{IpAddr, SocketAddr};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::net;
+use tokio::process::Command;
+
+pub const TARGET_EMAIL: &str = "email";
+pub const TARGET_WEBHOOK: &str = "webhook";
+
+/// How many refs and reports are sent per subscription and run, the rest is picked up next time
+const NOTIFY_LIMIT: i64 = 100;
+/// Check the target of a subscription and return its normalized kind
+pub fn validate_target(kind: &str, target: &str) -> Result<&'static str> {
+    match kind {
+        TARGET_EMAIL => {
+            // this ends up in a mail header, be strict about it
+            if target.is_empty() || target.len() > 254 {
+                return Err(Error::InvalidSubscription("invalid email address"));
+            }
+            if target.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                return Err(Error::InvalidSubscription("invalid email address"));
+            }
+            match target.split_once('@') {
+                Some((local, domain)) if !local.is_empty() && domain.contains('.') => {
+                    Ok(TARGET_EMAIL)
+                }
+                _ => Err(Error::InvalidSubscription("invalid email address")),
+            }
+        }
+        TARGET_WEBHOOK => {
+            let Ok(url) = reqwest::Url::parse(target) else {
+                return Err(Error::InvalidSubscription("invalid webhook url"));
+            };
+            if url.scheme() != "https" || url.host_str().is_none() {
+                return Err(Error::InvalidSubscription("webhook url needs to be https"));
+            }
+            Ok(TARGET_WEBHOOK)
+        }
+        _ => Err(Error::InvalidSubscription("unknown target kind")),
+    }
+}
+
+/// Only deliver to addresses that are reachable from the internet
+fn is_public_addr(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // shared address space, used for carrier-grade nat
+                || (a == 100 && (b & 0xc0) == 64)
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_addr(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local
+                || (first & 0xfe00) == 0xfc00
+                // link-local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolve the host of a webhook and refuse to talk to internal services
+pub async fn resolve_webhook(url: &str) -> Result<(String, Vec<SocketAddr>)> {
+    let invalid = || Error::InvalidSubscription("invalid webhook url");
+    let url = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    let host = url.host_str().ok_or_else(invalid)?;
+    let port = url.port_or_known_default().ok_or_else(invalid)?;
+
+    let lookup = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs = net::lookup_host((lookup, port))
+        .await
+        .map_err(|_| Error::InvalidSubscription("webhook host does not resolve"))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(Error::InvalidSubscription("webhook host does not resolve"));
+    }
+    if !addrs.iter().all(|addr| is_public_addr(addr.ip())) {
+        return Err(Error::InvalidSubscription(
+            "webhook host resolves to a non-public address",
+        ));
+    }
+    Ok((host.to_string(), addrs))
+}
+
+/// Post to a webhook, pinned to the addresses we've checked and without following redirects
+async fn deliver_webhook(url: &str, notification: &Notification<'_>) -> Result<()> {
+    let (host, addrs) = resolve_webhook(url).await?;
+    let http = reqwest::ClientBuilder::new()
+        .user_agent(HttpIdentity::from_env().user_agent().as_ref())
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .connect_timeout(utils::CONNECT_TIMEOUT)
+        .read_timeout(utils::READ_TIMEOUT)
+        .build()?;
+    http.post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(notification)?)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct Notification<'a> {
+    pub vendor: Option<&'a str>,
+    pub package: Option<&'a str>,
+    pub refs: Vec<RefView>,
+    pub reports: Vec<Report>,
+}
+
+impl Notification<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.refs.is_empty() && self.reports.is_empty()
+    }
+}
+
+fn describe_filter(vendor: Option<&str>, package: Option<&str>) -> String {
+    match (vendor, package) {
+        (Some(vendor), Some(package)) => format!("{vendor}:{package}"),
+        (Some(vendor), None) => format!("all packages of {vendor}"),
+        (None, Some(package)) => package.to_string(),
+        (None, None) => "all packages".to_string(),
+    }
+}
+
+/// Renders and sends mails through sendmail
+pub struct Mailer {
+    sendmail: String,
+    from: String,
+    base_url: String,
+}
+
+impl Mailer {
+    /// Links point to `WHATSRC_HTTP_INSTANCE_URL`, the same url we identify ourselves with
+    pub fn from_env() -> Self {
+        let base_url = HttpIdentity::from_env().base_url().to_string();
+        Mailer {
+            sendmail: env::var("WHATSRC_SENDMAIL").unwrap_or_else(|_| "/usr/sbin/sendmail".into()),
+            from: env::var("WHATSRC_MAIL_FROM").unwrap_or_else(|_| "noreply@whatsrc.org".into()),
+            base_url,
+        }
+    }
+
+    fn link(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url.trim_end_matches('/'))
+    }
+
+    /// Ask the recipient to confirm a subscription, nothing else is sent before that
+    pub fn format_confirmation(&self, subscription: &Subscription) -> String {
+        let filter = describe_filter(
+            subscription.vendor.as_deref(),
+            subscription.package.as_deref(),
+        );
+        let mut mail = String::new();
+        writeln!(mail, "From: {}", self.from).ok();
+        writeln!(mail, "To: {}", subscription.target).ok();
+        writeln!(mail, "Subject: Confirm your What the src?! subscription").ok();
+        writeln!(mail, "Content-Type: text/plain; charset=utf-8").ok();
+        writeln!(mail).ok();
+        writeln!(
+            mail,
+            "Somebody asked to send updates for {filter} to this address."
+        )
+        .ok();
+        writeln!(mail, "To confirm the subscription, open this link:").ok();
+        writeln!(mail).ok();
+        writeln!(
+            mail,
+            "{}",
+            self.link(&format!(
+                "/subscribe/confirm/{}",
+                subscription.confirm_token
+            ))
+        )
+        .ok();
+        writeln!(mail).ok();
+        writeln!(
+            mail,
+            "If this wasn't you, ignore this mail. Unconfirmed subscriptions are removed after 7 days."
+        )
+        .ok();
+        mail
+    }
+
+    /// Render a notification as mail, including headers, to be handed to sendmail
+    pub fn format_notification(
+        &self,
+        subscription: &Subscription,
+        notification: &Notification,
+    ) -> String {
+        let filter = describe_filter(notification.vendor, notification.package);
+        let unsubscribe = self.link(&format!("/unsubscribe/{}", subscription.unsubscribe_token));
+        let mut mail = String::new();
+        writeln!(mail, "From: {}", self.from).ok();
+        writeln!(mail, "To: {}", subscription.target).ok();
+        writeln!(mail, "Subject: What the src?! updates for {filter}").ok();
+        writeln!(mail, "Content-Type: text/plain; charset=utf-8").ok();
+        writeln!(mail, "List-Unsubscribe: <{unsubscribe}>").ok();
+        writeln!(mail, "List-Unsubscribe-Post: List-Unsubscribe=One-Click").ok();
+        writeln!(mail).ok();
+
+        if !notification.refs.is_empty() {
+            writeln!(mail, "New releases:").ok();
+            for r in &notification.refs {
+                writeln!(
+                    mail,
+                    "- {}: {} {} ({})",
+                    r.display_vendor,
+                    r.package,
+                    r.version,
+                    self.link(&format!("/artifact/{}", r.chksum))
+                )
+                .ok();
+            }
+            writeln!(mail).ok();
+        }
+
+        if !notification.reports.is_empty() {
+            writeln!(mail, "New reports:").ok();
+            for report in &notification.reports {
+                writeln!(
+                    mail,
+                    "- [{}] {} {}: {} ({})",
+                    report.kind,
+                    report.package.as_deref().unwrap_or("-"),
+                    report.version.as_deref().unwrap_or("-"),
+                    report.summary,
+                    self.link(&format!("/artifact/{}", report.chksum)),
+                )
+                .ok();
+            }
+            writeln!(mail).ok();
+        }
+
+        writeln!(
+            mail,
+            "You receive this mail because you subscribed to {filter}."
+        )
+        .ok();
+        writeln!(mail, "Unsubscribe: {unsubscribe}").ok();
+        mail
+    }
+
+    async fn send(&self, mail: &str) -> Result<()> {
+        let mut child = Command::new(&self.sendmail)
+            .args(["-t", "-i"])
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(mail.as_bytes()).await?;
+        drop(stdin);
+
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(Error::NotificationFailed(format!(
+                "sendmail exited with {status}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+async fn notify(
+    db: &db::Client,
+    mailer: &Mailer,
+    subscription: &Subscription,
+    until: &DateTime<Utc>,
+) -> Result<()> {
+    let refs = db
+        .get_new_refs_for(subscription, until, NOTIFY_LIMIT)
+        .await?;
+    let reports = db
+        .get_new_reports_for(subscription, until, NOTIFY_LIMIT)
+        .await?;
+    let notification = Notification {
+        vendor: subscription.vendor.as_deref(),
+        package: subscription.package.as_deref(),
+        refs: refs.into_iter().map(RefView::from).collect(),
+        reports,
+    };
+
+    if !notification.is_empty() {
+        info!(
+            "Notifying subscription id={} ({} refs, {} reports)",
+            subscription.id,
+            notification.refs.len(),
+            notification.reports.len()
+        );
+        match subscription.target_kind.as_str() {
+            TARGET_EMAIL => {
+                let mail = mailer.format_notification(subscription, &notification);
+                mailer.send(&mail).await?;
+            }
+            TARGET_WEBHOOK => deliver_webhook(&subscription.target, &notification).await?,
+            kind => {
+                return Err(Error::NotificationFailed(format!(
+                    "unknown target kind: {kind:?}"
+                )))
+            }
+        }
+    }
+
+    db.bump_subscription_notified(subscription, until).await?;
+    Ok(())
+}
+
+async fn send_confirmations(db: &db::Client, mailer: &Mailer) -> Result<()> {
+    let expired = db.delete_expired_subscriptions().await?;
+    if expired > 0 {
+        info!("Removed {expired} unconfirmed subscriptions");
+    }
+
+    for subscription in db.get_pending_confirmations().await? {
+        if subscription.target_kind != TARGET_EMAIL {
+            continue;
+        }
+        info!(
+            "Sending confirmation mail for subscription id={}",
+            subscription.id
+        );
+        // mark it first, if sending fails we don't want to retry in a loop
+        db.bump_confirmation_sent(&subscription).await?;
+        let mail = mailer.format_confirmation(&subscription);
+        if let Err(err) = mailer.send(&mail).await {
+            warn!(
+                "Failed to send confirmation for subscription id={}: {err:#}",
+                subscription.id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Deliver everything that happened since the last run, failed deliveries are retried next time
+pub async fn run_once(db: &db::Client) -> Result<()> {
+    let mailer = Mailer::from_env();
+    send_confirmations(db, &mailer).await?;
+
+    let until = Utc::now();
+    for subscription in db.get_subscriptions().await? {
+        if let Err(err) = notify(db, &mailer, &subscription, &until).await {
+            warn!(
+                "Failed to notify subscription id={}: {err:#}",
+                subscription.id
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_validate_target() {
+        assert_eq!(
+            validate_target("email", "alice@example.com").unwrap(),
+            TARGET_EMAIL
+        );
+        assert!(validate_target("email", "alice").is_err());
+        assert!(validate_target("email", "alice@example.com\nBcc: x@example.com").is_err());
+        assert_eq!(
+            validate_target("webhook", "https://hooks.example.com/abc").unwrap(),
+            TARGET_WEBHOOK
+        );
+        assert!(validate_target("webhook", "http://hooks.example.com/abc").is_err());
+        assert!(validate_target("webhook", "file:///etc/passwd").is_err());
+        assert!(validate_target("irc", "#whatsrc").is_err());
+    }
+
+    #[test]
+    fn test_is_public_addr() {
+        for ip in ["1.1.1.1", "93.184.215.14", "2606:4700:4700::1111"] {
+            assert!(is_public_addr(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_addr(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_webhook_loopback() {
+        for url in [
+            "https://127.0.0.1/hook",
+            "https://[::1]/hook",
+            "https://169.254.169.254/latest/meta-data/",
+        ] {
+            let err = resolve_webhook(url).await.unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidSubscription(_)),
+                "{url}: {err:?}"
+            );
+        }
+    }
+
+    fn subscription() -> Subscription {
+        Subscription {
+            id: 1,
+            token: "aaaa".to_string(),
+            confirm_token: "bbbb".to_string(),
+            unsubscribe_token: "cccc".to_string(),
+            target_kind: TARGET_EMAIL.to_string(),
+            target: "alice@example.com".to_string(),
+            vendor: Some("archlinux".to_string()),
+            package: Some("xz".to_string()),
+            created_at: Utc::now(),
+            last_notified: Utc::now(),
+            confirmation_sent_at: None,
+            confirmed_at: None,
+        }
+    }
+
+    fn mailer() -> Mailer {
+        Mailer {
+            sendmail: "/usr/sbin/sendmail".to_string(),
+            from: "noreply@whatsrc.example.com".to_string(),
+            base_url: "https://whatsrc.example.com/".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_confirmation() {
+        let mail = mailer().format_confirmation(&subscription());
+        assert_eq!(
+            mail,
+            "From: noreply@whatsrc.example.com
+To: alice@example.com
+Subject: Confirm your What the src?! subscription
+Content-Type: text/plain; charset=utf-8
+
+Somebody asked to send updates for archlinux:xz to this address.
+To confirm the subscription, open this link:
+
+https://whatsrc.example.com/subscribe/confirm/bbbb
+
+If this wasn't you, ignore this mail. Unconfirmed subscriptions are removed after 7 days.
+"
+        );
+    }
+
+    #[test]
+    fn test_format_notification() {
+        let notification = Notification {
+            vendor: Some("archlinux"),
+            package: Some("xz"),
+            refs: vec![RefView {
+                chksum: "sha256:0f3b1a".to_string(),
+                vendor: "archlinux".to_string(),
+                display_vendor: Cow::Borrowed("Arch Linux"),
+                package: "xz".to_string(),
+                version: "5.6.2-1".to_string(),
+                filename: None,
+                href: None,
+                purl: None,
+            }],
+            reports: vec![],
+        };
+        let mail = mailer().format_notification(&subscription(), &notification);
+        assert_eq!(
+            mail,
+            "From: noreply@whatsrc.example.com
+To: alice@example.com
+Subject: What the src?! updates for archlinux:xz
+Content-Type: text/plain; charset=utf-8
+List-Unsubscribe: <https://whatsrc.example.com/unsubscribe/cccc>
+List-Unsubscribe-Post: List-Unsubscribe=One-Click
+
+New releases:
+- Arch Linux: xz 5.6.2-1 (https://whatsrc.example.com/artifact/sha256:0f3b1a)
+
+You receive this mail because you subscribed to archlinux:xz.
+Unsubscribe: https://whatsrc.example.com/unsubscribe/cccc
+"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::utils::{self, HttpClient};
+use serde::{Deserialize, Serialize};
+
+pub const QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// The OSV ecosystem that tracks the packages of a vendor, by source package name
+pub fn ecosystem_for(vendor: &str) -> Option<&'static str> {
+    match vendor {
+        "alpine" => Some("Alpine"),
+        "debian" => Some("Debian"),
+        "ubuntu" => Some("Ubuntu"),
+        "wolfi" => Some("Wolfi"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Query<'a> {
+    version: &'a str,
+    package: QueryPackage<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryPackage<'a> {
+    name: &'a str,
+    ecosystem: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryResponse {
+    #[serde(default)]
+    vulns: Vec<Vulnerability>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vulnerability {
+    pub id: String,
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// Strip the vulnerability records down to what we display
+pub fn parse_vulns(value: serde_json::Value) -> Result<Vec<Vulnerability>> {
+    let vulns = serde_json::from_value(value)?;
+    Ok(vulns)
+}
+
+pub async fn query(
+    http: &HttpClient,
+    ecosystem: &str,
+    package: &str,
+    version: &str,
+) -> Result<Vec<Vulnerability>> {
+    let mut vulns = Vec::new();
+    let mut page_token = None;
+    loop {
+        let query = Query {
+            version,
+            package: QueryPackage {
+                name: package,
+                ecosystem,
+            },
+            page_token,
+        };
+        let response = http
+            .post_json_response::<_, QueryResponse>(QUERY_URL, &query)
+            .await?;
+        vulns.extend(response.vulns);
+
+        page_token = response.next_page_token;
+        if page_token.is_none() {
+            break;
+        }
+    }
+    Ok(vulns)
+}
+
+pub async fn run(args: &args::SyncOsv) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = utils::http_client(None)?;
+
+    let vendors = match &args.vendor {
+        Some(vendor) => vec![vendor.as_str()],
+        None => vec!["alpine", "debian", "ubuntu", "wolfi"],
+    };
+    let ecosystems = vendors
+        .iter()
+        .filter_map(|vendor| Some((*vendor, ecosystem_for(vendor)?)))
+        .collect::<Vec<_>>();
+    if ecosystems.is_empty() {
+        warn!("Vendor is not tracked by OSV: {:?}", args.vendor);
+        return Ok(());
+    }
+
+    for (ecosystem, package, version) in db.get_osv_candidates(&ecosystems, args.limit).await? {
+        debug!("Querying OSV: ecosystem={ecosystem:?} package={package:?} version={version:?}");
+        let vulns = match query(&http, &ecosystem, &package, &version).await {
+            Ok(vulns) => vulns,
+            Err(err) => {
+                warn!("Failed to query OSV for {package:?} {version:?}: {err:#}");
+                continue;
+            }
+        };
+        if !vulns.is_empty() {
+            info!(
+                "Found advisories for {ecosystem:?} {package:?} {version:?}: {}",
+                vulns.len()
+            );
+        }
+        db.insert_advisories(&ecosystem, &package, &version, &vulns)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_response() {
+        let data = r#"{
+  "vulns": [
+    {
+      "id": "DSA-5649-1",
+      "summary": "xz-utils - security update",
+      "modified": "2024-03-30T05:06:11Z",
+      "published": "2024-03-29T00:00:00Z",
+      "upstream": ["CVE-2024-3094"],
+      "affected": []
+    },
+    {
+      "id": "CVE-2024-3094",
+      "modified": "2024-04-12T22:42:58Z",
+      "aliases": ["GHSA-rxwq-x6h5-x525"],
+      "affected": []
+    }
+  ]
+}"#;
+        let response = serde_json::from_str::<QueryResponse>(data).unwrap();
+        assert_eq!(response.next_page_token, None);
+        assert_eq!(
+            response.vulns,
+            [
+                Vulnerability {
+                    id: "DSA-5649-1".to_string(),
+                    summary: Some("xz-utils - security update".to_string()),
+                    aliases: vec![],
+                },
+                Vulnerability {
+                    id: "CVE-2024-3094".to_string(),
+                    summary: None,
+                    aliases: vec!["GHSA-rxwq-x6h5-x525".to_string()],
+                },
+            ]
+        );
+
+        let response = serde_json::from_str::<QueryResponse>("{}").unwrap();
+        assert!(response.vulns.is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::errors::*;
+use std::collections::BTreeMap;
 use std::str;
 use yash_syntax::syntax::{self, TextUnit, Value, WordUnit};
 
@@ -7,6 +8,16 @@ pub struct Pkgbuild {
     pub sha256sums: Vec<String>,
     pub sha512sums: Vec<String>,
     pub b2sums: Vec<String>,
+    pub validpgpkeys: Vec<String>,
+    /// Checksums of `source_<arch>=()`, from `sha256sums_<arch>=()` and friends
+    pub arch: BTreeMap<String, ArchSums>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ArchSums {
+    pub sha256sums: Vec<String>,
+    pub sha512sums: Vec<String>,
+    pub b2sums: Vec<String>,
 }
 
 pub fn parse(bytes: &[u8]) -> Result<Pkgbuild> {
@@ -33,7 +44,20 @@ pub fn parse(bytes: &[u8]) -> Result<Pkgbuild> {
                     "sha256sums" => &mut pkgbuild.sha256sums,
                     "sha512sums" => &mut pkgbuild.sha512sums,
                     "b2sums" => &mut pkgbuild.b2sums,
-                    _ => continue,
+                    "validpgpkeys" => &mut pkgbuild.validpgpkeys,
+                    _ => {
+                        let Some((kind @ ("sha256sums" | "sha512sums" | "b2sums"), arch)) =
+                            name.split_once('_')
+                        else {
+                            continue;
+                        };
+                        let sums = pkgbuild.arch.entry(arch.to_string()).or_default();
+                        match kind {
+                            "sha256sums" => &mut sums.sha256sums,
+                            "sha512sums" => &mut sums.sha512sums,
+                            _ => &mut sums.b2sums,
+                        }
+                    }
                 };
 
                 let Value::Array(values) = &assign.value else {
@@ -86,6 +110,8 @@ mod tests {
 sha256sums+=(\"f9a4925f7d7bb7de54e17cd9ad7c584dfae88ad182d943b79cf403425000f128\")
 b2sums=('cd594be73fcf632544195d09518901b1055ae86dcf463a5d446a83beba66073c70a9dfb75efd9d826c2ecf7215ab6cd76128a20104d5ef4ea57470061d2e29bf'
         'f4f89b720bcbe23c5413c6cbc2d0793d8e379fc53861a6fbd83f506e56a86132bb92236498b4357310b09e51fd05aa5ccc941649a4f205fb4e53cb6bc32cdd64')
+validpgpkeys=('3690C240CE51B4670D30AD1C38EE757D69184620'  # Lasse Collin <lasse.collin@tukaani.org>
+)
 ";
         let pkgbuild = parse(script).unwrap();
         assert_eq!(
@@ -100,6 +126,37 @@ b2sums=('cd594be73fcf632544195d09518901b1055ae86dcf463a5d446a83beba66073c70a9dfb
                     "cd594be73fcf632544195d09518901b1055ae86dcf463a5d446a83beba66073c70a9dfb75efd9d826c2ecf7215ab6cd76128a20104d5ef4ea57470061d2e29bf".to_string(),
                     "f4f89b720bcbe23c5413c6cbc2d0793d8e379fc53861a6fbd83f506e56a86132bb92236498b4357310b09e51fd05aa5ccc941649a4f205fb4e53cb6bc32cdd64".to_string(),
                 ],
+                validpgpkeys: vec!["3690C240CE51B4670D30AD1C38EE757D69184620".to_string()],
+                arch: BTreeMap::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_arch_specific() {
+        init();
+
+        let script = b"pkgname=(foo-bin foo-docs)
+sha256sums=('7a1258a5dfc48c54cea1092adddb6bcfb1fcf19c7272c0a6a9e1d2d7daee6e12')
+sha256sums_x86_64=('f9a4925f7d7bb7de54e17cd9ad7c584dfae88ad182d943b79cf403425000f128')
+sha256sums_aarch64=('56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e')
+b2sums_aarch64=('SKIP')
+_pkgname=foo
+";
+        let pkgbuild = parse(script).unwrap();
+        assert_eq!(pkgbuild.sha256sums.len(), 1);
+        assert_eq!(
+            pkgbuild.arch,
+            maplit::btreemap! {
+                "aarch64".to_string() => ArchSums {
+                    sha256sums: vec!["56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e".to_string()],
+                    sha512sums: vec![],
+                    b2sums: vec!["SKIP".to_string()],
+                },
+                "x86_64".to_string() => ArchSums {
+                    sha256sums: vec!["f9a4925f7d7bb7de54e17cd9ad7c584dfae88ad182d943b79cf403425000f128".to_string()],
+                    ..Default::default()
+                },
             }
         );
     }
@@ -0,0 +1,230 @@
+use crate::errors::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Mapping between vendors and the purl type and namespace of their packages
+const VENDORS: &[(&str, &str, Option<&str>)] = &[
+    ("alpine", "apk", Some("alpine")),
+    ("archlinux", "alpm", Some("arch")),
+    ("conan", "conan", None),
+    ("debian", "deb", Some("debian")),
+    ("fedora", "rpm", Some("fedora")),
+    ("kali", "deb", Some("kali")),
+    ("msys2", "alpm", Some("msys2")),
+    ("opensuse", "rpm", Some("opensuse")),
+    ("pypi", "pypi", None),
+    ("ubuntu", "deb", Some("ubuntu")),
+    ("wolfi", "apk", Some("wolfi")),
+];
+
+/// A package url, as described in <https://github.com/package-url/purl-spec>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Purl {
+    pub ty: String,
+    pub namespace: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+    pub qualifiers: BTreeMap<String, String>,
+}
+
+impl Purl {
+    pub fn new(ty: &str, name: &str, version: Option<&str>) -> Self {
+        Purl {
+            ty: ty.to_string(),
+            namespace: None,
+            name: name.to_string(),
+            version: version.map(String::from),
+            qualifiers: BTreeMap::new(),
+        }
+    }
+
+    /// The purl of a package in a registry, as listed in a lockfile
+    pub fn for_registry_package(ty: &str, name: &str, version: &str) -> Self {
+        match ty {
+            // scoped packages are `@scope/name`
+            "npm" => match name.split_once('/') {
+                Some((scope, name)) if scope.starts_with('@') => Purl {
+                    namespace: Some(scope.to_string()),
+                    ..Purl::new(ty, name, Some(version))
+                },
+                _ => Purl::new(ty, name, Some(version)),
+            },
+            "golang" | "composer" => match name.rsplit_once('/') {
+                Some((namespace, name)) => Purl {
+                    namespace: Some(namespace.to_string()),
+                    ..Purl::new(ty, name, Some(version))
+                },
+                None => Purl::new(ty, name, Some(version)),
+            },
+            "pypi" => {
+                let name = name.to_lowercase().replace('_', "-");
+                Purl::new(ty, &name, Some(version))
+            }
+            _ => Purl::new(ty, name, Some(version)),
+        }
+    }
+
+    /// The purl of the source package a ref belongs to, if the vendor has a purl type
+    pub fn for_ref(vendor: &str, package: &str, version: &str) -> Option<Self> {
+        let (_, ty, namespace) = VENDORS.iter().find(|(v, _, _)| *v == vendor)?;
+        let mut purl = Purl::new(ty, package, Some(version));
+        purl.namespace = namespace.map(String::from);
+        match *ty {
+            "deb" => {
+                purl.qualifiers.insert("arch".into(), "source".into());
+            }
+            "rpm" => {
+                purl.qualifiers.insert("arch".into(), "src".into());
+            }
+            _ => (),
+        }
+        Some(purl)
+    }
+
+    /// The vendor this purl refers to, if it's the package of a known vendor
+    pub fn vendor(&self) -> Option<&'static str> {
+        VENDORS
+            .iter()
+            .find(|(_, ty, namespace)| *ty == self.ty && *namespace == self.namespace.as_deref())
+            .map(|(vendor, _, _)| *vendor)
+    }
+}
+
+fn decode(txt: &str) -> String {
+    url_escape::decode(txt).into_owned()
+}
+
+impl FromStr for Purl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPurl(s.to_string());
+
+        let rest = s.strip_prefix("pkg:").ok_or_else(invalid)?;
+        let rest = rest.trim_start_matches('/');
+        // the subpath is not relevant for us
+        let rest = rest.split_once('#').map(|x| x.0).unwrap_or(rest);
+        let (rest, qualifiers) = match rest.split_once('?') {
+            Some((rest, qualifiers)) => (rest, Some(qualifiers)),
+            None => (rest, None),
+        };
+        let (rest, version) = match rest.rsplit_once('@') {
+            Some((rest, version)) if !rest.ends_with('/') => (rest, Some(decode(version))),
+            _ => (rest, None),
+        };
+
+        let (ty, rest) = rest.split_once('/').ok_or_else(invalid)?;
+        let rest = rest.trim_end_matches('/');
+        let (namespace, name) = match rest.rsplit_once('/') {
+            Some((namespace, name)) => {
+                let namespace = namespace
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(decode)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (Some(namespace), decode(name))
+            }
+            None => (None, decode(rest)),
+        };
+        if ty.is_empty() || name.is_empty() {
+            return Err(invalid());
+        }
+
+        let qualifiers = qualifiers
+            .into_iter()
+            .flat_map(|q| q.split('&'))
+            .filter_map(|kv| kv.split_once('='))
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(k, v)| (k.to_lowercase(), decode(v)))
+            .collect();
+
+        Ok(Purl {
+            ty: ty.to_lowercase(),
+            namespace: namespace.filter(|ns| !ns.is_empty()),
+            name,
+            version,
+            qualifiers,
+        })
+    }
+}
+
+impl fmt::Display for Purl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pkg:{}/", self.ty)?;
+        if let Some(namespace) = &self.namespace {
+            for segment in namespace.split('/') {
+                write!(f, "{}/", url_escape::encode_component(segment))?;
+            }
+        }
+        write!(f, "{}", url_escape::encode_component(&self.name))?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", url_escape::encode_component(version))?;
+        }
+        for (i, (key, value)) in self.qualifiers.iter().enumerate() {
+            let sep = if i == 0 { '?' } else { '&' };
+            write!(f, "{sep}{key}={}", url_escape::encode_component(value))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_purl() {
+        let purl = "pkg:cargo/serde@1.0.200".parse::<Purl>().unwrap();
+        assert_eq!(purl, Purl::new("cargo", "serde", Some("1.0.200")));
+        assert_eq!(purl.to_string(), "pkg:cargo/serde@1.0.200");
+
+        let purl = "pkg:rpm/fedora/xz@5.6.1-1.fc40?arch=src"
+            .parse::<Purl>()
+            .unwrap();
+        assert_eq!(purl.ty, "rpm");
+        assert_eq!(purl.namespace.as_deref(), Some("fedora"));
+        assert_eq!(purl.name, "xz");
+        assert_eq!(purl.version.as_deref(), Some("5.6.1-1.fc40"));
+        assert_eq!(purl.qualifiers.get("arch").map(String::as_str), Some("src"));
+        assert_eq!(purl.vendor(), Some("fedora"));
+
+        let purl = "pkg:npm/%40babel/core@7.25.2".parse::<Purl>().unwrap();
+        assert_eq!(purl.namespace.as_deref(), Some("@babel"));
+        assert_eq!(purl.name, "core");
+
+        let purl = "pkg:golang/github.com/sirupsen/logrus"
+            .parse::<Purl>()
+            .unwrap();
+        assert_eq!(purl.namespace.as_deref(), Some("github.com/sirupsen"));
+        assert_eq!(purl.version, None);
+
+        assert!("cargo/serde@1.0.200".parse::<Purl>().is_err());
+        assert!("pkg:cargo".parse::<Purl>().is_err());
+    }
+
+    #[test]
+    fn test_purl_for_ref() {
+        let purl = Purl::for_ref("debian", "xz-utils", "5.6.1+really5.4.5-1").unwrap();
+        assert_eq!(
+            purl.to_string(),
+            "pkg:deb/debian/xz-utils@5.6.1%2Breally5.4.5-1?arch=source"
+        );
+        assert_eq!(purl.to_string().parse::<Purl>().unwrap(), purl);
+
+        let purl = Purl::for_ref("archlinux", "xz", "1:5.6.2-1").unwrap();
+        assert_eq!(purl.to_string(), "pkg:alpm/arch/xz@1%3A5.6.2-1");
+        assert_eq!(Purl::for_ref("live-bootstrap", "xz", "5.4.1"), None);
+    }
+
+    #[test]
+    fn test_purl_for_registry_package() {
+        let purl = Purl::for_registry_package("npm", "@babel/core", "7.25.2");
+        assert_eq!(purl.to_string(), "pkg:npm/%40babel/core@7.25.2");
+        let purl = Purl::for_registry_package("pypi", "Typing_Extensions", "4.12.2");
+        assert_eq!(purl.to_string(), "pkg:pypi/typing-extensions@4.12.2");
+        let purl = Purl::for_registry_package("golang", "golang.org/x/sys", "v0.25.0");
+        assert_eq!(purl.to_string(), "pkg:golang/golang.org/x/sys@v0.25.0");
+    }
+}
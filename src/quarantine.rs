@@ -0,0 +1,129 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::ingest::tar::Entry;
+
+/// Archives with more files than this need a human to take a look
+pub const MAX_FILES: usize = 250_000;
+
+/// Check if a url matches one of the configured untrusted url prefixes
+pub fn is_untrusted(patterns: &[String], url: &str) -> bool {
+    patterns
+        .iter()
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| url.starts_with(pattern.as_str()))
+}
+
+/// Automated policy for artifacts from untrusted sources, returns the reason for holding it back
+///
+/// `declared` is true if the digest of the artifact is already referenced by a ref we didn't
+/// create from this download, e.g. a checksum from a vendor's package build instructions.
+pub fn check(files: &[Entry], declared: bool) -> Option<String> {
+    if files.len() > MAX_FILES {
+        return Some(format!(
+            "Archive has too many files ({} > {MAX_FILES})",
+            files.len()
+        ));
+    }
+
+    for file in files {
+        let path = file.path.as_str();
+        if path.starts_with('/') || path.split('/').any(|component| component == "..") {
+            return Some(format!("Archive contains suspicious path: {path:?}"));
+        }
+    }
+
+    if !declared {
+        return Some("Digest is not declared by any known ref".to_string());
+    }
+
+    None
+}
+
+pub async fn run(args: &args::ReviewQuarantine) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    if let Some(chksum) = &args.approve {
+        let num = db.release_quarantine(chksum).await?;
+        info!("Approved artifact: chksum={chksum:?} released={num}");
+    } else if let Some(chksum) = &args.reject {
+        db.reject_quarantine(chksum).await?;
+        info!("Rejected artifact: chksum={chksum:?}");
+    } else {
+        for entry in db.get_quarantined(args.all).await? {
+            if args.recheck && !entry.rejected {
+                let Some(artifact) = db.get_artifact(&entry.chksum).await? else {
+                    continue;
+                };
+                let files = artifact.get_files()?.unwrap_or_default();
+                let declared = !db.get_all_refs_for(&entry.chksum).await?.is_empty();
+                if check(&files, declared).is_none() {
+                    let num = db.release_quarantine(&entry.chksum).await?;
+                    info!(
+                        "Artifact passed policy check: chksum={:?} released={num}",
+                        entry.chksum
+                    );
+                    continue;
+                }
+            }
+
+            println!(
+                "{} {:?} rejected={} reason={:?}",
+                entry.chksum, entry.url, entry.rejected, entry.reason
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::tar::Metadata;
+
+    fn entry(path: &str) -> Entry {
+        Entry {
+            path: path.to_string(),
+            digest: None,
+            metadata: Metadata {
+                mode: None,
+                links_to: None,
+                mtime: None,
+                uid: None,
+                username: None,
+                gid: None,
+                groupname: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_is_untrusted() {
+        let patterns = vec!["https://github.com/".to_string(), "".to_string()];
+        assert!(is_untrusted(
+            &patterns,
+            "https://github.com/kpcyrd/what-the-src/archive/v0.1.0.tar.gz"
+        ));
+        assert!(!is_untrusted(
+            &patterns,
+            "https://crates.io/api/v1/crates/what-the-src/0.1.0/download"
+        ));
+    }
+
+    #[test]
+    fn test_check_policy() {
+        let files = [entry("foo-1.0/"), entry("foo-1.0/src/main.rs")];
+        assert_eq!(check(&files, true), None);
+        assert_eq!(
+            check(&files, false),
+            Some("Digest is not declared by any known ref".to_string())
+        );
+
+        let files = [entry("foo-1.0/"), entry("foo-1.0/../../etc/passwd")];
+        assert_eq!(
+            check(&files, true),
+            Some("Archive contains suspicious path: \"foo-1.0/../../etc/passwd\"".to_string())
+        );
+    }
+}
@@ -4,59 +4,170 @@ use crate::errors::*;
 use crate::sbom;
 use crate::utils;
 use futures::StreamExt;
-use sqlx::types::chrono::Utc;
+use sqlx::types::chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashSet;
+use tokio::fs;
 
-pub async fn run_url(args: &args::ReindexUrl) -> Result<()> {
-    let db = db::Client::create().await?;
+/// How many artifacts are loaded at once, progress is saved after each batch
+const BATCH_SIZE: usize = 500;
 
-    let mut scheduled = 0;
-    let now = Utc::now();
+/// Parse `--since`, a date means the start of that day
+fn parse_since(txt: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(txt, "%Y-%m-%d") {
+        let time = date
+            .and_hms_opt(0, 0, 0)
+            .ok_or_else(|| Error::InvalidTimestamp(txt.to_string()))?;
+        Ok(time.and_utc())
+    } else {
+        let time = DateTime::parse_from_rfc3339(txt)
+            .map_err(|_| Error::InvalidTimestamp(txt.to_string()))?;
+        Ok(time.with_timezone(&Utc))
+    }
+}
 
-    let stream = db.get_all_artifacts_by_age();
-    tokio::pin!(stream);
-    while let Some(artifact) = stream.next().await {
-        let artifact = artifact?;
+/// Visits the artifacts matching a filter in batches, oldest import first
+struct ArtifactWalk<'a> {
+    db: &'a db::Client,
+    filter: db::ArtifactFilter,
+    checkpoint: db::ReindexCheckpoint,
+    /// Whether the checkpoint is persisted
+    resumable: bool,
+    cursor: Option<(DateTime<Utc>, String)>,
+}
 
-        if let Some(limit) = &args.limit {
-            if scheduled >= *limit {
-                info!("Reached schedule limit of {limit} items, exiting");
-                break;
+impl<'a> ArtifactWalk<'a> {
+    /// Start a new walk, or resume the checkpoint with the same name
+    async fn open(
+        db: &'a db::Client,
+        job: &str,
+        args: &args::ReindexFilter,
+        age: Option<i64>,
+    ) -> Result<Self> {
+        let name = args.checkpoint.as_ref().map(|name| format!("{job}:{name}"));
+        let existing = match &name {
+            Some(name) => db.get_reindex_checkpoint(name).await?,
+            None => None,
+        };
+        let checkpoint = if let Some(checkpoint) = existing {
+            info!(
+                "Resuming from checkpoint {:?}: processed={} chksum={:?}",
+                checkpoint.name, checkpoint.processed, checkpoint.chksum
+            );
+            checkpoint
+        } else {
+            db::ReindexCheckpoint {
+                name: name.clone().unwrap_or_default(),
+                started_at: Utc::now(),
+                last_imported: None,
+                chksum: None,
+                processed: 0,
             }
+        };
+
+        let filter = db::ArtifactFilter {
+            imported_before: checkpoint.started_at,
+            age,
+            since: args.since.as_deref().map(parse_since).transpose()?,
+            chksum_prefix: args.chksum_prefix.clone(),
+            vendor: args.vendor.clone(),
+        };
+        let cursor = checkpoint.last_imported.zip(checkpoint.chksum.clone());
+
+        Ok(ArtifactWalk {
+            db,
+            filter,
+            checkpoint,
+            resumable: name.is_some(),
+            cursor,
+        })
+    }
+
+    async fn next_batch(&mut self) -> Result<Vec<db::Artifact>> {
+        self.db
+            .get_artifacts_batch(&self.filter, self.cursor.as_ref(), BATCH_SIZE)
+            .await
+    }
+
+    /// Mark an artifact as done
+    fn advance(&mut self, artifact: &db::Artifact) {
+        self.cursor = Some((artifact.last_imported, artifact.chksum.clone()));
+        self.checkpoint.processed += 1;
+    }
+
+    async fn save(&mut self) -> Result<()> {
+        let (last_imported, chksum) = self.cursor.clone().unzip();
+        self.checkpoint.last_imported = last_imported;
+        self.checkpoint.chksum = chksum;
+        info!(
+            "Reindex progress: processed={} chksum={:?}",
+            self.checkpoint.processed, self.checkpoint.chksum
+        );
+        if self.resumable {
+            self.db.insert_reindex_checkpoint(&self.checkpoint).await?;
         }
+        Ok(())
+    }
 
-        if let Some(age) = &args.age {
-            let delta = now.signed_duration_since(artifact.last_imported);
-            if delta.num_days() < *age {
-                // since we sort by age, no further artifact will match
-                break;
-            }
+    /// All artifacts have been visited, a new run starts from the beginning
+    async fn finish(self) -> Result<()> {
+        if self.resumable {
+            self.db
+                .delete_reindex_checkpoint(&self.checkpoint.name)
+                .await?;
         }
+        Ok(())
+    }
+}
 
-        let refs = db.get_all_refs_for(&artifact.chksum).await?;
+pub async fn run_url(args: &args::ReindexUrl) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let mut scheduled = 0;
+    let mut walk = ArtifactWalk::open(&db, "url", &args.filter_artifacts, args.age).await?;
+    loop {
+        let batch = walk.next_batch().await?;
+        if batch.is_empty() {
+            break;
+        }
 
-        let mut refs = refs
-            .into_iter()
-            .flat_map(|r| r.filename)
-            .filter(|filename| {
-                if let Some(filter) = &args.filter {
-                    filename.contains(filter)
-                } else {
-                    true
+        for artifact in batch {
+            if let Some(limit) = &args.limit {
+                if scheduled >= *limit {
+                    info!("Reached schedule limit of {limit} items, exiting");
+                    return walk.save().await;
                 }
-            })
-            .collect::<Vec<_>>();
-        fastrand::shuffle(&mut refs);
+            }
 
-        let Some(filename) = refs.into_iter().next() else {
-            continue;
-        };
+            let refs = db.get_all_refs_for(&artifact.chksum).await?;
 
-        if let Some(task) = utils::task_for_url(&filename) {
-            info!("Inserting task: {task:?}");
-            db.insert_task(&task).await?;
-            scheduled += 1;
+            let mut refs = refs
+                .into_iter()
+                .flat_map(|r| r.filename)
+                .filter(|filename| {
+                    if let Some(filter) = &args.filter {
+                        filename.contains(filter)
+                    } else {
+                        true
+                    }
+                })
+                .collect::<Vec<_>>();
+            fastrand::shuffle(&mut refs);
+
+            if let Some(task) = refs
+                .into_iter()
+                .next()
+                .and_then(|filename| utils::task_for_url(&filename))
+            {
+                info!("Inserting task: {task:?}");
+                db.insert_task(&task).await?;
+                scheduled += 1;
+            }
+            walk.advance(&artifact);
         }
+        walk.save().await?;
     }
+    walk.finish().await?;
+    info!("Scheduled tasks: {scheduled}");
 
     Ok(())
 }
@@ -64,7 +175,8 @@ pub async fn run_url(args: &args::ReindexUrl) -> Result<()> {
 pub async fn run_sbom(args: &args::ReindexSbom) -> Result<()> {
     let db = db::Client::create().await?;
 
-    let mut scheduled = 0;
+    let mut indexed = 0;
+    let mut failed = 0;
     let stream = db.get_all_sboms();
     tokio::pin!(stream);
     while let Some(sbom) = stream.next().await {
@@ -77,8 +189,8 @@ pub async fn run_sbom(args: &args::ReindexSbom) -> Result<()> {
         }
 
         if let Some(limit) = &args.limit {
-            if scheduled >= *limit {
-                info!("Reached schedule limit of {limit} items, exiting");
+            if indexed + failed >= *limit {
+                info!("Reached limit of {limit} items, exiting");
                 break;
             }
         }
@@ -88,6 +200,9 @@ pub async fn run_sbom(args: &args::ReindexSbom) -> Result<()> {
             Ok(sbom) => sbom,
             Err(err) => {
                 error!("Failed to parse sbom: {err:#}");
+                // packages indexed by an older, more lenient parser would otherwise stay around
+                db.delete_sbom_packages(chksum, &sbom.strain).await?;
+                failed += 1;
                 continue;
             }
         };
@@ -96,10 +211,141 @@ pub async fn run_sbom(args: &args::ReindexSbom) -> Result<()> {
         info!("Indexing sbom ({strain}: {chksum:?}");
         if let Err(err) = sbom::index(&db, &sbom).await {
             error!("Failed to index sbom: {err:#}");
+            failed += 1;
             continue;
         }
-        scheduled += 1;
+        indexed += 1;
+    }
+    info!("Indexed sboms: {indexed} (failed: {failed})");
+
+    Ok(())
+}
+
+pub async fn run_files(args: &args::ReindexFiles) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let mut indexed = 0;
+    let mut walk = ArtifactWalk::open(&db, "files", &args.filter_artifacts, None).await?;
+    loop {
+        let batch = walk.next_batch().await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        for artifact in batch {
+            if let Some(limit) = &args.limit {
+                if indexed >= *limit {
+                    info!("Reached limit of {limit} items, exiting");
+                    return walk.save().await;
+                }
+            }
+
+            if let Some(files) = artifact.get_files()? {
+                debug!("Indexing files of artifact: {:?}", artifact.chksum);
+                db.insert_artifact_files(&artifact.chksum, &files).await?;
+                indexed += 1;
+            }
+            walk.advance(&artifact);
+        }
+        walk.save().await?;
     }
+    walk.finish().await?;
+    info!("Indexed artifacts: {indexed}");
 
     Ok(())
 }
+
+/// Read package names from a list, ignoring empty lines and comments
+pub fn parse_package_list(txt: &str) -> impl Iterator<Item = &str> {
+    txt.lines()
+        .map(|line| line.split_once('#').map(|x| x.0).unwrap_or(line))
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+}
+
+pub async fn run_packages(args: &args::ReindexPackages) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let mut packages = args.packages.clone();
+    if let Some(path) = &args.file {
+        let txt = fs::read_to_string(path).await?;
+        packages.extend(parse_package_list(&txt).map(String::from));
+    }
+
+    let mut scheduled = HashSet::new();
+    for package in &packages {
+        let refs = db
+            .get_refs_for_package(package, None, None, args.limit)
+            .await?;
+        info!(
+            "Found refs for package: package={package:?} refs={}",
+            refs.len()
+        );
+
+        for r in refs {
+            let mut tasks = Vec::new();
+
+            if let Some(task) = r
+                .filename
+                .as_deref()
+                .and_then(|url| utils::task_for_ref(url, &r.chksum))
+            {
+                tasks.push(task);
+            }
+
+            if let Some(artifact) = db.resolve_artifact(&r.chksum).await? {
+                for sbom in db.get_sbom_refs_for_archive(&artifact.chksum).await? {
+                    tasks.push(db::Task::new(
+                        format!("sbom:{}:{}", sbom.sbom_strain, sbom.sbom_chksum),
+                        &db::TaskData::IndexSbom {
+                            strain: Some(sbom.sbom_strain),
+                            chksum: sbom.sbom_chksum,
+                        },
+                    )?);
+                }
+            }
+
+            for task in tasks {
+                if scheduled.insert(task.key.clone()) {
+                    info!("Inserting task: {task:?}");
+                    db.insert_task(&task).await?;
+                }
+            }
+        }
+    }
+    info!("Scheduled tasks: {}", scheduled.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_list() {
+        let txt = "# CVE-2024-3094
+xz
+xz-utils # debian
+
+  liblzma
+";
+        let list = parse_package_list(txt).collect::<Vec<_>>();
+        assert_eq!(list, ["xz", "xz-utils", "liblzma"]);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(
+            parse_since("2024-03-29").unwrap().to_rfc3339(),
+            "2024-03-29T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("2024-03-29T12:00:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-03-29T10:00:00+00:00"
+        );
+        assert!(parse_since("last week").is_err());
+    }
+}
@@ -0,0 +1,465 @@
+use crate::analyze::{self, autotools, Drift};
+use crate::args;
+use crate::chksums;
+use crate::db;
+use crate::errors::*;
+use crate::sbom;
+
+pub const KIND_TARBALL_DRIFT: &str = "tarball-drift";
+pub const KIND_UNOFFICIAL_DEPENDENCY: &str = "unofficial-dependency";
+pub const KIND_AUTOTOOLS_MISMATCH: &str = "autotools-mismatch";
+pub const KIND_CHECKSUM_CHANGE: &str = "checksum-change";
+pub const KIND_BLOB_CORRUPTED: &str = "blob-corrupted";
+
+/// A changed checksum without a version bump is rare and always worth a look
+const CHECKSUM_CHANGE_SCORE: i32 = 100;
+/// Archived content that doesn't match its digest can't be trusted for anything
+pub const BLOB_CORRUPTED_SCORE: i32 = 100;
+
+/// Only these strains tell us if a package comes from the official registry
+pub const REGISTRY_STRAINS: &[&str] = &[
+    sbom::cargo::STRAIN,
+    sbom::gemfile::STRAIN,
+    sbom::pipfile::STRAIN,
+    sbom::poetry::STRAIN,
+];
+
+/// Where the lockfile checks continue on the next run
+const SBOMS_CURSOR: &str = "reports-sboms";
+
+/// How many paths are listed in a summary before it's cut short
+const SUMMARY_PATHS: usize = 5;
+
+fn join_truncated(list: &[String]) -> String {
+    let mut out = list
+        .iter()
+        .take(SUMMARY_PATHS)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if list.len() > SUMMARY_PATHS {
+        out.push_str(&format!(" (and {} more)", list.len() - SUMMARY_PATHS));
+    }
+    out
+}
+
+/// Describe the anomalies of a release tarball, returns None if there's nothing to report
+pub fn summarize_drift(drift: &Drift) -> Option<(String, i32)> {
+    if drift.anomalies.is_empty() {
+        return None;
+    }
+    let summary = format!(
+        "Release tarball contains files that are not in the VCS snapshot: {}",
+        join_truncated(&drift.anomalies)
+    );
+    Some((summary, drift.score as i32))
+}
+
+/// Describe the dependencies of a lockfile that are not fetched from the official registry
+///
+/// Packages without checksum and download location are local to the project and not reported.
+pub fn summarize_unofficial(packages: &[sbom::Package]) -> Option<(String, i32)> {
+    let unofficial = packages
+        .iter()
+        .filter(|pkg| !pkg.official_registry)
+        .filter(|pkg| pkg.checksum.is_some() || pkg.download_location.is_some())
+        .map(|pkg| format!("{} {}", pkg.name, pkg.version))
+        .collect::<Vec<_>>();
+    if unofficial.is_empty() {
+        return None;
+    }
+    let summary = format!(
+        "Lockfile has dependencies from outside the official registry: {}",
+        join_truncated(&unofficial)
+    );
+    Some((summary, unofficial.len() as i32))
+}
+
+/// Describe why a configure script doesn't match its inputs, returns None if it does
+pub fn summarize_autotools(analysis: &autotools::Analysis) -> Option<(String, i32)> {
+    if analysis.findings.is_empty() {
+        return None;
+    }
+    let messages = analysis
+        .findings
+        .iter()
+        .map(|f| f.message.clone())
+        .collect::<Vec<_>>();
+    let summary = format!(
+        "Generated configure script doesn't match its inputs: {}",
+        join_truncated(&messages)
+    );
+    Some((summary, analysis.score as i32))
+}
+
+/// Describe archived content that doesn't match its recorded digest or size, returns None if it does
+pub fn summarize_blob(blob: &db::Blob, data: &[u8]) -> Option<(String, i32)> {
+    let mut problems = Vec::new();
+    if data.len() as i64 != blob.size {
+        problems.push(format!(
+            "size is {} bytes, expected {} bytes",
+            data.len(),
+            blob.size
+        ));
+    }
+    let chksum = chksums::sha256(data);
+    if chksum != blob.chksum {
+        problems.push(format!("content hashes to {chksum}"));
+    }
+    if problems.is_empty() {
+        return None;
+    }
+    let summary = format!(
+        "Archived content doesn't match what was recorded: {}",
+        problems.join(", ")
+    );
+    Some((summary, BLOB_CORRUPTED_SCORE))
+}
+
+/// Record a problem with archived content, reported on the digest of the file
+pub fn blob_corrupted(blob: &db::Blob, summary: String, score: i32) -> db::NewReport {
+    db::NewReport {
+        chksum: blob.chksum.clone(),
+        kind: KIND_BLOB_CORRUPTED,
+        vendor: None,
+        package: None,
+        version: None,
+        summary,
+        score,
+    }
+}
+
+/// Describe a file that changed its checksum without a new version, reported on the new checksum
+pub fn checksum_change(r: &db::Ref, previous: &str, chksum: &str, filename: &str) -> db::NewReport {
+    db::NewReport {
+        chksum: chksum.to_string(),
+        kind: KIND_CHECKSUM_CHANGE,
+        vendor: Some(r.vendor.clone()),
+        package: Some(r.package.clone()),
+        version: Some(r.version.clone()),
+        summary: format!(
+            "Checksum of {filename} changed from {previous} to {chksum} without a version bump"
+        ),
+        score: CHECKSUM_CHANGE_SCORE,
+    }
+}
+
+/// Record the autotools analysis of a freshly ingested artifact
+pub async fn check_autotools(
+    db: &db::Client,
+    chksum: &str,
+    analysis: &autotools::Analysis,
+) -> Result<()> {
+    if let Some((summary, score)) = summarize_autotools(analysis) {
+        let r = db.get_all_refs_for(chksum).await?.into_iter().next();
+        info!("Found autotools mismatch in {chksum:?}: score={score}");
+        db.insert_report(&db::NewReport {
+            chksum: chksum.to_string(),
+            kind: KIND_AUTOTOOLS_MISMATCH,
+            vendor: r.as_ref().map(|r| r.vendor.clone()),
+            package: r.as_ref().map(|r| r.package.clone()),
+            version: r.as_ref().map(|r| r.version.clone()),
+            summary,
+            score,
+        })
+        .await?;
+    } else {
+        db.delete_report(chksum, KIND_AUTOTOOLS_MISMATCH).await?;
+    }
+    Ok(())
+}
+
+async fn check_drift(db: &db::Client, r: &db::Ref, vcs_chksum: &str) -> Result<()> {
+    let Some(artifact) = db.resolve_artifact(&r.chksum).await? else {
+        return Ok(());
+    };
+    let Some(vcs) = db.get_artifact(vcs_chksum).await? else {
+        return Ok(());
+    };
+    let (Some(files), Some(vcs_files)) = (artifact.get_files()?, vcs.get_files()?) else {
+        return Ok(());
+    };
+
+    let drift = analyze::detect_drift(&files, &vcs_files);
+    db.insert_vcs_drift(&db::NewVcsDrift {
+        chksum: artifact.chksum.clone(),
+        vcs_chksum: vcs.chksum.clone(),
+        categories: serde_json::to_value(&drift.categories)?,
+        anomalies: serde_json::to_value(&drift.anomalies)?,
+        score: drift.score as i32,
+    })
+    .await?;
+
+    if let Some((summary, score)) = summarize_drift(&drift) {
+        info!(
+            "Found drift in {:?} {:?} {:?}: score={score}",
+            r.vendor, r.package, r.version
+        );
+        db.insert_report(&db::NewReport {
+            chksum: artifact.chksum,
+            kind: KIND_TARBALL_DRIFT,
+            vendor: Some(r.vendor.clone()),
+            package: Some(r.package.clone()),
+            version: Some(r.version.clone()),
+            summary,
+            score,
+        })
+        .await?;
+    } else {
+        db.delete_report(&artifact.chksum, KIND_TARBALL_DRIFT)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Check all registry lockfiles of an archive, the report covers the archive as a whole
+async fn check_archive_sboms(db: &db::Client, archive: &str, sboms: &[db::Sbom]) -> Result<()> {
+    let mut packages = Vec::new();
+    for sbom in sboms {
+        match sbom::Sbom::try_from(sbom).and_then(|sbom| sbom.to_packages()) {
+            Ok(list) => packages.extend(list),
+            Err(err) => warn!("Failed to parse package lock: {err:#}"),
+        }
+    }
+
+    if let Some((summary, score)) = summarize_unofficial(&packages) {
+        let r = db.get_all_refs_for(archive).await?.into_iter().next();
+        info!("Found unofficial dependencies in {archive:?}: score={score}");
+        db.insert_report(&db::NewReport {
+            chksum: archive.to_string(),
+            kind: KIND_UNOFFICIAL_DEPENDENCY,
+            vendor: r.as_ref().map(|r| r.vendor.clone()),
+            package: r.as_ref().map(|r| r.package.clone()),
+            version: r.as_ref().map(|r| r.version.clone()),
+            summary,
+            score,
+        })
+        .await?;
+    } else {
+        db.delete_report(archive, KIND_UNOFFICIAL_DEPENDENCY)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Group lockfiles by the archive they were found in, expects them ordered by archive
+fn group_by_archive(rows: Vec<(db::Sbom, String)>) -> Vec<(String, Vec<db::Sbom>)> {
+    let mut groups = Vec::<(String, Vec<db::Sbom>)>::new();
+    for (sbom, archive) in rows {
+        match groups.last_mut() {
+            Some((last, sboms)) if *last == archive => sboms.push(sbom),
+            _ => groups.push((archive, vec![sbom])),
+        }
+    }
+    groups
+}
+
+/// Check the lockfiles of the next `limit` archives, continues where the previous run stopped
+pub async fn check_sboms(db: &db::Client, limit: i64) -> Result<usize> {
+    let cursor = db.get_sync_cursor(SBOMS_CURSOR).await?.unwrap_or_default();
+    let rows = db
+        .get_sboms_in_archives(REGISTRY_STRAINS, &cursor, limit)
+        .await?;
+    let archives = group_by_archive(rows);
+    for (archive, sboms) in &archives {
+        check_archive_sboms(db, archive, sboms).await?;
+    }
+
+    // start over once we've reached the end
+    let next = match archives.last() {
+        Some((archive, _)) if archives.len() as i64 >= limit => archive.as_str(),
+        _ => "",
+    };
+    db.set_sync_cursor(SBOMS_CURSOR, next).await?;
+    Ok(archives.len())
+}
+
+/// Compare release tarballs with the VCS snapshot of the same version, returns the number of refs checked
+pub async fn compare_vcs(
+    db: &db::Client,
+    vendor: Option<&str>,
+    only_new: bool,
+    limit: i64,
+) -> Result<usize> {
+    let candidates = db.get_drift_candidates(vendor, only_new, limit).await?;
+    for (r, vcs_chksum) in &candidates {
+        check_drift(db, r, vcs_chksum).await?;
+    }
+    Ok(candidates.len())
+}
+
+pub async fn run(args: &args::GenerateReports) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    compare_vcs(&db, args.vendor.as_deref(), false, args.limit).await?;
+
+    if args.vendor.is_none() {
+        check_sboms(&db, args.limit).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(
+        name: &str,
+        download_location: Option<&str>,
+        official_registry: bool,
+    ) -> sbom::Package {
+        sbom::Package {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            checksum: None,
+            download_location: download_location.map(String::from),
+            official_registry,
+        }
+    }
+
+    #[test]
+    fn test_summarize_drift() {
+        assert_eq!(summarize_drift(&Drift::default()), None);
+
+        let drift = Drift {
+            categories: Default::default(),
+            anomalies: (0..7).map(|i| format!("tests/files/bad-{i}.xz")).collect(),
+            score: 35,
+        };
+        assert_eq!(
+            summarize_drift(&drift),
+            Some((
+                "Release tarball contains files that are not in the VCS snapshot: tests/files/bad-0.xz, tests/files/bad-1.xz, tests/files/bad-2.xz, tests/files/bad-3.xz, tests/files/bad-4.xz (and 2 more)".to_string(),
+                35
+            ))
+        );
+    }
+
+    #[test]
+    fn test_summarize_autotools() {
+        assert_eq!(summarize_autotools(&Default::default()), None);
+
+        let analysis = autotools::Analysis {
+            findings: vec![autotools::Finding {
+                file: "m4/build-to-host.m4".to_string(),
+                message:
+                    "m4/build-to-host.m4 has serial 30, but aclocal.m4 was generated from serial 3"
+                        .to_string(),
+                score: 10,
+            }],
+            score: 10,
+        };
+        assert_eq!(
+            summarize_autotools(&analysis),
+            Some((
+                "Generated configure script doesn't match its inputs: m4/build-to-host.m4 has serial 30, but aclocal.m4 was generated from serial 3".to_string(),
+                10
+            ))
+        );
+    }
+
+    #[test]
+    fn test_summarize_unofficial() {
+        assert_eq!(
+            summarize_unofficial(&[package("serde", None, true), package("my-app", None, false)]),
+            None
+        );
+        assert_eq!(
+            summarize_unofficial(&[
+                package("serde", None, true),
+                package(
+                    "patched-ring",
+                    Some("git+https://github.com/example/ring?branch=main#0123456789abcdef"),
+                    false
+                )
+            ]),
+            Some((
+                "Lockfile has dependencies from outside the official registry: patched-ring 1.0.0"
+                    .to_string(),
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_group_by_archive() {
+        let sbom = |chksum: &str| db::Sbom {
+            chksum: chksum.to_string(),
+            strain: sbom::cargo::STRAIN.to_string(),
+            data: String::new(),
+        };
+        let groups = group_by_archive(vec![
+            (sbom("sha256:1111"), "sha256:aaaa".to_string()),
+            (sbom("sha256:2222"), "sha256:aaaa".to_string()),
+            (sbom("sha256:3333"), "sha256:bbbb".to_string()),
+        ]);
+        let groups = groups
+            .iter()
+            .map(|(archive, sboms)| {
+                (
+                    archive.as_str(),
+                    sboms.iter().map(|s| s.chksum.as_str()).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            [
+                ("sha256:aaaa", vec!["sha256:1111", "sha256:2222"]),
+                ("sha256:bbbb", vec!["sha256:3333"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize_blob() {
+        let blob = db::Blob {
+            chksum: "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                .to_string(),
+            size: 5,
+            compressed_size: 14,
+            first_seen: Default::default(),
+        };
+        assert_eq!(summarize_blob(&blob, b"hello"), None);
+        assert_eq!(
+            summarize_blob(&blob, b"hellO"),
+            Some((
+                "Archived content doesn't match what was recorded: content hashes to sha256:04a6f55face2f46be8c23f627d539827615851e10751b63ec59db6d2c706b770".to_string(),
+                100
+            ))
+        );
+        assert_eq!(
+            summarize_blob(&blob, b""),
+            Some((
+                "Archived content doesn't match what was recorded: size is 0 bytes, expected 5 bytes, content hashes to sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string(),
+                100
+            ))
+        );
+    }
+
+    #[test]
+    fn test_checksum_change() {
+        let r = db::Ref {
+            chksum: "sha256:bbbb".to_string(),
+            vendor: "debian".to_string(),
+            package: "xz-utils".to_string(),
+            version: "5.6.1+really5.4.5-1".to_string(),
+            filename: Some("xz-utils_5.6.1+really5.4.5.orig.tar.xz".to_string()),
+        };
+        let report = checksum_change(
+            &r,
+            "sha256:aaaa",
+            "sha256:bbbb",
+            "xz-utils_5.6.1+really5.4.5.orig.tar.xz",
+        );
+        assert_eq!(report.chksum, "sha256:bbbb");
+        assert_eq!(report.kind, KIND_CHECKSUM_CHANGE);
+        assert_eq!(
+            report.summary,
+            "Checksum of xz-utils_5.6.1+really5.4.5.orig.tar.xz changed from sha256:aaaa to sha256:bbbb without a version bump"
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use crate::errors::*;
+use std::collections::HashMap;
+
+/// How often a macro may expand into another macro
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    /// The upstream url, if the source is not a file shipped next to the spec
+    pub url: Option<String>,
+    /// The name of the file, either the last path segment or the `#/` override
+    pub filename: String,
+}
+
+impl Source {
+    fn parse(value: &str) -> Self {
+        // `https://example.com/v1.0.tar.gz#/foo-1.0.tar.gz` renames the download
+        let (location, rename) = match value.split_once("#/") {
+            Some((location, rename)) => (location, Some(rename)),
+            None => (value, None),
+        };
+        let url = (location.starts_with("https://") || location.starts_with("http://"))
+            .then(|| location.to_string());
+        let filename = rename
+            .unwrap_or_else(|| location.rsplit('/').next().unwrap_or(location))
+            .to_string();
+        Source { url, filename }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Spec {
+    pub name: String,
+    pub version: String,
+    pub release: Option<String>,
+    /// The homepage of the upstream project
+    pub url: Option<String>,
+    pub sources: Vec<Source>,
+}
+
+#[derive(Debug, Default)]
+struct Macros {
+    values: HashMap<String, String>,
+}
+
+impl Macros {
+    fn expand(&self, txt: &str) -> Option<String> {
+        let mut txt = txt.to_string();
+        for _ in 0..MAX_EXPANSION_DEPTH {
+            let expanded = self.expand_once(&txt)?;
+            if expanded == txt {
+                return Some(txt);
+            }
+            txt = expanded;
+        }
+        None
+    }
+
+    fn expand_once(&self, mut txt: &str) -> Option<String> {
+        let mut out = String::new();
+        while let Some((before, after)) = txt.split_once('%') {
+            out.push_str(before);
+            if let Some(after) = after.strip_prefix('%') {
+                out.push('%');
+                txt = after;
+            } else if let Some(after) = after.strip_prefix('{') {
+                let (name, after) = after.split_once('}')?;
+                // `%{?dist}` is empty if the macro isn't defined
+                if let Some(name) = name.strip_prefix('?') {
+                    out.push_str(self.values.get(name).map(String::as_str).unwrap_or(""));
+                } else {
+                    out.push_str(self.values.get(name)?);
+                }
+                txt = after;
+            } else {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                if end > 0 {
+                    out.push_str(self.values.get(&after[..end])?);
+                } else if after.starts_with(['(', '[']) {
+                    // shell and expression expansion
+                    return None;
+                } else {
+                    out.push('%');
+                }
+                txt = &after[end..];
+            }
+        }
+        out.push_str(txt);
+        Some(out)
+    }
+}
+
+/// Read the metadata of a spec file, sources with macros we can't resolve are skipped
+pub fn parse(txt: &str) -> Result<Spec> {
+    let mut macros = Macros::default();
+    let mut name = None;
+    let mut version = None;
+    let mut release = None;
+    let mut url = None;
+    let mut sources = Vec::new();
+
+    for line in txt.lines() {
+        let line = line.trim();
+        // the preamble ends with the first build section
+        if ["%prep", "%build", "%install", "%changelog"].contains(&line) {
+            break;
+        }
+
+        if let Some(define) = line
+            .strip_prefix("%define ")
+            .or_else(|| line.strip_prefix("%global "))
+        {
+            let Some((key, value)) = define.trim().split_once(char::is_whitespace) else {
+                continue;
+            };
+            if let Some(value) = macros.expand(value.trim()) {
+                macros.values.insert(key.to_string(), value);
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let Some(value) = macros.expand(value.trim()) else {
+            debug!("Failed to expand macros in spec: {line:?}");
+            continue;
+        };
+        match key.as_str() {
+            "name" => {
+                macros.values.insert("name".to_string(), value.clone());
+                name = Some(value);
+            }
+            "version" => {
+                macros.values.insert("version".to_string(), value.clone());
+                version = Some(value);
+            }
+            "release" => {
+                macros.values.insert("release".to_string(), value.clone());
+                release = Some(value);
+            }
+            "url" => {
+                macros.values.insert("url".to_string(), value.clone());
+                url = Some(value);
+            }
+            key if key.starts_with("source")
+                && key["source".len()..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                sources.push(Source::parse(&value));
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Spec {
+        name: name.ok_or(Error::RpmSpecMissingField("Name"))?,
+        version: version.ok_or(Error::RpmSpecMissingField("Version"))?,
+        release,
+        url,
+        sources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        let txt = "#
+# spec file for package xz
+#
+%define _unpackaged_files_terminate_build 0
+%global sover 5
+Name:           xz
+Version:        5.6.3
+Release:        0
+Summary:        A Program for Compressing Files with the Lempel–Ziv–Markov algorithm
+License:        0BSD AND GPL-2.0-or-later
+URL:            https://tukaani.org/xz/
+Source0:        https://github.com/tukaani-project/xz/releases/download/v%{version}/%{name}-%{version}.tar.gz
+Source1:        https://github.com/tukaani-project/xz/releases/download/v%{version}/%{name}-%{version}.tar.gz.sig
+Source2:        https://tukaani.org/misc/lasse_collin_pubkey.txt#/%{name}.keyring
+Source3:        xznew
+Source4:        %{undefined_macro}.tar.gz
+Source5:        baselibs.conf
+Patch0:         xz-5.2.4-no-tests.patch
+BuildRequires:  pkgconfig
+%if 0%{?suse_version} > 1500
+Provides:       lzma-alone = 4.43
+%endif
+
+%package -n liblzma%{sover}
+Summary:        Lempel–Ziv–Markov chain algorithm compression library
+
+%prep
+%autosetup -p1
+Source9: not-a-source
+";
+        let spec = parse(txt).unwrap();
+        assert_eq!(spec.name, "xz");
+        assert_eq!(spec.version, "5.6.3");
+        assert_eq!(spec.release.as_deref(), Some("0"));
+        assert_eq!(spec.url.as_deref(), Some("https://tukaani.org/xz/"));
+        assert_eq!(
+            spec.sources,
+            [
+                Source {
+                    url: Some("https://github.com/tukaani-project/xz/releases/download/v5.6.3/xz-5.6.3.tar.gz".to_string()),
+                    filename: "xz-5.6.3.tar.gz".to_string(),
+                },
+                Source {
+                    url: Some("https://github.com/tukaani-project/xz/releases/download/v5.6.3/xz-5.6.3.tar.gz.sig".to_string()),
+                    filename: "xz-5.6.3.tar.gz.sig".to_string(),
+                },
+                Source {
+                    url: Some("https://tukaani.org/misc/lasse_collin_pubkey.txt".to_string()),
+                    filename: "xz.keyring".to_string(),
+                },
+                Source {
+                    url: None,
+                    filename: "xznew".to_string(),
+                },
+                Source {
+                    url: None,
+                    filename: "baselibs.conf".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_macros() {
+        let mut macros = Macros::default();
+        macros
+            .values
+            .insert("name".to_string(), "python-foo".to_string());
+        macros
+            .values
+            .insert("modname".to_string(), "foo".to_string());
+        assert_eq!(
+            macros.expand("%{name}-%modname%{?dist}.tar.gz").as_deref(),
+            Some("python-foo-foo.tar.gz")
+        );
+        assert_eq!(macros.expand("100%%").as_deref(), Some("100%"));
+        assert_eq!(macros.expand("%{pypi_source}"), None);
+    }
+
+    #[test]
+    fn test_missing_version() {
+        assert!(parse("Name: foo\n").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use crate::errors::*;
+use crate::sbom::Package;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+pub const STRAIN: &str = "cyclonedx-json";
+
+/// Hash algorithms we can represent, in order of preference
+const ALGORITHMS: &[(&str, &str)] = &[
+    ("SHA-256", "sha256"),
+    ("SHA-512", "sha512"),
+    ("BLAKE2b-512", "blake2b"),
+    ("SHA-1", "sha1"),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct CycloneDxJson {
+    pub data: String,
+}
+
+impl CycloneDxJson {
+    pub fn parse(&self) -> Result<ParsedBom> {
+        let bom = serde_json::from_str::<Bom>(&self.data)?;
+        let mut packages = VecDeque::new();
+        flatten(bom.components, &mut packages);
+        Ok(ParsedBom { packages })
+    }
+}
+
+/// Components can be nested, e.g. the files of a container image layer
+fn flatten(components: Vec<Component>, packages: &mut VecDeque<Package>) {
+    for mut component in components {
+        let children = std::mem::take(&mut component.components);
+        if let Some(package) = component.into_package() {
+            packages.push_back(package);
+        }
+        flatten(children, packages);
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Bom {
+    #[serde(default)]
+    components: Vec<Component>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Component {
+    name: String,
+    version: Option<String>,
+    purl: Option<String>,
+    #[serde(default)]
+    hashes: Vec<Hash>,
+    #[serde(default)]
+    external_references: Vec<ExternalReference>,
+    #[serde(default)]
+    components: Vec<Component>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Hash {
+    alg: String,
+    content: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct ExternalReference {
+    #[serde(rename = "type")]
+    ref_type: String,
+    url: String,
+}
+
+impl Component {
+    fn into_package(self) -> Option<Package> {
+        let version = self.version?;
+
+        let checksum = ALGORITHMS.iter().find_map(|(alg, family)| {
+            self.hashes
+                .iter()
+                .find(|hash| hash.alg == *alg)
+                .map(|hash| format!("{family}:{}", hash.content.to_lowercase()))
+        });
+
+        let download_location = self
+            .external_references
+            .into_iter()
+            .find(|r| r.ref_type == "distribution")
+            .map(|r| r.url)
+            .or(self.purl);
+
+        Some(Package {
+            name: self.name,
+            version,
+            checksum,
+            download_location,
+            official_registry: false,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedBom {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedBom {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::Sbom;
+
+    #[test]
+    fn test_parse_cyclonedx_json() {
+        let data = r#"{
+  "bomFormat": "CycloneDX",
+  "specVersion": "1.5",
+  "version": 1,
+  "metadata": {
+    "component": {
+      "type": "container",
+      "name": "alpine:3.20"
+    }
+  },
+  "components": [
+    {
+      "type": "library",
+      "name": "musl",
+      "version": "1.2.5-r0",
+      "purl": "pkg:apk/alpine/musl@1.2.5-r0?arch=x86_64&distro=3.20.3",
+      "components": [
+        {
+          "type": "file",
+          "name": "lib/ld-musl-x86_64.so.1",
+          "hashes": [
+            {
+              "alg": "SHA-1",
+              "content": "e6c3a5d2ae5ffd8d8d5a0b5a84d71cd9d3e1b4a8"
+            }
+          ]
+        }
+      ]
+    },
+    {
+      "type": "library",
+      "name": "zlib",
+      "version": "1.3.1",
+      "hashes": [
+        {
+          "alg": "SHA-1",
+          "content": "f535367b1a11e2f9ac3bec723fb007fbc0d189e5"
+        },
+        {
+          "alg": "SHA-256",
+          "content": "9A93B2B7DFDAC77CEBA5A558A580E74667DD6FEDE4585B91EEFB60F03B72DF23"
+        }
+      ],
+      "externalReferences": [
+        {
+          "type": "website",
+          "url": "https://zlib.net/"
+        },
+        {
+          "type": "distribution",
+          "url": "https://zlib.net/zlib-1.3.1.tar.gz"
+        }
+      ]
+    }
+  ]
+}"#;
+        let sbom = Sbom::new(STRAIN, data.to_string()).unwrap();
+        let list = sbom.to_packages().unwrap();
+        assert_eq!(
+            list,
+            [
+                Package {
+                    name: "musl".to_string(),
+                    version: "1.2.5-r0".to_string(),
+                    checksum: None,
+                    download_location: Some(
+                        "pkg:apk/alpine/musl@1.2.5-r0?arch=x86_64&distro=3.20.3".to_string()
+                    ),
+                    official_registry: false,
+                },
+                Package {
+                    name: "zlib".to_string(),
+                    version: "1.3.1".to_string(),
+                    checksum: Some(
+                        "sha256:9a93b2b7dfdac77ceba5a558a580e74667dd6fede4585b91eefb60f03b72df23"
+                            .to_string()
+                    ),
+                    download_location: Some("https://zlib.net/zlib-1.3.1.tar.gz".to_string()),
+                    official_registry: false,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use crate::errors::*;
+use crate::sbom::Package;
+use std::collections::{HashMap, VecDeque};
+
+pub const STRAIN: &str = "gemfile-lock";
+
+#[derive(Debug, PartialEq)]
+pub struct GemfileLock {
+    pub data: String,
+}
+
+impl GemfileLock {
+    pub fn parse(&self) -> Result<ParsedLock> {
+        let mut packages = VecDeque::new();
+        // bundler >= 2.5 optionally records checksums in a separate section
+        let mut checksums = HashMap::new();
+
+        let mut section = "";
+        let mut remote = None;
+        for line in self.data.lines() {
+            if !line.starts_with(' ') {
+                section = line.trim();
+                remote = None;
+                continue;
+            }
+
+            if section == "CHECKSUMS" {
+                let Some((spec, chksum)) = line.trim().rsplit_once(' ') else {
+                    continue;
+                };
+                if let Some(digest) = chksum.strip_prefix("sha256=") {
+                    checksums.insert(spec.to_string(), format!("sha256:{digest}"));
+                }
+                continue;
+            }
+
+            if let Some(url) = line.strip_prefix("  remote: ") {
+                remote = Some(url.trim());
+                continue;
+            }
+
+            // gems are indented by 4 spaces, their dependencies by 6
+            let Some(spec) = line.strip_prefix("    ") else {
+                continue;
+            };
+            if spec.starts_with(' ') {
+                continue;
+            }
+            let Some((name, version)) = spec.split_once(" (") else {
+                continue;
+            };
+            let Some(version) = version.strip_suffix(')') else {
+                continue;
+            };
+
+            let official_registry = section == "GEM"
+                && remote.is_some_and(|url| url.trim_end_matches('/') == "https://rubygems.org");
+            let download_location = if official_registry {
+                Some(format!(
+                    "https://rubygems.org/downloads/{name}-{version}.gem"
+                ))
+            } else if section == "GIT" {
+                remote.map(String::from)
+            } else {
+                None
+            };
+
+            packages.push_back(Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                checksum: None,
+                download_location,
+                official_registry,
+            });
+        }
+
+        for pkg in &mut packages {
+            pkg.checksum = checksums.remove(&format!("{} ({})", pkg.name, pkg.version));
+        }
+
+        Ok(ParsedLock { packages })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedLock {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedLock {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::Sbom;
+
+    #[test]
+    fn test_parse_gemfile_lock() {
+        let data = r#"GIT
+  remote: https://github.com/example/vendored.git
+  revision: 6c2c0ba0bd3b2e49da6ac1f1d0f4ab3b7bb4d1e2
+  specs:
+    vendored (0.1.0)
+
+GEM
+  remote: https://rubygems.org/
+  specs:
+    mini_portile2 (2.8.7)
+    nokogiri (1.16.7)
+      mini_portile2 (~> 2.8.2)
+      racc (~> 1.4)
+    racc (1.8.1)
+
+PLATFORMS
+  ruby
+
+DEPENDENCIES
+  nokogiri
+  vendored!
+
+CHECKSUMS
+  mini_portile2 (2.8.7) sha256=13eef5ab459bbfd33d61e539564ec25a9c2cf593b0a5ea6d4d7ef8c19b162ee0
+  nokogiri (1.16.7) sha256=f819cbfdfb0a7b19c9c52c6a2ca63df0e58a6125f1ad2e5f3b5b8ed4bbd1bf15
+
+BUNDLED WITH
+   2.5.16
+"#;
+        let sbom = Sbom::new(STRAIN, data.to_string()).unwrap();
+        let list = sbom.to_packages().unwrap();
+        assert_eq!(
+            list,
+            [
+                Package {
+                    name: "vendored".to_string(),
+                    version: "0.1.0".to_string(),
+                    checksum: None,
+                    download_location: Some("https://github.com/example/vendored.git".to_string()),
+                    official_registry: false,
+                },
+                Package {
+                    name: "mini_portile2".to_string(),
+                    version: "2.8.7".to_string(),
+                    checksum: Some(
+                        "sha256:13eef5ab459bbfd33d61e539564ec25a9c2cf593b0a5ea6d4d7ef8c19b162ee0"
+                            .to_string()
+                    ),
+                    download_location: Some(
+                        "https://rubygems.org/downloads/mini_portile2-2.8.7.gem".to_string()
+                    ),
+                    official_registry: true,
+                },
+                Package {
+                    name: "nokogiri".to_string(),
+                    version: "1.16.7".to_string(),
+                    checksum: Some(
+                        "sha256:f819cbfdfb0a7b19c9c52c6a2ca63df0e58a6125f1ad2e5f3b5b8ed4bbd1bf15"
+                            .to_string()
+                    ),
+                    download_location: Some(
+                        "https://rubygems.org/downloads/nokogiri-1.16.7.gem".to_string()
+                    ),
+                    official_registry: true,
+                },
+                Package {
+                    name: "racc".to_string(),
+                    version: "1.8.1".to_string(),
+                    checksum: None,
+                    download_location: Some(
+                        "https://rubygems.org/downloads/racc-1.8.1.gem".to_string()
+                    ),
+                    official_registry: true,
+                },
+            ]
+        );
+    }
+}
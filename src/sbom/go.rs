@@ -1,6 +1,98 @@
+use crate::errors::*;
+use crate::sbom::Package;
+use crate::sync::goproxy;
+use std::collections::VecDeque;
+
 pub const STRAIN: &str = "go-sum";
 
 #[derive(Debug, PartialEq)]
 pub struct GoSum {
     pub data: String,
 }
+
+impl GoSum {
+    pub fn parse(&self) -> Result<ParsedLock> {
+        let mut packages = VecDeque::new();
+        for line in self.data.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(name), Some(version), Some(hash)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            // the checksum of the go.mod alone, the module zip is listed separately
+            if version.ends_with("/go.mod") {
+                continue;
+            }
+            if !hash.starts_with("h1:") {
+                return Err(Error::InvalidData);
+            }
+
+            packages.push_back(Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                checksum: Some(hash.to_string()),
+                download_location: Some(goproxy::module_zip_url(goproxy::PROXY_URL, name, version)),
+                // verified with the checksum database
+                official_registry: true,
+            });
+        }
+        Ok(ParsedLock { packages })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedLock {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedLock {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_go_sum() {
+        let data =
+            "github.com/BurntSushi/toml v1.4.0 h1:kuoIxZQy2WRRk1pttg9asf+WVv6tWQuBNVmK8+nqPr0=
+github.com/BurntSushi/toml v1.4.0/go.mod h1:ukJfTF/6rtPPRCnwkur4qwRxa8vTRFBF0uk2lLoLwho=
+golang.org/x/sys v0.25.0 h1:r+8e+loiHxRqhXVl6ML1nO3l1+oFoWbnlu2Ehimmi34=
+golang.org/x/sys v0.25.0/go.mod h1:/VUhepiaJMQUp4+oa/7Zr1D23ma6VTLIYjOOTFZPUcA=
+";
+        let sbom = GoSum {
+            data: data.to_string(),
+        };
+        let packages = sbom.parse().unwrap().collect::<Vec<_>>();
+        assert_eq!(
+            packages,
+            [
+                Package {
+                    name: "github.com/BurntSushi/toml".to_string(),
+                    version: "v1.4.0".to_string(),
+                    checksum: Some("h1:kuoIxZQy2WRRk1pttg9asf+WVv6tWQuBNVmK8+nqPr0=".to_string()),
+                    download_location: Some(
+                        "https://proxy.golang.org/github.com/!burnt!sushi/toml/@v/v1.4.0.zip"
+                            .to_string()
+                    ),
+                    official_registry: true,
+                },
+                Package {
+                    name: "golang.org/x/sys".to_string(),
+                    version: "v0.25.0".to_string(),
+                    checksum: Some("h1:r+8e+loiHxRqhXVl6ML1nO3l1+oFoWbnlu2Ehimmi34=".to_string()),
+                    download_location: Some(
+                        "https://proxy.golang.org/golang.org/x/sys/@v/v0.25.0.zip".to_string()
+                    ),
+                    official_registry: true,
+                },
+            ]
+        );
+    }
+}
@@ -1,21 +1,35 @@
 pub mod cargo;
 pub mod composer;
+pub mod cyclonedx;
+pub mod gemfile;
 pub mod go;
 pub mod npm;
+pub mod pipfile;
+pub mod poetry;
+pub mod spdx;
 pub mod yarn;
 
 use crate::args;
+use crate::chksums;
 use crate::db;
 use crate::errors::*;
+use crate::purl::Purl;
+use crate::sync::goproxy;
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use tokio::fs;
 
 #[derive(Debug, PartialEq)]
 pub enum Sbom {
     Cargo(cargo::CargoLock),
     Composer(composer::ComposerLock),
+    CycloneDx(cyclonedx::CycloneDxJson),
+    Gemfile(gemfile::GemfileLock),
     Go(go::GoSum),
     Npm(npm::PackageLockJson),
+    Pipfile(pipfile::PipfileLock),
+    Poetry(poetry::PoetryLock),
+    Spdx(spdx::SpdxJson),
     Yarn(yarn::YarnLock),
 }
 
@@ -32,8 +46,13 @@ impl Sbom {
         match strain {
             cargo::STRAIN => Ok(Sbom::Cargo(cargo::CargoLock { data })),
             composer::STRAIN => Ok(Sbom::Composer(composer::ComposerLock { data })),
+            cyclonedx::STRAIN => Ok(Sbom::CycloneDx(cyclonedx::CycloneDxJson { data })),
+            gemfile::STRAIN => Ok(Sbom::Gemfile(gemfile::GemfileLock { data })),
             go::STRAIN => Ok(Sbom::Go(go::GoSum { data })),
             npm::STRAIN => Ok(Sbom::Npm(npm::PackageLockJson { data })),
+            pipfile::STRAIN => Ok(Sbom::Pipfile(pipfile::PipfileLock { data })),
+            poetry::STRAIN => Ok(Sbom::Poetry(poetry::PoetryLock { data })),
+            spdx::STRAIN => Ok(Sbom::Spdx(spdx::SpdxJson { data })),
             yarn::STRAIN => Ok(Sbom::Yarn(yarn::YarnLock { data })),
             _ => Err(Error::UnknownSbomStrain(strain.to_string())),
         }
@@ -43,18 +62,41 @@ impl Sbom {
         match self {
             Sbom::Cargo(_) => cargo::STRAIN,
             Sbom::Composer(_) => composer::STRAIN,
+            Sbom::CycloneDx(_) => cyclonedx::STRAIN,
+            Sbom::Gemfile(_) => gemfile::STRAIN,
             Sbom::Go(_) => go::STRAIN,
             Sbom::Npm(_) => npm::STRAIN,
+            Sbom::Pipfile(_) => pipfile::STRAIN,
+            Sbom::Poetry(_) => poetry::STRAIN,
+            Sbom::Spdx(_) => spdx::STRAIN,
             Sbom::Yarn(_) => yarn::STRAIN,
         }
     }
 
+    /// The purl type of the packages listed in this sbom, if they all come from the same kind of registry
+    pub fn purl_type(&self) -> Option<&'static str> {
+        match self {
+            Sbom::Cargo(_) => Some("cargo"),
+            Sbom::Composer(_) => Some("composer"),
+            Sbom::Gemfile(_) => Some("gem"),
+            Sbom::Go(_) => Some("golang"),
+            Sbom::Npm(_) | Sbom::Yarn(_) => Some("npm"),
+            Sbom::Pipfile(_) | Sbom::Poetry(_) => Some("pypi"),
+            Sbom::CycloneDx(_) | Sbom::Spdx(_) => None,
+        }
+    }
+
     pub fn data(&self) -> &str {
         match self {
             Sbom::Cargo(sbom) => &sbom.data,
             Sbom::Composer(sbom) => &sbom.data,
+            Sbom::CycloneDx(sbom) => &sbom.data,
+            Sbom::Gemfile(sbom) => &sbom.data,
             Sbom::Go(sbom) => &sbom.data,
             Sbom::Npm(sbom) => &sbom.data,
+            Sbom::Pipfile(sbom) => &sbom.data,
+            Sbom::Poetry(sbom) => &sbom.data,
+            Sbom::Spdx(sbom) => &sbom.data,
             Sbom::Yarn(sbom) => &sbom.data,
         }
     }
@@ -65,6 +107,30 @@ impl Sbom {
                 let sbom = sbom.parse()?;
                 sbom.collect::<Result<Vec<_>>>()
             }
+            Sbom::CycloneDx(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
+            Sbom::Gemfile(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
+            Sbom::Go(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
+            Sbom::Pipfile(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
+            Sbom::Poetry(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
+            Sbom::Spdx(sbom) => {
+                let sbom = sbom.parse()?;
+                Ok(sbom.collect::<Vec<_>>())
+            }
             Sbom::Yarn(sbom) => {
                 let sbom = sbom.parse()?;
                 Ok(sbom.collect::<Vec<_>>())
@@ -79,9 +145,82 @@ pub struct Package {
     pub name: String,
     pub version: String,
     pub checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_location: Option<String>,
     pub official_registry: bool,
 }
 
+impl Package {
+    pub fn purl(&self, ty: &str) -> Purl {
+        Purl::for_registry_package(ty, &self.name, &self.version)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PackageVersions {
+    pub name: String,
+    pub versions: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChangedPackage {
+    pub name: String,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+/// The difference between the packages of two lockfiles, sorted by name
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Diff {
+    pub added: Vec<PackageVersions>,
+    pub removed: Vec<PackageVersions>,
+    pub changed: Vec<ChangedPackage>,
+}
+
+/// Compare packages by name, lockfiles may contain multiple versions of the same package
+pub fn diff(old: &[Package], new: &[Package]) -> Diff {
+    fn by_name(packages: &[Package]) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut map = BTreeMap::<_, BTreeSet<_>>::new();
+        for pkg in packages {
+            map.entry(pkg.name.as_str())
+                .or_default()
+                .insert(pkg.version.as_str());
+        }
+        map
+    }
+    fn owned(versions: &BTreeSet<&str>) -> Vec<String> {
+        versions.iter().map(|v| v.to_string()).collect()
+    }
+
+    let old = by_name(old);
+    let new = by_name(new);
+
+    let mut diff = Diff::default();
+    for (name, from) in &old {
+        match new.get(name) {
+            Some(to) if to != from => diff.changed.push(ChangedPackage {
+                name: name.to_string(),
+                from: owned(from),
+                to: owned(to),
+            }),
+            Some(_) => (),
+            None => diff.removed.push(PackageVersions {
+                name: name.to_string(),
+                versions: owned(from),
+            }),
+        }
+    }
+    for (name, to) in &new {
+        if !old.contains_key(name) {
+            diff.added.push(PackageVersions {
+                name: name.to_string(),
+                versions: owned(to),
+            });
+        }
+    }
+    diff
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Ref {
     pub strain: &'static str,
@@ -96,11 +235,20 @@ pub fn detect_from_filename(filename: Option<&str>) -> Option<&'static str> {
         Some("yarn.lock") => Some(yarn::STRAIN),
         Some("composer.lock") => Some(composer::STRAIN),
         Some("go.sum") => Some(go::STRAIN),
+        Some("poetry.lock") => Some(poetry::STRAIN),
+        Some("Pipfile.lock") => Some(pipfile::STRAIN),
+        Some("Gemfile.lock") => Some(gemfile::STRAIN),
+        Some(name) if name.ends_with(".spdx.json") => Some(spdx::STRAIN),
+        Some(name) if name.ends_with(".cdx.json") => Some(cyclonedx::STRAIN),
         _ => None,
     }
 }
 
 pub async fn index(db: &db::Client, sbom: &Sbom) -> Result<()> {
+    let chksum = chksums::sha256(sbom.data().as_bytes());
+    db.insert_sbom_packages(&chksum, sbom.strain(), &sbom.to_packages()?)
+        .await?;
+
     match sbom.strain() {
         cargo::STRAIN => {
             for pkg in sbom.to_packages()? {
@@ -139,6 +287,21 @@ pub async fn index(db: &db::Client, sbom: &Sbom) -> Result<()> {
                 .await?;
             }
         }
+        go::STRAIN => {
+            for pkg in sbom.to_packages()? {
+                let Some(chksum) = pkg.checksum else { continue };
+                if db.resolve_artifact(&chksum).await?.is_some() {
+                    continue;
+                }
+
+                info!(
+                    "Adding module download task path={:?} version={:?}",
+                    pkg.name, pkg.version
+                );
+                db.insert_task(&goproxy::task_for_module(&pkg.name, &pkg.version)?)
+                    .await?;
+            }
+        }
         yarn::STRAIN => {
             for pkg in sbom.to_packages()? {
                 let full_name = &pkg.name;
@@ -206,3 +369,60 @@ pub async fn run(args: &args::IngestSbom) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pkg(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            checksum: None,
+            download_location: None,
+            official_registry: true,
+        }
+    }
+
+    #[test]
+    fn test_diff_packages() {
+        let old = [
+            pkg("libc", "0.2.158"),
+            pkg("syn", "1.0.109"),
+            pkg("syn", "2.0.77"),
+            pkg("memchr", "2.7.4"),
+            pkg("openssl", "0.10.66"),
+        ];
+        let new = [
+            pkg("libc", "0.2.159"),
+            pkg("syn", "2.0.77"),
+            pkg("memchr", "2.7.4"),
+            pkg("rustls", "0.23.13"),
+        ];
+        assert_eq!(
+            diff(&old, &new),
+            Diff {
+                added: vec![PackageVersions {
+                    name: "rustls".to_string(),
+                    versions: vec!["0.23.13".to_string()],
+                }],
+                removed: vec![PackageVersions {
+                    name: "openssl".to_string(),
+                    versions: vec!["0.10.66".to_string()],
+                }],
+                changed: vec![
+                    ChangedPackage {
+                        name: "libc".to_string(),
+                        from: vec!["0.2.158".to_string()],
+                        to: vec!["0.2.159".to_string()],
+                    },
+                    ChangedPackage {
+                        name: "syn".to_string(),
+                        from: vec!["1.0.109".to_string(), "2.0.77".to_string()],
+                        to: vec!["2.0.77".to_string()],
+                    },
+                ],
+            }
+        );
+    }
+}
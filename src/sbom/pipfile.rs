@@ -0,0 +1,192 @@
+use crate::errors::*;
+use crate::sbom::poetry;
+use crate::sbom::Package;
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+
+pub const STRAIN: &str = "pipfile-lock";
+
+#[derive(Debug, PartialEq)]
+pub struct PipfileLock {
+    pub data: String,
+}
+
+impl PipfileLock {
+    pub fn parse(&self) -> Result<ParsedLock> {
+        let lock = serde_json::from_str::<Lockfile>(&self.data)?;
+
+        let official_index = |index: Option<&str>| {
+            let source = match index {
+                Some(index) => lock.meta.sources.iter().find(|s| s.name == index),
+                // without an explicit index, pipenv uses the first configured source
+                None => lock.meta.sources.first(),
+            };
+            source.is_some_and(|source| poetry::is_pypi_url(&source.url))
+        };
+
+        let mut packages = VecDeque::new();
+        for (name, pkg) in lock.default.iter().chain(lock.develop.iter()) {
+            // git and path dependencies have no version
+            let Some(version) = &pkg.version else {
+                continue;
+            };
+            let version = version.strip_prefix("==").unwrap_or(version);
+
+            // the lockfile doesn't record which file a hash belongs to,
+            // it's only unambiguous if there's exactly one
+            let checksum = match pkg.hashes.as_slice() {
+                [hash] => Some(hash.to_lowercase()),
+                _ => None,
+            };
+
+            packages.push_back(Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                checksum,
+                download_location: None,
+                official_registry: official_index(pkg.index.as_deref()),
+            });
+        }
+        Ok(ParsedLock { packages })
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Lockfile {
+    #[serde(rename = "_meta")]
+    meta: Meta,
+    #[serde(default)]
+    default: BTreeMap<String, PipfilePackage>,
+    #[serde(default)]
+    develop: BTreeMap<String, PipfilePackage>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Meta {
+    #[serde(default)]
+    sources: Vec<Source>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Source {
+    name: String,
+    url: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct PipfilePackage {
+    version: Option<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+    index: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedLock {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedLock {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::Sbom;
+
+    #[test]
+    fn test_parse_pipfile_lock() {
+        let data = r#"{
+    "_meta": {
+        "hash": {
+            "sha256": "7f7606f08e0544d8d012ef4d097dabdd6df6843a28793eb6551245d4b2db4242"
+        },
+        "pipfile-spec": 6,
+        "requires": {
+            "python_version": "3.12"
+        },
+        "sources": [
+            {
+                "name": "pypi",
+                "url": "https://pypi.org/simple",
+                "verify_ssl": true
+            },
+            {
+                "name": "internal",
+                "url": "https://pkgs.example.com/simple",
+                "verify_ssl": true
+            }
+        ]
+    },
+    "default": {
+        "idna": {
+            "hashes": [
+                "sha256:12f65c9b470abda6dc35cf8e63cc574b1c52b11df2c86030af0ac09b01b13ea9",
+                "sha256:946d195a0d259cbba61165e88e65941f16e9b36ea6ddb97f00452bae8b1287d3"
+            ],
+            "index": "pypi",
+            "markers": "python_version >= '3.6'",
+            "version": "==3.10"
+        },
+        "internal-utils": {
+            "hashes": [
+                "sha256:3F1D2C4B5A6978E0D1C2B3A4F5E6D7C8B9A0F1E2D3C4B5A6978E0D1C2B3A4F5E"
+            ],
+            "index": "internal",
+            "version": "==0.3.0"
+        },
+        "vendored": {
+            "git": "https://github.com/example/vendored.git",
+            "ref": "6c2c0ba0bd3b2e49da6ac1f1d0f4ab3b7bb4d1e2"
+        }
+    },
+    "develop": {
+        "iniconfig": {
+            "hashes": [
+                "sha256:b6a85871a79d2e3b22d2d1b94ac2824226a63c6b741c88f7ae975f18b6778374"
+            ],
+            "version": "==2.0.0"
+        }
+    }
+}"#;
+        let sbom = Sbom::new(STRAIN, data.to_string()).unwrap();
+        let list = sbom.to_packages().unwrap();
+        assert_eq!(
+            list,
+            [
+                Package {
+                    name: "idna".to_string(),
+                    version: "3.10".to_string(),
+                    checksum: None,
+                    download_location: None,
+                    official_registry: true,
+                },
+                Package {
+                    name: "internal-utils".to_string(),
+                    version: "0.3.0".to_string(),
+                    checksum: Some(
+                        "sha256:3f1d2c4b5a6978e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b5a6978e0d1c2b3a4f5e"
+                            .to_string()
+                    ),
+                    download_location: None,
+                    official_registry: false,
+                },
+                Package {
+                    name: "iniconfig".to_string(),
+                    version: "2.0.0".to_string(),
+                    checksum: Some(
+                        "sha256:b6a85871a79d2e3b22d2d1b94ac2824226a63c6b741c88f7ae975f18b6778374"
+                            .to_string()
+                    ),
+                    download_location: None,
+                    official_registry: true,
+                },
+            ]
+        );
+    }
+}
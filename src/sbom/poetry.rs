@@ -0,0 +1,221 @@
+use crate::errors::*;
+use crate::sbom::Package;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+pub const STRAIN: &str = "poetry-lock";
+
+/// Check if a package index url points to the Python Package Index
+pub fn is_pypi_url(url: &str) -> bool {
+    let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    else {
+        return false;
+    };
+    let host = rest.split('/').next().unwrap_or(rest);
+    matches!(
+        host,
+        "pypi.org" | "pypi.python.org" | "files.pythonhosted.org"
+    )
+}
+
+/// The canonical download url of a source distribution on files.pythonhosted.org
+pub fn sdist_url(name: &str, filename: &str) -> Option<String> {
+    let first = name.chars().next()?;
+    Some(format!(
+        "https://files.pythonhosted.org/packages/source/{first}/{name}/{filename}"
+    ))
+}
+
+/// Source distributions are the files that aren't wheels
+pub fn is_sdist(filename: &str) -> bool {
+    filename.ends_with(".tar.gz") || filename.ends_with(".zip")
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PoetryLock {
+    pub data: String,
+}
+
+impl PoetryLock {
+    pub fn parse(&self) -> Result<ParsedLock> {
+        let lock = toml::from_str::<Lockfile>(&self.data)?;
+        let packages = lock
+            .packages
+            .into_iter()
+            .map(PoetryPackage::into_package)
+            .collect();
+        Ok(ParsedLock { packages })
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Lockfile {
+    #[serde(default, rename = "package")]
+    packages: Vec<PoetryPackage>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct PoetryPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    files: Vec<PoetryFile>,
+    source: Option<PoetrySource>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct PoetryFile {
+    file: String,
+    hash: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct PoetrySource {
+    #[serde(rename = "type")]
+    source_type: String,
+    url: Option<String>,
+}
+
+impl PoetryPackage {
+    fn into_package(self) -> Package {
+        // packages without an explicit source are resolved from pypi
+        let official_registry = match &self.source {
+            None => true,
+            Some(source) => {
+                source.source_type == "legacy" && source.url.as_deref().is_some_and(is_pypi_url)
+            }
+        };
+
+        let sdist = self.files.iter().find(|file| is_sdist(&file.file));
+        let checksum = sdist
+            .map(|file| file.hash.to_lowercase())
+            .filter(|hash| hash.starts_with("sha256:"));
+        let download_location = if official_registry {
+            sdist.and_then(|file| sdist_url(&self.name, &file.file))
+        } else {
+            self.source.and_then(|source| source.url)
+        };
+
+        Package {
+            name: self.name,
+            version: self.version,
+            checksum,
+            download_location,
+            official_registry,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedLock {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedLock {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::Sbom;
+
+    #[test]
+    fn test_parse_poetry_lock() {
+        let data = r#"# This file is automatically @generated by Poetry 1.8.3 and should not be changed by hand.
+
+[[package]]
+name = "certifi"
+version = "2024.8.30"
+description = "Python package for providing Mozilla's CA Bundle."
+optional = false
+python-versions = ">=3.6"
+files = [
+    {file = "certifi-2024.8.30-py3-none-any.whl", hash = "sha256:922820b53db7a7257ffbda3f597266d435245903d80737e34f8a45ff3e3230d8"},
+    {file = "certifi-2024.8.30.tar.gz", hash = "sha256:BEC941D2AAC3E0D3AB8E6A7E4D2C7E1BE5B0B2AB1B8F1D1C2D8D0C7E8B1A5B7C"},
+]
+
+[[package]]
+name = "internal-utils"
+version = "0.3.0"
+description = ""
+optional = false
+python-versions = "*"
+files = [
+    {file = "internal_utils-0.3.0.tar.gz", hash = "sha256:3f1d2c4b5a6978e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b5a6978e0d1c2b3a4f5e"},
+]
+
+[package.source]
+type = "legacy"
+url = "https://pkgs.example.com/simple"
+reference = "internal"
+
+[[package]]
+name = "vendored"
+version = "1.0.0"
+description = ""
+optional = false
+python-versions = "*"
+files = []
+
+[package.source]
+type = "git"
+url = "https://github.com/example/vendored.git"
+reference = "HEAD"
+resolved_reference = "6c2c0ba0bd3b2e49da6ac1f1d0f4ab3b7bb4d1e2"
+
+[metadata]
+lock-version = "2.0"
+python-versions = "^3.12"
+content-hash = "0000000000000000000000000000000000000000000000000000000000000000"
+"#;
+        let sbom = Sbom::new(STRAIN, data.to_string()).unwrap();
+        let list = sbom.to_packages().unwrap();
+        assert_eq!(
+            list,
+            [
+                Package {
+                    name: "certifi".to_string(),
+                    version: "2024.8.30".to_string(),
+                    checksum: Some(
+                        "sha256:bec941d2aac3e0d3ab8e6a7e4d2c7e1be5b0b2ab1b8f1d1c2d8d0c7e8b1a5b7c"
+                            .to_string()
+                    ),
+                    download_location: Some("https://files.pythonhosted.org/packages/source/c/certifi/certifi-2024.8.30.tar.gz".to_string()),
+                    official_registry: true,
+                },
+                Package {
+                    name: "internal-utils".to_string(),
+                    version: "0.3.0".to_string(),
+                    checksum: Some(
+                        "sha256:3f1d2c4b5a6978e0d1c2b3a4f5e6d7c8b9a0f1e2d3c4b5a6978e0d1c2b3a4f5e"
+                            .to_string()
+                    ),
+                    download_location: Some("https://pkgs.example.com/simple".to_string()),
+                    official_registry: false,
+                },
+                Package {
+                    name: "vendored".to_string(),
+                    version: "1.0.0".to_string(),
+                    checksum: None,
+                    download_location: Some("https://github.com/example/vendored.git".to_string()),
+                    official_registry: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_pypi_url() {
+        assert!(is_pypi_url("https://pypi.org/simple"));
+        assert!(is_pypi_url("https://files.pythonhosted.org/packages/"));
+        assert!(!is_pypi_url("https://pypi.org.example.com/simple"));
+        assert!(!is_pypi_url("https://pkgs.example.com/simple"));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::errors::*;
+use crate::sbom::Package;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+pub const STRAIN: &str = "spdx-json";
+
+/// Checksum algorithms we can represent, in order of preference
+const ALGORITHMS: &[(&str, &str)] = &[
+    ("SHA256", "sha256"),
+    ("SHA512", "sha512"),
+    ("BLAKE2b-512", "blake2b"),
+    ("SHA1", "sha1"),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct SpdxJson {
+    pub data: String,
+}
+
+impl SpdxJson {
+    pub fn parse(&self) -> Result<ParsedSpdx> {
+        let document = serde_json::from_str::<Document>(&self.data)?;
+        let packages = document
+            .packages
+            .into_iter()
+            .filter_map(SpdxPackage::into_package)
+            .collect();
+        Ok(ParsedSpdx { packages })
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Document {
+    #[serde(default)]
+    packages: Vec<SpdxPackage>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    version_info: Option<String>,
+    download_location: Option<String>,
+    #[serde(default)]
+    checksums: Vec<SpdxChecksum>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxChecksum {
+    algorithm: String,
+    checksum_value: String,
+}
+
+impl SpdxPackage {
+    fn into_package(self) -> Option<Package> {
+        let version = self.version_info?;
+
+        let checksum = ALGORITHMS.iter().find_map(|(algorithm, family)| {
+            self.checksums
+                .iter()
+                .find(|chksum| chksum.algorithm == *algorithm)
+                .map(|chksum| format!("{family}:{}", chksum.checksum_value.to_lowercase()))
+        });
+
+        // `NONE` and `NOASSERTION` are placeholders, not locations
+        let download_location = self
+            .download_location
+            .filter(|location| !matches!(location.as_str(), "NONE" | "NOASSERTION"));
+
+        Some(Package {
+            name: self.name,
+            version,
+            checksum,
+            download_location,
+            official_registry: false,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedSpdx {
+    packages: VecDeque<Package>,
+}
+
+impl Iterator for ParsedSpdx {
+    type Item = Package;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.packages.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sbom::{self, Sbom};
+
+    #[test]
+    fn test_parse_spdx_json() {
+        let data = r#"{
+  "spdxVersion": "SPDX-2.3",
+  "dataLicense": "CC0-1.0",
+  "SPDXID": "SPDXRef-DOCUMENT",
+  "name": "curl-8.10.1",
+  "packages": [
+    {
+      "name": "curl",
+      "SPDXID": "SPDXRef-Package-curl",
+      "versionInfo": "8.10.1",
+      "downloadLocation": "https://curl.se/download/curl-8.10.1.tar.xz",
+      "checksums": [
+        {
+          "algorithm": "SHA1",
+          "checksumValue": "0d0e1d5c5f1a8a2b1c0bd1b8f8c6d0a0c4e1f2a3"
+        },
+        {
+          "algorithm": "SHA256",
+          "checksumValue": "73A4B0E99596A09FA5924A4FB7E4B995A85FDA0D18A2C02AB9CF134AEBCE04EE"
+        }
+      ]
+    },
+    {
+      "name": "zlib",
+      "SPDXID": "SPDXRef-Package-zlib",
+      "versionInfo": "1.3.1",
+      "downloadLocation": "NOASSERTION"
+    },
+    {
+      "name": "unversioned",
+      "SPDXID": "SPDXRef-Package-unversioned",
+      "downloadLocation": "NONE"
+    }
+  ]
+}"#;
+        let sbom = Sbom::new(STRAIN, data.to_string()).unwrap();
+        let list = sbom.to_packages().unwrap();
+        assert_eq!(
+            list,
+            [
+                Package {
+                    name: "curl".to_string(),
+                    version: "8.10.1".to_string(),
+                    checksum: Some(
+                        "sha256:73a4b0e99596a09fa5924a4fb7e4b995a85fda0d18a2c02ab9cf134aebce04ee"
+                            .to_string()
+                    ),
+                    download_location: Some(
+                        "https://curl.se/download/curl-8.10.1.tar.xz".to_string()
+                    ),
+                    official_registry: false,
+                },
+                Package {
+                    name: "zlib".to_string(),
+                    version: "1.3.1".to_string(),
+                    checksum: None,
+                    download_location: None,
+                    official_registry: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_spdx_filename() {
+        assert_eq!(
+            sbom::detect_from_filename(Some("curl-8.10.1.spdx.json")),
+            Some(STRAIN)
+        );
+        assert_eq!(sbom::detect_from_filename(Some("spdx.json")), None);
+    }
+}
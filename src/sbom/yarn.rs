@@ -27,6 +27,7 @@ impl YarnLock {
                 name: entry.name.to_string(),
                 version: entry.version.to_string(),
                 checksum,
+                download_location: None,
                 official_registry: false,
             });
         }
@@ -0,0 +1,153 @@
+use crate::args;
+use crate::chksums;
+use crate::db;
+use crate::errors::*;
+use crate::reports;
+use crate::storage::Storage;
+use crate::utils;
+use futures::StreamExt;
+
+#[derive(Debug, Default)]
+struct Report {
+    checked: usize,
+    corrupted: usize,
+}
+
+async fn scrub_sboms(db: &db::Client, limit: Option<usize>) -> Result<Report> {
+    let mut report = Report::default();
+    let stream = db.get_all_sboms();
+    tokio::pin!(stream);
+    while let Some(sbom) = stream.next().await {
+        let sbom = sbom?;
+
+        if limit.is_some_and(|limit| report.checked >= limit) {
+            info!("Reached scrub limit of {} sboms", report.checked);
+            break;
+        }
+        report.checked += 1;
+
+        let chksum = chksums::sha256(sbom.data.as_bytes());
+        if chksum != sbom.chksum {
+            error!(
+                "Stored sbom does not match its digest: strain={:?} recorded={:?} actual={chksum:?}",
+                sbom.strain, sbom.chksum
+            );
+            report.corrupted += 1;
+        }
+    }
+    Ok(report)
+}
+
+async fn scrub_artifacts(db: &db::Client, limit: Option<usize>) -> Result<Report> {
+    let mut report = Report::default();
+    let stream = db.get_all_artifacts_by_age();
+    tokio::pin!(stream);
+    while let Some(artifact) = stream.next().await {
+        let artifact = artifact?;
+
+        if limit.is_some_and(|limit| report.checked >= limit) {
+            info!("Reached scrub limit of {} artifacts", report.checked);
+            break;
+        }
+        report.checked += 1;
+
+        if let Err(err) = artifact.get_files() {
+            error!(
+                "Failed to decode stored file listing: chksum={:?} err={err:#}",
+                artifact.chksum
+            );
+            report.corrupted += 1;
+        }
+    }
+    Ok(report)
+}
+
+async fn scrub_blobs(db: &db::Client, storage: &Storage, limit: Option<usize>) -> Result<Report> {
+    let http = utils::http_client(None)?;
+    let mut report = Report::default();
+    let stream = db.get_all_blobs();
+    tokio::pin!(stream);
+    while let Some(blob) = stream.next().await {
+        let blob = blob?;
+
+        if limit.is_some_and(|limit| report.checked >= limit) {
+            info!("Reached scrub limit of {} blobs", report.checked);
+            break;
+        }
+        report.checked += 1;
+
+        // anything larger than recorded is a mismatch, no need to download all of it
+        let finding = match storage
+            .fetch_blob(&http, &blob.chksum, blob.size as u64)
+            .await
+        {
+            Ok(data) => reports::summarize_blob(&blob, &data),
+            Err(Error::DownloadTooLarge { .. }) => Some((
+                format!(
+                    "Archived content is larger than the recorded {} bytes",
+                    blob.size
+                ),
+                reports::BLOB_CORRUPTED_SCORE,
+            )),
+            Err(Error::Reqwest(err)) if err.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                Some((
+                    "Archived content is missing from blob storage".to_string(),
+                    reports::BLOB_CORRUPTED_SCORE,
+                ))
+            }
+            Err(err) => {
+                error!("Failed to fetch blob: chksum={:?} err={err:#}", blob.chksum);
+                report.corrupted += 1;
+                continue;
+            }
+        };
+
+        if let Some((summary, score)) = finding {
+            error!(
+                "Stored blob does not match its digest: chksum={:?} summary={summary:?}",
+                blob.chksum
+            );
+            db.insert_report(&reports::blob_corrupted(&blob, summary, score))
+                .await?;
+            report.corrupted += 1;
+        } else {
+            db.delete_report(&blob.chksum, reports::KIND_BLOB_CORRUPTED)
+                .await?;
+        }
+    }
+    Ok(report)
+}
+
+pub async fn run(args: &args::Scrub) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let sboms = scrub_sboms(&db, args.limit).await?;
+    info!(
+        "Scrubbed sboms: checked={} corrupted={}",
+        sboms.checked, sboms.corrupted
+    );
+
+    let artifacts = scrub_artifacts(&db, args.limit).await?;
+    info!(
+        "Scrubbed artifacts: checked={} corrupted={}",
+        artifacts.checked, artifacts.corrupted
+    );
+
+    let blobs = if let Some(storage) = Storage::from_env()? {
+        scrub_blobs(&db, &storage, args.limit).await?
+    } else {
+        info!("Blob storage is not configured, skipping blobs");
+        Report::default()
+    };
+    info!(
+        "Scrubbed blobs: checked={} corrupted={}",
+        blobs.checked, blobs.corrupted
+    );
+
+    let corrupted = sboms.corrupted + artifacts.corrupted + blobs.corrupted;
+    if corrupted > 0 {
+        Err(Error::ScrubFailed(corrupted))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+use crate::args;
+use crate::chksums::Hasher;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::utils::HttpClient;
+use data_encoding::BASE64;
+use std::env;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::fs;
+use tokio::io::{self, AsyncWriteExt};
+use tokio::process::Command;
+use x509_parser::extensions::GeneralName;
+
+pub const KIND_PGP: &str = "pgp";
+pub const KIND_COSIGN: &str = "cosign";
+
+pub const STATUS_VALID: &str = "valid";
+/// A valid sigstore signature, but we don't know which identity to expect
+pub const STATUS_VALID_UNPINNED: &str = "valid-unpinned";
+pub const STATUS_INVALID: &str = "invalid";
+pub const STATUS_UNKNOWN_KEY: &str = "unknown-key";
+pub const STATUS_EXPIRED_SIGNATURE: &str = "expired-signature";
+pub const STATUS_EXPIRED_KEY: &str = "expired-key";
+pub const STATUS_REVOKED_KEY: &str = "revoked-key";
+pub const STATUS_ERROR: &str = "error";
+
+/// Fulcio certificate extensions of the OIDC issuer, v2 is DER encoded, v1 is the raw string
+const OID_FULCIO_ISSUER_V2: &str = "1.3.6.1.4.1.57264.1.8";
+const OID_FULCIO_ISSUER_V1: &str = "1.3.6.1.4.1.57264.1.1";
+
+/// File extensions of detached signatures, along with the kind of signature
+const EXTENSIONS: &[(&str, &str)] = &[
+    (".sig", KIND_PGP),
+    (".asc", KIND_PGP),
+    (".sign", KIND_PGP),
+    (".sigstore.json", KIND_COSIGN),
+    (".sigstore", KIND_COSIGN),
+    (".bundle", KIND_COSIGN),
+];
+
+/// Detect the kind of a detached signature, along with the url of the file it signs
+pub fn detect(signature_url: &str) -> Option<(&'static str, &str)> {
+    // check the longest extension first, `.sigstore.json` is not a `.json` signature
+    EXTENSIONS
+        .iter()
+        .filter_map(|(ext, kind)| Some((*kind, signature_url.strip_suffix(ext)?)))
+        .min_by_key(|(_, url)| url.len())
+        .filter(|(_, url)| !url.is_empty() && !url.ends_with('/'))
+}
+
+/// Normalize an OpenPGP fingerprint to uppercase hex, short key ids are rejected
+pub fn normalize_fingerprint(fingerprint: &str) -> Option<String> {
+    let fingerprint = fingerprint
+        .trim()
+        .trim_start_matches("0x")
+        .replace(' ', "")
+        .to_ascii_uppercase();
+    // v4 and v6 keys
+    let valid =
+        matches!(fingerprint.len(), 40 | 64) && fingerprint.bytes().all(|b| b.is_ascii_hexdigit());
+    valid.then_some(fingerprint)
+}
+
+/// Fedora ships the keyring of upstream as `gpgkey-<fingerprint>.gpg`
+pub fn fingerprint_from_keyring_filename(filename: &str) -> Option<String> {
+    let fingerprint = filename
+        .strip_prefix("gpgkey-")?
+        .strip_suffix(".gpg")
+        .or_else(|| filename.strip_prefix("gpgkey-")?.strip_suffix(".asc"))?;
+    normalize_fingerprint(fingerprint)
+}
+
+pub fn task_for(url: &str, signature_url: &str) -> Result<Task> {
+    Task::new(
+        format!("verify-signature:{signature_url}"),
+        &TaskData::VerifySignature {
+            url: url.to_string(),
+            signature_url: signature_url.to_string(),
+        },
+    )
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Verification {
+    pub status: &'static str,
+    pub signer: Option<String>,
+}
+
+impl Verification {
+    fn new(status: &'static str, signer: Option<&str>) -> Self {
+        Verification {
+            status,
+            signer: signer.map(String::from),
+        }
+    }
+}
+
+/// Interpret the `--status-fd` output of gpgv, along with whether gpgv exited successfully
+///
+/// gpgv also emits `VALIDSIG` for expired or revoked keys, a signature is only considered valid
+/// if there's both a `GOODSIG` and a `VALIDSIG` and nothing else went wrong.
+pub fn parse_gpg_status(output: &str, success: bool) -> Verification {
+    let mut good = false;
+    let mut valid = None;
+    let mut expired_key = None;
+    let mut expired_sig = None;
+    let mut missing = None;
+    let mut errsig = None;
+    for line in output.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let mut fields = line.split(' ');
+        match fields.next() {
+            Some("BADSIG") => return Verification::new(STATUS_INVALID, fields.next()),
+            Some("REVKEYSIG") => return Verification::new(STATUS_REVOKED_KEY, fields.next()),
+            Some("EXPKEYSIG") => expired_key = fields.next().or(expired_key),
+            Some("EXPSIG") => expired_sig = fields.next().or(expired_sig),
+            Some("GOODSIG") => good = true,
+            // the last field is the fingerprint of the primary key
+            Some("VALIDSIG") => valid = fields.next_back().or(valid),
+            Some("NO_PUBKEY") => missing = fields.next().or(missing),
+            Some("ERRSIG") => errsig = fields.next().or(errsig),
+            _ => (),
+        }
+    }
+
+    if let Some(keyid) = expired_key {
+        Verification::new(STATUS_EXPIRED_KEY, Some(keyid))
+    } else if let Some(keyid) = expired_sig {
+        Verification::new(STATUS_EXPIRED_SIGNATURE, Some(keyid))
+    } else if let Some(keyid) = missing {
+        Verification::new(STATUS_UNKNOWN_KEY, Some(keyid))
+    } else if let Some(keyid) = errsig {
+        Verification::new(STATUS_ERROR, Some(keyid))
+    } else if let (true, true, Some(fingerprint)) = (good, success, valid) {
+        Verification::new(STATUS_VALID, Some(fingerprint))
+    } else {
+        Verification::new(STATUS_ERROR, None)
+    }
+}
+
+/// The identity and issuer of the certificate in a sigstore bundle, formatted as `identity (issuer)`
+pub fn cosign_signer(bundle: &[u8]) -> Option<String> {
+    let bundle = serde_json::from_slice::<serde_json::Value>(bundle).ok()?;
+    let der = if let Some(raw) = bundle
+        .pointer("/verificationMaterial/certificate/rawBytes")
+        .or_else(|| {
+            bundle.pointer("/verificationMaterial/x509CertificateChain/certificates/0/rawBytes")
+        })
+        .and_then(|v| v.as_str())
+    {
+        BASE64.decode(raw.as_bytes()).ok()?
+    } else {
+        // bundles of `cosign sign-blob --bundle` contain a base64 encoded pem
+        let pem = bundle.get("cert")?.as_str()?;
+        let pem = BASE64.decode(pem.as_bytes()).ok()?;
+        let (_, pem) = x509_parser::pem::parse_x509_pem(&pem).ok()?;
+        pem.contents
+    };
+    let (_, cert) = x509_parser::parse_x509_certificate(&der).ok()?;
+
+    let identity = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .and_then(|san| {
+            san.value.general_names.iter().find_map(|name| match name {
+                GeneralName::RFC822Name(name) | GeneralName::URI(name) => Some(*name),
+                _ => None,
+            })
+        })?;
+
+    let mut issuer = None;
+    for ext in cert.extensions() {
+        match ext.oid.to_id_string().as_str() {
+            OID_FULCIO_ISSUER_V2 => {
+                if let Ok((_, value)) =
+                    x509_parser::der_parser::der::parse_der_utf8string(ext.value)
+                {
+                    issuer = value.as_str().ok().map(String::from);
+                }
+            }
+            OID_FULCIO_ISSUER_V1 if issuer.is_none() => {
+                issuer = String::from_utf8(ext.value.to_vec()).ok();
+            }
+            _ => (),
+        }
+    }
+
+    Some(match issuer {
+        Some(issuer) => format!("{identity} ({issuer})"),
+        None => identity.to_string(),
+    })
+}
+
+async fn verify_pgp(keyring: Option<&str>, signature: &Path, data: &Path) -> Result<Verification> {
+    let gpgv = env::var("WHATSRC_GPGV").unwrap_or_else(|_| "gpgv".into());
+    let mut cmd = Command::new(gpgv);
+    cmd.args(["--status-fd", "1"]);
+    if let Some(keyring) = keyring {
+        cmd.arg("--keyring").arg(keyring);
+    }
+    // the exit code is non-zero for anything but a good signature, the status output tells us why
+    let output = cmd.arg(signature).arg(data).output().await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_gpg_status(&stdout, output.status.success()))
+}
+
+/// Verify a clearsigned file (like an apt `InRelease`), returns the signed content that gpgv vouched for
+pub async fn verify_clearsigned(keyring: &str, data: &[u8]) -> Result<(Verification, Vec<u8>)> {
+    let gpgv = env::var("WHATSRC_GPGV").unwrap_or_else(|_| "gpgv".into());
+    let mut child = Command::new(gpgv)
+        .args(["--status-fd", "2", "--output", "-", "--keyring"])
+        .arg(keyring)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = async {
+        let ret = stdin.write_all(data).await;
+        drop(stdin);
+        ret
+    };
+    let (written, output) = tokio::join!(writer, child.wait_with_output());
+    written?;
+    let output = output?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let verification = parse_gpg_status(&stderr, output.status.success());
+    Ok((verification, output.stdout))
+}
+
+async fn verify_cosign(signature: &Path, data: &Path) -> Result<Verification> {
+    let cosign = env::var("WHATSRC_COSIGN").unwrap_or_else(|_| "cosign".into());
+    // we don't know whom to expect, this checks the bundle is a valid, logged signature of the file
+    // by anybody, the identity is recorded so people can judge for themselves
+    let signer = cosign_signer(&fs::read(signature).await?);
+    let status = Command::new(cosign)
+        .args([
+            "verify-blob",
+            "--certificate-identity-regexp",
+            ".*",
+            "--certificate-oidc-issuer-regexp",
+            ".*",
+            "--bundle",
+        ])
+        .arg(signature)
+        .arg(data)
+        .status()
+        .await?;
+    let status = if status.success() {
+        STATUS_VALID_UNPINNED
+    } else {
+        STATUS_INVALID
+    };
+    Ok(Verification { status, signer })
+}
+
+pub struct Verifier<'a> {
+    pub db: &'a db::Client,
+    pub http: &'a HttpClient,
+    pub tmp: &'a Path,
+    pub pgp_keyring: Option<&'a str>,
+}
+
+impl Verifier<'_> {
+    async fn download(&self, url: &str, path: &Path) -> Result<Vec<String>> {
+        let reader = self.http.fetch(url).await?;
+        let mut reader = Hasher::new(reader);
+        let mut file = fs::File::create(path).await?;
+        io::copy(&mut reader, &mut file).await?;
+        let (_, chksums) = reader.digests();
+        Ok(vec![chksums.sha256, chksums.sha512, chksums.blake2b])
+    }
+
+    async fn verify_files(
+        &self,
+        url: &str,
+        signature_url: &str,
+        data: &Path,
+        signature: &Path,
+    ) -> Result<()> {
+        let Some((kind, _)) = detect(signature_url) else {
+            return Err(Error::UnsupportedSignature(signature_url.to_string()));
+        };
+
+        info!("Downloading signed file: {url:?}");
+        let mut artifact = None;
+        for chksum in self.download(url, data).await? {
+            artifact = self.db.resolve_artifact_chksum(&chksum).await?;
+            if artifact.is_some() {
+                break;
+            }
+        }
+        // retried later, the artifact is likely still waiting to be fetched
+        let Some(chksum) = artifact else {
+            return Err(Error::UnknownSignedArtifact(url.to_string()));
+        };
+
+        info!("Downloading signature: {signature_url:?}");
+        self.download(signature_url, signature).await?;
+
+        let verification = match kind {
+            KIND_COSIGN => verify_cosign(signature, data).await?,
+            _ => verify_pgp(self.pgp_keyring, signature, data).await?,
+        };
+        info!("Signature of {chksum:?} is {verification:?}");
+
+        self.db
+            .insert_signature(&db::NewSignature {
+                chksum,
+                url: url.to_string(),
+                signature_url: signature_url.to_string(),
+                kind: kind.to_string(),
+                status: verification.status.to_string(),
+                signer: verification.signer,
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn verify(&self, url: &str, signature_url: &str) -> Result<()> {
+        let name = format!("whatsrc-signature-{:016x}", fastrand::u64(..));
+        let data = self.tmp.join(&name);
+        let signature = data.with_extension("sig");
+
+        let ret = self
+            .verify_files(url, signature_url, &data, &signature)
+            .await;
+        for path in [&data, &signature] {
+            if let Err(err) = fs::remove_file(path).await {
+                if err.kind() != io::ErrorKind::NotFound {
+                    warn!("Failed to remove temporary file {path:?}: {err:#}");
+                }
+            }
+        }
+        ret
+    }
+}
+
+pub async fn run(args: &args::VerifySignature) -> Result<()> {
+    let db = db::Client::create().await?;
+    let url = match &args.url {
+        Some(url) => url.as_str(),
+        None => {
+            let Some((_, url)) = detect(&args.signature_url) else {
+                return Err(Error::UnsupportedSignature(args.signature_url.clone()));
+            };
+            url
+        }
+    };
+    info!("Queueing signature verification: url={url:?}");
+    db.insert_task(&task_for(url, &args.signature_url)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_signature() {
+        assert_eq!(
+            detect("https://ftp.gnu.org/gnu/tar/tar-1.35.tar.xz.sig"),
+            Some((KIND_PGP, "https://ftp.gnu.org/gnu/tar/tar-1.35.tar.xz"))
+        );
+        assert_eq!(
+            detect("https://example.com/foo-1.0.tar.gz.asc"),
+            Some((KIND_PGP, "https://example.com/foo-1.0.tar.gz"))
+        );
+        assert_eq!(
+            detect("https://example.com/foo-1.0.tar.gz.sigstore.json"),
+            Some((KIND_COSIGN, "https://example.com/foo-1.0.tar.gz"))
+        );
+        assert_eq!(detect("https://example.com/foo-1.0.tar.gz"), None);
+        assert_eq!(detect("https://example.com/.sig"), None);
+    }
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(
+            normalize_fingerprint("3690 c240 ce51 b467 0d30  ad1c 38ee 757d 6918 4620").as_deref(),
+            Some("3690C240CE51B4670D30AD1C38EE757D69184620")
+        );
+        assert_eq!(normalize_fingerprint("38EE757D69184620"), None);
+        assert_eq!(
+            fingerprint_from_keyring_filename(
+                "gpgkey-3690C240CE51B4670D30AD1C38EE757D69184620.gpg"
+            )
+            .as_deref(),
+            Some("3690C240CE51B4670D30AD1C38EE757D69184620")
+        );
+        assert_eq!(
+            fingerprint_from_keyring_filename("xz-5.6.3.tar.gz.sig"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_gpg_status() {
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] KEY_CONSIDERED 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4 0
+[GNUPG:] SIG_ID 8a6Yw0Pq3S9lPjzJ1fW6k6mC8dA 2023-07-18 1689672873
+[GNUPG:] GOODSIG 3E1D2E27E1B7F3A4 Sergey Poznyakoff <gray@gnu.org>
+[GNUPG:] VALIDSIG 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4 2023-07-18 1689672873 0 4 0 1 8 00 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4
+";
+        assert_eq!(
+            parse_gpg_status(output, true),
+            Verification {
+                status: STATUS_VALID,
+                signer: Some("7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4".to_string()),
+            }
+        );
+        // a good signature is not enough if gpgv still exited with an error
+        assert_eq!(parse_gpg_status(output, false).status, STATUS_ERROR);
+
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] ERRSIG 3E1D2E27E1B7F3A4 1 8 00 1689672873 9 -
+[GNUPG:] NO_PUBKEY 3E1D2E27E1B7F3A4
+";
+        assert_eq!(
+            parse_gpg_status(output, false),
+            Verification {
+                status: STATUS_UNKNOWN_KEY,
+                signer: Some("3E1D2E27E1B7F3A4".to_string()),
+            }
+        );
+
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] BADSIG 3E1D2E27E1B7F3A4 Sergey Poznyakoff <gray@gnu.org>
+";
+        assert_eq!(parse_gpg_status(output, false).status, STATUS_INVALID);
+        assert_eq!(parse_gpg_status("", true).status, STATUS_ERROR);
+    }
+
+    #[test]
+    fn test_parse_gpg_status_expired_revoked() {
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] KEYEXPIRED 1689672873
+[GNUPG:] EXPKEYSIG 3E1D2E27E1B7F3A4 Sergey Poznyakoff <gray@gnu.org>
+[GNUPG:] VALIDSIG 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4 2023-07-18 1689672873 0 4 0 1 8 00 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4
+";
+        assert_eq!(
+            parse_gpg_status(output, true),
+            Verification {
+                status: STATUS_EXPIRED_KEY,
+                signer: Some("3E1D2E27E1B7F3A4".to_string()),
+            }
+        );
+
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] EXPSIG 3E1D2E27E1B7F3A4 Sergey Poznyakoff <gray@gnu.org>
+[GNUPG:] VALIDSIG 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4 2023-07-18 1689672873 1689759273 4 0 1 8 00 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4
+";
+        assert_eq!(
+            parse_gpg_status(output, true).status,
+            STATUS_EXPIRED_SIGNATURE
+        );
+
+        let output = "[GNUPG:] NEWSIG
+[GNUPG:] REVKEYSIG 3E1D2E27E1B7F3A4 Sergey Poznyakoff <gray@gnu.org>
+[GNUPG:] VALIDSIG 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4 2023-07-18 1689672873 0 4 0 1 8 00 7A6AC285E2D98827B0FC7D1A3E1D2E27E1B7F3A4
+";
+        assert_eq!(parse_gpg_status(output, true).status, STATUS_REVOKED_KEY);
+    }
+
+    #[test]
+    fn test_cosign_signer() {
+        let cert = "MIIB6DCCAY6gAwIBAgIUW8SGGxIFJNOHnGDmR8s469/ogN8wCgYIKoZIzj0EAwIwADAeFw0yNjEwMTYxNzI3NTlaFw0yNjEwMTcxNzI3NTlaMAAwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQYDh4dOc7KzTlc6dg0QHgi1i+iCWzPGUop/9g94ZsDzSpBnpHEWc+gsfEMJF9fYsOIKLZgfNusHUCZGW5XY1hto4HlMIHiMB0GA1UdDgQWBBRU55dfFalGsJXFY1CyS7c5PhVqwDAfBgNVHSMEGDAWgBRU55dfFalGsJXFY1CyS7c5PhVqwDAPBgNVHRMBAf8EBTADAQH/MFIGA1UdEQRLMEmGR2h0dHBzOi8vZ2l0aHViLmNvbS94ei94ei8uZ2l0aHViL3dvcmtmbG93cy9yZWxlYXNlLnltbEByZWZzL3RhZ3MvdjUuNi4zMDsGCisGAQQBg78wAQgELQwraHR0cHM6Ly90b2tlbi5hY3Rpb25zLmdpdGh1YnVzZXJjb250ZW50LmNvbTAKBggqhkjOPQQDAgNIADBFAiAe++bo6G9YnsxC/nV/B6g/CWC9RIgUpA656jS549ucPAIhALyhl5nvOKpqYdWEIAC4c+My5NwW4syQnNWXXPFnVnEJ";
+        let bundle = format!(
+            r#"{{"mediaType":"application/vnd.dev.sigstore.bundle.v0.3+json","verificationMaterial":{{"certificate":{{"rawBytes":"{cert}"}}}}}}"#
+        );
+        let signer = "https://github.com/xz/xz/.github/workflows/release.yml@refs/tags/v5.6.3 (https://token.actions.githubusercontent.com)";
+        assert_eq!(cosign_signer(bundle.as_bytes()).as_deref(), Some(signer));
+
+        // legacy bundles of `cosign sign-blob --bundle`
+        let pem = format!("-----BEGIN CERTIFICATE-----\n{cert}\n-----END CERTIFICATE-----\n");
+        let bundle = format!(
+            r#"{{"base64Signature":"","cert":"{}"}}"#,
+            BASE64.encode(pem.as_bytes())
+        );
+        assert_eq!(cosign_signer(bundle.as_bytes()).as_deref(), Some(signer));
+
+        assert_eq!(cosign_signer(b"{}"), None);
+    }
+}
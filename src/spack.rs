@@ -0,0 +1,257 @@
+/// A `version(...)` directive of a spack package
+#[derive(Debug, Default, PartialEq)]
+pub struct Version {
+    pub version: String,
+    pub sha256: Option<String>,
+    /// Overrides the url derived from the package url
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Package {
+    pub url: Option<String>,
+    pub versions: Vec<Version>,
+    /// The package computes urls in python, we can't derive them
+    pub url_for_version: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Artifact {
+    pub version: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl Package {
+    /// The release tarballs of this package, along with their checksum
+    ///
+    /// Like spack, the url of a version is derived by swapping the version in the package url.
+    pub fn artifacts(&self) -> Vec<Artifact> {
+        let default_url = self.url.as_deref().filter(|_| !self.url_for_version);
+        // the longest match, so `1.2.11` isn't mistaken for `1.2.1`
+        let template = default_url.and_then(|url| {
+            self.versions
+                .iter()
+                .map(|v| v.version.as_str())
+                .filter(|v| url.contains(v))
+                .max_by_key(|v| v.len())
+                .map(|v| (url, v))
+        });
+
+        let mut artifacts = Vec::new();
+        for version in &self.versions {
+            let Some(sha256) = &version.sha256 else {
+                continue;
+            };
+            let url = match (&version.url, template) {
+                (Some(url), _) => url.clone(),
+                (None, Some((url, current))) => url.replace(current, &version.version),
+                (None, None) => continue,
+            };
+            artifacts.push(Artifact {
+                version: version.version.clone(),
+                url,
+                sha256: sha256.clone(),
+            });
+        }
+        artifacts
+    }
+}
+
+/// Split the arguments of a call, commas inside of strings and brackets are kept
+fn split_args(args: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut begin = 0;
+    for (i, c) in args.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('(' | '[' | '{', None) => depth += 1,
+            (')' | ']' | '}', None) => depth -= 1,
+            (',', None) if depth == 0 => {
+                out.push(args[begin..i].trim());
+                begin = i + 1;
+            }
+            _ => (),
+        }
+    }
+    out.push(args[begin..].trim());
+    out.retain(|arg| !arg.is_empty());
+    out
+}
+
+/// Only plain string literals are supported
+fn string_literal(value: &str) -> Option<&str> {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+}
+
+/// Find the end of a call, returns the arguments
+fn call_args(txt: &str) -> Option<&str> {
+    let mut quote = None;
+    let mut depth = 1;
+    for (i, c) in txt.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('(', None) => depth += 1,
+            (')', None) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&txt[..i]);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+fn parse_version(args: &str) -> Option<Version> {
+    let mut args = split_args(args).into_iter();
+    let mut version = Version {
+        version: string_literal(args.next()?)?.to_string(),
+        ..Default::default()
+    };
+    for arg in args {
+        match arg.split_once('=') {
+            Some((key, value)) => match key.trim() {
+                "sha256" => version.sha256 = string_literal(value).map(String::from),
+                "url" => version.url = string_literal(value).map(String::from),
+                _ => (),
+            },
+            // old packages pass the checksum as second positional argument
+            None => {
+                if let Some(value) = string_literal(arg).filter(|v| v.len() == 64) {
+                    version.sha256 = Some(value.to_string());
+                }
+            }
+        }
+    }
+    Some(version)
+}
+
+/// A constrained parse of a spack `package.py`, only literal values are read
+pub fn parse(script: &str) -> Package {
+    let mut package = Package::default();
+    let mut rest = script;
+    while let Some(idx) = rest.find('\n') {
+        let line = &rest[..idx];
+        let trimmed = line.trim_start();
+        let class_level = line.len() - trimmed.len() == 4;
+
+        if class_level && trimmed.starts_with("def url_for_version(") {
+            package.url_for_version = true;
+        } else if let Some(value) = trimmed.strip_prefix("url = ").filter(|_| class_level) {
+            package.url = string_literal(value).map(String::from);
+        } else if let Some(call) = trimmed.strip_prefix("version(").filter(|_| class_level) {
+            // the call may span multiple lines
+            if let Some(args) = call_args(&rest[line.len() - call.len()..]) {
+                package.versions.extend(parse_version(args));
+            }
+        }
+        rest = &rest[idx + 1..];
+    }
+    package
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package() {
+        let script = r#"# Copyright Spack Project Developers. See COPYRIGHT file for details.
+#
+# SPDX-License-Identifier: (Apache-2.0 OR MIT)
+
+from spack.package import *
+
+
+class Zlib(MakefilePackage, Package):
+    """A free, general-purpose, legally unencumbered lossless
+    data-compression library.
+    """
+
+    homepage = "https://zlib.net"
+    # URL must remain http:// so Spack can bootstrap curl
+    url = "http://zlib.net/fossils/zlib-1.2.11.tar.gz"
+    git = "https://github.com/madler/zlib.git"
+
+    version("1.3.1", sha256="9a93b2b7dfdac77ceba5a558a580e74667dd6fede4585b91eefb60f03b72df23")
+    version(
+        "1.2.13",
+        sha256="b3a24de97a8fdbc835b9833169501030b8977031bcb54b3b3ac13740f846ab30",
+        deprecated=True,
+    )
+    version("1.2.11", "c3e5e9fdd5004dcb542feda5ee4f0ff0744628baf8ed2dd5d66f8ca1197cb1a1")
+    version("develop", branch="develop")
+    version(
+        "1.2.8",
+        sha256="36658cb768a54c1d4dec43c3116c27ed893e88b02ecfcb44f2166f9c0b7f2a0d",
+        url="https://zlib.net/fossils/zlib-1.2.8.tar.gz",
+    )
+
+    variant("pic", default=True, description="Produce position-independent code")
+
+    def install(self, spec, prefix):
+        version("0.0", sha256="not this")
+"#;
+        let package = parse(script);
+        assert_eq!(
+            package.url.as_deref(),
+            Some("http://zlib.net/fossils/zlib-1.2.11.tar.gz")
+        );
+        assert_eq!(package.versions.len(), 5);
+        assert_eq!(
+            package.artifacts(),
+            [
+                Artifact {
+                    version: "1.3.1".to_string(),
+                    url: "http://zlib.net/fossils/zlib-1.3.1.tar.gz".to_string(),
+                    sha256: "9a93b2b7dfdac77ceba5a558a580e74667dd6fede4585b91eefb60f03b72df23"
+                        .to_string(),
+                },
+                Artifact {
+                    version: "1.2.13".to_string(),
+                    url: "http://zlib.net/fossils/zlib-1.2.13.tar.gz".to_string(),
+                    sha256: "b3a24de97a8fdbc835b9833169501030b8977031bcb54b3b3ac13740f846ab30"
+                        .to_string(),
+                },
+                Artifact {
+                    version: "1.2.11".to_string(),
+                    url: "http://zlib.net/fossils/zlib-1.2.11.tar.gz".to_string(),
+                    sha256: "c3e5e9fdd5004dcb542feda5ee4f0ff0744628baf8ed2dd5d66f8ca1197cb1a1"
+                        .to_string(),
+                },
+                Artifact {
+                    version: "1.2.8".to_string(),
+                    url: "https://zlib.net/fossils/zlib-1.2.8.tar.gz".to_string(),
+                    sha256: "36658cb768a54c1d4dec43c3116c27ed893e88b02ecfcb44f2166f9c0b7f2a0d"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_url_for_version() {
+        let script = r#"class Foo(Package):
+    url = "https://example.com/foo-1.0.tar.gz"
+
+    version("1.1", sha256="0000000000000000000000000000000000000000000000000000000000000000")
+    version("1.0", sha256="1111111111111111111111111111111111111111111111111111111111111111")
+
+    def url_for_version(self, version):
+        return f"https://example.com/{version.up_to(1)}/foo-{version}.tar.gz"
+"#;
+        let package = parse(script);
+        assert!(package.url_for_version);
+        assert_eq!(package.artifacts(), []);
+    }
+}
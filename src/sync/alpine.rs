@@ -4,7 +4,7 @@ use crate::errors::*;
 use crate::utils;
 use async_compression::tokio::bufread::GzipDecoder;
 use futures::{Stream, StreamExt};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 use tokio::io::{self, AsyncBufReadExt, AsyncRead};
 use tokio_tar::{Archive, EntryType};
@@ -55,11 +55,39 @@ fn parse<R: AsyncRead + Unpin>(reader: R) -> impl Stream<Item = Result<Pkg>> {
     }
 }
 
-pub async fn run(args: &args::SyncAlpine) -> Result<()> {
-    let db = db::Client::create().await?;
-    let vendor = &args.vendor;
+/// The APKINDEX files to read, with the repository their APKBUILDs are located in
+fn indexes(args: &args::SyncAlpine) -> Result<Vec<(String, Option<String>)>> {
+    if args.arches.is_empty() {
+        if args.repos.len() > 1 {
+            return Err(Error::AlpineMultipleRepos);
+        }
+        return Ok(vec![(args.file.clone(), args.repos.first().cloned())]);
+    }
+
+    let base = args.file.trim_end_matches('/');
+    let mut indexes = Vec::new();
+    if args.repos.is_empty() {
+        // wolfi has no repositories, only architectures
+        for arch in &args.arches {
+            indexes.push((format!("{base}/{arch}/APKINDEX.tar.gz"), None));
+        }
+    }
+    for repo in &args.repos {
+        for arch in &args.arches {
+            let path = format!("{base}/{repo}/{arch}/APKINDEX.tar.gz");
+            indexes.push((path, Some(repo.clone())));
+        }
+    }
+    Ok(indexes)
+}
 
-    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+async fn sync_index(
+    db: &db::Client,
+    vendor: &str,
+    repo: Option<&String>,
+    reader: Box<dyn AsyncRead + Send + Unpin>,
+    seen: &mut HashSet<(String, String)>,
+) -> Result<()> {
     let mut reader = io::BufReader::new(reader);
     {
         // discard first part of apkv2
@@ -92,11 +120,16 @@ pub async fn run(args: &args::SyncAlpine) -> Result<()> {
             let version = pkg.version;
             let Some(commit) = pkg.commit else { continue };
 
-            // mark all refs known for this package as "last_seen now"
-            db.bump_named_refs(vendor, &origin, &version).await?;
+            // subpackages and other architectures share the APKBUILD of their origin
+            if !seen.insert((origin.clone(), commit.clone())) {
+                continue;
+            }
 
-            // check if package already imported
-            if db.get_package(vendor, &origin, &commit).await?.is_some() {
+            // mark all refs known for this package as "last_seen now", skip if already imported
+            if !db
+                .refresh_package(vendor, &origin, &version, &commit)
+                .await?
+            {
                 debug!("Package is already imported: vendor={vendor:?} origin={origin:?} commit={commit:?}");
                 continue;
             }
@@ -106,8 +139,8 @@ pub async fn run(args: &args::SyncAlpine) -> Result<()> {
             db.insert_task(&db::Task::new(
                 format!("{vendor}-apkbuild:{origin}:{commit}"),
                 &db::TaskData::ApkbuildGit {
-                    vendor: vendor.clone(),
-                    repo: args.repo.clone(),
+                    vendor: vendor.to_string(),
+                    repo: repo.cloned(),
                     origin,
                     version,
                     commit,
@@ -120,6 +153,20 @@ pub async fn run(args: &args::SyncAlpine) -> Result<()> {
     Ok(())
 }
 
+pub async fn run(args: &args::SyncAlpine) -> Result<()> {
+    let indexes = indexes(args)?;
+    let db = db::Client::create().await?;
+
+    let mut seen = HashSet::new();
+    for (path, repo) in &indexes {
+        info!("Reading index: {path:?}");
+        let reader = utils::fetch_or_open(path, args.fetch).await?;
+        sync_index(&db, &args.vendor, repo.as_ref(), reader, &mut seen).await?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +230,66 @@ p:cmd:aaudit=0.7.2-r3
             ][..]
         );
     }
+
+    fn sync_args(repos: &[&str], arches: &[&str], file: &str) -> args::SyncAlpine {
+        args::SyncAlpine {
+            vendor: "alpine".to_string(),
+            repos: repos.iter().map(|s| s.to_string()).collect(),
+            arches: arches.iter().map(|s| s.to_string()).collect(),
+            fetch: true,
+            file: file.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_indexes() {
+        let args = sync_args(
+            &["main", "community"],
+            &["x86_64", "aarch64"],
+            "https://dl-cdn.alpinelinux.org/alpine/edge/",
+        );
+        assert_eq!(
+            indexes(&args).unwrap(),
+            [
+                (
+                    "https://dl-cdn.alpinelinux.org/alpine/edge/main/x86_64/APKINDEX.tar.gz"
+                        .to_string(),
+                    Some("main".to_string())
+                ),
+                (
+                    "https://dl-cdn.alpinelinux.org/alpine/edge/main/aarch64/APKINDEX.tar.gz"
+                        .to_string(),
+                    Some("main".to_string())
+                ),
+                (
+                    "https://dl-cdn.alpinelinux.org/alpine/edge/community/x86_64/APKINDEX.tar.gz"
+                        .to_string(),
+                    Some("community".to_string())
+                ),
+                (
+                    "https://dl-cdn.alpinelinux.org/alpine/edge/community/aarch64/APKINDEX.tar.gz"
+                        .to_string(),
+                    Some("community".to_string())
+                ),
+            ]
+        );
+
+        let args = sync_args(&[], &["x86_64"], "https://packages.wolfi.dev/os");
+        assert_eq!(
+            indexes(&args).unwrap(),
+            [(
+                "https://packages.wolfi.dev/os/x86_64/APKINDEX.tar.gz".to_string(),
+                None
+            )]
+        );
+
+        let args = sync_args(&["main"], &[], "APKINDEX.tar.gz");
+        assert_eq!(
+            indexes(&args).unwrap(),
+            [("APKINDEX.tar.gz".to_string(), Some("main".to_string()))]
+        );
+
+        let args = sync_args(&["main", "community"], &[], "APKINDEX.tar.gz");
+        assert!(indexes(&args).is_err());
+    }
 }
@@ -1,29 +1,73 @@
 use crate::apt;
 use crate::args;
+use crate::chksums;
 use crate::compression::Decompressor;
 use crate::db::{self, Task, TaskData};
 use crate::errors::*;
+use crate::signature;
+use crate::sync::cache;
 use crate::utils;
 use apt_parser::Release;
+use sqlx::types::chrono::{DateTime, Utc};
 use tokio::io::{self, AsyncReadExt};
 
-async fn find_source_index_path(
+/// The pocket that holds the packages a release was published with
+const POCKET_RELEASE: &str = "release";
+
+/// The directory of a pocket in `dists/`, e.g. `bookworm-security` or `noble-updates`
+fn dist_name(release: &str, pocket: &str) -> String {
+    if pocket == POCKET_RELEASE {
+        release.to_string()
+    } else {
+        format!("{release}-{pocket}")
+    }
+}
+
+/// Download the Release file of a release, with `keyring` only the signed InRelease file is accepted
+///
+/// Returns the fingerprint of the signer, if the signature was verified.
+async fn fetch_release(
     http: &utils::HttpClient,
     url: &str,
-    suite: &str,
-) -> Result<(String, &'static str)> {
-    let mut reader = http.fetch(url).await?;
+    keyring: Option<&str>,
+) -> Result<(Release, Option<String>)> {
+    let Some(keyring) = keyring else {
+        let url = format!("{url}/Release");
+        info!("Fetching Release file: url={url:?}");
+        let mut buf = String::new();
+        http.fetch(&url).await?.read_to_string(&mut buf).await?;
+        return Ok((Release::from(&buf)?, None));
+    };
 
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf).await?;
-    let release = Release::from(&buf)?;
+    let url = format!("{url}/InRelease");
+    info!("Fetching InRelease file: url={url:?}");
+    let mut buf = Vec::new();
+    http.fetch(&url).await?.read_to_end(&mut buf).await?;
 
-    for file in release.sha256sum.into_iter().flatten() {
-        let name = file.filename;
+    let (verification, content) = signature::verify_clearsigned(keyring, &buf).await?;
+    if verification.status != signature::STATUS_VALID {
+        return Err(Error::AptInvalidSignature(verification.status));
+    }
+    info!(
+        "Verified InRelease signature: signer={:?}",
+        verification.signer
+    );
+    let content = String::from_utf8_lossy(&content);
+    Ok((Release::from(&content)?, verification.signer))
+}
+
+/// Find the Sources index of a suite, along with its compression and expected checksum
+fn find_source_index_path(
+    release: &Release,
+    suite: &str,
+) -> Result<(String, &'static str, String)> {
+    for file in release.sha256sum.iter().flatten() {
+        let name = &file.filename;
+        let chksum = format!("sha256:{}", file.hash.to_lowercase());
 
         match name.strip_prefix(suite) {
-            Some("/source/Sources.xz") => return Ok((name, "xz")),
-            Some("/source/Sources.gz") => return Ok((name, "gz")),
+            Some("/source/Sources.xz") => return Ok((name.clone(), "xz", chksum)),
+            Some("/source/Sources.gz") => return Ok((name.clone(), "gz", chksum)),
             _ => (),
         }
     }
@@ -31,22 +75,118 @@ async fn find_source_index_path(
     Err(Error::AptIndexMissingSources)
 }
 
+/// The `Date` of a Release file, e.g. `Sat, 12 Oct 2024 20:11:37 UTC`
+fn parse_release_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    let date = date
+        .strip_suffix(" UTC")
+        .map(|date| format!("{date} +0000"))
+        .unwrap_or_else(|| date.to_string());
+    let date = DateTime::parse_from_rfc2822(&date).ok()?;
+    Some(date.with_timezone(&Utc))
+}
+
+/// The url of the .dsc of this source package, if there is one
+fn dsc_url(base_url: &str, pkg: &apt::SourcePkg) -> Option<String> {
+    let directory = pkg.directory.as_ref()?;
+    let dsc = pkg
+        .checksums_sha256
+        .iter()
+        .find(|entry| entry.filename.ends_with(".dsc"))?;
+    Some(format!("{base_url}/{directory}/{}", dsc.filename))
+}
+
+/// Queue the import of the .dsc files, unless the version has already been imported
+async fn queue_dscs(
+    db: &db::Client,
+    vendor: &str,
+    base_url: &str,
+    pkgs: &[apt::SourcePkg],
+) -> Result<()> {
+    let candidates = pkgs
+        .iter()
+        .filter_map(|pkg| {
+            let version = pkg.version.clone()?;
+            let url = dsc_url(base_url, pkg)?;
+            Some((pkg.package.clone(), version, url))
+        })
+        .collect::<Vec<_>>();
+
+    let changed = db
+        .refresh_packages(
+            vendor,
+            &candidates
+                .iter()
+                .map(|(package, version, _)| (package.clone(), version.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    let mut tasks = Vec::new();
+    for ((package, version, url), changed) in candidates.into_iter().zip(changed) {
+        if !changed {
+            continue;
+        }
+        info!("Queueing source package: url={url:?}");
+        tasks.push(Task::new(
+            format!("debian-source:{url}"),
+            &TaskData::DebianSource {
+                vendor: vendor.to_string(),
+                package,
+                version,
+                url,
+            },
+        )?);
+    }
+    db.insert_tasks(&tasks).await?;
+    Ok(())
+}
+
 pub async fn run(args: &args::SyncApt) -> Result<()> {
     let base_url = args.url.strip_suffix('/').unwrap_or(&args.url);
 
     let db = db::Client::create().await?;
     let http = utils::http_client(None)?;
 
-    for release in &args.releases {
+    let dists = args.releases.iter().flat_map(|release| {
+        args.pockets
+            .iter()
+            .map(move |pocket| (pocket, dist_name(release, pocket)))
+    });
+    for (pocket, dist) in dists {
+        let url = format!("{base_url}/dists/{dist}");
+        let (release_file, signer) = fetch_release(&http, &url, args.keyring.as_deref()).await?;
+        let snapshot_date = release_file.date.as_deref().and_then(parse_release_date);
+        info!("Release was published at {snapshot_date:?}");
+
         for suite in &args.suites {
-            let url = format!("{base_url}/dists/{release}/Release");
-            info!("Fetching Release file: url={url:?}");
-            let (filename, compression) = find_source_index_path(&http, &url, suite).await?;
+            let (filename, compression, expected) =
+                match find_source_index_path(&release_file, suite) {
+                    Ok(index) => index,
+                    // pockets like -updates only carry the components that have uploads
+                    Err(Error::AptIndexMissingSources) if pocket != POCKET_RELEASE => {
+                        warn!("No Sources index for suite={suite:?} in {dist:?}, skipping");
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
 
-            let url = format!("{base_url}/dists/{release}/{filename}");
+            let url = format!("{base_url}/dists/{dist}/{filename}");
             info!("Fetching Sources index: url={url:?}");
-            let reader = http.fetch(&url).await?;
-            let reader = io::BufReader::new(reader);
+            let force = args.force || args.reindex;
+            let Some(metadata) = cache::fetch_if_changed(&db, &http, &url, force).await? else {
+                continue;
+            };
+            // the signature only covers the Release file, the index is pinned by its checksum
+            let chksum = chksums::sha256(&metadata.body);
+            if chksum != expected {
+                return Err(Error::ChecksumMismatch {
+                    expected,
+                    actual: chksum,
+                });
+            }
+            let metadata = metadata.snapshot(snapshot_date, signer.clone());
+            let reader = io::BufReader::new(&metadata.body[..]);
             let mut reader = match compression {
                 "gz" => Decompressor::gz(reader),
                 "xz" => Decompressor::xz(reader),
@@ -58,10 +198,16 @@ pub async fn run(args: &args::SyncApt) -> Result<()> {
 
             let sources = apt::SourcesIndex::parse(&buf)?;
 
+            if args.patches {
+                queue_dscs(&db, &args.vendor, base_url, &sources.pkgs).await?;
+            }
+
+            let mut refs = Vec::new();
+            let mut urls = Vec::new();
             for pkg in &sources.pkgs {
                 debug!("pkg={pkg:?}");
-                pkg.version.as_ref().unwrap();
-                pkg.directory.as_ref().unwrap();
+                let version = pkg.version.as_ref().unwrap();
+                let directory = pkg.directory.as_ref().unwrap();
 
                 for entry in &pkg.checksums_sha256 {
                     let name = entry.filename.clone();
@@ -71,42 +217,100 @@ pub async fn run(args: &args::SyncApt) -> Result<()> {
                     {
                         let chksum = format!("sha256:{}", entry.hash);
                         let package = pkg.package.to_string();
-                        let version = pkg.version.clone().unwrap();
                         info!(
                             "digest={chksum:?} package={package:?} version={version:?} name={name:?}"
                         );
-                        let obj = db::Ref {
+                        if !name.starts_with("chromium_") {
+                            urls.push((chksum.clone(), format!("{base_url}/{directory}/{name}")));
+                        }
+                        refs.push(db::Ref {
                             chksum,
                             vendor: args.vendor.to_string(),
                             package,
-                            version,
-                            filename: Some(name.clone()),
-                        };
-                        db.insert_ref(&obj).await?;
+                            version: version.clone(),
+                            filename: Some(name),
+                        });
+                    }
+                }
+            }
+            db.insert_refs(&refs).await?;
+            db.insert_ref_suites(&refs, &dist, suite).await?;
 
-                        if name.starts_with("chromium_") {
-                            continue;
-                        }
+            let known = if args.reindex {
+                Default::default()
+            } else {
+                let chksums = urls
+                    .iter()
+                    .map(|(chksum, _)| chksum.clone())
+                    .collect::<Vec<_>>();
+                db.resolve_artifacts_bulk(&chksums).await?
+            };
 
-                        if args.reindex || db.resolve_artifact(&obj.chksum).await?.is_none() {
-                            let directory = pkg.directory.as_ref().unwrap();
-                            let url = format!("{base_url}/{directory}/{name}");
-                            info!("url={url:?}");
-                            db.insert_task(&Task::new(
-                                format!("fetch:{url}"),
-                                &TaskData::FetchTar {
-                                    url,
-                                    compression: None,
-                                    success_ref: None,
-                                },
-                            )?)
-                            .await?;
-                        }
-                    }
+            let mut tasks = Vec::new();
+            for (chksum, url) in urls {
+                if known.contains_key(&chksum) {
+                    continue;
                 }
+                info!("url={url:?}");
+                tasks.push(Task::new(
+                    format!("fetch:{url}"),
+                    &TaskData::FetchTar {
+                        url,
+                        compression: None,
+                        success_ref: None,
+                    },
+                )?);
             }
+            db.insert_tasks(&tasks).await?;
+            metadata.done(&db).await?;
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dist_name() {
+        assert_eq!(dist_name("bookworm", POCKET_RELEASE), "bookworm");
+        assert_eq!(dist_name("bookworm", "security"), "bookworm-security");
+        assert_eq!(dist_name("noble", "updates"), "noble-updates");
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        let date = parse_release_date("Sat, 12 Oct 2024 20:11:37 UTC").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-10-12T20:11:37+00:00");
+        assert_eq!(parse_release_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_find_source_index_path() {
+        let release = Release::from(
+            "Origin: Debian
+Suite: unstable
+Codename: sid
+Date: Sat, 12 Oct 2024 20:11:37 UTC
+Architectures: all amd64
+Components: main contrib
+SHA256:
+ 0c8b5b3b6b2fd0e3a7a5b0b2e36d39b7d0ee70a1b7f2f7b0c8a9ec3c48a3c4b1 12345 contrib/source/Sources.xz
+ B4E8FB7E5E7A0A4E3B7C1B98C1E5A6E1D4E0F2A3C4B5D6E7F8091A2B3C4D5E6F 67890 main/source/Sources.xz
+",
+        )
+        .unwrap();
+        assert_eq!(
+            find_source_index_path(&release, "main").unwrap(),
+            (
+                "main/source/Sources.xz".to_string(),
+                "xz",
+                "sha256:b4e8fb7e5e7a0a4e3b7c1b98c1e5a6e1d4e0f2a3c4b5d6e7f8091a2b3c4d5e6f"
+                    .to_string()
+            )
+        );
+        assert!(find_source_index_path(&release, "non-free").is_err());
+    }
+}
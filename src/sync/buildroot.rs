@@ -0,0 +1,144 @@
+use crate::args;
+use crate::buildroot;
+use crate::db;
+use crate::errors::*;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::Archive;
+
+#[derive(Debug, Default)]
+struct Files {
+    mk: Option<String>,
+    hash: Option<String>,
+}
+
+/// Match `<prefix>/package/<name>/<name>.{mk,hash}`
+fn metadata_from_path(path: &Path) -> Option<(&str, &str)> {
+    let path = path.to_str()?;
+    let (_, path) = path.split_once("/package/")?;
+    let (package, filename) = path.split_once('/')?;
+    let (name, extension) = filename.rsplit_once('.')?;
+    (name == package).then_some((package, extension))
+}
+
+pub async fn run(args: &args::SyncBuildroot) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    // the .mk and .hash file of a package may show up in any order
+    let mut packages = BTreeMap::<String, Files>::new();
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let Some((package, extension)) = metadata_from_path(&path) else {
+            continue;
+        };
+        let package = package.to_string();
+        let extension = extension.to_string();
+
+        let mut buf = String::new();
+        entry.read_to_string(&mut buf).await?;
+
+        let files = packages.entry(package).or_default();
+        match extension.as_str() {
+            "mk" => files.mk = Some(buf),
+            "hash" => files.hash = Some(buf),
+            _ => (),
+        }
+    }
+
+    let mut errors = 0;
+    for (package, files) in packages {
+        let Some(mk) = files.mk else {
+            continue;
+        };
+        let hash = files.hash.unwrap_or_default();
+
+        let artifact = match buildroot::parse(&package, &mk, &hash) {
+            Ok(Some(artifact)) => artifact,
+            Ok(None) => {
+                debug!("No upstream source found for package={package:?}");
+                continue;
+            }
+            Err(err) => {
+                error!("Failed to parse package={package:?}: {err:#}");
+                errors += 1;
+                continue;
+            }
+        };
+
+        let chksum = match (artifact.chksum, artifact.commit) {
+            (Some(chksum), _) => chksum,
+            (_, Some(commit)) => format!("git:{commit}"),
+            _ => continue,
+        };
+
+        let task = if db.resolve_artifact(&chksum).await?.is_none() {
+            utils::task_for_ref(&artifact.url, &chksum)
+        } else {
+            None
+        };
+
+        let r = db::Ref {
+            chksum,
+            vendor: vendor.to_string(),
+            package: package.to_string(),
+            version: artifact.version,
+            filename: Some(artifact.url),
+        };
+        debug!("insert: {r:?}");
+        db.insert_ref(&r).await?;
+
+        if let Some(task) = task {
+            info!("Adding task: {task:?}");
+            db.insert_task(&task).await?;
+        }
+    }
+
+    if errors > 0 {
+        warn!("Encounted {errors} errors while processing snapshot");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_from_path() {
+        assert_eq!(
+            metadata_from_path(Path::new("buildroot-2024.08/package/xz/xz.mk")),
+            Some(("xz", "mk"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("buildroot-2024.08/package/xz/xz.hash")),
+            Some(("xz", "hash"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("buildroot-2024.08/package/xz/Config.in")),
+            None
+        );
+        assert_eq!(
+            metadata_from_path(Path::new(
+                "buildroot-2024.08/package/xz/0001-fix-build.patch"
+            )),
+            None
+        );
+    }
+}
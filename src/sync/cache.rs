@@ -0,0 +1,104 @@
+use crate::chksums;
+use crate::db::{self, SyncState};
+use crate::errors::*;
+use crate::utils::HttpClient;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Upstream metadata that changed since the last sync
+#[derive(Debug)]
+pub struct Metadata {
+    pub body: Vec<u8>,
+    state: SyncState,
+}
+
+impl Metadata {
+    /// Record when and by whom the metadata was published
+    pub fn snapshot(mut self, date: Option<DateTime<Utc>>, signer: Option<String>) -> Self {
+        self.state.snapshot_date = date;
+        self.state.signer = signer;
+        self
+    }
+
+    /// Remember this metadata as processed, only call this once the sync went through
+    pub async fn done(self, db: &db::Client) -> Result<()> {
+        db.insert_sync_state(&self.state).await
+    }
+}
+
+fn is_unchanged(previous: Option<&SyncState>, current: &SyncState) -> bool {
+    previous.is_some_and(|previous| previous.digest == current.digest)
+}
+
+/// Download the metadata, unless the server or the digest tell us we've already processed it
+///
+/// With `force` the previous state is ignored.
+pub async fn fetch_if_changed(
+    db: &db::Client,
+    http: &HttpClient,
+    url: &str,
+    force: bool,
+) -> Result<Option<Metadata>> {
+    let previous = if force {
+        None
+    } else {
+        db.get_sync_state(url).await?
+    };
+
+    let Some(resp) = http
+        .fetch_conditional(
+            url,
+            previous.as_ref().and_then(|p| p.etag.as_deref()),
+            previous.as_ref().and_then(|p| p.last_modified.as_deref()),
+        )
+        .await?
+    else {
+        info!("Metadata was not modified since last sync: url={url:?}");
+        return Ok(None);
+    };
+
+    let state = SyncState {
+        url: url.to_string(),
+        etag: resp.etag,
+        last_modified: resp.last_modified,
+        digest: chksums::sha256(&resp.body),
+        snapshot_date: None,
+        signer: None,
+    };
+    if is_unchanged(previous.as_ref(), &state) {
+        info!("Metadata is unchanged since last sync: url={url:?}");
+        // keep the new validators, so the next run can use a conditional request
+        db.insert_sync_state(&state).await?;
+        return Ok(None);
+    }
+
+    Ok(Some(Metadata {
+        body: resp.body,
+        state,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unchanged() {
+        let state = SyncState {
+            url: "https://deb.debian.org/debian/dists/sid/main/source/Sources.xz".to_string(),
+            etag: Some("\"5f2a0b1\"".to_string()),
+            last_modified: None,
+            digest: "sha256:aaaa".to_string(),
+            snapshot_date: None,
+            signer: None,
+        };
+        assert!(!is_unchanged(None, &state));
+        assert!(is_unchanged(Some(&state), &state));
+
+        let changed = SyncState {
+            etag: None,
+            digest: "sha256:bbbb".to_string(),
+            ..state.clone()
+        };
+        assert!(!is_unchanged(Some(&state), &changed));
+    }
+}
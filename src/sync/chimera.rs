@@ -0,0 +1,121 @@
+use crate::args;
+use crate::chimera;
+use crate::db;
+use crate::errors::*;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use std::path::Path;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::Archive;
+
+/// Match `<prefix>/<repo>/<name>/template.py`, subpackages are symlinks and don't match
+fn metadata_from_path(path: &Path) -> Option<(&str, &str)> {
+    let path = path.to_str()?;
+    let path = path.strip_suffix("/template.py")?;
+    let mut parts = path.rsplit('/');
+    let package = parts.next()?;
+    let repo = parts.next()?;
+    // the top-level folder of the snapshot
+    parts.next()?;
+    parts.next().is_none().then_some((repo, package))
+}
+
+pub async fn run(args: &args::SyncChimera) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    let mut errors = 0;
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let Some((repo, package)) = metadata_from_path(&path) else {
+            continue;
+        };
+        let repo = repo.to_string();
+        let package = package.to_string();
+
+        let mut buf = String::new();
+        entry.read_to_string(&mut buf).await?;
+
+        let template = match chimera::parse(&buf) {
+            Ok(template) => template,
+            Err(err) => {
+                error!("Failed to parse repo={repo:?} package={package:?}: {err:#}");
+                errors += 1;
+                continue;
+            }
+        };
+        let version = template.version();
+
+        for (url, sha256) in template.artifacts() {
+            if sha256.len() != 64 {
+                warn!("Unexpected checksum length for Chimera Linux: {sha256:?}");
+                continue;
+            }
+            let chksum = format!("sha256:{sha256}");
+
+            let task = if db.resolve_artifact(&chksum).await?.is_none() {
+                utils::task_for_url(url)
+            } else {
+                None
+            };
+
+            let r = db::Ref {
+                chksum,
+                vendor: vendor.to_string(),
+                package: template.pkgname.clone(),
+                version: version.clone(),
+                filename: Some(url.to_string()),
+            };
+            debug!("insert: {r:?}");
+            db.insert_ref(&r).await?;
+
+            if let Some(task) = task {
+                info!("Adding task: {task:?}");
+                db.insert_task(&task).await?;
+            }
+        }
+    }
+
+    if errors > 0 {
+        warn!("Encounted {errors} errors while processing snapshot");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_from_path() {
+        assert_eq!(
+            metadata_from_path(Path::new("cports-master/main/xz/template.py")),
+            Some(("main", "xz"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("cports-master/user/ripgrep/template.py")),
+            Some(("user", "ripgrep"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("cports-master/src/cbuild/core/template.py")),
+            None
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("cports-master/main/xz/update.py")),
+            None
+        );
+    }
+}
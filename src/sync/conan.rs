@@ -0,0 +1,216 @@
+use crate::args;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::{Archive, EntryType};
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct ConanData {
+    #[serde(default)]
+    pub sources: BTreeMap<String, Sources>,
+}
+
+/// A version either has a single source or a list of them (e.g. source code and license file)
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Sources {
+    Single(Source),
+    Multiple(Vec<Source>),
+}
+
+impl Sources {
+    pub fn iter(&self) -> impl Iterator<Item = &Source> {
+        match self {
+            Sources::Single(source) => std::slice::from_ref(source).iter(),
+            Sources::Multiple(list) => list.iter(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Source {
+    pub url: Urls,
+    pub sha256: Option<String>,
+}
+
+/// Mirrors for the same file, the first one is the canonical one
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Urls {
+    Single(String),
+    Mirrors(Vec<String>),
+}
+
+impl Urls {
+    pub fn first(&self) -> Option<&str> {
+        match self {
+            Urls::Single(url) => Some(url),
+            Urls::Mirrors(list) => list.first().map(String::as_str),
+        }
+    }
+}
+
+async fn import_recipe(
+    db: &db::Client,
+    vendor: &str,
+    package: &str,
+    data: &ConanData,
+) -> Result<()> {
+    for (version, sources) in &data.sources {
+        for source in sources.iter() {
+            let (Some(url), Some(sha256)) = (source.url.first(), &source.sha256) else {
+                continue;
+            };
+            let chksum = format!("sha256:{}", sha256.to_lowercase());
+            debug!("package={package:?} version={version:?} url={url:?} ({chksum})");
+
+            if !utils::is_possible_tar_artifact(url) {
+                continue;
+            }
+
+            if db.resolve_artifact(&chksum).await?.is_none() {
+                info!("Adding download task: url={url:?}");
+                db.insert_task(&Task::new(
+                    format!("fetch:{url}"),
+                    &TaskData::FetchTar {
+                        url: url.to_string(),
+                        compression: None,
+                        success_ref: None,
+                    },
+                )?)
+                .await?;
+            }
+
+            let obj = db::Ref {
+                chksum,
+                vendor: vendor.to_string(),
+                package: package.to_string(),
+                version: version.to_string(),
+                filename: Some(url.to_string()),
+            };
+            info!("insert: {obj:?}");
+            db.insert_ref(&obj).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::SyncConan) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+
+        let path = entry.path()?.into_owned();
+        // the snapshot is expected to contain a single top-level directory,
+        // recipes are stored like `recipes/<name>/<folder>/conandata.yml`
+        let mut components = path.iter().skip(1);
+        if components.next() != Some("recipes".as_ref()) {
+            continue;
+        }
+        let (Some(package), Some(_folder), Some(filename), None) = (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) else {
+            continue;
+        };
+        if filename != "conandata.yml" {
+            continue;
+        }
+        let package = package.to_string_lossy().into_owned();
+
+        let mut buf = String::new();
+        entry.read_to_string(&mut buf).await?;
+        let data = match serde_yaml::from_str::<ConanData>(&buf) {
+            Ok(data) => data,
+            Err(err) => {
+                warn!("Failed to parse conandata.yml of recipe {package:?}: {err:#}");
+                continue;
+            }
+        };
+
+        import_recipe(&db, &args.vendor, &package, &data).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conandata() {
+        let data = r#"sources:
+  "1.3.1":
+    url:
+      - "https://zlib.net/fossils/zlib-1.3.1.tar.gz"
+      - "https://github.com/madler/zlib/releases/download/v1.3.1/zlib-1.3.1.tar.gz"
+    sha256: "9a93b2b7dfdac77ceba5a558a580e74667dd6fede4585b91eefb60f03b72df23"
+  "1.2.13":
+    url: "https://zlib.net/fossils/zlib-1.2.13.tar.gz"
+    sha256: "B3A24DE97A8FDBC835B9833169501030B8977031BCB54B3B3AC13740F846AB30"
+  "2.0.0":
+    - url: "https://example.com/foo-2.0.0.tar.gz"
+      sha256: "1111111111111111111111111111111111111111111111111111111111111111"
+    - url: "https://example.com/LICENSE"
+      sha256: "2222222222222222222222222222222222222222222222222222222222222222"
+patches:
+  "1.2.13":
+    - patch_file: "patches/1.2.13-0001-fix-cmake.patch"
+      patch_description: "separate static/shared builds"
+"#;
+        let data = serde_yaml::from_str::<ConanData>(data).unwrap();
+        let sources = data
+            .sources
+            .iter()
+            .flat_map(|(version, sources)| {
+                sources
+                    .iter()
+                    .map(move |s| (version.as_str(), s.url.first(), s.sha256.as_deref()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [
+                (
+                    "1.2.13",
+                    Some("https://zlib.net/fossils/zlib-1.2.13.tar.gz"),
+                    Some("B3A24DE97A8FDBC835B9833169501030B8977031BCB54B3B3AC13740F846AB30")
+                ),
+                (
+                    "1.3.1",
+                    Some("https://zlib.net/fossils/zlib-1.3.1.tar.gz"),
+                    Some("9a93b2b7dfdac77ceba5a558a580e74667dd6fede4585b91eefb60f03b72df23")
+                ),
+                (
+                    "2.0.0",
+                    Some("https://example.com/foo-2.0.0.tar.gz"),
+                    Some("1111111111111111111111111111111111111111111111111111111111111111")
+                ),
+                (
+                    "2.0.0",
+                    Some("https://example.com/LICENSE"),
+                    Some("2222222222222222222222222222222222222222222222222222222222222222")
+                ),
+            ]
+        );
+    }
+}
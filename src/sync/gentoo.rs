@@ -4,7 +4,7 @@ use crate::errors::*;
 use crate::utils;
 use async_compression::tokio::bufread::GzipDecoder;
 use futures::StreamExt;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use tokio::io::{self, AsyncBufReadExt, AsyncReadExt};
 use tokio_tar::{Archive, EntryType};
 
@@ -59,44 +59,65 @@ pub struct Metadata {
     pub inputs: BTreeMap<String, String>,
 }
 
-fn parse_metadata(data: &str) -> Result<Metadata> {
-    let mut metadata = Metadata::default();
-
-    let mut value = data
-        .lines()
-        .filter_map(|line| line.strip_prefix("SRC_URI="))
-        .next()
-        .unwrap_or("");
+/// Parse the dependency-spec syntax of SRC_URI, USE-conditional groups like `foo? ( url )` are
+/// flattened so every variant is included
+fn parse_src_uri(value: &str) -> Vec<(&str, &str)> {
+    let mut out = Vec::new();
+    let mut tokens = value.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        if matches!(token, "(" | ")" | "||") || token.ends_with('?') {
+            continue;
+        }
 
-    while !value.is_empty() {
-        let (url, remaining) = value.split_once(' ').unwrap_or((value, ""));
+        // EAPI 8 allows overriding RESTRICT per url
+        let url = token
+            .strip_prefix("fetch+")
+            .or_else(|| token.strip_prefix("mirror+"))
+            .unwrap_or(token);
 
-        let (filename, remaining) = if let Some(remaining) = remaining.strip_prefix("-> ") {
-            remaining.split_once(' ').unwrap_or((remaining, ""))
+        let filename = if tokens.next_if_eq(&"->").is_some() {
+            tokens.next()
         } else {
-            let (_, filename) = url.rsplit_once('/').unwrap_or(("", url));
-            (filename, remaining)
+            None
         };
+        let filename =
+            filename.unwrap_or_else(|| url.rsplit_once('/').map(|(_, f)| f).unwrap_or(url));
 
-        let remaining = remaining.strip_prefix("verify-sig? ").unwrap_or(remaining);
+        out.push((filename, url));
+    }
+    out
+}
 
-        let remaining = if remaining.starts_with('(') {
-            remaining
-                .split_once(')')
-                .map(|(_options, remaining)| remaining)
-                .unwrap_or(remaining)
-        } else {
-            remaining
-        };
+fn parse_metadata(data: &str) -> Result<Metadata> {
+    let value = data
+        .lines()
+        .filter_map(|line| line.strip_prefix("SRC_URI="))
+        .next()
+        .unwrap_or("");
 
-        metadata
-            .inputs
-            .insert(filename.to_string(), url.to_string());
+    let inputs = parse_src_uri(value)
+        .into_iter()
+        .map(|(filename, url)| (filename.to_string(), url.to_string()))
+        .collect();
+    Ok(Metadata { inputs })
+}
 
-        value = remaining;
+/// Compare the files of SRC_URI with the DIST entries of the Manifest
+fn cross_check(pkg: &str, data: &Package) {
+    let mut referenced = BTreeSet::new();
+    for (version, metadata) in &data.metadata {
+        for filename in metadata.inputs.keys() {
+            if !data.artifacts.contains_key(filename) {
+                warn!("File is missing from Manifest: pkg={pkg:?} version={version:?} file={filename:?}");
+            }
+            referenced.insert(filename);
+        }
+    }
+    for filename in data.artifacts.keys() {
+        if !referenced.contains(filename) {
+            debug!("Manifest entry isn't referenced by any version: pkg={pkg:?} file={filename:?}");
+        }
     }
-
-    Ok(metadata)
 }
 
 pub fn parse_pkgname_version(filename: &str) -> Result<(&str, &str)> {
@@ -188,6 +209,7 @@ pub async fn run(args: &args::SyncGentoo) -> Result<()> {
     }
 
     for (pkg, data) in index {
+        cross_check(&pkg, &data);
         for (version, metadata) in data.metadata {
             debug!(
                 "Found package: pkg={pkg:?} version={version:?} inputs={:?} known_hashes={:?}",
@@ -380,10 +402,16 @@ _md5_=b977a58ded16b7cd6e10cde4e52ed083
         assert_eq!(
             metadata,
             Metadata {
-                inputs: [(
-                    "mawk-1.3.4-20240123.tgz",
-                    "https://invisible-mirror.net/archives/mawk/mawk-1.3.4-20240123.tgz"
-                )]
+                inputs: [
+                    (
+                        "mawk-1.3.4-20240123.tgz",
+                        "https://invisible-mirror.net/archives/mawk/mawk-1.3.4-20240123.tgz"
+                    ),
+                    (
+                        "mawk-1.3.4-20240123.tgz.asc",
+                        "https://invisible-island.net/archives/mawk/mawk-1.3.4-20240123.tgz.asc"
+                    ),
+                ]
                 .iter()
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect()
@@ -398,4 +426,24 @@ _md5_=b977a58ded16b7cd6e10cde4e52ed083
         let split = parse_pkgname_version("apparmor-utils-3.0.10-r1").unwrap();
         assert_eq!(split, ("apparmor-utils", "3.0.10-r1"));
     }
+
+    #[test]
+    fn test_parse_src_uri_use_conditionals() {
+        let value = "https://example.com/foo-1.0.tar.gz doc? ( https://example.com/foo-docs-1.0.tar.xz ) !system-bar? ( amd64? ( https://example.com/bar/v2.1.tar.gz -> bar-2.1.tar.gz ) arm64? ( fetch+https://example.com/bar-arm64-2.1.tar.gz ) )";
+        assert_eq!(
+            parse_src_uri(value),
+            [
+                ("foo-1.0.tar.gz", "https://example.com/foo-1.0.tar.gz"),
+                (
+                    "foo-docs-1.0.tar.xz",
+                    "https://example.com/foo-docs-1.0.tar.xz"
+                ),
+                ("bar-2.1.tar.gz", "https://example.com/bar/v2.1.tar.gz"),
+                (
+                    "bar-arm64-2.1.tar.gz",
+                    "https://example.com/bar-arm64-2.1.tar.gz"
+                ),
+            ]
+        );
+    }
 }
@@ -0,0 +1,181 @@
+use crate::args;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::ingest;
+use crate::utils::{self, HttpClient};
+use serde::Deserialize;
+use sqlx::types::chrono::Utc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::time::{self, Duration};
+
+pub const VENDOR: &str = "proxy.golang.org";
+pub const PROXY_URL: &str = "https://proxy.golang.org";
+/// The name the position in the index is stored as
+const CURSOR: &str = "goproxy";
+/// How long to wait if the index has no new modules
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// How long to wait if the index is unavailable
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct IndexEntry {
+    pub path: String,
+    pub version: String,
+    pub timestamp: String,
+}
+
+/// The index is served as one json object per line
+pub fn parse_index(txt: &str) -> Result<Vec<IndexEntry>> {
+    let mut entries = Vec::new();
+    for line in txt.lines().filter(|line| !line.is_empty()) {
+        entries.push(serde_json::from_str(line)?);
+    }
+    Ok(entries)
+}
+
+/// Uppercase letters are written as `!` followed by the lowercase letter
+fn escape(txt: &str) -> String {
+    let mut out = String::new();
+    for c in txt.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('!');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub fn module_zip_url(proxy: &str, path: &str, version: &str) -> String {
+    format!("{proxy}/{}/@v/{}.zip", escape(path), escape(version))
+}
+
+pub fn task_for_module(path: &str, version: &str) -> Result<Task> {
+    Task::new(
+        format!("go-module:{path}@{version}"),
+        &TaskData::GoModule {
+            path: path.to_string(),
+            version: version.to_string(),
+        },
+    )
+}
+
+/// Import a downloaded module zip, the go.sum hash is registered as alias during ingest
+pub async fn import_module<R: AsyncRead + Unpin>(
+    db: &db::Client,
+    reader: R,
+    url: &str,
+    path: &str,
+    version: &str,
+) -> Result<()> {
+    let summary = ingest::zip::stream_data(Some(db), reader).await?;
+
+    let r = db::Ref {
+        chksum: summary.outer_digests.sha256,
+        vendor: VENDOR.to_string(),
+        package: path.to_string(),
+        version: version.to_string(),
+        filename: Some(url.to_string()),
+    };
+    debug!("insert: {r:?}");
+    db.insert_ref(&r).await?;
+
+    Ok(())
+}
+
+async fn fetch_index(http: &HttpClient, url: &str) -> Result<Vec<IndexEntry>> {
+    debug!("Downloading url: {url:?}");
+    let mut buf = String::new();
+    http.fetch(url).await?.read_to_string(&mut buf).await?;
+    parse_index(&buf)
+}
+
+pub async fn run(args: &args::SyncGoproxy) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = utils::http_client(None)?;
+
+    let mut since = if let Some(since) = &args.since {
+        since.clone()
+    } else if let Some(cursor) = db.get_sync_cursor(CURSOR).await? {
+        cursor
+    } else {
+        // start with new modules, backfilling the whole index is out of scope
+        Utc::now().format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+    };
+
+    loop {
+        let url = format!("{}?since={since}&limit={}", args.url, args.limit);
+        let entries = match fetch_index(&http, &url).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Failed to fetch module index: {err:#}");
+                time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        for entry in &entries {
+            if db
+                .get_named_ref(VENDOR, &entry.path, &entry.version)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+            info!(
+                "Adding task: path={:?} version={:?}",
+                entry.path, entry.version
+            );
+            db.insert_task(&task_for_module(&entry.path, &entry.version)?)
+                .await?;
+        }
+
+        // the timestamp is inclusive, the last entry shows up again in the next page
+        if let Some(last) = entries.last() {
+            since = last.timestamp.clone();
+            db.set_sync_cursor(CURSOR, &since).await?;
+        }
+
+        if entries.len() < args.limit {
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index() {
+        let txt = r#"{"Path":"github.com/BurntSushi/toml","Version":"v1.4.0","Timestamp":"2024-06-05T21:45:20.117964Z"}
+{"Path":"golang.org/x/sys","Version":"v0.25.0","Timestamp":"2024-09-04T15:33:13.488391Z"}
+"#;
+        let entries = parse_index(txt).unwrap();
+        assert_eq!(
+            entries,
+            [
+                IndexEntry {
+                    path: "github.com/BurntSushi/toml".to_string(),
+                    version: "v1.4.0".to_string(),
+                    timestamp: "2024-06-05T21:45:20.117964Z".to_string(),
+                },
+                IndexEntry {
+                    path: "golang.org/x/sys".to_string(),
+                    version: "v0.25.0".to_string(),
+                    timestamp: "2024-09-04T15:33:13.488391Z".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_module_zip_url() {
+        assert_eq!(
+            module_zip_url(PROXY_URL, "github.com/BurntSushi/toml", "v1.4.0"),
+            "https://proxy.golang.org/github.com/!burnt!sushi/toml/@v/v1.4.0.zip"
+        );
+    }
+}
@@ -3,6 +3,7 @@ use crate::db::{self, Task, TaskData};
 use crate::errors::*;
 use crate::utils;
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use tokio::io::AsyncReadExt;
 
 #[derive(Debug, Deserialize)]
@@ -11,6 +12,24 @@ pub struct Formula {
     versions: Versions,
     urls: SourceSet,
     revision: u16,
+    #[serde(default)]
+    bottle: Bottles,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Bottles {
+    stable: Option<BottleSet>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BottleSet {
+    /// Keyed by platform, like `arm64_sonoma` or `x86_64_linux`
+    files: BTreeMap<String, Bottle>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Bottle {
+    sha256: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,6 +50,70 @@ pub struct SourceUrl {
     checksum: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct Cask {
+    token: String,
+    version: String,
+    url: String,
+    /// Either a hex digest or `no_check`
+    sha256: String,
+}
+
+/// Casks are mostly prebuilt binaries, only casks with a known checksum are recorded
+async fn sync_casks(db: &db::Client, vendor: &str, casks: Vec<Cask>) -> Result<()> {
+    for cask in casks {
+        debug!("cask={cask:?}");
+        if cask.sha256.len() != 64 || !cask.sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            continue;
+        }
+        let chksum = format!("sha256:{}", cask.sha256.to_lowercase());
+
+        if utils::is_possible_tar_artifact(&cask.url)
+            && db.resolve_artifact(&chksum).await?.is_none()
+        {
+            info!("Found tarball url: {:?}", cask.url);
+            db.insert_task(&Task::new(
+                format!("fetch:{}", cask.url),
+                &TaskData::FetchTar {
+                    url: cask.url.clone(),
+                    compression: None,
+                    success_ref: None,
+                },
+            )?)
+            .await?;
+        }
+
+        let obj = db::Ref {
+            chksum,
+            vendor: vendor.to_string(),
+            package: cask.token,
+            version: cask.version,
+            filename: Some(cask.url),
+        };
+        db.insert_ref(&obj).await?;
+    }
+    Ok(())
+}
+
+/// Point the checksums of prebuilt bottles to the source they were built from
+async fn register_bottles(db: &db::Client, bottles: &BottleSet, chksum: &str) -> Result<()> {
+    // retried on the next sync if the source hasn't been ingested yet
+    let Some(artifact) = db.resolve_artifact_chksum(chksum).await? else {
+        return Ok(());
+    };
+    for (platform, bottle) in &bottles.files {
+        let bottle_chksum = format!("sha256:{}", bottle.sha256.to_lowercase());
+        debug!("Registering bottle for {platform:?}: {bottle_chksum:?} -> {artifact:?}");
+        db.insert_alias_from_to(
+            &bottle_chksum,
+            &artifact,
+            &format!("homebrew-bottle({platform})"),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
 pub async fn run(args: &args::SyncHomebrew) -> Result<()> {
     let db = db::Client::create().await?;
     let vendor = &args.vendor;
@@ -40,6 +123,11 @@ pub async fn run(args: &args::SyncHomebrew) -> Result<()> {
     let mut buf = String::new();
     reader.read_to_string(&mut buf).await?;
 
+    if args.casks {
+        let casks = serde_json::from_str::<Vec<Cask>>(&buf)?;
+        return sync_casks(&db, vendor, casks).await;
+    }
+
     let formulas = serde_json::from_str::<Vec<Formula>>(&buf)?;
     for formula in formulas {
         debug!("formula={formula:?}");
@@ -72,16 +160,23 @@ pub async fn run(args: &args::SyncHomebrew) -> Result<()> {
                 .await?;
             } else if url.starts_with("git+https://") {
                 info!("Found git remote: {url:?}");
+                let commit = chksum.strip_prefix("git:");
                 db.insert_task(&Task::new(
-                    format!("git-clone:{url}"),
+                    utils::vcs_task_key("git-clone", &url, &[("commit", commit)]),
                     &TaskData::GitSnapshot {
                         url: url.to_string(),
+                        tag: None,
+                        commit: commit.map(String::from),
                     },
                 )?)
                 .await?;
             }
         }
 
+        if let Some(bottles) = &formula.bottle.stable {
+            register_bottles(&db, bottles, &chksum).await?;
+        }
+
         debug!("package={package:?} version={version:?} url={url:?} ({chksum})");
         let obj = db::Ref {
             chksum,
@@ -95,3 +190,82 @@ pub async fn run(args: &args::SyncHomebrew) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formula_bottles() {
+        let json = r#"{
+  "name": "xz",
+  "versions": {"stable": "5.6.2", "head": null, "bottle": true},
+  "urls": {
+    "stable": {
+      "url": "https://github.com/tukaani-project/xz/releases/download/v5.6.2/xz-5.6.2.tar.gz",
+      "tag": null,
+      "revision": null,
+      "using": null,
+      "checksum": "8bfd20c0e1d86f0402f2497cfa71c6ab62d4cd35fd704276e3140bfb71414519"
+    }
+  },
+  "revision": 0,
+  "bottle": {
+    "stable": {
+      "rebuild": 0,
+      "root_url": "https://ghcr.io/v2/homebrew/core",
+      "files": {
+        "arm64_sonoma": {
+          "cellar": ":any",
+          "url": "https://ghcr.io/v2/homebrew/core/xz/blobs/sha256:5ae2a2b2e8b9e4c7b4e4b7c2d6c9e6f0f3a8c1f1b2d3e4f5a6b7c8d9e0f1a2b3",
+          "sha256": "5ae2a2b2e8b9e4c7b4e4b7c2d6c9e6f0f3a8c1f1b2d3e4f5a6b7c8d9e0f1a2b3"
+        },
+        "x86_64_linux": {
+          "cellar": ":any_skip_relocation",
+          "url": "https://ghcr.io/v2/homebrew/core/xz/blobs/sha256:0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0",
+          "sha256": "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0"
+        }
+      }
+    }
+  }
+}"#;
+        let formula = serde_json::from_str::<Formula>(json).unwrap();
+        let bottles = formula.bottle.stable.unwrap();
+        assert_eq!(
+            bottles.files.keys().collect::<Vec<_>>(),
+            ["arm64_sonoma", "x86_64_linux"]
+        );
+        assert_eq!(
+            bottles.files["x86_64_linux"].sha256,
+            "0f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0"
+        );
+
+        // formulae without bottles, like the ones of third party taps
+        let formula = serde_json::from_str::<Formula>(
+            r#"{"name": "foo", "versions": {"stable": "1.0"}, "urls": {"stable": {"url": "https://example.com/foo-1.0.tar.gz"}}, "revision": 0}"#,
+        )
+        .unwrap();
+        assert!(formula.bottle.stable.is_none());
+    }
+
+    #[test]
+    fn test_parse_cask() {
+        let json = r#"[{
+  "token": "firefox",
+  "full_token": "firefox",
+  "name": ["Mozilla Firefox"],
+  "version": "131.0.3",
+  "url": "https://download-installer.cdn.mozilla.net/pub/firefox/releases/131.0.3/mac/en-US/Firefox%20131.0.3.dmg",
+  "sha256": "1f6b0c2ea8c5c0b4e3d6a4f2c1e0d9b8a7f6e5d4c3b2a1908f7e6d5c4b3a2918"
+}, {
+  "token": "google-chrome",
+  "version": "latest",
+  "url": "https://dl.google.com/chrome/mac/universal/stable/GGRO/googlechrome.dmg",
+  "sha256": "no_check"
+}]"#;
+        let casks = serde_json::from_str::<Vec<Cask>>(json).unwrap();
+        assert_eq!(casks.len(), 2);
+        assert_eq!(casks[0].token, "firefox");
+        assert_eq!(casks[1].sha256, "no_check");
+    }
+}
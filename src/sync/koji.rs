@@ -0,0 +1,490 @@
+use crate::args;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::utils::{self, HttpClient};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Build state of a successful build
+const BUILD_COMPLETE: i64 = 1;
+const DEFAULT_VOLUME: &str = "DEFAULT";
+
+/// The subset of XML-RPC values used by the Koji api
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    Array(Vec<Value>),
+    Struct(BTreeMap<String, Value>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn write_xml(&self, out: &mut String) {
+        out.push_str("<value>");
+        match self {
+            Value::Nil => out.push_str("<nil/>"),
+            Value::Bool(b) => out.push_str(&format!("<boolean>{}</boolean>", u8::from(*b))),
+            Value::Int(n) => out.push_str(&format!("<int>{n}</int>")),
+            Value::Double(n) => out.push_str(&format!("<double>{n}</double>")),
+            Value::String(s) => out.push_str(&format!("<string>{}</string>", escape(s))),
+            Value::Array(values) => {
+                out.push_str("<array><data>");
+                for value in values {
+                    value.write_xml(out);
+                }
+                out.push_str("</data></array>");
+            }
+            Value::Struct(map) => {
+                out.push_str("<struct>");
+                for (key, value) in map {
+                    out.push_str(&format!("<member><name>{}</name>", escape(key)));
+                    value.write_xml(out);
+                    out.push_str("</member>");
+                }
+                out.push_str("</struct>");
+            }
+        }
+        out.push_str("</value>");
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Encode a call with keyword arguments, the way the koji client passes them
+pub fn encode_call(method: &str, args: &[Value], kwargs: BTreeMap<String, Value>) -> String {
+    let mut out = String::from("<?xml version=\"1.0\"?><methodCall>");
+    out.push_str(&format!(
+        "<methodName>{}</methodName><params>",
+        escape(method)
+    ));
+    let mut kwargs = kwargs;
+    kwargs.insert("__starstar".to_string(), Value::Bool(true));
+    for value in args.iter().chain([&Value::Struct(kwargs)]) {
+        out.push_str("<param>");
+        value.write_xml(&mut out);
+        out.push_str("</param>");
+    }
+    out.push_str("</params></methodCall>");
+    out
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+fn parse_tree(xml: &str) -> Result<Element> {
+    let mut stack = vec![Element::default()];
+    for event in EventReader::from_str(xml) {
+        match event? {
+            XmlEvent::StartElement { name, .. } => stack.push(Element {
+                name: name.local_name,
+                ..Default::default()
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop();
+                if let (Some(element), Some(parent)) = (element, stack.last_mut()) {
+                    parent.children.push(element);
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => (),
+        }
+    }
+    stack
+        .pop()
+        .and_then(|root| root.children.into_iter().next())
+        .ok_or_else(|| Error::InvalidXmlRpc("empty document".to_string()))
+}
+
+fn parse_value(element: &Element) -> Result<Value> {
+    // a value without a type is a string
+    let Some(typed) = element.children.first() else {
+        return Ok(Value::String(element.text.clone()));
+    };
+    let text = typed.text.trim();
+    let invalid = || Error::InvalidXmlRpc(format!("invalid {}: {text:?}", typed.name));
+    let value = match typed.name.as_str() {
+        "nil" => Value::Nil,
+        "boolean" => Value::Bool(text == "1"),
+        "int" | "i4" | "i8" => Value::Int(text.parse().map_err(|_| invalid())?),
+        "double" => Value::Double(text.parse().map_err(|_| invalid())?),
+        "string" | "dateTime.iso8601" | "base64" => Value::String(typed.text.clone()),
+        "array" => {
+            let data = typed.child("data").ok_or_else(invalid)?;
+            Value::Array(
+                data.children
+                    .iter()
+                    .map(parse_value)
+                    .collect::<Result<_>>()?,
+            )
+        }
+        "struct" => {
+            let mut map = BTreeMap::new();
+            for member in &typed.children {
+                let name = member.child("name").ok_or_else(invalid)?;
+                let value = member.child("value").ok_or_else(invalid)?;
+                map.insert(name.text.clone(), parse_value(value)?);
+            }
+            Value::Struct(map)
+        }
+        other => return Err(Error::InvalidXmlRpc(format!("unknown type {other:?}"))),
+    };
+    Ok(value)
+}
+
+/// Decode a `methodResponse`, faults are returned as errors
+pub fn decode_response(xml: &str) -> Result<Value> {
+    let root = parse_tree(xml)?;
+    if let Some(fault) = root.child("fault") {
+        let value = fault.child("value").map(parse_value).transpose()?;
+        let message = value
+            .as_ref()
+            .and_then(|v| v.get("faultString"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown fault");
+        return Err(Error::KojiFault(message.to_string()));
+    }
+    let value = root
+        .child("params")
+        .and_then(|params| params.child("param"))
+        .and_then(|param| param.child("value"))
+        .ok_or_else(|| Error::InvalidXmlRpc("missing return value".to_string()))?;
+    parse_value(value)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Build {
+    pub name: String,
+    pub version: String,
+    pub release: String,
+    pub volume: Option<String>,
+}
+
+impl Build {
+    fn from_value(value: &Value) -> Option<Self> {
+        let field = |key| value.get(key).and_then(Value::as_str).map(String::from);
+        if let Some(state) = value.get("state").and_then(Value::as_int) {
+            if state != BUILD_COMPLETE {
+                return None;
+            }
+        }
+        Some(Build {
+            name: field("name")?,
+            version: field("version")?,
+            release: field("release")?,
+            volume: field("volume_name").filter(|volume| volume != DEFAULT_VOLUME),
+        })
+    }
+
+    /// The version, formatted like the rpm repodata import
+    pub fn full_version(&self) -> String {
+        format!("{}-{}", self.version, self.release)
+    }
+
+    /// Where koji publishes the source rpm of this build
+    pub fn srpm_url(&self, topurl: &str) -> String {
+        let topurl = topurl.strip_suffix('/').unwrap_or(topurl);
+        let base = match &self.volume {
+            Some(volume) => format!("{topurl}/vol/{volume}/packages"),
+            None => format!("{topurl}/packages"),
+        };
+        let Build {
+            name,
+            version,
+            release,
+            ..
+        } = self;
+        format!("{base}/{name}/{version}/{release}/src/{name}-{version}-{release}.src.rpm")
+    }
+}
+
+async fn call(
+    http: &HttpClient,
+    url: &str,
+    method: &str,
+    args: &[Value],
+    kwargs: BTreeMap<String, Value>,
+) -> Result<Value> {
+    debug!("Calling koji api: method={method:?} args={args:?} kwargs={kwargs:?}");
+    let body = encode_call(method, args, kwargs);
+    let response = http.post_xml(url, body).await?;
+    decode_response(&response)
+}
+
+/// Recent rpm builds of the whole hub, this includes side tags and the components of modules
+async fn list_recent_builds(http: &HttpClient, args: &args::SyncKoji) -> Result<Value> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let after = now.saturating_sub(args.days * 24 * 60 * 60);
+
+    let mut query_opts = BTreeMap::new();
+    query_opts.insert("order".to_string(), Value::String("-build_id".to_string()));
+    query_opts.insert("limit".to_string(), Value::Int(args.limit));
+
+    let mut kwargs = BTreeMap::new();
+    kwargs.insert("state".to_string(), Value::Int(BUILD_COMPLETE));
+    kwargs.insert("type".to_string(), Value::String("rpm".to_string()));
+    kwargs.insert("completeAfter".to_string(), Value::Double(after as f64));
+    kwargs.insert("queryOpts".to_string(), Value::Struct(query_opts));
+    call(http, &args.url, "listBuilds", &[], kwargs).await
+}
+
+async fn list_tagged_builds(http: &HttpClient, args: &args::SyncKoji, tag: &str) -> Result<Value> {
+    let mut kwargs = BTreeMap::new();
+    kwargs.insert("latest".to_string(), Value::Bool(true));
+    kwargs.insert("type".to_string(), Value::String("rpm".to_string()));
+    call(
+        http,
+        &args.url,
+        "listTagged",
+        &[Value::String(tag.to_string())],
+        kwargs,
+    )
+    .await
+}
+
+pub async fn run(args: &args::SyncKoji) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = utils::http_client(None)?;
+    let vendor = &args.vendor;
+
+    let builds = if let Some(tag) = &args.tag {
+        info!("Listing latest builds of tag: {tag:?}");
+        list_tagged_builds(&http, args, tag).await?
+    } else {
+        info!("Listing builds of the last {} days", args.days);
+        list_recent_builds(&http, args).await?
+    };
+    let Value::Array(builds) = builds else {
+        return Err(Error::InvalidXmlRpc(
+            "expected a list of builds".to_string(),
+        ));
+    };
+    let builds = builds
+        .iter()
+        .filter_map(Build::from_value)
+        .collect::<Vec<_>>();
+    info!("Found {} builds", builds.len());
+
+    // mark all refs known for these packages as "last_seen now", skip if already imported
+    let changed = db
+        .refresh_packages(
+            vendor,
+            &builds
+                .iter()
+                .map(|build| (build.name.clone(), build.full_version()))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    let mut tasks = Vec::new();
+    for (build, changed) in builds.into_iter().zip(changed) {
+        let version = build.full_version();
+        let package = build.name.clone();
+        if !changed {
+            debug!("Package is already imported: vendor={vendor:?} package={package:?} version={version:?}");
+            continue;
+        }
+
+        let url = build.srpm_url(&args.topurl);
+        info!("package={package:?} version={version:?} url={url:?}");
+        // same key as the repodata import, a build that shows up in both is only fetched once
+        tasks.push(Task::new(
+            format!("source-rpm:{vendor}:{package}:{version}"),
+            &TaskData::SourceRpm {
+                vendor: vendor.to_string(),
+                package,
+                version,
+                url,
+            },
+        )?);
+    }
+    db.insert_tasks(&tasks).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_call() {
+        let mut kwargs = BTreeMap::new();
+        kwargs.insert("latest".to_string(), Value::Bool(true));
+        let xml = encode_call("listTagged", &[Value::String("epel9".to_string())], kwargs);
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\"?><methodCall><methodName>listTagged</methodName><params>\
+<param><value><string>epel9</string></value></param>\
+<param><value><struct>\
+<member><name>__starstar</name><value><boolean>1</boolean></value></member>\
+<member><name>latest</name><value><boolean>1</boolean></value></member>\
+</struct></value></param>\
+</params></methodCall>"
+        );
+    }
+
+    #[test]
+    fn test_decode_builds() {
+        let xml = r#"<?xml version='1.0'?>
+<methodResponse>
+<params>
+<param>
+<value><array><data>
+<value><struct>
+<member>
+<name>build_id</name>
+<value><int>2571023</int></value>
+</member>
+<member>
+<name>name</name>
+<value><string>xz</string></value>
+</member>
+<member>
+<name>version</name>
+<value><string>5.6.3</string></value>
+</member>
+<member>
+<name>release</name>
+<value><string>1.fc42</string></value>
+</member>
+<member>
+<name>epoch</name>
+<value><nil/></value></member>
+<member>
+<name>state</name>
+<value><int>1</int></value>
+</member>
+<member>
+<name>volume_name</name>
+<value><string>DEFAULT</string></value>
+</member>
+</struct></value>
+<value><struct>
+<member>
+<name>name</name>
+<value><string>kernel</string></value>
+</member>
+<member>
+<name>version</name>
+<value><string>6.11.3</string></value>
+</member>
+<member>
+<name>release</name>
+<value><string>300.fc41</string></value>
+</member>
+<member>
+<name>volume_name</name>
+<value><string>fedora_koji_archive05</string></value>
+</member>
+</struct></value>
+</data></array></value>
+</param>
+</params>
+</methodResponse>
+"#;
+        let Value::Array(builds) = decode_response(xml).unwrap() else {
+            panic!("expected an array");
+        };
+        let builds = builds
+            .iter()
+            .filter_map(Build::from_value)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            builds,
+            [
+                Build {
+                    name: "xz".to_string(),
+                    version: "5.6.3".to_string(),
+                    release: "1.fc42".to_string(),
+                    volume: None,
+                },
+                Build {
+                    name: "kernel".to_string(),
+                    version: "6.11.3".to_string(),
+                    release: "300.fc41".to_string(),
+                    volume: Some("fedora_koji_archive05".to_string()),
+                },
+            ]
+        );
+        assert_eq!(builds[0].full_version(), "5.6.3-1.fc42");
+        assert_eq!(
+            builds[0].srpm_url("https://kojipkgs.fedoraproject.org/"),
+            "https://kojipkgs.fedoraproject.org/packages/xz/5.6.3/1.fc42/src/xz-5.6.3-1.fc42.src.rpm"
+        );
+        assert_eq!(
+            builds[1].srpm_url("https://kojipkgs.fedoraproject.org"),
+            "https://kojipkgs.fedoraproject.org/vol/fedora_koji_archive05/packages/kernel/6.11.3/300.fc41/src/kernel-6.11.3-300.fc41.src.rpm"
+        );
+    }
+
+    #[test]
+    fn test_decode_fault() {
+        let xml = r#"<?xml version='1.0'?>
+<methodResponse>
+<fault>
+<value><struct>
+<member>
+<name>faultCode</name>
+<value><int>1000</int></value>
+</member>
+<member>
+<name>faultString</name>
+<value><string>koji.GenericError: No such tagInfo: 'epel99'</string></value>
+</member>
+</struct></value>
+</fault>
+</methodResponse>
+"#;
+        let err = decode_response(xml).unwrap_err();
+        assert!(
+            matches!(&err, Error::KojiFault(msg) if msg == "koji.GenericError: No such tagInfo: 'epel99'"),
+            "{err:?}"
+        );
+    }
+}
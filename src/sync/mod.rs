@@ -1,10 +1,23 @@
 pub mod alpine;
 pub mod apt;
+pub mod buildroot;
+pub mod cache;
+pub mod chimera;
+pub mod conan;
 pub mod gentoo;
+pub mod goproxy;
 pub mod guix;
 pub mod homebrew;
+pub mod koji;
 pub mod live_bootstrap;
+pub mod msys2;
+pub mod npm_registry;
+pub mod obs;
 pub mod pacman;
+pub mod pypi;
 pub mod rpm;
+pub mod spack;
+pub mod termux;
+pub mod vcpkg;
 pub mod void;
 pub mod yocto;
@@ -0,0 +1,175 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::ingest::pacman::{self, Snapshot};
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use serde::Deserialize;
+use srcinfo::Srcinfo;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::io::{self, AsyncReadExt};
+
+/// An entry of the `srcinfo.json.gz` cache published by MSYS2
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Entry {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    /// The .SRCINFO of this PKGBUILD, one for each environment it's built for
+    pub srcinfo: BTreeMap<String, String>,
+}
+
+pub fn parse(json: &str) -> Result<HashMap<String, Entry>> {
+    let entries = serde_json::from_str(json)?;
+    Ok(entries)
+}
+
+/// The sources of a PKGBUILD, shared by all environments it's built for
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    pub git_ref: String,
+    pub package: String,
+    pub version: String,
+    pub srcinfo: String,
+}
+
+/// Reduce the cache to one .SRCINFO per pkgbase and version
+///
+/// Each environment names its binary package differently (`mingw-w64-x86_64-xz`,
+/// `mingw-w64-ucrt-x86_64-xz`, ...), but they're built from the same pkgbase and sources.
+pub fn sources(entries: HashMap<String, Entry>) -> Vec<Source> {
+    let mut seen = HashSet::new();
+    let mut sources = Vec::new();
+    for (_, entry) in entries {
+        for srcinfo in entry.srcinfo.into_values() {
+            let parsed = match Srcinfo::parse_buf(srcinfo.as_bytes()) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    warn!("Failed to parse .SRCINFO: {err:#}");
+                    continue;
+                }
+            };
+            let package = parsed.base.pkgbase.clone();
+            let version = parsed.version();
+            if seen.insert((package.clone(), version.clone())) {
+                sources.push(Source {
+                    git_ref: entry.git_ref.clone(),
+                    package,
+                    version,
+                    srcinfo,
+                });
+            }
+        }
+    }
+    sources
+}
+
+/// Import the sources listed in the `srcinfo.json.gz` cache of MSYS2
+///
+/// Unlike Arch Linux, the pacman databases of MSYS2 only describe the binary packages of each
+/// environment, the PKGBUILD and its checksums live in a git repository. Instead of resolving
+/// every binary package back to its PKGBUILD, we use the cache MSYS2 publishes for its own
+/// tooling, which already contains the parsed .SRCINFO of every PKGBUILD in one download.
+pub async fn run(args: &args::SyncMsys2) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let mut reader = GzipDecoder::new(reader);
+
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).await?;
+
+    for source in sources(parse(&buf)?) {
+        let Source {
+            git_ref,
+            package,
+            version,
+            srcinfo,
+        } = source;
+
+        // mark all refs known for this package as "last_seen now", skip if already imported
+        if !db
+            .refresh_package(vendor, &package, &version, &version)
+            .await?
+        {
+            debug!("Package is already imported: vendor={vendor:?} package={package:?} version={version:?}");
+            continue;
+        }
+
+        info!("package={package:?} version={version:?} ref={git_ref:?}");
+        let snapshot = Snapshot::from_srcinfo(srcinfo);
+        pacman::import_snapshot(&db, &snapshot, vendor, &package, &version).await?;
+        db.insert_package(&db::Package {
+            vendor: vendor.to_string(),
+            package,
+            version,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srcinfo_cache() {
+        let data = r#"{
+  "0a5c3e1b8de5e6d6f6d8b3d0a6c4a1b5e1f0c2d3a4b5c6d7e8f9a0b1c2d3e4f5": {
+    "ref": "3b1bd27e9e3ca1ae1f1c0ef0ea7dd8a7f4e3d1f2",
+    "repo": "https://github.com/msys2/MINGW-packages",
+    "path": "mingw-w64-xz",
+    "date": "2024-04-02 10:00:00",
+    "srcinfo": {
+      "mingw64": "pkgbase = mingw-w64-xz\n\tpkgver = 5.4.6\n\tpkgrel = 1\n\tsource = https://github.com/tukaani-project/xz/releases/download/v5.4.6/xz-5.4.6.tar.bz2\n\tsha256sums = 913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49\n\npkgname = mingw-w64-x86_64-xz\n"
+    }
+  }
+}"#;
+        let entries = parse(data).unwrap();
+        let entry = entries.values().next().unwrap();
+        assert_eq!(entry.git_ref, "3b1bd27e9e3ca1ae1f1c0ef0ea7dd8a7f4e3d1f2");
+
+        let srcinfo = entry.srcinfo.get("mingw64").unwrap();
+        let parsed = Srcinfo::parse_buf(srcinfo.as_bytes()).unwrap();
+        assert_eq!(parsed.base.pkgbase, "mingw-w64-xz");
+        assert_eq!(parsed.version(), "5.4.6-1");
+
+        let entries = Snapshot::from_srcinfo(srcinfo.clone())
+            .source_entries()
+            .unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.preferred_chksum())
+                .collect::<Vec<_>>(),
+            vec![Some(
+                "sha256:913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_environments_share_sources() {
+        let data = r#"{
+  "0a5c3e1b8de5e6d6f6d8b3d0a6c4a1b5e1f0c2d3a4b5c6d7e8f9a0b1c2d3e4f5": {
+    "ref": "3b1bd27e9e3ca1ae1f1c0ef0ea7dd8a7f4e3d1f2",
+    "srcinfo": {
+      "mingw64": "pkgbase = mingw-w64-xz\n\tpkgver = 5.4.6\n\tpkgrel = 1\n\tsource = https://github.com/tukaani-project/xz/releases/download/v5.4.6/xz-5.4.6.tar.bz2\n\tsha256sums = 913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49\n\npkgname = mingw-w64-x86_64-xz\n",
+      "ucrt64": "pkgbase = mingw-w64-xz\n\tpkgver = 5.4.6\n\tpkgrel = 1\n\tsource = https://github.com/tukaani-project/xz/releases/download/v5.4.6/xz-5.4.6.tar.bz2\n\tsha256sums = 913851b274e8e1d31781ec949f1c23e8dbcf0ecf6e73a2436dc21769dd3e6f49\n\npkgname = mingw-w64-ucrt-x86_64-xz\n"
+    }
+  }
+}"#;
+        let sources = sources(parse(data).unwrap());
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].package, "mingw-w64-xz");
+        assert_eq!(sources[0].version, "5.4.6-1");
+        assert_eq!(
+            sources[0].git_ref,
+            "3b1bd27e9e3ca1ae1f1c0ef0ea7dd8a7f4e3d1f2"
+        );
+    }
+}
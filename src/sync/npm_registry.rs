@@ -0,0 +1,231 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::utils::{self, HttpClient};
+use data_encoding::BASE64;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tokio::io::AsyncReadExt;
+use tokio::time::{self, Duration};
+
+pub const VENDOR: &str = "registry.npmjs.org";
+/// The name the position in the changes feed is stored as
+const CURSOR: &str = "npm-registry";
+/// How long to wait if there are no new changes
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How long to wait if the changes feed is unavailable
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Changes {
+    pub results: Vec<Change>,
+    pub last_seq: serde_json::Value,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Change {
+    pub id: String,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct DbInfo {
+    pub update_seq: serde_json::Value,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Packument {
+    pub name: String,
+    #[serde(default)]
+    pub versions: BTreeMap<String, Version>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Version {
+    pub dist: Dist,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Dist {
+    pub tarball: String,
+    /// Subresource integrity of the tarball, missing for very old releases
+    pub integrity: Option<String>,
+}
+
+impl Dist {
+    /// Convert the `sha512-<base64>` integrity into a checksum, sha1 is ignored
+    pub fn chksum(&self) -> Result<Option<String>> {
+        let Some(integrity) = &self.integrity else {
+            return Ok(None);
+        };
+        let Some(hash) = integrity.strip_prefix("sha512-") else {
+            return Ok(None);
+        };
+        let digest = hex::encode(BASE64.decode(hash.as_bytes())?);
+        Ok(Some(format!("sha512:{digest}")))
+    }
+}
+
+/// The sequence is a number on npm but an opaque string on CouchDB
+fn seq_to_string(seq: &serde_json::Value) -> String {
+    match seq {
+        serde_json::Value::String(seq) => seq.clone(),
+        other => other.to_string(),
+    }
+}
+
+async fn fetch_json<T: for<'de> Deserialize<'de>>(http: &HttpClient, url: &str) -> Result<T> {
+    debug!("Downloading url: {url:?}");
+    let mut buf = String::new();
+    http.fetch(url).await?.read_to_string(&mut buf).await?;
+    let json = serde_json::from_str(&buf)?;
+    Ok(json)
+}
+
+async fn import_package(
+    db: &db::Client,
+    http: &HttpClient,
+    args: &args::SyncNpmRegistry,
+    name: &str,
+) -> Result<()> {
+    // scoped packages are requested as `@scope%2Fname`
+    let url = format!(
+        "{}/{}",
+        args.registry.trim_end_matches('/'),
+        name.replace('/', "%2F")
+    );
+    let packument = fetch_json::<Packument>(http, &url).await?;
+
+    for (version, release) in &packument.versions {
+        let Some(chksum) = release.dist.chksum()? else {
+            continue;
+        };
+        let url = &release.dist.tarball;
+
+        let task = if args.download && db.resolve_artifact(&chksum).await?.is_none() {
+            utils::task_for_url(url)
+        } else {
+            None
+        };
+
+        let r = db::Ref {
+            chksum,
+            vendor: VENDOR.to_string(),
+            package: packument.name.clone(),
+            version: version.to_string(),
+            filename: Some(url.to_string()),
+        };
+        debug!("insert: {r:?}");
+        db.insert_ref(&r).await?;
+
+        if let Some(task) = task {
+            info!("Adding task: {task:?}");
+            db.insert_task(&task).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::SyncNpmRegistry) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = utils::http_client(None)?;
+    let base_url = args.url.trim_end_matches('/');
+
+    let mut since = if let Some(since) = &args.since {
+        since.clone()
+    } else if let Some(cursor) = db.get_sync_cursor(CURSOR).await? {
+        cursor
+    } else {
+        // start with new changes, backfilling the whole registry is out of scope
+        let info = fetch_json::<DbInfo>(&http, &format!("{base_url}/")).await?;
+        seq_to_string(&info.update_seq)
+    };
+
+    loop {
+        let url = format!("{base_url}/_changes?since={since}&limit={}", args.limit);
+        let changes = match fetch_json::<Changes>(&http, &url).await {
+            Ok(changes) => changes,
+            Err(err) => {
+                error!("Failed to fetch changes feed: {err:#}");
+                time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        for change in &changes.results {
+            if change.deleted {
+                continue;
+            }
+            info!("Processing change: package={:?}", change.id);
+            if let Err(err) = import_package(&db, &http, args, &change.id).await {
+                error!("Failed to import package={:?}: {err:#}", change.id);
+            }
+        }
+
+        since = seq_to_string(&changes.last_seq);
+        db.set_sync_cursor(CURSOR, &since).await?;
+
+        if changes.results.is_empty() {
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_changes() {
+        let json = r#"{
+  "results": [
+    {"seq": 44069021, "id": "left-pad", "changes": [{"rev": "87-0ab1f2"}]},
+    {"seq": 44069022, "id": "@babel/core", "changes": [{"rev": "401-a2c3d4"}]},
+    {"seq": 44069023, "id": "spam-package", "changes": [{"rev": "2-ff00aa"}], "deleted": true}
+  ],
+  "last_seq": 44069023
+}"#;
+        let changes = serde_json::from_str::<Changes>(json).unwrap();
+        assert_eq!(changes.results.len(), 3);
+        assert_eq!(changes.results[1].id, "@babel/core");
+        assert!(changes.results[2].deleted);
+        assert_eq!(seq_to_string(&changes.last_seq), "44069023");
+    }
+
+    #[test]
+    fn test_parse_packument() {
+        let json = r#"{
+  "_id": "left-pad",
+  "name": "left-pad",
+  "dist-tags": {"latest": "1.3.0"},
+  "versions": {
+    "0.0.0": {
+      "name": "left-pad",
+      "version": "0.0.0",
+      "dist": {
+        "shasum": "c8cae4beb3a59a5b1c6e9d3ac2f0c7c6e9ac1e37",
+        "tarball": "https://registry.npmjs.org/left-pad/-/left-pad-0.0.0.tgz"
+      }
+    },
+    "1.3.0": {
+      "name": "left-pad",
+      "version": "1.3.0",
+      "dist": {
+        "integrity": "sha512-XI5MPzVNApjAyhQzphX8BkmKsKUxD4LdyK24iZeQEYZNNqdTCL0pyuCvRvhR9jDFkcVGsCD7kP+z0dNTzcuu2g==",
+        "shasum": "5b8a3a7765dfe001261dde915589e782f8c94d1e",
+        "tarball": "https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz"
+      }
+    }
+  }
+}"#;
+        let packument = serde_json::from_str::<Packument>(json).unwrap();
+        assert_eq!(packument.name, "left-pad");
+        assert_eq!(packument.versions["0.0.0"].dist.chksum().unwrap(), None);
+        assert_eq!(
+            packument.versions["1.3.0"].dist.chksum().unwrap().as_deref(),
+            Some("sha512:5c8e4c3f354d0298c0ca1433a615fc06498ab0a5310f82ddc8adb889979011864d36a75308bd29cae0af46f851f630c591c546b020fb90ffb3d1d353cdcbaeda")
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::args;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::rpm_spec;
+use crate::upstream;
+use crate::utils::{self, HttpClient};
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct SourceInfoList {
+    #[serde(rename = "sourceinfo", default)]
+    packages: Vec<SourceInfo>,
+}
+
+impl SourceInfoList {
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let xml = serde_xml_rs::from_str(xml)?;
+        Ok(xml)
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct SourceInfo {
+    package: String,
+    /// Identifies the current state of the package sources
+    srcmd5: Option<String>,
+    #[serde(rename = "filename", default)]
+    filenames: Vec<String>,
+}
+
+impl SourceInfo {
+    /// Multibuild flavors and broken links don't have a spec of their own
+    fn spec(&self) -> Option<&str> {
+        self.filenames
+            .iter()
+            .map(String::as_str)
+            .find(|f| f.ends_with(".spec"))
+    }
+}
+
+pub fn project_url(base_url: &str, project: &str) -> String {
+    format!("{}/source/{project}", base_url.trim_end_matches('/'))
+}
+
+fn source_url(project_url: &str, package: &str, filename: &str, rev: &str) -> String {
+    format!("{project_url}/{package}/{filename}?rev={rev}")
+}
+
+fn is_tarball(filename: &str) -> bool {
+    filename.contains(".tar") || filename.ends_with(".tgz")
+}
+
+/// Download the spec of a package and queue its sources for import
+pub async fn import_package(
+    db: &db::Client,
+    http: &HttpClient,
+    project_url: &str,
+    vendor: &str,
+    package: &str,
+    spec: &str,
+    srcmd5: &str,
+) -> Result<()> {
+    let url = source_url(project_url, package, spec, srcmd5);
+    info!("Downloading spec: {url:?}");
+    let mut buf = String::new();
+    http.fetch(&url).await?.read_to_string(&mut buf).await?;
+    let spec = rpm_spec::parse(&buf)?;
+
+    let version = match &spec.release {
+        // the release of openSUSE:Factory is always 0, it's assigned during build
+        Some(release) if release != "0" => format!("{}-{release}", spec.version),
+        _ => spec.version.clone(),
+    };
+
+    if let Some(url) = &spec.url {
+        upstream::insert(db, url, vendor, package).await?;
+    }
+
+    for source in &spec.sources {
+        // prefer the upstream url, so it's recorded in the ref
+        let url = match &source.url {
+            Some(url) if utils::is_possible_tar_artifact(url) => url.clone(),
+            None if is_tarball(&source.filename) => {
+                source_url(project_url, package, &source.filename, srcmd5)
+            }
+            _ => continue,
+        };
+
+        info!("package={package:?} version={version:?} url={url:?}");
+        db.insert_task(&Task::new(
+            format!("fetch:{url}"),
+            &TaskData::FetchTar {
+                url,
+                compression: None,
+                success_ref: Some(db::DownloadRef {
+                    vendor: vendor.to_string(),
+                    package: package.to_string(),
+                    version: version.clone(),
+                }),
+            },
+        )?)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::SyncObs) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+    let project = &args.project;
+
+    let http = utils::http_client(None)?;
+
+    let url = format!("{}?view=info", project_url(&args.url, project));
+    info!("Downloading url: {url:?}");
+    let mut buf = String::new();
+    http.fetch(&url).await?.read_to_string(&mut buf).await?;
+
+    info!("Processing xml");
+    let list = SourceInfoList::from_xml(&buf)?;
+    for info in &list.packages {
+        let (Some(spec), Some(srcmd5)) = (info.spec(), &info.srcmd5) else {
+            debug!("Package has no spec: {:?}", info.package);
+            continue;
+        };
+        let package = &info.package;
+
+        // the version is only known after fetching the spec, srcmd5 changes with every commit
+        if !db.refresh_package(vendor, package, srcmd5, srcmd5).await? {
+            debug!("Package is already imported: vendor={vendor:?} package={package:?} srcmd5={srcmd5:?}");
+            continue;
+        }
+
+        info!("package={package:?} srcmd5={srcmd5:?}");
+        db.insert_task(&Task::new(
+            format!("obs:{project}:{package}:{srcmd5}"),
+            &TaskData::ObsPackage {
+                vendor: vendor.to_string(),
+                url: args.url.clone(),
+                project: project.to_string(),
+                package: package.to_string(),
+                spec: spec.to_string(),
+                srcmd5: srcmd5.to_string(),
+            },
+        )?)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sourceinfo() {
+        let xml = r#"<sourceinfolist>
+  <sourceinfo package="xz" rev="96" vrev="1" srcmd5="3c5e0e2e3d0e4a1d6f0b8f1c2a7c9d4e" verifymd5="3c5e0e2e3d0e4a1d6f0b8f1c2a7c9d4e">
+    <filename>xz.spec</filename>
+  </sourceinfo>
+  <sourceinfo package="python-pip" rev="112" vrev="1" srcmd5="0f9a2c7d8e1b3a4c5d6e7f8091a2b3c4" verifymd5="0f9a2c7d8e1b3a4c5d6e7f8091a2b3c4">
+    <filename>python-pip.spec</filename>
+  </sourceinfo>
+  <sourceinfo package="broken-link" rev="3" vrev="3" srcmd5="99999999999999999999999999999999">
+    <error>conflict in file foo.spec</error>
+  </sourceinfo>
+</sourceinfolist>"#;
+        let list = SourceInfoList::from_xml(xml).unwrap();
+        assert_eq!(list.packages.len(), 3);
+        assert_eq!(list.packages[0].package, "xz");
+        assert_eq!(list.packages[0].spec(), Some("xz.spec"));
+        assert_eq!(
+            list.packages[1].srcmd5.as_deref(),
+            Some("0f9a2c7d8e1b3a4c5d6e7f8091a2b3c4")
+        );
+        assert_eq!(list.packages[2].spec(), None);
+    }
+}
@@ -29,6 +29,7 @@ pub async fn run(args: &args::SyncPacman) -> Result<()> {
     let reader = GzipDecoder::new(reader);
     let mut tar = Archive::new(reader);
 
+    let mut packages = Vec::new();
     let mut entries = tar.entries()?;
     while let Some(entry) = entries.next().await {
         let mut entry = entry?;
@@ -53,29 +54,40 @@ pub async fn run(args: &args::SyncPacman) -> Result<()> {
             continue;
         };
         let Some(tag) = chunker.next() else { continue };
+        packages.push((pkgbase.to_string(), version.to_string(), tag.to_string()));
+    }
 
-        // mark all refs known for this package as "last_seen now"
-        db.bump_named_refs(vendor, pkgbase, version).await?;
+    // mark all refs known for these packages as "last_seen now", skip if already imported
+    let changed = db
+        .refresh_packages(
+            vendor,
+            &packages
+                .iter()
+                .map(|(pkgbase, version, _)| (pkgbase.clone(), version.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
 
-        // check if package already imported
-        if db.get_package(vendor, pkgbase, version).await?.is_some() {
+    let mut tasks = Vec::new();
+    for ((pkgbase, version, tag), changed) in packages.into_iter().zip(changed) {
+        if !changed {
             debug!("Package is already imported: vendor={vendor:?} package={pkgbase:?} version={version:?}");
             continue;
         }
 
         // queue for import
         info!("package={pkgbase:?} version={version:?} tag={tag:?}");
-        db.insert_task(&db::Task::new(
+        tasks.push(db::Task::new(
             format!("pacman-git-snapshot:{pkgbase}:{tag}"),
             &db::TaskData::PacmanGitSnapshot {
                 vendor: vendor.to_string(),
-                package: pkgbase.to_string(),
-                version: version.to_string(),
-                tag: tag.to_string(),
+                package: pkgbase,
+                version,
+                tag,
             },
-        )?)
-        .await?;
+        )?);
     }
+    db.insert_tasks(&tasks).await?;
 
     Ok(())
 }
@@ -0,0 +1,236 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::utils::{self, HttpClient};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tokio::io::AsyncReadExt;
+
+pub const VENDOR: &str = "pypi";
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Project {
+    #[serde(default)]
+    pub releases: BTreeMap<String, Vec<File>>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct File {
+    pub packagetype: String,
+    pub url: String,
+    pub digests: Digests,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Digests {
+    pub sha256: Option<String>,
+}
+
+impl Project {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let project = serde_json::from_str(json)?;
+        Ok(project)
+    }
+}
+
+/// Normalize a project name, as described in PEP 503
+pub fn normalize_name(name: &str) -> String {
+    let mut out = String::new();
+    for part in name.split(['-', '_', '.']).filter(|p| !p.is_empty()) {
+        if !out.is_empty() {
+            out.push('-');
+        }
+        out.push_str(&part.to_lowercase());
+    }
+    out
+}
+
+/// Read the project names from the html of the simple index
+pub fn parse_simple_index(html: &str) -> Vec<&str> {
+    html.split("<a ")
+        .skip(1)
+        .filter_map(|link| {
+            let (_, name) = link.split_once('>')?;
+            let (name, _) = name.split_once("</a>")?;
+            Some(name.trim())
+        })
+        .collect()
+}
+
+async fn fetch_string(http: &HttpClient, url: &str) -> Result<String> {
+    info!("Downloading url: {url:?}");
+    let mut buf = String::new();
+    http.fetch(url).await?.read_to_string(&mut buf).await?;
+    Ok(buf)
+}
+
+async fn import_project(
+    db: &db::Client,
+    http: &HttpClient,
+    args: &args::SyncPypi,
+    name: &str,
+) -> Result<()> {
+    let vendor = VENDOR;
+    let package = normalize_name(name);
+    let url = format!("{}/pypi/{package}/json", args.url.trim_end_matches('/'));
+    let project = Project::from_json(&fetch_string(http, &url).await?)?;
+
+    for (version, files) in &project.releases {
+        let sdists = files
+            .iter()
+            .filter(|file| file.packagetype == "sdist")
+            .collect::<Vec<_>>();
+        if sdists.is_empty() {
+            continue;
+        }
+
+        // mark all refs known for this package as "last_seen now", skip if already imported
+        if !db
+            .refresh_package(vendor, &package, version, version)
+            .await?
+        {
+            debug!("Package is already imported: vendor={vendor:?} package={package:?} version={version:?}");
+            continue;
+        }
+
+        for file in sdists {
+            let Some(sha256) = &file.digests.sha256 else {
+                continue;
+            };
+            if file.yanked {
+                debug!("Skipping yanked sdist: {:?}", file.url);
+                continue;
+            }
+            let chksum = format!("sha256:{}", sha256.to_lowercase());
+
+            let task = if args.download && db.resolve_artifact(&chksum).await?.is_none() {
+                utils::task_for_url(&file.url)
+            } else {
+                None
+            };
+
+            let r = db::Ref {
+                chksum,
+                vendor: vendor.to_string(),
+                package: package.clone(),
+                version: version.to_string(),
+                filename: Some(file.url.clone()),
+            };
+            debug!("insert: {r:?}");
+            db.insert_ref(&r).await?;
+
+            if let Some(task) = task {
+                info!("Adding task: {task:?}");
+                db.insert_task(&task).await?;
+            }
+        }
+
+        db.insert_package(&db::Package {
+            vendor: vendor.to_string(),
+            package: package.clone(),
+            version: version.to_string(),
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::SyncPypi) -> Result<()> {
+    let db = db::Client::create().await?;
+    let http = utils::http_client(None)?;
+
+    let packages = if args.packages.is_empty() {
+        let url = format!("{}/simple/", args.url.trim_end_matches('/'));
+        let html = fetch_string(&http, &url).await?;
+        parse_simple_index(&html)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    } else {
+        args.packages.clone()
+    };
+
+    let mut errors = 0;
+    for package in &packages {
+        if let Err(err) = import_project(&db, &http, args, package).await {
+            error!("Failed to import package={package:?}: {err:#}");
+            errors += 1;
+        }
+    }
+
+    if errors > 0 {
+        warn!("Encounted {errors} errors while processing index");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Typing_Extensions"), "typing-extensions");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("foo__-bar"), "foo-bar");
+    }
+
+    #[test]
+    fn test_parse_simple_index() {
+        let html = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta name="pypi:repository-version" content="1.1">
+    <title>Simple index</title>
+  </head>
+  <body>
+    <a href="/simple/0/">0</a>
+    <a href="/simple/typing-extensions/">typing_extensions</a>
+    <a href="/simple/zope-interface/">zope.interface</a>
+  </body>
+</html>"#;
+        assert_eq!(
+            parse_simple_index(html),
+            ["0", "typing_extensions", "zope.interface"]
+        );
+    }
+
+    #[test]
+    fn test_parse_project() {
+        let json = r#"{
+  "info": {"name": "six", "version": "1.16.0"},
+  "last_serial": 10508564,
+  "releases": {
+    "1.16.0": [
+      {
+        "digests": {"blake2b_256": "d9", "md5": "9d", "sha256": "8abb2f1d86890a2dfb989f9a77cfcfd3e47c2a354b01111771326f8aa26e0254"},
+        "filename": "six-1.16.0-py2.py3-none-any.whl",
+        "packagetype": "bdist_wheel",
+        "url": "https://files.pythonhosted.org/packages/d9/5a/six-1.16.0-py2.py3-none-any.whl",
+        "yanked": false
+      },
+      {
+        "digests": {"blake2b_256": "71", "md5": "a7", "sha256": "1e61c37477a1626458e36f7b1d82aa5c9b094fa4802892072e49de9c60c4c926"},
+        "filename": "six-1.16.0.tar.gz",
+        "packagetype": "sdist",
+        "url": "https://files.pythonhosted.org/packages/71/39/six-1.16.0.tar.gz",
+        "yanked": false
+      }
+    ],
+    "0.9.0": []
+  }
+}"#;
+        let project = Project::from_json(json).unwrap();
+        assert_eq!(project.releases.len(), 2);
+        let files = &project.releases["1.16.0"];
+        assert_eq!(files[1].packagetype, "sdist");
+        assert_eq!(
+            files[1].digests.sha256.as_deref(),
+            Some("1e61c37477a1626458e36f7b1d82aa5c9b094fa4802892072e49de9c60c4c926")
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::args;
 use crate::db;
 use crate::errors::*;
+use crate::sync::cache;
 use crate::utils;
 use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use serde::Deserialize;
@@ -86,45 +87,64 @@ pub async fn run(args: &args::SyncRpm) -> Result<()> {
     let url = format!("{base_url}/{}", repomd.find_primary_location()?);
 
     info!("Downloading url: {url:?}");
-    let reader = http.fetch(&url).await?;
-    let reader = io::BufReader::new(reader);
-    let mut reader: Box<dyn AsyncRead + Unpin> = if url.ends_with(".zst") {
-        Box::new(ZstdDecoder::new(reader))
-    } else {
-        Box::new(GzipDecoder::new(reader))
+    let Some(metadata) = cache::fetch_if_changed(&db, &http, &url, args.force).await? else {
+        return Ok(());
     };
-
     let mut buf = String::new();
-    reader.read_to_string(&mut buf).await?;
+    {
+        let reader = io::BufReader::new(&metadata.body[..]);
+        let mut reader: Box<dyn AsyncRead + Unpin> = if url.ends_with(".zst") {
+            Box::new(ZstdDecoder::new(reader))
+        } else {
+            Box::new(GzipDecoder::new(reader))
+        };
+        reader.read_to_string(&mut buf).await?;
+    }
 
     info!("Processing xml");
     let md = Metadata::from_xml(&buf)?;
-    for pkg in md.packages {
-        let package = pkg.name;
-        let version = format!("{}-{}", pkg.version.ver, pkg.version.rel);
+    let packages = md
+        .packages
+        .into_iter()
+        .map(|pkg| {
+            let version = format!("{}-{}", pkg.version.ver, pkg.version.rel);
+            (pkg.name, version, pkg.location.href)
+        })
+        .collect::<Vec<_>>();
 
-        // mark all refs known for this package as "last_seen now"
-        db.bump_named_refs(vendor, &package, &version).await?;
+    // mark all refs known for these packages as "last_seen now", skip if already imported
+    let changed = db
+        .refresh_packages(
+            vendor,
+            &packages
+                .iter()
+                .map(|(package, version, _)| (package.clone(), version.clone()))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
 
-        if db.get_package(vendor, &package, &version).await?.is_some() {
+    let mut tasks = Vec::new();
+    for ((package, version, href), changed) in packages.into_iter().zip(changed) {
+        if !changed {
             debug!("Package is already imported: vendor={vendor:?} package={package:?} version={version:?}");
             continue;
         }
 
-        let url = format!("{base_url}/{}", pkg.location.href);
+        let url = format!("{base_url}/{href}");
 
         info!("package={package:?} version={version:?} url={url:?}");
-        db.insert_task(&db::Task::new(
+        tasks.push(db::Task::new(
             format!("source-rpm:{vendor}:{package}:{version}"),
             &db::TaskData::SourceRpm {
                 vendor: vendor.to_string(),
-                package: package.to_string(),
-                version: version.to_string(),
+                package,
+                version,
                 url,
             },
-        )?)
-        .await?;
+        )?);
     }
+    db.insert_tasks(&tasks).await?;
+    metadata.done(&db).await?;
 
     Ok(())
 }
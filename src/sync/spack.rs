@@ -0,0 +1,110 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::spack;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use std::path::Path;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::Archive;
+
+/// Match `<prefix>/packages/<name>/package.py`
+fn package_from_path(path: &Path) -> Option<&str> {
+    let path = path.to_str()?;
+    let path = path.strip_suffix("/package.py")?;
+    let (_, package) = path.rsplit_once("/packages/")?;
+    (!package.contains('/')).then_some(package)
+}
+
+pub async fn run(args: &args::SyncSpack) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let Some(package) = package_from_path(&path) else {
+            continue;
+        };
+        let package = package.to_string();
+
+        let mut buf = String::new();
+        entry.read_to_string(&mut buf).await?;
+
+        let artifacts = spack::parse(&buf).artifacts();
+        if artifacts.is_empty() {
+            debug!("No release tarballs found for package={package:?}");
+            continue;
+        }
+
+        for artifact in artifacts {
+            if artifact.sha256.len() != 64 {
+                warn!(
+                    "Unexpected checksum length for Spack: {:?}",
+                    artifact.sha256
+                );
+                continue;
+            }
+            let chksum = format!("sha256:{}", artifact.sha256.to_lowercase());
+
+            let task = if db.resolve_artifact(&chksum).await?.is_none() {
+                utils::task_for_url(&artifact.url)
+            } else {
+                None
+            };
+
+            let r = db::Ref {
+                chksum,
+                vendor: vendor.to_string(),
+                package: package.clone(),
+                version: artifact.version,
+                filename: Some(artifact.url),
+            };
+            debug!("insert: {r:?}");
+            db.insert_ref(&r).await?;
+
+            if let Some(task) = task {
+                info!("Adding task: {task:?}");
+                db.insert_task(&task).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_from_path() {
+        assert_eq!(
+            package_from_path(Path::new(
+                "spack-develop/var/spack/repos/builtin/packages/zlib/package.py"
+            )),
+            Some("zlib")
+        );
+        assert_eq!(
+            package_from_path(Path::new(
+                "spack-develop/var/spack/repos/builtin/packages/zlib/w_patch.patch"
+            )),
+            None
+        );
+        assert_eq!(
+            package_from_path(Path::new("spack-develop/lib/spack/spack/package.py")),
+            None
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::termux;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use std::path::Path;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::Archive;
+
+/// Match `<prefix>/<repo>/<name>/build.sh`, subpackages have a `.subpackage.sh` of their own
+fn metadata_from_path(path: &Path) -> Option<(&str, &str)> {
+    let path = path.to_str()?;
+    let path = path.strip_suffix("/build.sh")?;
+    let mut parts = path.rsplit('/');
+    let package = parts.next()?;
+    let repo = parts.next()?;
+    // the top-level folder of the snapshot
+    parts.next()?;
+    // packages, root-packages and x11-packages
+    if parts.next().is_some() || !repo.ends_with("packages") || repo == "disabled-packages" {
+        return None;
+    }
+    Some((repo, package))
+}
+
+pub async fn run(args: &args::SyncTermux) -> Result<()> {
+    let db = db::Client::create().await?;
+    let vendor = &args.vendor;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    let mut errors = 0;
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?;
+        let Some((repo, package)) = metadata_from_path(&path) else {
+            continue;
+        };
+        let repo = repo.to_string();
+        let package = package.to_string();
+
+        let mut buf = String::new();
+        entry.read_to_string(&mut buf).await?;
+
+        let build = match termux::parse(&buf) {
+            Ok(build) => build,
+            Err(err) => {
+                error!("Failed to parse repo={repo:?} package={package:?}: {err:#}");
+                errors += 1;
+                continue;
+            }
+        };
+        let Some(version) = build.version() else {
+            warn!("Missing version for repo={repo:?} package={package:?}");
+            continue;
+        };
+
+        for (url, sha256) in build.artifacts() {
+            if sha256.len() != 64 {
+                debug!("Unexpected checksum length for Termux: {sha256:?}");
+                continue;
+            }
+            let chksum = format!("sha256:{}", sha256.to_lowercase());
+
+            let task = if db.resolve_artifact(&chksum).await?.is_none() {
+                utils::task_for_url(url)
+            } else {
+                None
+            };
+
+            let r = db::Ref {
+                chksum,
+                vendor: vendor.to_string(),
+                package: package.clone(),
+                version: version.clone(),
+                filename: Some(url.to_string()),
+            };
+            debug!("insert: {r:?}");
+            db.insert_ref(&r).await?;
+
+            if let Some(task) = task {
+                info!("Adding task: {task:?}");
+                db.insert_task(&task).await?;
+            }
+        }
+    }
+
+    if errors > 0 {
+        warn!("Encounted {errors} errors while processing snapshot");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_from_path() {
+        assert_eq!(
+            metadata_from_path(Path::new(
+                "termux-packages-master/packages/liblzma/build.sh"
+            )),
+            Some(("packages", "liblzma"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new(
+                "termux-packages-master/x11-packages/xterm/build.sh"
+            )),
+            Some(("x11-packages", "xterm"))
+        );
+        assert_eq!(
+            metadata_from_path(Path::new(
+                "termux-packages-master/packages/liblzma/liblzma-static.subpackage.sh"
+            )),
+            None
+        );
+        assert_eq!(
+            metadata_from_path(Path::new(
+                "termux-packages-master/disabled-packages/foo/build.sh"
+            )),
+            None
+        );
+        assert_eq!(
+            metadata_from_path(Path::new("termux-packages-master/scripts/build/build.sh")),
+            None
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use crate::args;
+use crate::db::{self, Task, TaskData};
+use crate::errors::*;
+use crate::utils;
+use async_compression::tokio::bufread::GzipDecoder;
+use futures::StreamExt;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::io::{self, AsyncReadExt};
+use tokio_tar::{Archive, EntryType};
+
+/// Keywords of the vcpkg cmake helpers that take a list of values
+const MULTI_VALUE_KEYWORDS: &[&str] = &["URLS", "PATCHES", "HEADERS"];
+/// Keywords of the vcpkg cmake helpers that don't take a value
+const FLAG_KEYWORDS: &[&str] = &["SKIP_SHA512", "SILENT_EXIT", "QUIET", "USE_TARBALL_API"];
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    pub version_semver: Option<String>,
+    pub version_date: Option<String>,
+    pub version_string: Option<String>,
+    #[serde(default)]
+    pub port_version: u32,
+}
+
+impl Manifest {
+    pub fn upstream_version(&self) -> Option<&str> {
+        self.version
+            .as_ref()
+            .or(self.version_semver.as_ref())
+            .or(self.version_date.as_ref())
+            .or(self.version_string.as_ref())
+            .map(String::as_str)
+    }
+
+    /// The version including the port revision, formatted like vcpkg does (`1.3.1#2`)
+    pub fn full_version(&self) -> Option<String> {
+        let version = self.upstream_version()?;
+        if self.port_version > 0 {
+            Some(format!("{version}#{}", self.port_version))
+        } else {
+            Some(version.to_string())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Source {
+    pub url: String,
+    pub sha512: String,
+}
+
+/// Split the arguments of a cmake function call, honoring double quotes
+fn split_args(txt: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_arg = false;
+
+    for c in txt.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+fn is_keyword(arg: &str) -> bool {
+    arg.starts_with(|c: char| c.is_ascii_uppercase())
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Find all calls to a cmake function and group their arguments by keyword
+///
+/// The first `positional` arguments are skipped, e.g. the output variable.
+fn find_calls(
+    portfile: &str,
+    function: &str,
+    positional: usize,
+) -> Vec<BTreeMap<String, Vec<String>>> {
+    let mut calls = Vec::new();
+    let mut remaining = portfile;
+    while let Some(idx) = remaining.find(function) {
+        let after = &remaining[idx + function.len()..];
+        let Some(after) = after.trim_start().strip_prefix('(') else {
+            remaining = after;
+            continue;
+        };
+        let Some(end) = after.find(')') else {
+            break;
+        };
+
+        let body = after[..end]
+            .lines()
+            .map(|line| line.split_once('#').map(|x| x.0).unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut call = BTreeMap::<String, Vec<String>>::new();
+        let mut keyword = None::<String>;
+        for arg in split_args(&body).into_iter().skip(positional) {
+            match keyword.take() {
+                Some(key) if MULTI_VALUE_KEYWORDS.contains(&key.as_str()) => {
+                    if is_keyword(&arg) {
+                        keyword = Some(arg.clone());
+                        call.entry(arg).or_default();
+                    } else {
+                        call.entry(key.clone()).or_default().push(arg);
+                        keyword = Some(key);
+                    }
+                }
+                Some(key) => {
+                    call.entry(key).or_default().push(arg);
+                }
+                None => {
+                    call.entry(arg.clone()).or_default();
+                    if !FLAG_KEYWORDS.contains(&arg.as_str()) {
+                        keyword = Some(arg);
+                    }
+                }
+            }
+        }
+        calls.push(call);
+        remaining = &after[end..];
+    }
+    calls
+}
+
+fn single<'a>(call: &'a BTreeMap<String, Vec<String>>, key: &str) -> Option<&'a str> {
+    call.get(key)
+        .and_then(|values| values.first())
+        .map(String::as_str)
+}
+
+/// Extract the upstream sources and their checksums from a `portfile.cmake`
+pub fn parse_portfile(portfile: &str, version: &str) -> Vec<Source> {
+    let mut sources = Vec::new();
+    let interpolate = |txt: &str| {
+        let txt = txt.replace("${VERSION}", version);
+        // skip anything we can't fully resolve
+        (!txt.contains("${")).then_some(txt)
+    };
+
+    for call in find_calls(portfile, "vcpkg_from_github", 0) {
+        let (Some(repo), Some(git_ref), Some(sha512)) = (
+            single(&call, "REPO"),
+            single(&call, "REF"),
+            single(&call, "SHA512"),
+        ) else {
+            continue;
+        };
+        let Some(git_ref) = interpolate(git_ref) else {
+            continue;
+        };
+        sources.push(Source {
+            url: format!("https://github.com/{repo}/archive/{git_ref}.tar.gz"),
+            sha512: sha512.to_lowercase(),
+        });
+    }
+
+    for call in find_calls(portfile, "vcpkg_download_distfile", 1) {
+        let (Some(url), Some(sha512)) = (single(&call, "URLS"), single(&call, "SHA512")) else {
+            continue;
+        };
+        let Some(url) = interpolate(url) else {
+            continue;
+        };
+        sources.push(Source {
+            url,
+            sha512: sha512.to_lowercase(),
+        });
+    }
+
+    sources
+}
+
+async fn import_port(
+    db: &db::Client,
+    vendor: &str,
+    manifest: &Manifest,
+    portfile: &str,
+) -> Result<()> {
+    let Some(upstream_version) = manifest.upstream_version() else {
+        return Ok(());
+    };
+    let Some(version) = manifest.full_version() else {
+        return Ok(());
+    };
+    let package = &manifest.name;
+
+    for source in parse_portfile(portfile, upstream_version) {
+        let chksum = format!("sha512:{}", source.sha512);
+        let url = source.url;
+        debug!("package={package:?} version={version:?} url={url:?} ({chksum})");
+
+        if !utils::is_possible_tar_artifact(&url) {
+            continue;
+        }
+
+        if db.resolve_artifact(&chksum).await?.is_none() {
+            info!("Adding download task: url={url:?}");
+            db.insert_task(&Task::new(
+                format!("fetch:{url}"),
+                &TaskData::FetchTar {
+                    url: url.to_string(),
+                    compression: None,
+                    success_ref: None,
+                },
+            )?)
+            .await?;
+        }
+
+        let obj = db::Ref {
+            chksum,
+            vendor: vendor.to_string(),
+            package: package.to_string(),
+            version: version.to_string(),
+            filename: Some(url),
+        };
+        info!("insert: {obj:?}");
+        db.insert_ref(&obj).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn run(args: &args::SyncVcpkg) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let reader = utils::fetch_or_open(&args.file, args.fetch).await?;
+    let reader = io::BufReader::new(reader);
+    let reader = GzipDecoder::new(reader);
+    let mut tar = Archive::new(reader);
+
+    let mut manifests = BTreeMap::new();
+    let mut portfiles = BTreeMap::new();
+
+    let mut entries = tar.entries()?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+
+        let path = entry.path()?.into_owned();
+        // the snapshot is expected to contain a single top-level directory
+        let mut components = path.iter().skip(1);
+        if components.next() != Some("ports".as_ref()) {
+            continue;
+        }
+        let (Some(port), Some(filename), None) =
+            (components.next(), components.next(), components.next())
+        else {
+            continue;
+        };
+        let port = port.to_string_lossy().into_owned();
+
+        if filename == Path::new("vcpkg.json") {
+            let mut buf = String::new();
+            entry.read_to_string(&mut buf).await?;
+            match serde_json::from_str::<Manifest>(&buf) {
+                Ok(manifest) => {
+                    manifests.insert(port, manifest);
+                }
+                Err(err) => warn!("Failed to parse vcpkg.json of port {port:?}: {err:#}"),
+            }
+        } else if filename == Path::new("portfile.cmake") {
+            let mut buf = String::new();
+            entry.read_to_string(&mut buf).await?;
+            portfiles.insert(port, buf);
+        }
+    }
+
+    for (port, manifest) in manifests {
+        let Some(portfile) = portfiles.get(&port) else {
+            continue;
+        };
+        import_port(&db, &args.vendor, &manifest, portfile).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let data = r#"{
+  "name": "zlib",
+  "version": "1.3.1",
+  "port-version": 2,
+  "description": "A compression library",
+  "homepage": "https://www.zlib.net/",
+  "license": "Zlib"
+}"#;
+        let manifest = serde_json::from_str::<Manifest>(data).unwrap();
+        assert_eq!(manifest.upstream_version(), Some("1.3.1"));
+        assert_eq!(manifest.full_version(), Some("1.3.1#2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_portfile_github() {
+        let data = r#"vcpkg_from_github(
+    OUT_SOURCE_PATH SOURCE_PATH
+    REPO madler/zlib
+    REF "v${VERSION}"
+    SHA512 8C9642495BAFD6FAD4AB9FB67F09B268C69FF9AF0F4F20CF15DFC18852FF1F312BD8CA41DE761B3F8D8E90E77D79F2CCACD3D4C5B19E475ECF09D021FDFE9088
+    HEAD_REF master
+    PATCHES
+        0001-Prevent-invalid-inclusions-when-HAVE_-is-set-to-0.patch
+        0002-build-static-or-shared-not-both.patch
+)
+
+# This is generated during the cmake build
+file(REMOVE "${SOURCE_PATH}/zconf.h")
+"#;
+        let sources = parse_portfile(data, "1.3.1");
+        assert_eq!(sources, vec![Source {
+            url: "https://github.com/madler/zlib/archive/v1.3.1.tar.gz".to_string(),
+            sha512: "8c9642495bafd6fad4ab9fb67f09b268c69ff9af0f4f20cf15dfc18852ff1f312bd8ca41de761b3f8d8e90e77d79f2ccacd3d4c5b19e475ecf09d021fdfe9088".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_portfile_distfile() {
+        let data = r#"vcpkg_download_distfile(ARCHIVE
+    URLS "https://github.com/tukaani-project/xz/releases/download/v${VERSION}/xz-${VERSION}.tar.gz"
+         "https://tukaani.org/xz/xz-${VERSION}.tar.gz"
+    FILENAME "xz-${VERSION}.tar.gz"
+    SHA512 b6ad3ea3e6a0e5e4ff6bf1f3b9cd8c36a6dc7e8d1d6a4bb9a0d0b6b8e0d0a5e8b3d5c5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7
+)
+vcpkg_extract_source_archive(SOURCE_PATH ARCHIVE "${ARCHIVE}")
+"#;
+        let sources = parse_portfile(data, "5.4.6");
+        assert_eq!(sources, vec![Source {
+            url: "https://github.com/tukaani-project/xz/releases/download/v5.4.6/xz-5.4.6.tar.gz".to_string(),
+            sha512: "b6ad3ea3e6a0e5e4ff6bf1f3b9cd8c36a6dc7e8d1d6a4bb9a0d0b6b8e0d0a5e8b3d5c5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn test_parse_portfile_unresolved() {
+        let data = r#"vcpkg_from_github(
+    OUT_SOURCE_PATH SOURCE_PATH
+    REPO example/example
+    REF "${EXAMPLE_COMMIT}"
+    SHA512 0
+)"#;
+        assert_eq!(parse_portfile(data, "1.0"), vec![]);
+    }
+}
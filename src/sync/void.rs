@@ -56,11 +56,11 @@ pub async fn run(args: &args::SyncVoid) -> Result<()> {
                 return Err(Error::InvalidData);
             };
 
-            // mark all refs known for this package as "last_seen now"
-            db.bump_named_refs(vendor, &pkgname, version).await?;
-
-            // check if package already imported
-            if db.get_package(vendor, &pkgname, version).await?.is_some() {
+            // mark all refs known for this package as "last_seen now", skip if already imported
+            if !db
+                .refresh_package(vendor, &pkgname, version, version)
+                .await?
+            {
                 debug!("Package is already imported: srcpkg={srcpkg:?} commit={commit:?} package={pkgname:?} version={version:?}");
                 continue;
             }
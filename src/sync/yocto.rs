@@ -1,10 +1,12 @@
 use crate::args;
 use crate::db;
 use crate::errors::*;
+use crate::upstream;
 use crate::utils;
 use crate::yocto;
 use async_compression::tokio::bufread::GzipDecoder;
 use futures::StreamExt;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 use tokio::io::{self, AsyncReadExt};
 use tokio_tar::Archive;
@@ -22,6 +24,51 @@ fn metadata_from_path(path: &Path) -> Option<(&str, &str)> {
     Some((package, version))
 }
 
+/// Join a relative path onto a directory, resolving `.` and `..`
+fn join(dir: &str, path: &str) -> String {
+    let mut out = dir.split('/').filter(|c| !c.is_empty()).collect::<Vec<_>>();
+    for component in path.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out.join("/")
+}
+
+/// The files of all layers in the snapshot, used to resolve `require` and `include`
+#[derive(Debug, Default)]
+struct Layers {
+    files: HashMap<String, String>,
+    /// Directories that contain `recipes-*` folders
+    roots: BTreeSet<String>,
+}
+
+impl Layers {
+    fn add(&mut self, path: String, content: String) {
+        if let Some((root, _)) = path.split_once("/recipes") {
+            self.roots.insert(root.to_string());
+        }
+        self.files.insert(path, content);
+    }
+
+    /// Search the directory of the including file first, then the root of each layer (like BBPATH)
+    fn resolve(&self, from: &str, path: &str) -> Option<(String, String)> {
+        let dir = from.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        [dir]
+            .into_iter()
+            .chain(self.roots.iter().map(String::as_str))
+            .map(|dir| join(dir, path))
+            .find_map(|path| {
+                let content = self.files.get(&path)?.clone();
+                Some((path, content))
+            })
+    }
+}
+
 pub async fn run(args: &args::SyncYocto) -> Result<()> {
     let db = db::Client::create().await?;
     let vendor = &args.vendor;
@@ -31,8 +78,10 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
     let reader = GzipDecoder::new(reader);
     let mut tar = Archive::new(reader);
 
+    let mut layers = Layers::default();
+    let mut recipes = Vec::new();
+
     let mut entries = tar.entries()?;
-    let mut errors = 0;
     while let Some(entry) = entries.next().await {
         let mut entry = entry?;
         if !entry.header().entry_type().is_file() {
@@ -40,17 +89,33 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
         }
 
         let path = entry.path()?;
-        let Some((package, version)) = metadata_from_path(&path) else {
+        let Some(path) = path.to_str().map(|p| join("", p)) else {
             continue;
         };
-
-        let package = package.to_string();
-        let version = version.to_string();
+        if !path.ends_with(".bb") && !path.ends_with(".inc") {
+            continue;
+        }
 
         let mut buf = String::new();
         entry.read_to_string(&mut buf).await?;
 
-        let pkg = match yocto::parse(&buf, Some(package.clone()), Some(version.clone())) {
+        if let Some((package, version)) = metadata_from_path(Path::new(&path)) {
+            recipes.push((path.clone(), package.to_string(), version.to_string()));
+        }
+        layers.add(path, buf);
+    }
+
+    let mut errors = 0;
+    for (path, package, version) in recipes {
+        let script = &layers.files[&path];
+        let resolve = |from: &str, include: &str| layers.resolve(from, include);
+        let pkg = match yocto::parse_with_includes(
+            &path,
+            script,
+            Some(package.clone()),
+            Some(version.clone()),
+            &resolve,
+        ) {
             Ok(pkg) => pkg,
             Err(err) => {
                 error!("Failed to parse package={package:?} version={version:?}: {err:#}");
@@ -59,6 +124,10 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
             }
         };
 
+        if let Some(url) = pkg.homepage() {
+            upstream::insert(&db, &url, vendor, &package).await?;
+        }
+
         let artifacts = match pkg.artifacts() {
             Ok(list) => list,
             Err(err) => {
@@ -71,6 +140,10 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
         for artifact in artifacts {
             let (chksum, url) = match (artifact.sha256, artifact.commit) {
                 (Some(sha256), _) => (format!("sha256:{sha256}"), artifact.src),
+                (_, Some(commit)) if artifact.src.starts_with("hg+") => (
+                    format!("hg:{commit}"),
+                    format!("{}#rev={commit}", artifact.src),
+                ),
                 (_, Some(commit)) => (
                     format!("git:{commit}"),
                     format!("{}#commit={commit}", artifact.src),
@@ -79,7 +152,7 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
             };
 
             let task = if db.resolve_artifact(&chksum).await?.is_none() {
-                utils::task_for_url(&url)
+                utils::task_for_ref(&url, &chksum)
             } else {
                 None
             };
@@ -107,3 +180,49 @@ pub async fn run(args: &args::SyncYocto) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_includes() {
+        let mut layers = Layers::default();
+        layers.add(
+            "poky/meta/recipes-devtools/gcc/gcc-common.inc".to_string(),
+            "common".to_string(),
+        );
+        layers.add(
+            "poky/meta/recipes-devtools/gcc/gcc-14.2.inc".to_string(),
+            "gcc".to_string(),
+        );
+        layers.add(
+            "poky/meta/recipes-devtools/gcc/gcc_14.2.bb".to_string(),
+            String::new(),
+        );
+        let from = "poky/meta/recipes-devtools/gcc/gcc_14.2.bb";
+
+        assert_eq!(layers.resolve(from, "gcc-15.1.inc"), None);
+        assert_eq!(
+            layers.resolve(from, "gcc-14.2.inc"),
+            Some((
+                "poky/meta/recipes-devtools/gcc/gcc-14.2.inc".to_string(),
+                "gcc".to_string()
+            ))
+        );
+        assert_eq!(
+            layers.resolve(from, "recipes-devtools/gcc/gcc-common.inc"),
+            Some((
+                "poky/meta/recipes-devtools/gcc/gcc-common.inc".to_string(),
+                "common".to_string()
+            ))
+        );
+        assert_eq!(
+            layers.resolve(from, "../gcc/./gcc-common.inc"),
+            Some((
+                "poky/meta/recipes-devtools/gcc/gcc-common.inc".to_string(),
+                "common".to_string()
+            ))
+        );
+    }
+}
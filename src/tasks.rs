@@ -0,0 +1,61 @@
+use crate::args;
+use crate::db;
+use crate::errors::*;
+
+pub async fn run_requeue(args: &args::Requeue) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let prefix = args.prefix.strip_suffix('*').unwrap_or(&args.prefix);
+    let num = db.requeue_tasks(prefix).await?;
+    info!("Reset error counter of {num} tasks (prefix={prefix:?})");
+
+    Ok(())
+}
+
+/// Mark superseded tasks as obsolete and delete them, returns the number of deleted tasks
+pub async fn prune(db: &db::Client, failed_days: Option<i64>) -> Result<u64> {
+    let marked = db.mark_superseded_tasks().await?;
+    debug!("Marked {marked} tasks as obsolete");
+    db.prune_tasks(failed_days).await
+}
+
+pub async fn run(args: &args::Tasks) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    match &args.subcommand {
+        args::TasksCmd::ListFailed(args) => {
+            for task in db.get_failed_tasks(args.limit).await? {
+                let failed_at = task.failed_at.map(|t| t.to_rfc3339()).unwrap_or_default();
+                println!(
+                    "{} {} failed_at={failed_at} error={:?}",
+                    task.id,
+                    task.key,
+                    task.error.as_deref().unwrap_or("")
+                );
+            }
+        }
+        args::TasksCmd::Retry(args) => {
+            for id in &args.ids {
+                if db.retry_task(*id).await? {
+                    info!("Task has been requeued: id={id}");
+                } else {
+                    warn!("Task not found: id={id}");
+                }
+            }
+        }
+        args::TasksCmd::Prune(args) => {
+            if args.dry_run {
+                let tasks = db.get_superseded_tasks().await?;
+                for task in &tasks {
+                    println!("{} {}", task.id, task.key);
+                }
+                info!("Would mark {} tasks as obsolete", tasks.len());
+            } else {
+                let num = prune(&db, args.failed_days).await?;
+                info!("Deleted {num} tasks");
+            }
+        }
+    }
+
+    Ok(())
+}
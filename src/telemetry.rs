@@ -0,0 +1,61 @@
+use crate::errors::*;
+
+/// Flushes the remaining spans when dropped at the end of `main`
+#[derive(Default)]
+pub struct Guard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                warn!("Failed to flush tracing spans: {err:#}");
+            }
+        }
+    }
+}
+
+/// Export spans with OTLP/gRPC if `OTEL_EXPORTER_OTLP_ENDPOINT` is set
+///
+/// Logging is still done by env_logger, spans only show up in the collector.
+#[cfg(feature = "otel")]
+pub fn init() -> Result<Guard> {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        return Ok(Guard::default());
+    };
+    info!("Exporting tracing spans to {endpoint:?}");
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+
+    Ok(Guard {
+        provider: Some(provider),
+    })
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init() -> Result<Guard> {
+    Ok(Guard::default())
+}
@@ -0,0 +1,201 @@
+use crate::errors::*;
+use std::collections::HashMap;
+use yash_syntax::syntax::{self, Unquote, Value};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Build {
+    pub version: Option<String>,
+    pub revision: Option<String>,
+    pub srcurl: Vec<String>,
+    pub sha256: Vec<String>,
+}
+
+impl Build {
+    /// The version, as it shows up in the apt repository
+    pub fn version(&self) -> Option<String> {
+        let version = self.version.as_ref()?;
+        Some(match &self.revision {
+            Some(revision) if revision != "0" => format!("{version}-{revision}"),
+            _ => version.clone(),
+        })
+    }
+
+    /// Pair the urls with their checksum, like termux_step_get_source does
+    pub fn artifacts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.srcurl
+            .iter()
+            .map(String::as_str)
+            .zip(self.sha256.iter().map(String::as_str))
+    }
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Expand `$FOO` and `${FOO}`, anything more elaborate (like `${FOO#*:}`) is rejected
+fn resolve_vars(vars: &HashMap<String, String>, mut text: &str) -> Result<String> {
+    let mut out = String::new();
+    while let Some((before, after)) = text.split_once('$') {
+        out.push_str(before);
+        let (name, after) = if let Some(after) = after.strip_prefix('{') {
+            let Some((name, after)) = after.split_once('}') else {
+                return Err(Error::InvalidPkgbuild("Missing closing }".to_string()));
+            };
+            (name, after)
+        } else {
+            let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+            after.split_at(end)
+        };
+        if name.is_empty() || !name.chars().all(is_name) {
+            return Err(Error::UnknownVariable(name.to_string()));
+        }
+        let Some(value) = vars.get(name) else {
+            return Err(Error::UnknownVariable(name.to_string()));
+        };
+        out.push_str(value);
+        text = after;
+    }
+    out.push_str(text);
+    Ok(out)
+}
+
+/// Read the top-level assignments of a termux-packages `build.sh`
+pub fn parse(script: &str) -> Result<Build> {
+    let parsed: syntax::List = script
+        .parse()
+        .map_err(|err| Error::InvalidPkgbuild(format!("{err:#?}")))?;
+
+    let mut vars = HashMap::new();
+    let mut build = Build::default();
+
+    for item in &parsed.0 {
+        for cmd in &item.and_or.first.commands {
+            let syntax::Command::Simple(cmd) = cmd.as_ref() else {
+                continue;
+            };
+
+            for assign in &cmd.assigns {
+                let name = assign.name.as_str();
+                let values = match &assign.value {
+                    Value::Scalar(value) => vec![value.unquote().0],
+                    Value::Array(values) => values.iter().map(|v| v.unquote().0).collect(),
+                };
+                debug!("Found variable in build.sh: key={name:?} values={values:?}");
+
+                let values = match values
+                    .iter()
+                    .map(|value| resolve_vars(&vars, value))
+                    .collect::<Result<Vec<_>>>()
+                {
+                    Ok(values) => values,
+                    Err(err) => {
+                        debug!("Failed to evaluate {name:?} in build.sh: {err:#}");
+                        continue;
+                    }
+                };
+
+                match name {
+                    // multiple urls are separated by whitespace in old build.sh files
+                    "TERMUX_PKG_SRCURL" => {
+                        build.srcurl = values
+                            .iter()
+                            .flat_map(|v| v.split_whitespace())
+                            .map(String::from)
+                            .collect();
+                    }
+                    "TERMUX_PKG_SHA256" => {
+                        build.sha256 = values
+                            .iter()
+                            .flat_map(|v| v.split_whitespace())
+                            .map(String::from)
+                            .collect();
+                    }
+                    _ => {
+                        let Some(value) = values.into_iter().next() else {
+                            continue;
+                        };
+                        match name {
+                            "TERMUX_PKG_VERSION" => build.version = Some(value.clone()),
+                            "TERMUX_PKG_REVISION" => build.revision = Some(value.clone()),
+                            _ => (),
+                        }
+                        vars.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(build)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_liblzma() {
+        let script = r#"TERMUX_PKG_HOMEPAGE=https://tukaani.org/xz/
+TERMUX_PKG_DESCRIPTION="XZ-format compression library"
+TERMUX_PKG_LICENSE="LGPL-2.1, GPL-2.0"
+TERMUX_PKG_MAINTAINER="@termux"
+TERMUX_PKG_VERSION="5.6.3"
+TERMUX_PKG_REVISION=1
+TERMUX_PKG_SRCURL=https://github.com/tukaani-project/xz/releases/download/v${TERMUX_PKG_VERSION}/xz-${TERMUX_PKG_VERSION}.tar.xz
+TERMUX_PKG_SHA256=db0590629b6f0fa36e74aea5f9731dc6f8df068ce7b7bafa45301832a5eebc3a
+TERMUX_PKG_AUTO_UPDATE=true
+TERMUX_PKG_BREAKS="liblzma-dev"
+
+termux_step_pre_configure() {
+	TERMUX_PKG_SRCURL=https://example.com/not-this.tar.gz
+}
+"#;
+        let build = parse(script).unwrap();
+        assert_eq!(build.version().as_deref(), Some("5.6.3-1"));
+        assert_eq!(
+            build.artifacts().collect::<Vec<_>>(),
+            [(
+                "https://github.com/tukaani-project/xz/releases/download/v5.6.3/xz-5.6.3.tar.xz",
+                "db0590629b6f0fa36e74aea5f9731dc6f8df068ce7b7bafa45301832a5eebc3a"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_sources() {
+        let script = r#"_COMMIT=5f2a0b1
+TERMUX_PKG_VERSION=1.2.0
+TERMUX_PKG_SRCURL=(https://example.com/foo-$TERMUX_PKG_VERSION.tar.gz
+                   https://github.com/example/bar/archive/${_COMMIT}.tar.gz)
+TERMUX_PKG_SHA256=(0000000000000000000000000000000000000000000000000000000000000000
+                   1111111111111111111111111111111111111111111111111111111111111111)
+"#;
+        let build = parse(script).unwrap();
+        assert_eq!(build.version().as_deref(), Some("1.2.0"));
+        assert_eq!(
+            build.artifacts().collect::<Vec<_>>(),
+            [
+                (
+                    "https://example.com/foo-1.2.0.tar.gz",
+                    "0000000000000000000000000000000000000000000000000000000000000000"
+                ),
+                (
+                    "https://github.com/example/bar/archive/5f2a0b1.tar.gz",
+                    "1111111111111111111111111111111111111111111111111111111111111111"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_expansion() {
+        let script = r#"TERMUX_PKG_VERSION=1:2.0.1
+TERMUX_PKG_SRCURL=https://example.com/foo-${TERMUX_PKG_VERSION#*:}.tar.gz
+TERMUX_PKG_SHA256=0000000000000000000000000000000000000000000000000000000000000000
+"#;
+        let build = parse(script).unwrap();
+        assert_eq!(build.srcurl, Vec::<String>::new());
+        assert_eq!(build.artifacts().count(), 0);
+    }
+}
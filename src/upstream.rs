@@ -0,0 +1,218 @@
+use crate::chksums::{Checksums, Hasher};
+use crate::db::{self, NewUpstreamCheck};
+use crate::errors::*;
+use crate::utils::HttpClient;
+use reqwest::StatusCode;
+use tokio::io;
+use tokio_util::sync::CancellationToken;
+
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_GONE: &str = "gone";
+pub const STATUS_REPLACED: &str = "replaced";
+/// The check was inconclusive, previous findings are kept
+pub const STATUS_ERROR: &str = "error";
+
+/// How long a verified url is left alone before it's checked again
+const REVERIFY_AFTER_DAYS: i32 = 7;
+/// Larger files are only checked for existence
+const MAX_DOWNLOAD_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Normalize the homepage of a project, so different spellings of the same url are grouped together
+pub fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let rest = rest.split_once('#').map(|(rest, _)| rest).unwrap_or(rest);
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if host.is_empty() {
+        return None;
+    }
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if path.is_empty() {
+        Some(format!("https://{host}"))
+    } else {
+        Some(format!("https://{host}/{path}"))
+    }
+}
+
+/// Record the upstream project of a package, urls that aren't http(s) are ignored
+pub async fn insert(db: &db::Client, url: &str, vendor: &str, package: &str) -> Result<()> {
+    let Some(url) = normalize_url(url) else {
+        debug!("Ignoring upstream url for package={package:?}: {url:?}");
+        return Ok(());
+    };
+    db.insert_upstream(&url, vendor, package).await
+}
+
+/// The digest of the same checksum family, `None` if we don't compute this family
+fn observed_digest<'a>(expected: &str, digests: &'a Checksums) -> Option<&'a str> {
+    let (family, _) = expected.split_once(':')?;
+    match family {
+        "sha256" => Some(&digests.sha256),
+        "sha512" => Some(&digests.sha512),
+        "blake2b" => Some(&digests.blake2b),
+        _ => None,
+    }
+}
+
+fn is_verifiable(chksum: &str) -> bool {
+    matches!(
+        chksum.split_once(':'),
+        Some(("sha256" | "sha512" | "blake2b", _))
+    )
+}
+
+fn is_gone(status: StatusCode) -> bool {
+    matches!(status, StatusCode::NOT_FOUND | StatusCode::GONE)
+}
+
+/// Compare the downloaded content with the checksum of the ref
+fn compare(expected: &str, digests: &Checksums) -> (&'static str, Option<String>) {
+    match observed_digest(expected, digests) {
+        Some(observed) if observed != expected => (STATUS_REPLACED, Some(observed.to_string())),
+        _ => (STATUS_OK, None),
+    }
+}
+
+async fn download_digests(http: &HttpClient, url: &str) -> Result<Checksums> {
+    let reader = http.fetch(url).await?;
+    let mut reader = Hasher::new(reader);
+    io::copy(&mut reader, &mut io::sink()).await?;
+    let (_, digests) = reader.digests();
+    Ok(digests)
+}
+
+/// HEAD the url and, if it still exists, download it again to compare the checksum
+pub async fn verify(http: &HttpClient, url: &str, chksum: &str) -> NewUpstreamCheck {
+    let mut check = NewUpstreamCheck {
+        url: url.to_string(),
+        chksum: chksum.to_string(),
+        status: STATUS_ERROR,
+        http_status: None,
+        observed_chksum: None,
+    };
+
+    let (status, content_length) = match http.head(url).await {
+        Ok(head) => head,
+        Err(err) => {
+            warn!("Failed to check upstream url={url:?}: {err:#}");
+            return check;
+        }
+    };
+    check.http_status = Some(status.as_u16().into());
+
+    if is_gone(status) {
+        check.status = STATUS_GONE;
+        return check;
+    }
+    // some servers don't implement HEAD, the download tells us more
+    if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+        return check;
+    }
+    if !is_verifiable(chksum) || content_length.is_some_and(|len| len > MAX_DOWNLOAD_SIZE) {
+        check.status = STATUS_OK;
+        return check;
+    }
+
+    match download_digests(http, url).await {
+        Ok(digests) => {
+            let (status, observed) = compare(chksum, &digests);
+            check.status = status;
+            check.observed_chksum = observed;
+        }
+        Err(Error::Reqwest(err)) if err.status().is_some_and(is_gone) => {
+            check.status = STATUS_GONE;
+            check.http_status = err.status().map(|s| s.as_u16().into());
+        }
+        Err(err) => warn!("Failed to download upstream url={url:?}: {err:#}"),
+    }
+    check
+}
+
+/// Verify the upstream urls that haven't been checked recently, returns the number of checks
+///
+/// Stops early, without recording a result for the current url, once `shutdown` is cancelled.
+pub async fn verify_once(
+    db: &db::Client,
+    http: &HttpClient,
+    limit: i64,
+    shutdown: &CancellationToken,
+) -> Result<usize> {
+    let candidates = db
+        .get_upstream_check_candidates(REVERIFY_AFTER_DAYS, limit)
+        .await?;
+    let mut checked = 0;
+    for (url, chksum) in &candidates {
+        let check = tokio::select! {
+            check = verify(http, url, chksum) => check,
+            _ = shutdown.cancelled() => break,
+        };
+        match check.status {
+            STATUS_GONE => warn!("Upstream artifact vanished: url={url:?} chksum={chksum:?}"),
+            STATUS_REPLACED => warn!(
+                "Upstream artifact was replaced: url={url:?} chksum={chksum:?} observed={:?}",
+                check.observed_chksum
+            ),
+            _ => debug!("Verified upstream url={url:?}: {:?}", check.status),
+        }
+        db.insert_upstream_check(&check).await?;
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests() -> Checksums {
+        Checksums {
+            sha256: "sha256:aaaa".to_string(),
+            sha512: "sha512:bbbb".to_string(),
+            blake2b: "blake2b:cccc".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("sha256:aaaa", &digests()), (STATUS_OK, None));
+        assert_eq!(
+            compare("sha512:ffff", &digests()),
+            (STATUS_REPLACED, Some("sha512:bbbb".to_string()))
+        );
+        // we can't tell, only the existence is checked
+        assert_eq!(compare("md5:ffff", &digests()), (STATUS_OK, None));
+        assert!(!is_verifiable(
+            "git:7747534db4576db43eced4356859ef400351ca28"
+        ));
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        for url in [
+            "https://tukaani.org/xz/",
+            "http://www.tukaani.org/xz",
+            "https://Tukaani.org/xz#about",
+        ] {
+            assert_eq!(
+                normalize_url(url).as_deref(),
+                Some("https://tukaani.org/xz"),
+                "{url:?}"
+            );
+        }
+        assert_eq!(
+            normalize_url("https://github.com/tukaani-project/xz.git").as_deref(),
+            Some("https://github.com/tukaani-project/xz")
+        );
+        assert_eq!(
+            normalize_url("https://www.gnu.org/").as_deref(),
+            Some("https://gnu.org")
+        );
+        assert_eq!(normalize_url("ftp://ftp.gnu.org/gnu/mpc"), None);
+        assert_eq!(normalize_url("https://"), None);
+    }
+}
@@ -1,9 +1,22 @@
 use crate::db::{Task, TaskData};
 use crate::errors::*;
+use crate::ingest::{self, git::GitUrl};
 use futures::TryStreamExt;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::{Method, RequestBuilder};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
 use std::time::Duration;
 use tokio::fs;
-use tokio::io::{self, AsyncRead};
+use tokio::io::{self, AsyncRead, ReadBuf};
+use tokio::signal::{self, unix::SignalKind};
+use tokio::time::{self, Instant};
 use tokio_util::io::StreamReader;
 
 pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
@@ -11,33 +24,445 @@ pub const READ_TIMEOUT: Duration = Duration::from_secs(60);
 // do not immediately give away who we are, version string is from Debian bookworm
 pub const USER_AGENT: &str = "curl/7.88.1";
 
+/// Links point to this instance, unless `WHATSRC_HTTP_INSTANCE_URL` is set
+pub const DEFAULT_INSTANCE_URL: &str = "https://whatsrc.org";
+
+/// How we identify ourselves to the servers we download from
+///
+/// Configured with `WHATSRC_HTTP_INSTANCE_URL`, `WHATSRC_HTTP_CONTACT` and
+/// `WHATSRC_HTTP_USER_AGENT_OVERRIDES` (formatted like `host=user-agent,host2=user-agent`).
+#[derive(Debug, Default, PartialEq)]
+pub struct HttpIdentity {
+    pub instance_url: Option<String>,
+    pub contact: Option<String>,
+    pub overrides: HashMap<String, String>,
+}
+
+impl HttpIdentity {
+    pub fn from_env() -> Self {
+        let var = |key| {
+            env::var(key)
+                .ok()
+                .filter(|value: &String| !value.is_empty())
+        };
+        HttpIdentity {
+            instance_url: var("WHATSRC_HTTP_INSTANCE_URL"),
+            contact: var("WHATSRC_HTTP_CONTACT"),
+            overrides: var("WHATSRC_HTTP_USER_AGENT_OVERRIDES")
+                .map(|value| Self::parse_overrides(&value))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn parse_overrides(value: &str) -> HashMap<String, String> {
+        value
+            .split(',')
+            .filter_map(|item| item.split_once('='))
+            .map(|(host, user_agent)| (host.trim().to_string(), user_agent.trim().to_string()))
+            .collect()
+    }
+
+    pub fn user_agent(&self) -> Cow<'static, str> {
+        let details = [self.instance_url.as_deref(), self.contact.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        if details.is_empty() {
+            Cow::Borrowed(USER_AGENT)
+        } else {
+            let version = env!("CARGO_PKG_VERSION");
+            Cow::Owned(format!("what-the-src/{version} (+{})", details.join("; ")))
+        }
+    }
+
+    /// The public url of this instance without trailing slash, used for links we send out
+    pub fn base_url(&self) -> &str {
+        self.instance_url
+            .as_deref()
+            .unwrap_or(DEFAULT_INSTANCE_URL)
+            .trim_end_matches('/')
+    }
+
+    pub fn user_agent_for(&self, url: &str) -> Option<&str> {
+        let url = reqwest::Url::parse(url).ok()?;
+        let host = url.host_str()?;
+        self.overrides.get(host).map(String::as_str)
+    }
+}
+
+/// Maximum size of artifacts we download, so a single enormous file can't fill the disk
+///
+/// Configured with `WHATSRC_MAX_DOWNLOAD_SIZE` and `WHATSRC_MAX_DOWNLOAD_SIZE_HOSTS`
+/// (formatted like `host=size,host2=size`), sizes are in bytes or with a `K`, `M` or `G` suffix.
+#[derive(Debug, Default, PartialEq)]
+pub struct SizeLimits {
+    pub default: Option<u64>,
+    pub hosts: HashMap<String, u64>,
+}
+
+impl SizeLimits {
+    pub fn from_env() -> Result<Self> {
+        let var = |key| {
+            env::var(key)
+                .ok()
+                .filter(|value: &String| !value.is_empty())
+        };
+        let default = var("WHATSRC_MAX_DOWNLOAD_SIZE")
+            .map(|value| Self::parse_size(&value))
+            .transpose()?;
+        let hosts = var("WHATSRC_MAX_DOWNLOAD_SIZE_HOSTS")
+            .map(|value| Self::parse_hosts(&value))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(SizeLimits { default, hosts })
+    }
+
+    pub fn parse_size(value: &str) -> Result<u64> {
+        let value = value.trim();
+        let (num, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+            Some(idx) => value.split_at(idx),
+            None => (value, ""),
+        };
+        let unit = match unit {
+            "" => 1,
+            "K" => 1024,
+            "M" => 1024 * 1024,
+            "G" => 1024 * 1024 * 1024,
+            _ => return Err(Error::InvalidSizeLimit(value.to_string())),
+        };
+        let num = num
+            .parse::<u64>()
+            .map_err(|_| Error::InvalidSizeLimit(value.to_string()))?;
+        num.checked_mul(unit)
+            .ok_or_else(|| Error::InvalidSizeLimit(value.to_string()))
+    }
+
+    pub fn parse_hosts(value: &str) -> Result<HashMap<String, u64>> {
+        value
+            .split(',')
+            .filter_map(|item| item.split_once('='))
+            .map(|(host, size)| Ok((host.trim().to_string(), Self::parse_size(size)?)))
+            .collect()
+    }
+
+    /// The limit for this url, a host specific limit takes precedence
+    pub fn limit_for(&self, url: &str) -> Option<u64> {
+        let host_limit = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| self.hosts.get(url.host_str()?).copied());
+        host_limit.or(self.default)
+    }
+}
+
+/// Fails the read once more than `limit` bytes went through
+struct SizeLimited<R> {
+    reader: R,
+    read: u64,
+    limit: u64,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for SizeLimited<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let Poll::Ready(result) = Pin::new(&mut self.reader).poll_read(cx, buf) else {
+            return Poll::Pending;
+        };
+        self.read += (buf.filled().len() - before) as u64;
+        if self.read > self.limit {
+            self.exceeded.store(true, Ordering::Relaxed);
+            // a failed read must not hand out any data
+            buf.set_filled(before);
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Download exceeds the size limit of {} bytes", self.limit),
+            )));
+        }
+        Poll::Ready(result)
+    }
+}
+
+/// Tells if a download was aborted because of the size limit
+///
+/// Parsers don't reliably pass the io error through, so this is checked after processing.
+#[derive(Debug, Default)]
+pub struct SizeGuard {
+    limit: Option<u64>,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl SizeGuard {
+    /// Returns the limit if it was exceeded
+    pub fn exceeded(&self) -> Option<u64> {
+        self.limit.filter(|_| self.exceeded.load(Ordering::Relaxed))
+    }
+}
+
 pub fn http_client(socks5: Option<&String>) -> Result<HttpClient> {
+    let identity = HttpIdentity::from_env();
+    let size_limits = SizeLimits::from_env()?;
+
     let mut http = reqwest::ClientBuilder::new();
     if let Some(socks5) = socks5 {
         http = http.proxy(reqwest::Proxy::all(socks5)?);
     }
+    if let Some(contact) = &identity.contact {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(contact) {
+            headers.insert(header::FROM, value);
+        }
+        http = http.default_headers(headers);
+    }
     let http = http
-        .user_agent(USER_AGENT)
+        .user_agent(identity.user_agent().as_ref())
         .connect_timeout(CONNECT_TIMEOUT)
         .read_timeout(READ_TIMEOUT)
         .build()?;
-    Ok(HttpClient { reqwest: http })
+    Ok(HttpClient {
+        reqwest: http,
+        identity,
+        size_limits,
+        rate_limiter: HostRateLimiter::default(),
+    })
+}
+
+/// Spaces out requests to the same host, so we don't hammer a single mirror
+#[derive(Debug, Default)]
+pub struct HostRateLimiter {
+    delay: Duration,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl HostRateLimiter {
+    pub fn new(delay: Duration) -> Self {
+        HostRateLimiter {
+            delay,
+            next_slot: Mutex::default(),
+        }
+    }
+
+    /// Reserve the next free slot for this host
+    pub fn reserve(&self, host: &str, now: Instant) -> Instant {
+        let mut slots = self.next_slot.lock().unwrap();
+        let slot = slots
+            .get(host)
+            .copied()
+            .filter(|slot| *slot > now)
+            .unwrap_or(now);
+        slots.insert(host.to_string(), slot + self.delay);
+        slot
+    }
+
+    pub async fn wait(&self, url: &str) {
+        if self.delay.is_zero() {
+            return;
+        }
+        let Ok(url) = reqwest::Url::parse(url) else {
+            return;
+        };
+        let Some(host) = url.host_str() else {
+            return;
+        };
+        let slot = self.reserve(host, Instant::now());
+        time::sleep_until(slot).await;
+    }
 }
 
 pub struct HttpClient {
     reqwest: reqwest::Client,
+    identity: HttpIdentity,
+    size_limits: SizeLimits,
+    rate_limiter: HostRateLimiter,
 }
 
 impl HttpClient {
-    pub async fn fetch(&self, url: &str) -> Result<Box<dyn AsyncRead + Unpin>> {
-        let resp = self.reqwest.get(url).send().await?.error_for_status()?;
+    pub fn rate_limit(&mut self, delay: Duration) {
+        self.rate_limiter = HostRateLimiter::new(delay);
+    }
+
+    /// Wait for the rate limiter and start a request with the user-agent configured for this host
+    async fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.rate_limiter.wait(url).await;
+        let mut req = self.reqwest.request(method, url);
+        if let Some(user_agent) = self.identity.user_agent_for(url) {
+            req = req.header(header::USER_AGENT, user_agent);
+        }
+        req
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn fetch(&self, url: &str) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        let resp = self
+            .request(Method::GET, url)
+            .await
+            .send()
+            .await?
+            .error_for_status()?;
         let stream = resp.bytes_stream();
         let stream = StreamReader::new(stream.map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
         Ok(Box::new(stream))
     }
+
+    /// Like `fetch`, but enforce the size limit for artifacts
+    ///
+    /// An announced Content-Length above the limit fails early with `Error::DownloadTooLarge`.
+    #[tracing::instrument(skip(self))]
+    pub async fn fetch_artifact(
+        &self,
+        url: &str,
+    ) -> Result<(Box<dyn AsyncRead + Send + Unpin>, SizeGuard)> {
+        let Some(limit) = self.size_limits.limit_for(url) else {
+            return Ok((self.fetch(url).await?, SizeGuard::default()));
+        };
+
+        let resp = self
+            .request(Method::GET, url)
+            .await
+            .send()
+            .await?
+            .error_for_status()?;
+        if let Some(size) = resp.content_length().filter(|size| *size > limit) {
+            return Err(Error::DownloadTooLarge { size, limit });
+        }
+        let stream = resp.bytes_stream();
+        let stream = StreamReader::new(stream.map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+
+        let guard = SizeGuard {
+            limit: Some(limit),
+            exceeded: Arc::default(),
+        };
+        let reader = SizeLimited {
+            reader: stream,
+            read: 0,
+            limit,
+            exceeded: guard.exceeded.clone(),
+        };
+        Ok((Box::new(reader), guard))
+    }
+
+    /// Download the response into memory, unless the server says it didn't change (304)
+    pub async fn fetch_conditional(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<Option<Conditional>> {
+        let mut req = self.request(Method::GET, url).await;
+        if let Some(etag) = etag {
+            req = req.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            req = req.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let resp = req.send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let resp = resp.error_for_status()?;
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v: &HeaderValue| v.to_str().ok())
+                .map(String::from)
+        };
+        let etag = header(header::ETAG);
+        let last_modified = header(header::LAST_MODIFIED);
+        let body = resp.bytes().await?.to_vec();
+        Ok(Some(Conditional {
+            body,
+            etag,
+            last_modified,
+        }))
+    }
+
+    /// Returns the status code and content length, error statuses are not turned into errors
+    pub async fn head(&self, url: &str) -> Result<(reqwest::StatusCode, Option<u64>)> {
+        let resp = self.request(Method::HEAD, url).await.send().await?;
+        let content_length = resp
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        Ok((resp.status(), content_length))
+    }
+
+    async fn post<T: serde::Serialize>(&self, url: &str, body: &T) -> Result<reqwest::Response> {
+        let body = serde_json::to_vec(body)?;
+        let resp = self
+            .request(Method::POST, url)
+            .await
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp)
+    }
+
+    /// Send an XML-RPC call and return the response document
+    pub async fn post_xml(&self, url: &str, body: String) -> Result<String> {
+        let resp = self
+            .request(Method::POST, url)
+            .await
+            .header(header::CONTENT_TYPE, "text/xml")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(resp.text().await?)
+    }
+
+    pub async fn put(&self, url: &str, headers: HeaderMap, body: Vec<u8>) -> Result<()> {
+        self.request(Method::PUT, url)
+            .await
+            .headers(headers)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn post_json_response<T, R>(&self, url: &str, body: &T) -> Result<R>
+    where
+        T: serde::Serialize,
+        R: serde::de::DeserializeOwned,
+    {
+        let resp = self.post(url, body).await?;
+        let bytes = resp.bytes().await?;
+        let obj = serde_json::from_slice(&bytes)?;
+        Ok(obj)
+    }
 }
 
-pub async fn fetch_or_open(path: &str, should_fetch: bool) -> Result<Box<dyn AsyncRead + Unpin>> {
+/// A response of a conditional request, along with the validators for the next request
+#[derive(Debug)]
+pub struct Conditional {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Listen for SIGTERM and SIGINT, the returned future resolves once either is received
+pub fn shutdown_signal() -> Result<impl Future<Output = ()>> {
+    let mut sigterm = signal::unix::signal(SignalKind::terminate())?;
+    let mut sigint = signal::unix::signal(SignalKind::interrupt())?;
+    Ok(async move {
+        tokio::select! {
+            _ = sigterm.recv() => info!("Received SIGTERM, shutting down"),
+            _ = sigint.recv() => info!("Received SIGINT, shutting down"),
+        }
+    })
+}
+
+pub async fn fetch_or_open(
+    path: &str,
+    should_fetch: bool,
+) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
     if should_fetch {
         http_client(None)?.fetch(path).await
     } else {
@@ -55,6 +480,34 @@ pub fn is_possible_tar_artifact(url: &str) -> bool {
 }
 
 pub fn task_for_url(url: &str) -> Option<Task> {
+    task_for(url, None)
+}
+
+/// Like `task_for_url`, but a `git:`, `hg:` or `fossil:` checksum pins the snapshot to exactly that revision
+pub fn task_for_ref(url: &str, chksum: &str) -> Option<Task> {
+    let commit = chksum
+        .strip_prefix("git:")
+        .or_else(|| chksum.strip_prefix("hg:"))
+        .or_else(|| chksum.strip_prefix("fossil:"));
+    task_for(url, commit)
+}
+
+/// Task key of a vcs snapshot, every pinned revision of a repository needs its own task
+///
+/// Without pins the url is used as-is, since its fragment may already pin a revision.
+pub fn vcs_task_key(kind: &str, url: &str, pins: &[(&str, Option<&str>)]) -> String {
+    let fragment = pins
+        .iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", (*value)?)))
+        .collect::<Vec<_>>();
+    if fragment.is_empty() {
+        return format!("{kind}:{url}");
+    }
+    let base = url.split_once('#').map(|(base, _)| base).unwrap_or(url);
+    format!("{kind}:{base}#{}", fragment.join("&"))
+}
+
+fn task_for(url: &str, commit: Option<&str>) -> Option<Task> {
     match url.split_once("://") {
         Some(("https" | "http", _)) => {
             if is_possible_tar_artifact(url) {
@@ -71,12 +524,47 @@ pub fn task_for_url(url: &str) -> Option<Task> {
                 None
             }
         }
+        Some(("hg+https", _)) => {
+            debug!("Found hg remote: {url:?}");
+            url.parse::<ingest::hg::HgUrl>().ok()?;
+            if commit.is_some_and(|rev| !ingest::is_valid_vcs_rev(rev)) {
+                return None;
+            }
+            Task::new(
+                vcs_task_key("hg-clone", url, &[("rev", commit)]),
+                &TaskData::HgSnapshot {
+                    url: url.to_string(),
+                    rev: commit.map(String::from),
+                },
+            )
+            .ok()
+        }
+        Some(("fossil+https", _)) => {
+            debug!("Found fossil remote: {url:?}");
+            url.parse::<ingest::fossil::FossilUrl>().ok()?;
+            if commit.is_some_and(|rev| !ingest::is_valid_vcs_rev(rev)) {
+                return None;
+            }
+            Task::new(
+                vcs_task_key("fossil-clone", url, &[("rev", commit)]),
+                &TaskData::FossilSnapshot {
+                    url: url.to_string(),
+                    rev: commit.map(String::from),
+                },
+            )
+            .ok()
+        }
         Some((schema, _)) if schema.starts_with("git+") => {
             debug!("Found git remote: {url:?}");
+            let git = url.parse::<GitUrl>().ok()?;
+            let tag = git.tag();
+            let commit = commit.or(git.commit());
             Task::new(
-                format!("git-clone:{url}"),
+                vcs_task_key("git-clone", url, &[("tag", tag), ("commit", commit)]),
                 &TaskData::GitSnapshot {
                     url: url.to_string(),
+                    tag: tag.map(String::from),
+                    commit: commit.map(String::from),
                 },
             )
             .ok()
@@ -84,3 +572,182 @@ pub fn task_for_url(url: &str) -> Option<Task> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_identity_default() {
+        let identity = HttpIdentity::default();
+        assert_eq!(identity.user_agent(), USER_AGENT);
+        assert_eq!(identity.base_url(), "https://whatsrc.org");
+        assert_eq!(
+            identity.user_agent_for("https://example.com/foo.tar.gz"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_http_identity_configured() {
+        let identity = HttpIdentity {
+            instance_url: Some("https://whatsrc.example.com/".to_string()),
+            contact: Some("admin@example.com".to_string()),
+            overrides: HttpIdentity::parse_overrides(
+                "deb.debian.org=custom/1.0, ftp.gnu.org = curl/8.0",
+            ),
+        };
+        assert_eq!(
+            identity.user_agent(),
+            format!(
+                "what-the-src/{} (+https://whatsrc.example.com/; admin@example.com)",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(identity.base_url(), "https://whatsrc.example.com");
+        assert_eq!(
+            identity.user_agent_for("https://deb.debian.org/debian/pool/main/x/xz-utils/"),
+            Some("custom/1.0")
+        );
+        assert_eq!(
+            identity.user_agent_for("https://ftp.gnu.org/gnu/hello/hello-2.12.tar.gz"),
+            Some("curl/8.0")
+        );
+        assert_eq!(identity.user_agent_for("https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        assert_eq!(SizeLimits::parse_size("1024").unwrap(), 1024);
+        assert_eq!(SizeLimits::parse_size("512M").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(
+            SizeLimits::parse_size("2G").unwrap(),
+            2 * 1024 * 1024 * 1024
+        );
+        assert!(SizeLimits::parse_size("2GB").is_err());
+        assert!(SizeLimits::parse_size("").is_err());
+        assert!(SizeLimits::parse_size("99999999999999G").is_err());
+
+        let limits = SizeLimits {
+            default: Some(1024),
+            hosts: SizeLimits::parse_hosts("commondatastorage.googleapis.com=1, ftp.gnu.org = 4K")
+                .unwrap(),
+        };
+        assert_eq!(
+            limits.limit_for("https://commondatastorage.googleapis.com/chromium-browser-official/chromium-130.0.6723.58.tar.xz"),
+            Some(1)
+        );
+        assert_eq!(
+            limits.limit_for("https://ftp.gnu.org/gnu/hello/hello-2.12.tar.gz"),
+            Some(4096)
+        );
+        assert_eq!(limits.limit_for("https://example.com/"), Some(1024));
+        assert_eq!(
+            SizeLimits::default().limit_for("https://example.com/"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_size_limited() {
+        use tokio::io::AsyncReadExt;
+
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let guard = SizeGuard {
+            limit: Some(4),
+            exceeded: exceeded.clone(),
+        };
+        let mut reader = SizeLimited {
+            reader: &b"hello world"[..],
+            read: 0,
+            limit: 4,
+            exceeded,
+        };
+        let mut buf = Vec::new();
+        assert!(reader.read_to_end(&mut buf).await.is_err());
+        assert_eq!(guard.exceeded(), Some(4));
+    }
+
+    #[test]
+    fn test_task_for_ref_git() {
+        let task = task_for_ref(
+            "git+https://github.com/wolfi-dev/os.git#tag=v1.0.0",
+            "git:91e5c5e1baf31e19f6d3af3b0b6b81f849ce81da",
+        )
+        .unwrap();
+        assert_eq!(
+            task.key,
+            "git-clone:git+https://github.com/wolfi-dev/os.git#tag=v1.0.0&commit=91e5c5e1baf31e19f6d3af3b0b6b81f849ce81da"
+        );
+        let TaskData::GitSnapshot { tag, commit, .. } = task.data().unwrap() else {
+            panic!("Expected git snapshot task");
+        };
+        assert_eq!(tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(
+            commit.as_deref(),
+            Some("91e5c5e1baf31e19f6d3af3b0b6b81f849ce81da")
+        );
+
+        let task = task_for_url("git+https://github.com/rhinstaller/efivar.git#commit=c47820c37ac26286559ec004de07d48d05f3308c").unwrap();
+        assert_eq!(
+            task.key,
+            "git-clone:git+https://github.com/rhinstaller/efivar.git#commit=c47820c37ac26286559ec004de07d48d05f3308c"
+        );
+        let TaskData::GitSnapshot { tag, commit, .. } = task.data().unwrap() else {
+            panic!("Expected git snapshot task");
+        };
+        assert_eq!(tag, None);
+        assert_eq!(
+            commit.as_deref(),
+            Some("c47820c37ac26286559ec004de07d48d05f3308c")
+        );
+    }
+
+    #[test]
+    fn test_task_for_ref_pins() {
+        // bare urls with different pins must not share a task
+        let url = "git+https://github.com/rhinstaller/efivar.git";
+        let a = task_for_ref(url, "git:c47820c37ac26286559ec004de07d48d05f3308c").unwrap();
+        let b = task_for_ref(url, "git:0f8b2e4f5c1a3d7e9b6a8c0d2e4f6a8b0c2d4e6f").unwrap();
+        assert_eq!(
+            a.key,
+            "git-clone:git+https://github.com/rhinstaller/efivar.git#commit=c47820c37ac26286559ec004de07d48d05f3308c"
+        );
+        assert_ne!(a.key, b.key);
+
+        let task = task_for_ref(
+            "hg+https://hg.mozilla.org/projects/nspr",
+            "hg:b5b1c9e3b6c3b2d0cbb5bd4f4fbd3e1d7ad0d5a1",
+        )
+        .unwrap();
+        assert_eq!(
+            task.key,
+            "hg-clone:hg+https://hg.mozilla.org/projects/nspr#rev=b5b1c9e3b6c3b2d0cbb5bd4f4fbd3e1d7ad0d5a1"
+        );
+
+        let task = task_for_url("fossil+https://sqlite.org/src#rev=version-3.46.1").unwrap();
+        assert_eq!(
+            task.key,
+            "fossil-clone:fossil+https://sqlite.org/src#rev=version-3.46.1"
+        );
+
+        assert!(task_for_url("hg+http://hg.mozilla.org/projects/nspr#rev=NSPR_4_35_RTM").is_none());
+        assert!(task_for_url("fossil+https://sqlite.org/src#rev=--help").is_none());
+        assert!(task_for_ref("fossil+https://sqlite.org/src", "fossil:-Rrepo.fossil").is_none());
+    }
+
+    #[test]
+    fn test_host_rate_limiter() {
+        let limiter = HostRateLimiter::new(Duration::from_millis(500));
+        let now = Instant::now();
+        assert_eq!(limiter.reserve("example.com", now), now);
+        assert_eq!(
+            limiter.reserve("example.com", now),
+            now + Duration::from_millis(500)
+        );
+        assert_eq!(limiter.reserve("example.org", now), now);
+
+        let later = now + Duration::from_secs(5);
+        assert_eq!(limiter.reserve("example.com", later), later);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::analyze;
+use crate::args;
+use crate::db;
+use crate::errors::*;
+use crate::signature;
+use serde::Serialize;
+use sqlx::types::chrono::Utc;
+use std::collections::BTreeMap;
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+pub const PREDICATE_TYPE: &str = "https://slsa.dev/verification_summary/v1";
+pub const DEFAULT_VERIFIER_ID: &str = "https://whatsrc.org/";
+/// Non-SLSA level, the artifact is referenced by at least one vendor
+pub const LEVEL_VENDOR_REFERENCED: &str = "WHATSRC_VENDOR_REFERENCED";
+
+#[derive(Debug, Serialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: &'static str,
+    pub subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: &'static str,
+    pub predicate: Predicate,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Subject {
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Predicate {
+    pub verifier: Verifier,
+    pub time_verified: String,
+    pub resource_uri: String,
+    pub policy: Policy,
+    pub verification_result: &'static str,
+    pub verified_levels: Vec<&'static str>,
+    pub slsa_version: &'static str,
+    /// Additional data that has no equivalent in the VSA format
+    pub whatsrc: Summary,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Verifier {
+    pub id: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Policy {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub refs: Vec<db::RefView>,
+    pub sbom_refs: Vec<db::SbomRef>,
+    pub signatures: Vec<Signature>,
+    pub flags: Flags,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Signature {
+    pub kind: String,
+    pub status: String,
+    pub signer: Option<String>,
+}
+
+impl From<db::Signature> for Signature {
+    fn from(sig: db::Signature) -> Self {
+        Signature {
+            kind: sig.kind,
+            status: sig.status,
+            signer: sig.signer,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Flags {
+    pub suspecting_autotools: bool,
+    /// At least one signature was verified with a pinned key or identity
+    pub signature_verified: bool,
+}
+
+/// Only signatures verified against a pinned key or identity count as verified
+pub fn signature_verified(signatures: &[Signature]) -> bool {
+    signatures
+        .iter()
+        .any(|sig| sig.status == signature::STATUS_VALID)
+}
+
+/// Group the canonical checksum and its aliases into one subject per representation.
+///
+/// The alias reason is expected to be formatted like `sha512(gz(tar))`.
+pub fn subjects(chksum: &str, aliases: &[db::Alias]) -> Vec<Subject> {
+    let mut subjects = BTreeMap::<String, BTreeMap<String, String>>::new();
+
+    let mut insert = |label: &str, digest: &str| {
+        let Some((family, value)) = digest.split_once(':') else {
+            return;
+        };
+        let family = match family {
+            "git" => "gitCommit",
+            other => other,
+        };
+        subjects
+            .entry(label.to_string())
+            .or_default()
+            .insert(family.to_string(), value.to_string());
+    };
+
+    insert("tar", chksum);
+    for alias in aliases {
+        let Some(reason) = &alias.reason else {
+            continue;
+        };
+        if reason == "git-archive" {
+            insert(reason, &alias.alias_from);
+        } else if let Some((_family, label)) = reason.split_once('(') {
+            let Some(label) = label.strip_suffix(')') else {
+                continue;
+            };
+            insert(label, &alias.alias_from);
+        }
+    }
+
+    subjects
+        .into_iter()
+        .map(|(name, digest)| Subject { name, digest })
+        .collect()
+}
+
+pub async fn generate(
+    db: &db::Client,
+    chksum: &str,
+    verifier_id: &str,
+) -> Result<Option<Statement>> {
+    let Some(artifact) = db.resolve_artifact(chksum).await? else {
+        return Ok(None);
+    };
+    if db.is_quarantined(&artifact.chksum).await? {
+        return Ok(None);
+    }
+
+    let aliases = db.get_aliases_to(&artifact.chksum).await?;
+    let refs = db.get_all_refs_for(&artifact.chksum).await?;
+    let sbom_refs = db.get_sbom_refs_for_archive(&artifact.chksum).await?;
+    let signatures = db
+        .get_signatures(&artifact.chksum)
+        .await?
+        .into_iter()
+        .map(Signature::from)
+        .collect::<Vec<_>>();
+    let files = artifact.get_files()?;
+
+    let verifier_id = verifier_id.strip_suffix('/').unwrap_or(verifier_id);
+    let verified_levels = if refs.is_empty() {
+        vec![]
+    } else {
+        vec![LEVEL_VENDOR_REFERENCED]
+    };
+    let verification_result = if refs.is_empty() { "FAILED" } else { "PASSED" };
+
+    Ok(Some(Statement {
+        statement_type: STATEMENT_TYPE,
+        subject: subjects(&artifact.chksum, &aliases),
+        predicate_type: PREDICATE_TYPE,
+        predicate: Predicate {
+            verifier: Verifier {
+                id: format!("{verifier_id}/"),
+            },
+            time_verified: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            resource_uri: format!("{verifier_id}/artifact/{}", artifact.chksum),
+            policy: Policy {
+                uri: format!("{verifier_id}/"),
+            },
+            verification_result,
+            verified_levels,
+            slsa_version: "1.0",
+            whatsrc: Summary {
+                refs,
+                sbom_refs,
+                flags: Flags {
+                    suspecting_autotools: analyze::detect_autotools(files.as_deref()),
+                    signature_verified: signature_verified(&signatures),
+                },
+                signatures,
+            },
+        },
+    }))
+}
+
+pub async fn run(args: &args::ExportVsa) -> Result<()> {
+    let db = db::Client::create().await?;
+
+    let Some(statement) = generate(&db, &args.chksum, &args.verifier_id).await? else {
+        return Err(Error::UnknownArtifact(args.chksum.clone()));
+    };
+    let json = serde_json::to_string_pretty(&statement)?;
+    println!("{json}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(from: &str, reason: Option<&str>) -> db::Alias {
+        db::Alias {
+            alias_from: from.to_string(),
+            alias_to: "sha256:55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0"
+                .to_string(),
+            reason: reason.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_signature_verified() {
+        let sig = |status: &str| Signature {
+            kind: "cosign".to_string(),
+            status: status.to_string(),
+            signer: Some("https://github.com/login/oauth foo@example.com".to_string()),
+        };
+        assert!(!signature_verified(&[]));
+        assert!(!signature_verified(&[
+            sig(signature::STATUS_VALID_UNPINNED),
+            sig(signature::STATUS_EXPIRED_KEY),
+        ]));
+        assert!(signature_verified(&[
+            sig(signature::STATUS_INVALID),
+            sig(signature::STATUS_VALID),
+        ]));
+    }
+
+    #[test]
+    fn test_subjects_from_aliases() {
+        let subjects = subjects(
+            "sha256:55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0",
+            &[
+                alias("sha512:d2d1", Some("sha512(tar)")),
+                alias("sha256:9390", Some("sha256(gz(tar))")),
+                alias("blake2b:47e8", Some("blake2b(gz(tar))")),
+                alias(
+                    "git:7747534db4576db43eced4356859ef400351ca28",
+                    Some("git-archive"),
+                ),
+                alias("sha256:ffff", None),
+            ],
+        );
+        assert_eq!(
+            subjects,
+            vec![
+                Subject {
+                    name: "git-archive".to_string(),
+                    digest: maplit::btreemap! {
+                        "gitCommit".to_string() => "7747534db4576db43eced4356859ef400351ca28".to_string(),
+                    },
+                },
+                Subject {
+                    name: "gz(tar)".to_string(),
+                    digest: maplit::btreemap! {
+                        "blake2b".to_string() => "47e8".to_string(),
+                        "sha256".to_string() => "9390".to_string(),
+                    },
+                },
+                Subject {
+                    name: "tar".to_string(),
+                    digest: maplit::btreemap! {
+                        "sha256".to_string() => "55f514c48ef9359b792e23abbad6ca8a1e999065ba8879d8717fecb52efc1ea0".to_string(),
+                        "sha512".to_string() => "d2d1".to_string(),
+                    },
+                },
+            ]
+        );
+    }
+}
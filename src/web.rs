@@ -1,8 +1,21 @@
+use crate::analyze;
 use crate::args;
+use crate::chksums;
 use crate::db;
 use crate::errors::*;
+use crate::hexdump;
 use crate::ingest;
+use crate::notify;
+use crate::osv;
+use crate::purl::Purl;
+use crate::reports;
 use crate::sbom;
+use crate::storage::{self, Storage};
+use crate::sync;
+use crate::upstream;
+use crate::utils;
+use crate::vsa;
+use async_compression::tokio::bufread::GzipEncoder;
 use data_encoding::BASE64;
 use diffy_fork_filenames as diffy;
 use log::error;
@@ -11,6 +24,7 @@ use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use sqlx::types::chrono::{DateTime, NaiveDate, Utc};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
@@ -19,6 +33,7 @@ use std::result;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::OnceLock;
+use tokio::io::{self, AsyncReadExt};
 use tokio::task::JoinSet;
 use url_escape::percent_encoding::AsciiSet;
 use warp::http::Uri;
@@ -29,6 +44,14 @@ use warp::{
 };
 
 const SEARCH_LIMIT: usize = 250;
+const FEED_LIMIT: i64 = 50;
+/// How many packages of a lockfile are returned per page
+const SBOM_PAGE_LIMIT: usize = 1000;
+/// How many entries of an archive listing are rendered per page
+const FILES_PAGE_LIMIT: usize = 5000;
+/// Responses smaller than this are sent uncompressed
+const COMPRESSION_THRESHOLD: usize = 1024;
+const COMPRESSIBLE_TYPES: &[&str] = &["text/html", "application/json", "text/plain"];
 
 #[allow(clippy::declare_interior_mutable_const)]
 const CACHE_CONTROL_DEFAULT: HeaderValue =
@@ -36,6 +59,14 @@ const CACHE_CONTROL_DEFAULT: HeaderValue =
 #[allow(clippy::declare_interior_mutable_const)]
 const CACHE_CONTROL_SHORT: HeaderValue =
     HeaderValue::from_static("max-age=10, stale-while-revalidate=20, stale-if-error=60");
+#[allow(clippy::declare_interior_mutable_const)]
+const CACHE_CONTROL_NONE: HeaderValue = HeaderValue::from_static("no-store");
+
+/// Feeds link to `WHATSRC_HTTP_INSTANCE_URL`, the same url we identify ourselves with
+fn feed_base_url() -> &'static str {
+    static URL: OnceLock<String> = OnceLock::new();
+    URL.get_or_init(|| utils::HttpIdentity::from_env().base_url().to_string())
+}
 
 fn download_srcs_hashset() -> &'static HashSet<&'static str> {
     static SET: OnceLock<HashSet<&'static str>> = OnceLock::new();
@@ -43,6 +74,9 @@ fn download_srcs_hashset() -> &'static HashSet<&'static str> {
         let mut s = HashSet::new();
         s.insert(sbom::cargo::VENDOR);
         s.insert(sbom::yarn::VENDOR);
+        s.insert(sync::goproxy::VENDOR);
+        s.insert(sync::npm_registry::VENDOR);
+        s.insert(sync::pypi::VENDOR);
         s
     })
 }
@@ -137,46 +171,239 @@ fn cache_control(reply: impl warp::Reply, value: HeaderValue) -> impl warp::Repl
     warp::reply::with_header(reply, header::CACHE_CONTROL, value)
 }
 
+/// Check if the client accepts gzip, `gzip;q=0` explicitly refuses it
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding.split(',').any(|coding| {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let refused = params.any(|p| {
+            p.strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q <= 0.0)
+        });
+        (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+    })
+}
+
+fn is_compressible(headers: &header::HeaderMap) -> bool {
+    if headers.contains_key(header::CONTENT_ENCODING) {
+        return false;
+    }
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| COMPRESSIBLE_TYPES.iter().any(|t| v.starts_with(t)))
+}
+
+/// Gzip text responses if the client supports it
+async fn compress_reply(
+    accept_encoding: Option<String>,
+    reply: impl warp::Reply,
+) -> result::Result<warp::reply::Response, warp::Rejection> {
+    let mut response = reply.into_response();
+    if !is_compressible(response.headers()) {
+        return Ok(response);
+    }
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if !accept_encoding.as_deref().is_some_and(accepts_gzip) {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = warp::hyper::body::to_bytes(body)
+        .await
+        .map_err(|err| Error::from(io::Error::new(io::ErrorKind::Other, err)))?;
+    if body.len() < COMPRESSION_THRESHOLD {
+        return Ok(warp::reply::Response::from_parts(parts, body.into()));
+    }
+
+    let mut compressed = Vec::new();
+    GzipEncoder::new(&body[..])
+        .read_to_end(&mut compressed)
+        .await
+        .map_err(Error::from)?;
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Ok(warp::reply::Response::from_parts(parts, compressed.into()))
+}
+
 async fn index(hbs: Arc<Handlebars<'_>>) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
     let html = hbs.render("index.html.hbs", &()).map_err(Error::from)?;
     Ok(Box::new(warp::reply::html(html)))
 }
 
-fn detect_autotools(files: Option<&[ingest::tar::Entry]>) -> bool {
-    let Some(files) = files else {
-        return false;
-    };
+/// Parse the `at` query parameter, a plain date refers to the end of that day (UTC)
+fn parse_point_in_time(txt: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(txt, "%Y-%m-%d") {
+        let time = date
+            .and_hms_opt(23, 59, 59)
+            .ok_or_else(|| Error::InvalidTimestamp(txt.to_string()))?;
+        Ok(time.and_utc())
+    } else {
+        let time = DateTime::parse_from_rfc3339(txt)
+            .map_err(|_| Error::InvalidTimestamp(txt.to_string()))?;
+        Ok(time.with_timezone(&Utc))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct RefWithChangelog {
+    #[serde(flatten)]
+    r: db::RefView,
+    changelog: Option<db::Changelog>,
+    advisories: Vec<osv::Vulnerability>,
+    patches: Vec<db::PatchRef>,
+    /// The apt suites and components the ref was listed in
+    suites: Vec<db::RefSuite>,
+}
 
-    let mut configure = HashSet::new();
-    let mut configure_ac = HashSet::new();
+/// An upstream url of a ref that vanished or now serves different content
+#[derive(Debug, Clone, Serialize)]
+struct UpstreamWarning {
+    url: String,
+    chksum: String,
+    status: String,
+    observed_chksum: Option<String>,
+    since: Option<String>,
+    last_verified: String,
+}
 
-    for file in files {
-        if let Some(folder) = file.path.strip_suffix("/configure") {
-            if configure_ac.contains(folder) {
-                return true;
-            }
-            configure.insert(folder);
+impl From<db::UpstreamCheck> for UpstreamWarning {
+    fn from(check: db::UpstreamCheck) -> Self {
+        let since = match check.status.as_str() {
+            upstream::STATUS_GONE => check.gone_since,
+            _ => check.replaced_since,
+        };
+        UpstreamWarning {
+            url: check.url,
+            chksum: check.chksum,
+            status: check.status,
+            observed_chksum: check.observed_chksum,
+            since: since.map(|t| t.to_rfc3339()),
+            last_verified: check.last_verified.to_rfc3339(),
         }
-        if let Some(folder) = file.path.strip_suffix("/configure.ac") {
-            if configure.contains(folder) {
-                return true;
-            }
-            configure_ac.insert(folder);
+    }
+}
+
+async fn get_upstream_warnings(
+    db: &db::Client,
+    chksums: impl Iterator<Item = &str>,
+) -> Result<Vec<UpstreamWarning>> {
+    let mut chksums = chksums.map(String::from).collect::<Vec<_>>();
+    chksums.sort();
+    chksums.dedup();
+    let warnings = db.get_upstream_warnings(&chksums).await?;
+    Ok(warnings.into_iter().map(UpstreamWarning::from).collect())
+}
+
+async fn get_advisories_for_refs(
+    db: &db::Client,
+    refs: &[db::RefView],
+) -> Result<Vec<db::Advisory>> {
+    let keys = refs
+        .iter()
+        .filter_map(|r| {
+            let ecosystem = osv::ecosystem_for(&r.vendor)?;
+            Some((ecosystem, r.package.as_str(), r.version.as_str()))
+        })
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    db.get_advisories(&keys).await
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtifactQuery {
+    at: Option<String>,
+    page: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Counts of an archive listing, always shown even if only a page of the listing is rendered
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct ArchiveSummary {
+    entries: usize,
+    files: usize,
+    directories: usize,
+    links: usize,
+    /// Number of files whose content is archived, along with their total size
+    stored_files: i64,
+    stored_size: i64,
+}
+
+fn summarize_archive(files: &[ingest::tar::Entry]) -> ArchiveSummary {
+    let mut summary = ArchiveSummary {
+        entries: files.len(),
+        ..Default::default()
+    };
+    for entry in files {
+        if entry.metadata.links_to.is_some() {
+            summary.links += 1;
+        } else if entry.digest.is_some() {
+            summary.files += 1;
+        } else if entry.path.ends_with('/') {
+            summary.directories += 1;
         }
     }
+    summary
+}
 
-    false
+/// Links to the neighbouring pages of an archive listing, `None` if it fits on one page
+fn listing_pages(
+    total: usize,
+    page: usize,
+    limit: usize,
+    at: Option<&str>,
+) -> Option<serde_json::Value> {
+    if total <= limit {
+        return None;
+    }
+    let pages = total.div_ceil(limit);
+    let href = |page: usize| {
+        let mut href = format!("?page={page}&limit={limit}");
+        if let Some(at) = at {
+            href.push_str("&at=");
+            href.push_str(&url_escape::encode_component(at));
+        }
+        href
+    };
+    Some(json!({
+        "page": page,
+        "pages": pages,
+        "first": (page - 1) * limit + 1,
+        "last": (page * limit).min(total),
+        "prev": (page > 1).then(|| href(page - 1)),
+        "next": (page < pages).then(|| href(page + 1)),
+    }))
 }
 
+/// Show an artifact, optionally as it was known at a point in time (`?at=`)
+///
+/// There's no history table, "as of" only hides refs, attestations and signers that were
+/// first seen later. Data that is updated in place (sbom refs, signature status, upstream
+/// checks, vcs drift) has no past state and is left out of these responses.
 async fn artifact(
     hbs: Arc<Handlebars<'_>>,
     db: Arc<db::Client>,
     chksum: String,
+    query: ArtifactQuery,
 ) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let at = query.at.as_deref().map(parse_point_in_time).transpose()?;
+
+    let chksum = url_escape::decode(&chksum);
     let (chksum, json) = chksum
         .strip_suffix(".json")
         .map(|chksum| (chksum, true))
-        .unwrap_or((chksum.as_str(), false));
+        .unwrap_or((&chksum, false));
+    let (chksum, txt) = chksum
+        .strip_suffix(".txt")
+        .map(|chksum| (chksum, true))
+        .unwrap_or((chksum, false));
 
     let alias = db.get_artifact_alias(chksum).await?;
 
@@ -185,22 +412,121 @@ async fn artifact(
         .map(|a| a.alias_to.as_str())
         .unwrap_or(chksum);
     let Some(artifact) = db.get_artifact(resolved_chksum).await? else {
+        if let Some(pkg) = db.get_binary_package(chksum).await? {
+            return binary_package(&hbs, &db, pkg).await;
+        }
         return Err(reject::not_found());
     };
+    if at.is_some_and(|at| artifact.first_seen > at) || db.is_quarantined(&artifact.chksum).await? {
+        return Err(reject::not_found());
+    }
 
     let files = artifact.get_files()?;
-    let sbom_refs = db.get_sbom_refs_for_archive(resolved_chksum).await?;
+    let totals = artifact.get_totals()?;
+    if txt {
+        let Some(files) = files else {
+            return Err(reject::not_found());
+        };
+        let mut res = warp::reply::Response::new(hbs.render_archive(Some(&files))?.into());
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        return Ok(Box::new(res));
+    }
+
+    let mut sbom_refs = db.get_sbom_refs_for_archive(resolved_chksum).await?;
+    let mut attestations = db.get_attestations(&artifact.chksum).await?;
+    let mut signatures = db.get_signatures(&artifact.chksum).await?;
+    let refs = db
+        .get_all_refs_for_at(&artifact.chksum, at.as_ref())
+        .await?;
+    let mut upstream_warnings =
+        get_upstream_warnings(&db, refs.iter().map(|r| r.chksum.as_str())).await?;
+    let ref_chksums = refs.iter().map(|r| r.chksum.clone()).collect::<Vec<_>>();
+    let mut signers = db.get_ref_signers(&ref_chksums).await?;
+    if let Some(at) = at {
+        attestations.retain(|a| a.first_seen <= at);
+        signers.retain(|s| s.first_seen <= at);
+        sbom_refs.clear();
+        signatures.clear();
+        upstream_warnings.clear();
+    }
 
     if json {
         Ok(Box::new(warp::reply::json(&json!({
             "files": files,
             "sbom_refs": sbom_refs,
+            "refs": refs,
+            "attestations": attestations,
+            "signatures": signatures,
+            "upstream_warnings": upstream_warnings,
+            "signers": signers,
+            "totals": totals,
         }))))
     } else {
-        let suspecting_autotools = detect_autotools(files.as_deref());
+        let suspecting_autotools = analyze::detect_autotools(files.as_deref());
+        // prefer the stored comparison, fall back to comparing on the fly if it hasn't run yet
+        let drift = if at.is_some() {
+            None
+        } else if let Some(drift) = db.get_vcs_drift(&artifact.chksum).await? {
+            Some(json!({
+                "vcs": drift.vcs_chksum,
+                "categories": drift.categories,
+                "anomalies": drift.anomalies,
+                "score": drift.score,
+            }))
+        } else if let Some(vcs) = db.get_vcs_snapshot_for(&artifact.chksum).await? {
+            let vcs_files = vcs.get_files()?;
+            files
+                .as_deref()
+                .zip(vcs_files.as_deref())
+                .map(|(files, vcs_files)| analyze::detect_drift(files, vcs_files))
+                .map(|drift| {
+                    json!({
+                        "vcs": vcs.chksum,
+                        "categories": drift.categories,
+                        "anomalies": drift.anomalies,
+                        "score": drift.score,
+                    })
+                })
+        } else {
+            None
+        };
+
+        let summary = if let Some(files) = &files {
+            let mut summary = summarize_archive(files);
+            let digests = files
+                .iter()
+                .filter_map(|f| f.digest.clone())
+                .collect::<Vec<_>>();
+            (summary.stored_files, summary.stored_size) = db.get_blobs_size(&digests).await?;
+            Some(summary)
+        } else {
+            None
+        };
 
-        let refs = db.get_all_refs_for(&artifact.chksum).await?;
+        // huge listings are split into pages, the full listing is available as .txt
+        let limit = query
+            .limit
+            .unwrap_or(FILES_PAGE_LIMIT)
+            .clamp(1, FILES_PAGE_LIMIT);
+        let page = query.page.unwrap_or(1).max(1);
+        let (files, pages) = match files {
+            Some(files) => {
+                let page = page.min(files.len().div_ceil(limit).max(1));
+                let pages = listing_pages(files.len(), page, limit, query.at.as_deref());
+                let offset = (page - 1).saturating_mul(limit);
+                let (files, _) = paginate(files, offset, limit);
+                (Some(files), pages)
+            }
+            None => (None, None),
+        };
         let files = hbs.render_archive(files.as_deref())?;
+        let changelogs = db.get_changelogs_for_refs(&refs).await?;
+        let advisories = get_advisories_for_refs(&db, &refs).await?;
+        let patches = db.get_patches_for_refs(&refs).await?;
+        let suites = db.get_ref_suites_for_refs(&refs).await?;
 
         let mut build_inputs = Vec::new();
         let mut found_at = Vec::new();
@@ -208,9 +534,34 @@ async fn artifact(
         let set = download_srcs_hashset();
         for r in refs {
             if set.contains(r.vendor.as_str()) {
-                found_at.push(r);
+                found_at.push(RefWithChangelog {
+                    r,
+                    changelog: None,
+                    advisories: Vec::new(),
+                    patches: Vec::new(),
+                    suites: Vec::new(),
+                });
             } else {
-                build_inputs.push(r);
+                let changelog = changelogs.iter().find(|c| c.matches(&r)).cloned();
+                let advisories = advisories
+                    .iter()
+                    .find(|a| {
+                        osv::ecosystem_for(&r.vendor) == Some(a.ecosystem.as_str())
+                            && a.package == r.package
+                            && a.version == r.version
+                    })
+                    .map(|a| osv::parse_vulns(a.vulns.clone()))
+                    .transpose()?
+                    .unwrap_or_default();
+                let patches = patches.iter().filter(|p| p.matches(&r)).cloned().collect();
+                let suites = suites.iter().filter(|s| s.matches(&r)).cloned().collect();
+                build_inputs.push(RefWithChangelog {
+                    r,
+                    changelog,
+                    advisories,
+                    patches,
+                    suites,
+                });
             }
         }
 
@@ -221,6 +572,7 @@ async fn artifact(
                     "artifact": artifact,
                     "chksum": chksum,
                     "alias": alias,
+                    "at": at.map(|at| at.to_rfc3339()),
                     "refs": json!([{
                         "title": "Build input of",
                         "refs": build_inputs,
@@ -229,8 +581,168 @@ async fn artifact(
                         "refs": found_at,
                     }]),
                     "sbom_refs": sbom_refs,
+                    "attestations": attestations,
+                    "signatures": signatures,
                     "files": files,
+                    "summary": summary,
+                    "anomalies": totals.as_ref().is_some_and(|t| t.has_anomalies()),
+                    "totals": totals,
+                    "pages": pages,
                     "suspecting_autotools": suspecting_autotools,
+                    "drift": drift,
+                    "upstream_warnings": upstream_warnings,
+                    "signers": signers,
+                }),
+            )
+            .map_err(Error::from)?;
+        Ok(Box::new(warp::reply::html(html)))
+    }
+}
+
+/// List the source refs of a binary package, since we don't index its content
+async fn binary_package(
+    hbs: &Handlebars<'_>,
+    db: &db::Client,
+    pkg: db::BinaryPackage,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let refs = db
+        .get_all_named_refs(&pkg.vendor, &pkg.source_package, &pkg.source_version)
+        .await?;
+    let html = hbs.render(
+        "search.html.hbs",
+        &json!({
+            "search": format!("{} {} ({})", pkg.package, pkg.version, pkg.vendor),
+            "binary_package": pkg,
+            "refs": refs,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+#[derive(Debug, Deserialize)]
+struct FileQuery {
+    hex: Option<String>,
+}
+
+/// Show the recorded metadata of a single member of an artifact
+async fn artifact_file(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    http: Arc<utils::HttpClient>,
+    chksum: String,
+    path: warp::path::Tail,
+    query: FileQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(artifact) = db.resolve_artifact(&chksum).await? else {
+        return Err(reject::not_found());
+    };
+    if db.is_quarantined(&artifact.chksum).await? {
+        return Err(reject::not_found());
+    }
+
+    let path = url_escape::decode(path.as_str());
+    let files = artifact.get_files()?.unwrap_or_default();
+    let Some(file) = files.iter().find(|f| f.path == path) else {
+        return Err(reject::not_found());
+    };
+
+    let hex = query.hex.is_some_and(|hex| hex != "0");
+    let blob = match (&file.digest, Storage::from_env()?) {
+        (Some(digest), Some(storage)) => db
+            .get_blob(digest)
+            .await?
+            .map(|blob| (digest, storage, blob)),
+        _ => None,
+    };
+
+    let mut download = None;
+    let mut dump = None;
+    let mut file_type = None;
+    let mut too_large = false;
+    if let Some((digest, storage, blob)) = blob {
+        // presigned links expire, they need to outlive the cache of this page
+        download = Storage::blob_key(digest)
+            .map(|key| storage.presign_get(&key, &Utc::now(), storage::PRESIGN_EXPIRES_SECS));
+
+        if hex && blob.size as u64 <= hexdump::MAX_SIZE {
+            let data = storage.fetch_blob(&http, digest, hexdump::MAX_SIZE).await?;
+            file_type = hexdump::detect_type(&data);
+            dump = Some(hexdump::format(&data));
+        } else if hex {
+            too_large = true;
+        }
+    }
+
+    let html = hbs.render(
+        "file.html.hbs",
+        &json!({
+            "chksum": artifact.chksum,
+            "file": file,
+            "hex": hex,
+            "download": download,
+            "dump": dump,
+            "file_type": file_type,
+            "too_large": too_large,
+            "max_size": hexdump::MAX_SIZE / 1024,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn artifact_vsa(
+    db: Arc<db::Client>,
+    chksum: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(statement) = vsa::generate(&db, &chksum, vsa::DEFAULT_VERIFIER_ID).await? else {
+        return Err(reject::not_found());
+    };
+    Ok(Box::new(warp::reply::json(&statement)))
+}
+
+async fn random(db: Arc<db::Client>) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(chksum) = db.get_random_artifact().await? else {
+        return Err(reject::not_found());
+    };
+    let uri = format!("/artifact/{}", url_encode_artifact(&chksum))
+        .parse::<Uri>()
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::found(uri)))
+}
+
+async fn patch(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    chksum: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (chksum, txt) = chksum
+        .strip_suffix(".txt")
+        .map(|chksum| (chksum, true))
+        .unwrap_or((chksum.as_str(), false));
+
+    let patch_refs = db.get_patch_refs(chksum).await?;
+    if patch_refs.is_empty() {
+        return Err(reject::not_found());
+    }
+    let content = db.get_patch_content(chksum).await?;
+
+    if txt {
+        let Some(content) = content else {
+            return Err(reject::not_found());
+        };
+        let mut res = warp::reply::Response::new(content.into());
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        Ok(Box::new(res))
+    } else {
+        let html = hbs
+            .render(
+                "patch.html.hbs",
+                &json!({
+                    "chksum": chksum,
+                    "patch_refs": patch_refs,
+                    "content": content,
                 }),
             )
             .map_err(Error::from)?;
@@ -238,11 +750,31 @@ async fn artifact(
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct SbomQuery {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Select one page of a list, also returns the offset of the next page
+fn paginate<T>(items: Vec<T>, offset: usize, limit: usize) -> (Vec<T>, Option<usize>) {
+    let total = items.len();
+    let page = items.into_iter().skip(offset).take(limit).collect();
+    let next = offset.saturating_add(limit);
+    (page, (next < total).then_some(next))
+}
+
 async fn sbom(
     hbs: Arc<Handlebars<'_>>,
     db: Arc<db::Client>,
     chksum: String,
+    query: SbomQuery,
 ) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    if let Some(chksum) = chksum.strip_suffix(".json") {
+        return sbom_json(&db, chksum, &query).await;
+    }
+
     let (chksum, txt) = chksum
         .strip_suffix(".txt")
         .map(|chksum| (chksum, true))
@@ -251,6 +783,9 @@ async fn sbom(
     let Some(sbom) = db.get_sbom(chksum).await? else {
         return Err(reject::not_found());
     };
+    if db.is_sbom_quarantined(&sbom).await? {
+        return Err(reject::not_found());
+    }
 
     let sbom_refs = db.get_sbom_refs_for_sbom(&sbom).await?;
 
@@ -262,13 +797,8 @@ async fn sbom(
         );
         Ok(Box::new(res))
     } else {
-        let packages = match sbom::Sbom::try_from(&sbom).and_then(|sbom| sbom.to_packages()) {
-            Ok(packages) => packages,
-            Err(err) => {
-                warn!("Failed to parse package lock: {err:#}");
-                Vec::new()
-            }
-        };
+        let mut packages = sbom_packages(&sbom);
+        resolve_sbom_packages(&db, &mut packages).await?;
 
         let html = hbs
             .render(
@@ -285,6 +815,154 @@ async fn sbom(
     }
 }
 
+async fn sbom_json(
+    db: &db::Client,
+    chksum: &str,
+    query: &SbomQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(sbom) = db.get_sbom(chksum).await? else {
+        return Err(reject::not_found());
+    };
+    if db.is_sbom_quarantined(&sbom).await? {
+        return Err(reject::not_found());
+    }
+
+    let packages = sbom_packages(&sbom);
+    let total = packages.len();
+    let limit = query
+        .limit
+        .unwrap_or(SBOM_PAGE_LIMIT)
+        .clamp(1, SBOM_PAGE_LIMIT);
+    let (mut packages, next) = paginate(packages, query.offset, limit);
+    resolve_sbom_packages(db, &mut packages).await?;
+    let next = next.map(|offset| format!("/sbom/{chksum}.json?offset={offset}&limit={limit}"));
+
+    Ok(Box::new(warp::reply::json(&json!({
+        "chksum": sbom.chksum,
+        "strain": sbom.strain,
+        "total": total,
+        "offset": query.offset,
+        "packages": packages,
+        "next": next,
+    }))))
+}
+
+async fn sbom_diff(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    old: String,
+    new: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (Some(old_sbom), Some(new_sbom)) = tokio::try_join!(db.get_sbom(&old), db.get_sbom(&new))?
+    else {
+        return Err(reject::not_found());
+    };
+    if db.is_sbom_quarantined(&old_sbom).await? || db.is_sbom_quarantined(&new_sbom).await? {
+        return Err(reject::not_found());
+    }
+
+    let old_packages = sbom::Sbom::try_from(&old_sbom)?.to_packages()?;
+    let new_packages = sbom::Sbom::try_from(&new_sbom)?.to_packages()?;
+    let diff = sbom::diff(&old_packages, &new_packages);
+
+    let html = hbs.render(
+        "sbom-diff.html.hbs",
+        &json!({
+            "old": old_sbom.chksum,
+            "old_strain": old_sbom.strain,
+            "new": new_sbom.chksum,
+            "new_strain": new_sbom.strain,
+            "diff": diff,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+#[derive(Debug, Serialize)]
+struct PackageWithPurl {
+    #[serde(flatten)]
+    pkg: sbom::Package,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    /// Link to the artifact page, go.sum hashes contain `/`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    href: Option<String>,
+    /// The archived artifact the checksum resolves to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifact: Option<String>,
+}
+
+fn sbom_packages(sbom: &db::Sbom) -> Vec<PackageWithPurl> {
+    let sbom = match sbom::Sbom::try_from(sbom) {
+        Ok(sbom) => sbom,
+        Err(err) => {
+            warn!("Failed to parse package lock: {err:#}");
+            return Vec::new();
+        }
+    };
+    let packages = match sbom.to_packages() {
+        Ok(packages) => packages,
+        Err(err) => {
+            warn!("Failed to parse package lock: {err:#}");
+            return Vec::new();
+        }
+    };
+    let purl_type = sbom.purl_type();
+    packages
+        .into_iter()
+        .map(|pkg| PackageWithPurl {
+            purl: purl_type.map(|ty| pkg.purl(ty).to_string()),
+            href: pkg
+                .checksum
+                .as_deref()
+                .map(|chksum| format!("/artifact/{}", url_encode_artifact(chksum))),
+            artifact: None,
+            pkg,
+        })
+        .collect()
+}
+
+/// Look up which checksums of a lockfile point to an archived artifact
+async fn resolve_sbom_packages(db: &db::Client, packages: &mut [PackageWithPurl]) -> Result<()> {
+    let chksums = packages
+        .iter()
+        .filter_map(|p| p.pkg.checksum.clone())
+        .collect::<Vec<_>>();
+    if chksums.is_empty() {
+        return Ok(());
+    }
+    let resolved = db.resolve_artifacts_bulk(&chksums).await?;
+    for p in packages {
+        p.artifact = p
+            .pkg
+            .checksum
+            .as_ref()
+            .and_then(|chksum| resolved.get(chksum))
+            .cloned();
+    }
+    Ok(())
+}
+
+/// Split a search like `xz-5.6.1.tar.*` into package name and version
+fn detect_filename_search(txt: &str) -> Option<(&str, &str)> {
+    let mut txt = txt.trim_end_matches('*');
+    for ext in [".tar.", ".tgz", ".zip", ".crate", ".gem"] {
+        if let Some(idx) = txt.find(ext) {
+            txt = &txt[..idx];
+            break;
+        }
+    }
+    let txt = txt.strip_suffix(".tar").unwrap_or(txt);
+
+    // the version starts after the last dash that's followed by a digit
+    let idx = txt
+        .rmatch_indices('-')
+        .map(|(idx, _)| idx)
+        .find(|idx| txt[idx + 1..].starts_with(|c: char| c.is_ascii_digit()))?;
+    let (name, version) = (&txt[..idx], &txt[idx + 1..]);
+    (!name.is_empty()).then_some((name, version))
+}
+
 fn detect_hash_search(txt: &str) -> Option<Cow<'_, str>> {
     if let Some(value) = txt.strip_prefix("sha256") {
         if let Some(value) = value.strip_prefix(':') {
@@ -307,8 +985,14 @@ fn detect_hash_search(txt: &str) -> Option<Cow<'_, str>> {
     } else if let Some(value) = txt.strip_prefix("blake2b:") {
         // search is in format blake2b:<hex>
         (value.len() == 128).then_some(Cow::Borrowed(txt))
-    } else if let Some(_value) = txt.strip_prefix("git:") {
+    } else if ["git:", "hg:", "fossil:"]
+        .iter()
+        .any(|prefix| txt.starts_with(prefix))
+    {
         Some(Cow::Borrowed(txt))
+    } else if let Some(value) = txt.strip_prefix("h1:") {
+        // search is in format h1:<base64> (go.sum)
+        (value.len() == 44).then_some(Cow::Borrowed(txt))
     } else if let Ok(url) = txt.parse::<Uri>() {
         let path = url.path();
         path.strip_prefix("/artifact/")
@@ -322,6 +1006,8 @@ fn detect_hash_search(txt: &str) -> Option<Cow<'_, str>> {
 #[derive(Debug, Serialize, Deserialize)]
 struct SearchQuery {
     q: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at: Option<String>,
 }
 
 async fn search(
@@ -333,6 +1019,7 @@ async fn search(
     if trimmed.len() != search.q.len() {
         let query = serde_urlencoded::to_string(SearchQuery {
             q: trimmed.to_string(),
+            at: search.at.clone(),
         })
         .map_err(Error::from)?;
         let uri = format!("/search?{query}")
@@ -348,17 +1035,59 @@ async fn search(
         return Ok(Box::new(warp::redirect::found(uri)));
     }
 
+    if let Some(path) = trimmed.strip_prefix("path:") {
+        let path = path.trim_start_matches('/');
+        let files = if path.is_empty() {
+            Vec::new()
+        } else {
+            db.search_path(path, SEARCH_LIMIT).await?
+        };
+        let html = hbs.render(
+            "search.html.hbs",
+            &json!({
+                "search": search.q,
+                "files": files,
+            }),
+        )?;
+        return Ok(Box::new(warp::reply::html(html)));
+    }
+
+    let at = search.at.as_deref().map(parse_point_in_time).transpose()?;
+
     let mut query = search.q.clone();
     query.retain(|c| !"%_".contains(c));
     query.push('%');
 
-    let refs = db.search(&query, SEARCH_LIMIT).await?;
+    let mut refs = db.search(&query, at.as_ref(), SEARCH_LIMIT).await?;
+    if let Some((name, version)) = detect_filename_search(trimmed) {
+        refs.extend(
+            db.search_version(name, version, at.as_ref(), SEARCH_LIMIT)
+                .await?,
+        );
+    }
+    if refs.len() < SEARCH_LIMIT {
+        refs.extend(
+            db.search_substring(trimmed, at.as_ref(), SEARCH_LIMIT)
+                .await?,
+        );
+    }
+    let mut seen = HashSet::new();
+    refs.retain(|r| {
+        seen.insert((
+            r.vendor.clone(),
+            r.package.clone(),
+            r.version.clone(),
+            r.chksum.clone(),
+        ))
+    });
+    refs.truncate(SEARCH_LIMIT);
 
     let html = hbs
         .render(
             "search.html.hbs",
             &json!({
                 "search": search.q,
+                "at": at.map(|at| at.to_rfc3339()),
                 "refs": refs,
             }),
         )
@@ -366,37 +1095,768 @@ async fn search(
     Ok(Box::new(warp::reply::html(html)))
 }
 
+const WATCHLIST_REFS_LIMIT: usize = 25;
+
+#[derive(Debug, Serialize)]
+struct WatchedRef {
+    #[serde(flatten)]
+    r: db::RefView,
+    new: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct WatchedReport {
+    #[serde(flatten)]
+    report: db::Report,
+    new: bool,
+}
+
 #[derive(Debug, Deserialize)]
-struct StatsQuery {
+struct WatchlistForm {
     #[serde(default)]
-    dates: bool,
+    q: String,
+    #[serde(default)]
+    remove: bool,
+    /// Everything currently listed has been looked at, only what shows up afterwards is new
+    #[serde(default)]
+    seen: bool,
 }
 
-async fn stats(
+async fn watchlist_create(
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let token = random_token()?;
+    db.create_watchlist(&token).await?;
+    let uri = format!("/watchlist/{token}")
+        .parse::<Uri>()
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::see_other(uri)))
+}
+
+async fn watchlist(
     hbs: Arc<Handlebars<'_>>,
     db: Arc<db::Client>,
-    stats: StatsQuery,
+    token: String,
 ) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
-    let mut set = JoinSet::new();
-    if stats.dates {
-        let db = db.clone();
-        set.spawn(async move { ("import_dates", db.stats_import_dates().await) });
-    } else {
-        let db = db.clone();
-        set.spawn(async move { ("total_artifacts", db.stats_estimated_artifacts().await) });
-    }
-    {
-        let db = db.clone();
-        set.spawn(async move { ("vendor_refs", db.stats_vendor_refs().await) });
-    }
-    {
-        let db = db.clone();
-        set.spawn(async move { ("pending_tasks", db.stats_pending_tasks().await) });
+    let Some(watchlist) = db.get_watchlist(&token).await? else {
+        return Err(reject::not_found());
+    };
+
+    // the visit is only recorded with an explicit POST, so link previews don't reset it
+    let is_new = |first_seen| {
+        watchlist
+            .last_visit
+            .is_some_and(|last_visit| first_seen > last_visit)
+    };
+    let mut entries = Vec::new();
+    for query in db.get_watchlist_entries(&watchlist).await? {
+        let refs = db
+            .get_watched_refs(&query, WATCHLIST_REFS_LIMIT)
+            .await?
+            .into_iter()
+            .map(|r| WatchedRef {
+                new: is_new(r.first_seen),
+                r: r.into(),
+            })
+            .collect::<Vec<_>>();
+        let reports = db
+            .get_watched_reports(&query, WATCHLIST_REFS_LIMIT)
+            .await?
+            .into_iter()
+            .map(|report| WatchedReport {
+                new: is_new(report.first_seen),
+                report,
+            })
+            .collect::<Vec<_>>();
+        let new = refs.iter().filter(|r| r.new).count();
+        let new_reports = reports.iter().filter(|r| r.new).count();
+        entries.push(json!({
+            "query": query,
+            "new": new,
+            "new_reports": new_reports,
+            "refs": refs,
+            "reports": reports,
+        }));
     }
-    {
+
+    let html = hbs.render(
+        "watchlist.html.hbs",
+        &json!({
+            "token": watchlist.token,
+            "last_visit": watchlist.last_visit.map(|t| t.to_rfc3339()),
+            "entries": entries,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn watchlist_edit(
+    db: Arc<db::Client>,
+    token: String,
+    form: WatchlistForm,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(watchlist) = db.get_watchlist(&token).await? else {
+        return Err(reject::not_found());
+    };
+
+    let query = form.q.trim();
+    let query = detect_hash_search(query).unwrap_or(Cow::Borrowed(query));
+    if form.seen {
+        db.bump_watchlist_visit(&watchlist).await?;
+    } else if form.remove {
+        db.delete_watchlist_entry(&watchlist, &query).await?;
+    } else if !query.is_empty() {
+        db.insert_watchlist_entry(&watchlist, &query).await?;
+    }
+
+    let uri = format!("/watchlist/{}", watchlist.token)
+        .parse::<Uri>()
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::see_other(uri)))
+}
+
+#[derive(Debug, Serialize)]
+struct FeedEntry {
+    id: String,
+    title: String,
+    link: String,
+    updated: String,
+    summary: Option<String>,
+}
+
+fn render_feed(
+    hbs: &Handlebars<'_>,
+    title: &str,
+    path: &str,
+    entries: &[FeedEntry],
+) -> Result<Box<dyn warp::Reply>> {
+    let updated = entries
+        .first()
+        .map(|entry| entry.updated.clone())
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+    let xml = hbs.render(
+        "feed.xml.hbs",
+        &json!({
+            "title": title,
+            "id": format!("{}{path}", feed_base_url()),
+            "base": feed_base_url(),
+            "updated": updated,
+            "entries": entries,
+        }),
+    )?;
+
+    let mut res = warp::reply::Response::new(xml.into());
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/atom+xml; charset=utf-8"),
+    );
+    Ok(Box::new(res))
+}
+
+async fn feed_artifacts(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let entries = db
+        .get_recent_artifacts(FEED_LIMIT)
+        .await?
+        .into_iter()
+        .map(|artifact| {
+            let title = match (&artifact.vendor, &artifact.package, &artifact.version) {
+                (Some(vendor), Some(package), Some(version)) => {
+                    format!("{vendor}: {package} {version}")
+                }
+                _ => artifact.chksum.clone(),
+            };
+            let link = format!(
+                "{}/artifact/{}",
+                feed_base_url(),
+                url_encode_artifact(&artifact.chksum)
+            );
+            FeedEntry {
+                id: link.clone(),
+                title,
+                link,
+                updated: artifact.last_imported.to_rfc3339(),
+                summary: Some(artifact.chksum),
+            }
+        })
+        .collect::<Vec<_>>();
+    let reply = render_feed(
+        &hbs,
+        "What the src?! - New artifacts",
+        "/feed/artifacts.xml",
+        &entries,
+    )?;
+    Ok(reply)
+}
+
+async fn feed_reports(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let entries = db
+        .get_recent_reports(FEED_LIMIT)
+        .await?
+        .into_iter()
+        .map(|report| {
+            let title = match (&report.package, &report.version) {
+                (Some(package), Some(version)) => {
+                    format!("{}: {package} {version}", report.kind)
+                }
+                _ => format!("{}: {}", report.kind, report.chksum),
+            };
+            FeedEntry {
+                id: format!("{}/reports#{}", feed_base_url(), report.id),
+                title,
+                link: format!(
+                    "{}/{}/{}",
+                    feed_base_url(),
+                    if report.kind == reports::KIND_BLOB_CORRUPTED {
+                        "file"
+                    } else {
+                        "artifact"
+                    },
+                    url_encode_artifact(&report.chksum)
+                ),
+                updated: report.first_seen.to_rfc3339(),
+                summary: Some(report.summary),
+            }
+        })
+        .collect::<Vec<_>>();
+    let reply = render_feed(
+        &hbs,
+        "What the src?! - New reports",
+        "/feed/reports.xml",
+        &entries,
+    )?;
+    Ok(reply)
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscribeForm {
+    target_kind: String,
+    target: String,
+    #[serde(default)]
+    vendor: String,
+    #[serde(default)]
+    package: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionForm {
+    #[serde(default)]
+    remove: bool,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn random_token() -> Result<String> {
+    let mut token = [0; 16];
+    getrandom::getrandom(&mut token)?;
+    Ok(hex::encode(token))
+}
+
+async fn subscribe(
+    hbs: Arc<Handlebars<'_>>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let html = hbs.render("subscribe.html.hbs", &json!({}))?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn subscribe_create(
+    db: Arc<db::Client>,
+    form: SubscribeForm,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let target = form.target.trim();
+    let target_kind = notify::validate_target(&form.target_kind, target)?;
+    let vendor = non_empty(&form.vendor);
+    let package = non_empty(&form.package);
+    if vendor.is_none() && package.is_none() {
+        return Err(Error::InvalidSubscription("vendor or package is required").into());
+    }
+
+    if target_kind == notify::TARGET_WEBHOOK {
+        notify::resolve_webhook(target).await?;
+    }
+
+    let token = random_token()?;
+    db.insert_subscription(&db::NewSubscription {
+        token: token.clone(),
+        confirm_token: random_token()?,
+        unsubscribe_token: random_token()?,
+        target_kind,
+        target: target.to_string(),
+        vendor,
+        package,
+        // email addresses need to confirm they want these mails
+        confirmed: target_kind != notify::TARGET_EMAIL,
+    })
+    .await?;
+    let uri = format!("/subscribe/{token}")
+        .parse::<Uri>()
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::see_other(uri)))
+}
+
+async fn subscription(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    token: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(subscription) = db.get_subscription(&token).await? else {
+        return Err(reject::not_found());
+    };
+
+    let html = hbs.render(
+        "subscribe.html.hbs",
+        &json!({
+            "subscription": subscription,
+            "confirmed": subscription.confirmed_at.is_some(),
+            "last_notified": subscription.last_notified.to_rfc3339(),
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn subscription_edit(
+    db: Arc<db::Client>,
+    token: String,
+    form: SubscriptionForm,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(subscription) = db.get_subscription(&token).await? else {
+        return Err(reject::not_found());
+    };
+
+    let uri = if form.remove {
+        db.delete_subscription(&subscription.token).await?;
+        "/subscribe".to_string()
+    } else {
+        format!("/subscribe/{}", subscription.token)
+    };
+    let uri = uri.parse::<Uri>().map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::see_other(uri)))
+}
+
+async fn subscription_confirm_page(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    token: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    // this only renders a form, link scanners in mail clients must not confirm on their own
+    let exists = db.get_subscription_by_confirm_token(&token).await?;
+    let Some(subscription) = exists else {
+        return Err(reject::not_found());
+    };
+    let html = hbs.render(
+        "subscribe.html.hbs",
+        &json!({
+            "confirm": token,
+            "confirmed": subscription.confirmed_at.is_some(),
+            "subscription": subscription,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn subscription_confirm(
+    db: Arc<db::Client>,
+    token: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    if db.confirm_subscription(&token).await?.is_none() {
+        return Err(reject::not_found());
+    }
+    let uri = format!("/subscribe/confirm/{token}")
+        .parse::<Uri>()
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::see_other(uri)))
+}
+
+async fn unsubscribe_page(
+    hbs: Arc<Handlebars<'_>>,
+    token: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let html = hbs.render("subscribe.html.hbs", &json!({ "unsubscribe": token }))?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+/// Also used for one-click unsubscribe from mail clients (RFC 8058)
+async fn unsubscribe(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    token: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    if !db.delete_subscription_by_unsubscribe_token(&token).await? {
+        return Err(reject::not_found());
+    }
+    let html = hbs.render("subscribe.html.hbs", &json!({ "unsubscribed": true }))?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn api_artifact(
+    db: Arc<db::Client>,
+    chksum: String,
+    query: ArtifactQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let at = query.at.as_deref().map(parse_point_in_time).transpose()?;
+
+    let alias = db.get_artifact_alias(&chksum).await?;
+    let resolved_chksum = alias
+        .as_ref()
+        .map(|a| a.alias_to.as_str())
+        .unwrap_or(&chksum);
+    let Some(artifact) = db.get_artifact(resolved_chksum).await? else {
+        return Err(reject::not_found());
+    };
+    if at.is_some_and(|at| artifact.first_seen > at) || db.is_quarantined(&artifact.chksum).await? {
+        return Err(reject::not_found());
+    }
+
+    let files = artifact.get_files()?;
+    let refs = db
+        .get_all_refs_for_at(&artifact.chksum, at.as_ref())
+        .await?;
+    let sbom_refs = db.get_sbom_refs_for_archive(&artifact.chksum).await?;
+
+    Ok(Box::new(warp::reply::json(&json!({
+        "chksum": artifact.chksum,
+        "alias": alias,
+        "files": files,
+        "refs": refs,
+        "sbom_refs": sbom_refs,
+    }))))
+}
+
+/// How many files with the same digest are resolved to artifacts
+const FILE_DIGEST_LIMIT: usize = 1000;
+
+#[derive(Debug, Serialize)]
+struct FileOccurrence {
+    chksum: String,
+    paths: Vec<String>,
+    refs: Vec<db::RefView>,
+}
+
+/// Accept a bare sha256 digest, like the output of `sha256sum`
+fn parse_file_digest(digest: &str) -> Option<String> {
+    let digest = digest.trim();
+    if digest.contains(':') {
+        chksums::normalize(digest).ok()
+    } else {
+        chksums::normalize(&format!("sha256:{digest}")).ok()
+    }
+}
+
+/// Every artifact that contains a file with this digest, and the vendors referencing it
+///
+/// Also returns if the list was cut short at `FILE_DIGEST_LIMIT` files.
+async fn lookup_file_digest(
+    db: &db::Client,
+    digest: &str,
+) -> result::Result<(String, Vec<FileOccurrence>, bool), warp::Rejection> {
+    let Some(digest) = parse_file_digest(digest) else {
+        return Err(reject::not_found());
+    };
+    let mut files = db
+        .get_files_by_digest(&digest, FILE_DIGEST_LIMIT + 1)
+        .await?;
+    let truncated = files.len() > FILE_DIGEST_LIMIT;
+    files.truncate(FILE_DIGEST_LIMIT);
+
+    let mut artifacts = Vec::<FileOccurrence>::new();
+    for file in files {
+        match artifacts.last_mut() {
+            Some(artifact) if artifact.chksum == file.chksum => artifact.paths.push(file.path),
+            _ => artifacts.push(FileOccurrence {
+                chksum: file.chksum,
+                paths: vec![file.path],
+                refs: Vec::new(),
+            }),
+        }
+    }
+
+    let chksums = artifacts
+        .iter()
+        .map(|artifact| artifact.chksum.clone())
+        .collect::<Vec<_>>();
+    let mut refs = HashMap::<_, Vec<_>>::new();
+    for resolved in db.get_all_refs_for_bulk(&chksums).await? {
+        refs.entry(resolved.artifact)
+            .or_default()
+            .push(resolved.r.into());
+    }
+    for artifact in &mut artifacts {
+        artifact.refs = refs.remove(&artifact.chksum).unwrap_or_default();
+    }
+
+    Ok((digest, artifacts, truncated))
+}
+
+async fn file_digest(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    digest: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (digest, artifacts, truncated) = lookup_file_digest(&db, &digest).await?;
+    let html = hbs.render(
+        "file-digest.html.hbs",
+        &json!({
+            "digest": digest,
+            "artifacts": artifacts,
+            "truncated": truncated,
+            "limit": FILE_DIGEST_LIMIT,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn api_file_digest(
+    db: Arc<db::Client>,
+    digest: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (digest, artifacts, truncated) = lookup_file_digest(&db, &digest).await?;
+    Ok(Box::new(warp::reply::json(&json!({
+        "digest": digest,
+        "artifacts": artifacts,
+        "truncated": truncated,
+    }))))
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiRefsQuery {
+    package: Option<String>,
+    version: Option<String>,
+    vendor: Option<String>,
+    purl: Option<String>,
+}
+
+async fn api_refs(
+    db: Arc<db::Client>,
+    query: ApiRefsQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let refs = if let Some(purl) = &query.purl {
+        let purl = purl.parse::<Purl>()?;
+        // only purls of vendor packages can be mapped to refs
+        if let Some(vendor) = purl.vendor() {
+            db.get_refs_for_package(
+                &purl.name,
+                purl.version.as_deref(),
+                Some(vendor),
+                SEARCH_LIMIT,
+            )
+            .await?
+        } else {
+            Vec::new()
+        }
+    } else if let Some(package) = &query.package {
+        db.get_refs_for_package(
+            package,
+            query.version.as_deref(),
+            query.vendor.as_deref(),
+            SEARCH_LIMIT,
+        )
+        .await?
+    } else {
+        return Err(Error::MissingQueryParameter("package or purl").into());
+    };
+    Ok(Box::new(warp::reply::json(&json!({
+        "refs": refs,
+    }))))
+}
+
+async fn api_sbom(
+    db: Arc<db::Client>,
+    chksum: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(sbom) = db.get_sbom(&chksum).await? else {
+        return Err(reject::not_found());
+    };
+    if db.is_sbom_quarantined(&sbom).await? {
+        return Err(reject::not_found());
+    }
+
+    let sbom_refs = db.get_sbom_refs_for_sbom(&sbom).await?;
+    let packages = sbom_packages(&sbom);
+
+    Ok(Box::new(warp::reply::json(&json!({
+        "chksum": sbom.chksum,
+        "strain": sbom.strain,
+        "data": sbom.data,
+        "packages": packages,
+        "sbom_refs": sbom_refs,
+    }))))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportsQuery {
+    vendor: Option<String>,
+    package: Option<String>,
+}
+
+async fn reports(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    query: ReportsQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let reports = db
+        .get_reports(
+            query.vendor.as_deref(),
+            query.package.as_deref(),
+            SEARCH_LIMIT as i64,
+        )
+        .await?;
+
+    let html = hbs
+        .render(
+            "reports.html.hbs",
+            &json!({
+                "vendor": query.vendor,
+                "package": query.package,
+                "reports": reports,
+            }),
+        )
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn stats_tasks(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (errors, failed) = tokio::join!(db.stats_failed_tasks(50), db.get_failed_tasks(50));
+
+    let html = hbs
+        .render(
+            "stats-tasks.html.hbs",
+            &json!({
+                "errors": errors?,
+                "failed": failed_tasks_json(failed?),
+            }),
+        )
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn api_failed_tasks(
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (errors, failed) = tokio::join!(db.stats_failed_tasks(250), db.get_failed_tasks(250));
+    let errors = errors?
+        .into_iter()
+        .map(|(error, num)| json!({ "error": error, "tasks": num }))
+        .collect::<Vec<_>>();
+
+    Ok(Box::new(warp::reply::json(&json!({
+        "errors": errors,
+        "failed": failed_tasks_json(failed?),
+    }))))
+}
+
+fn failed_tasks_json(tasks: Vec<db::Task>) -> Vec<serde_json::Value> {
+    tasks
+        .into_iter()
+        .map(|task| {
+            json!({
+                "id": task.id,
+                "key": task.key,
+                "error": task.error,
+                "failed_at": task.failed_at.map(|t| t.to_rfc3339()),
+            })
+        })
+        .collect()
+}
+
+/// The process is up, this doesn't touch the database
+async fn healthz() -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    Ok(Box::new("ok\n"))
+}
+
+/// The database is reachable and all migrations of this binary have been applied
+async fn readyz(db: Arc<db::Client>) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (message, code) = match db.status_migration_version().await {
+        Ok(version) if version >= db::latest_migration_version() => ("ok\n", StatusCode::OK),
+        Ok(_) => ("migrations pending\n", StatusCode::SERVICE_UNAVAILABLE),
+        Err(err) => {
+            warn!("Failed to query migration version: {err:#}");
+            ("database unavailable\n", StatusCode::SERVICE_UNAVAILABLE)
+        }
+    };
+    Ok(Box::new(warp::reply::with_status(message, code)))
+}
+
+async fn status(db: Arc<db::Client>) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (migration, last_sync, workers, backlog) = match tokio::try_join!(
+        db.status_migration_version(),
+        db.status_last_sync(),
+        db.status_worker_heartbeats(),
+        db.status_task_backlog(),
+    ) {
+        Ok(status) => status,
+        Err(err) => {
+            warn!("Failed to query instance status: {err:#}");
+            let reply = warp::reply::json(&json!({
+                "database": "unavailable",
+            }));
+            return Ok(Box::new(warp::reply::with_status(
+                reply,
+                StatusCode::SERVICE_UNAVAILABLE,
+            )));
+        }
+    };
+
+    let last_sync = last_sync
+        .into_iter()
+        .map(|(vendor, last_seen)| (vendor, last_seen.to_rfc3339()))
+        .collect::<HashMap<_, _>>();
+    let workers = workers.into_iter().collect::<HashMap<_, _>>();
+
+    Ok(Box::new(warp::reply::json(&json!({
+        "database": "ok",
+        "migration_version": migration,
+        "last_sync": last_sync,
+        "worker_heartbeat_age_secs": workers,
+        "task_backlog": backlog,
+    }))))
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    #[serde(default)]
+    dates: bool,
+}
+
+async fn stats(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    stats: StatsQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let mut set = JoinSet::new();
+    if stats.dates {
+        let db = db.clone();
+        set.spawn(async move { ("import_dates", db.stats_import_dates().await) });
+    } else {
+        let db = db.clone();
+        set.spawn(async move { ("total_artifacts", db.stats_estimated_artifacts().await) });
+    }
+    {
+        let db = db.clone();
+        set.spawn(async move { ("vendor_refs", db.stats_vendor_refs().await) });
+    }
+    {
+        let db = db.clone();
+        set.spawn(async move { ("pending_tasks", db.stats_pending_tasks().await) });
+    }
+    {
         let db = db.clone();
         set.spawn(async move { ("aliases_with_reason", db.stats_aliases_with_reason().await) });
     }
+    {
+        let db = db.clone();
+        set.spawn(async move { ("worker_heartbeats", db.status_worker_heartbeats().await) });
+    }
     set.spawn(async move {
         (
             "compressed_artifacts",
@@ -521,67 +1981,434 @@ fn url_encode_artifact(txt: &str) -> Cow<'_, str> {
     url_escape::encode(txt, &ARTIFACT_SET)
 }
 
-async fn diff_redirect(
-    query: DiffRedirectQuery,
-) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
-    // diff_from comes from type=hidden so we expect a well-formatted identifier
-    // diff_to comes from type=text so we try to do a fuzzy match to be user friendly
-    let diff_to = detect_hash_search(&query.diff_to).unwrap_or(Cow::Borrowed(&query.diff_to));
+async fn stats_sboms(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let (strains, top_dependencies, with_checksum, resolvable) = tokio::join!(
+        db.stats_sbom_strains(),
+        db.stats_sbom_top_dependencies(50),
+        db.stats_sbom_packages_with_checksum(),
+        db.stats_sbom_packages_resolvable(),
+    );
+
+    let html = hbs
+        .render(
+            "stats-sboms.html.hbs",
+            &json!({
+                "strains": strains?,
+                "top_dependencies": top_dependencies?,
+                "with_checksum": with_checksum?,
+                "resolvable": resolvable?,
+            }),
+        )
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+async fn diff_redirect(
+    query: DiffRedirectQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    // diff_from comes from type=hidden so we expect a well-formatted identifier
+    // diff_to comes from type=text so we try to do a fuzzy match to be user friendly
+    let diff_to = detect_hash_search(&query.diff_to).unwrap_or(Cow::Borrowed(&query.diff_to));
+
+    let uri = format!(
+        "/diff/{}/{}",
+        url_encode_artifact(&query.diff_from),
+        url_encode_artifact(&diff_to)
+    );
+    let uri = uri.parse::<Uri>().map_err(Error::from)?;
+    Ok(Box::new(warp::redirect::found(uri)))
+}
+
+/// Parse a `vendor:package:version` identifier, the version may contain colons itself
+fn parse_named_ref(txt: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = txt.splitn(3, ':');
+    let vendor = parts.next().filter(|s| !s.is_empty())?;
+    let package = parts.next().filter(|s| !s.is_empty())?;
+    let version = parts.next().filter(|s| !s.is_empty())?;
+    Some((vendor, package, version))
+}
+
+/// Resolve one side of a diff, given either as digest or as `vendor:package:version`
+async fn resolve_diff_artifact(db: &db::Client, txt: &str) -> Result<Option<db::Artifact>> {
+    if let Some((vendor, package, version)) = parse_named_ref(txt) {
+        if let Some(r) = db.get_named_ref(vendor, package, version).await? {
+            return db.resolve_artifact(&r.chksum).await;
+        }
+    }
+    db.resolve_artifact(txt).await
+}
+
+async fn diff(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    options: Diff,
+    diff_from: String,
+    diff_to: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(artifact1) = resolve_diff_artifact(&db, &diff_from).await? else {
+        return Err(reject::not_found());
+    };
+
+    let Some(artifact2) = resolve_diff_artifact(&db, &diff_to).await? else {
+        return Err(reject::not_found());
+    };
+    if db.is_quarantined(&artifact1.chksum).await? || db.is_quarantined(&artifact2.chksum).await? {
+        return Err(reject::not_found());
+    }
+
+    let chksum_from = artifact1.chksum.clone();
+    let chksum_to = artifact2.chksum.clone();
+
+    let mut artifact_files1 = artifact1.get_files()?;
+    let mut artifact_files2 = artifact2.get_files()?;
+
+    if options != Diff::default() {
+        artifact_files1 = process_files_list(artifact_files1, options.sorted, options.trim_left)?;
+        artifact_files2 = process_files_list(artifact_files2, options.sorted, options.trim_right)?;
+    }
+
+    let artifact1 = hbs.render_archive(artifact_files1.as_deref())?;
+    let artifact2 = hbs.render_archive(artifact_files2.as_deref())?;
+
+    let diff = diffy::create_file_patch(&artifact1, &artifact2, &diff_from, &diff_to);
+    let diff = diff.to_string();
+    let diff_lines = diff.split('\n').collect::<Vec<_>>();
+
+    let html = hbs
+        .render(
+            "diff.html.hbs",
+            &json!({
+                "diff": diff_lines,
+                "diff_from": diff_from,
+                "diff_to": diff_to,
+                "chksum_from": chksum_from,
+                "chksum_to": chksum_to,
+                "options": options,
+                "sorted": options.sorted,
+                "trimmed": options.trim_left || options.trim_right,
+                "trim_left": options.trim_left,
+                "trim_right": options.trim_right,
+            }),
+        )
+        .map_err(Error::from)?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+#[derive(Debug, Serialize)]
+struct PackageVersion {
+    #[serde(flatten)]
+    r: db::RefView,
+    last_seen: String,
+    /// Link to the diff from the previous artifact, if it differs
+    diff: Option<String>,
+    upstream_warning: Option<UpstreamWarning>,
+}
+
+async fn package(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    vendor: String,
+    package: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let vendor = url_escape::decode(&vendor);
+    let package = url_escape::decode(&package);
+
+    let refs = db.get_package_versions(&vendor, &package).await?;
+    if refs.is_empty() {
+        return Err(reject::not_found());
+    }
+
+    let upstream_warnings =
+        get_upstream_warnings(&db, refs.iter().map(|r| r.chksum.as_str())).await?;
+
+    // refs are sorted newest first, so the previous artifact is the next entry
+    let mut versions = Vec::new();
+    for (i, r) in refs.iter().enumerate() {
+        let previous = refs[i + 1..].iter().find(|p| p.version != r.version);
+        let diff = previous.filter(|p| p.chksum != r.chksum).map(|p| {
+            format!(
+                "/diff/{}/{}",
+                url_encode_artifact(&p.chksum),
+                url_encode_artifact(&r.chksum)
+            )
+        });
+        let upstream_warning = upstream_warnings
+            .iter()
+            .find(|w| w.chksum == r.chksum && r.filename.as_deref() == Some(w.url.as_str()))
+            .cloned();
+        versions.push(PackageVersion {
+            last_seen: r.last_seen.to_rfc3339(),
+            diff,
+            upstream_warning,
+            r: r.clone().into(),
+        });
+    }
+
+    let display_vendor = versions
+        .first()
+        .map(|v| v.r.display_vendor.clone())
+        .unwrap_or_default();
+    let has_patches = db.has_patches(&vendor, &package).await?;
+    let upstreams = db
+        .get_upstreams_for_package(&vendor, &package)
+        .await?
+        .into_iter()
+        .map(|url| {
+            let href = format!("/upstream?url={}", url_escape::encode_component(&url));
+            json!({ "url": url, "href": href })
+        })
+        .collect::<Vec<_>>();
+    let html = hbs.render(
+        "package.html.hbs",
+        &json!({
+            "vendor": vendor,
+            "display_vendor": display_vendor,
+            "package": package,
+            "versions": versions,
+            "has_patches": has_patches,
+            "upstreams": upstreams,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+/// How many refs are listed on the upstream page
+const UPSTREAM_REFS_LIMIT: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+struct UpstreamQuery {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UpstreamPackage {
+    vendor: String,
+    display_vendor: String,
+    package: String,
+    versions: Vec<PackageVersion>,
+}
+
+async fn upstream(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    query: UpstreamQuery,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let Some(url) = upstream::normalize_url(&query.url) else {
+        return Err(reject::not_found());
+    };
+    let refs = db.get_upstream_refs(&url, UPSTREAM_REFS_LIMIT).await?;
+    if refs.is_empty() {
+        return Err(reject::not_found());
+    }
+
+    let mut packages = Vec::<UpstreamPackage>::new();
+    for r in refs {
+        let version = PackageVersion {
+            last_seen: r.last_seen.to_rfc3339(),
+            diff: None,
+            upstream_warning: None,
+            r: r.into(),
+        };
+        if let Some(pkg) = packages
+            .iter_mut()
+            .find(|p| p.vendor == version.r.vendor && p.package == version.r.package)
+        {
+            pkg.versions.push(version);
+        } else {
+            packages.push(UpstreamPackage {
+                vendor: version.r.vendor.clone(),
+                display_vendor: version.r.display_vendor.to_string(),
+                package: version.r.package.clone(),
+                versions: vec![version],
+            });
+        }
+    }
+
+    let html = hbs.render(
+        "upstream.html.hbs",
+        &json!({
+            "url": url,
+            "packages": packages,
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct PatchChange {
+    filename: String,
+    chksum: String,
+    size: i64,
+    /// `added` or `changed` compared to the previous version, `None` if unchanged
+    status: Option<&'static str>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct PatchVersion {
+    version: String,
+    patches: Vec<PatchChange>,
+    removed: Vec<String>,
+}
+
+/// Group patches by version and compare each version to the one before it
+///
+/// Patches are expected to be sorted by version, newest first.
+fn patch_history(patches: Vec<db::PatchRef>) -> Vec<PatchVersion> {
+    let mut versions = Vec::<(String, Vec<db::PatchRef>)>::new();
+    for patch in patches {
+        match versions.last_mut() {
+            Some((version, list)) if *version == patch.version => list.push(patch),
+            _ => versions.push((patch.version.clone(), vec![patch])),
+        }
+    }
 
-    let uri = format!(
-        "/diff/{}/{}",
-        url_encode_artifact(&query.diff_from),
-        url_encode_artifact(&diff_to)
-    );
-    let uri = uri.parse::<Uri>().map_err(Error::from)?;
-    Ok(Box::new(warp::redirect::found(uri)))
+    let mut history = Vec::new();
+    for (i, (version, list)) in versions.iter().enumerate() {
+        let previous = versions.get(i + 1).map(|(_, list)| list);
+        let patches = list
+            .iter()
+            .map(|patch| {
+                let status = previous.and_then(|previous| {
+                    match previous.iter().find(|p| p.filename == patch.filename) {
+                        None => Some("added"),
+                        Some(p) if p.chksum != patch.chksum => Some("changed"),
+                        Some(_) => None,
+                    }
+                });
+                PatchChange {
+                    filename: patch.filename.clone(),
+                    chksum: patch.chksum.clone(),
+                    size: patch.size,
+                    status,
+                }
+            })
+            .collect();
+        let removed = previous
+            .into_iter()
+            .flatten()
+            .filter(|p| !list.iter().any(|patch| patch.filename == p.filename))
+            .map(|p| p.filename.clone())
+            .collect();
+        history.push(PatchVersion {
+            version: version.clone(),
+            patches,
+            removed,
+        });
+    }
+    history
 }
 
-async fn diff(
+async fn package_patches(
     hbs: Arc<Handlebars<'_>>,
     db: Arc<db::Client>,
-    options: Diff,
-    diff_from: String,
-    diff_to: String,
+    vendor: String,
+    package: String,
 ) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
-    let Some(artifact1) = db.resolve_artifact(&diff_from).await? else {
-        return Err(reject::not_found());
-    };
+    let vendor = url_escape::decode(&vendor);
+    let package = url_escape::decode(&package);
 
-    let Some(artifact2) = db.resolve_artifact(&diff_to).await? else {
+    let patches = db.get_package_patches(&vendor, &package).await?;
+    if patches.is_empty() {
         return Err(reject::not_found());
+    }
+
+    let html = hbs.render(
+        "patches.html.hbs",
+        &json!({
+            "vendor": vendor,
+            "package": package,
+            "versions": patch_history(patches),
+        }),
+    )?;
+    Ok(Box::new(warp::reply::html(html)))
+}
+
+/// Strip the epoch and the vendor specific package revision from a version
+fn upstream_version(version: &str) -> &str {
+    let version = match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => version,
     };
+    version
+        .rsplit_once('-')
+        .map(|(version, _)| version)
+        .unwrap_or(version)
+}
 
-    let mut artifact_files1 = artifact1.get_files()?;
-    let mut artifact_files2 = artifact2.get_files()?;
+#[derive(Debug, Serialize)]
+struct CompareGroup {
+    artifact: String,
+    vendors: Vec<String>,
+    refs: Vec<db::RefView>,
+    /// Set if this isn't the artifact most vendors agree on
+    differs: bool,
+}
 
-    if options != Diff::default() {
-        artifact_files1 = process_files_list(artifact_files1, options.sorted, options.trim_left)?;
-        artifact_files2 = process_files_list(artifact_files2, options.sorted, options.trim_right)?;
+/// Group refs by the artifact they resolve to, the group with the most vendors comes first
+fn group_by_artifact(refs: Vec<db::ResolvedRef>) -> Vec<CompareGroup> {
+    let mut groups = Vec::<CompareGroup>::new();
+    for resolved in refs {
+        let group = match groups
+            .iter_mut()
+            .position(|g| g.artifact == resolved.artifact)
+        {
+            Some(idx) => &mut groups[idx],
+            None => {
+                groups.push(CompareGroup {
+                    artifact: resolved.artifact,
+                    vendors: Vec::new(),
+                    refs: Vec::new(),
+                    differs: false,
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+        if !group.vendors.contains(&resolved.r.vendor) {
+            group.vendors.push(resolved.r.vendor.clone());
+        }
+        group.refs.push(resolved.r.into());
     }
 
-    let artifact1 = hbs.render_archive(artifact_files1.as_deref())?;
-    let artifact2 = hbs.render_archive(artifact_files2.as_deref())?;
-
-    let diff = diffy::create_file_patch(&artifact1, &artifact2, &diff_from, &diff_to);
-    let diff = diff.to_string();
-    let diff_lines = diff.split('\n').collect::<Vec<_>>();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.vendors.len()));
+    for group in groups.iter_mut().skip(1) {
+        group.differs = true;
+    }
+    groups
+}
 
-    let html = hbs
-        .render(
-            "diff.html.hbs",
-            &json!({
-                "diff": diff_lines,
-                "diff_from": diff_from,
-                "diff_to": diff_to,
-                "options": options,
-                "sorted": options.sorted,
-                "trimmed": options.trim_left || options.trim_right,
-                "trim_left": options.trim_left,
-                "trim_right": options.trim_right,
-            }),
-        )
-        .map_err(Error::from)?;
+async fn compare(
+    hbs: Arc<Handlebars<'_>>,
+    db: Arc<db::Client>,
+    package: String,
+    version: String,
+) -> result::Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let package = url_escape::decode(&package);
+    let version = url_escape::decode(&version);
+
+    let refs = db
+        .get_refs_for_upstream_version(&package, &version)
+        .await?
+        .into_iter()
+        .filter(|resolved| {
+            resolved.r.version == version || upstream_version(&resolved.r.version) == version
+        })
+        .collect::<Vec<_>>();
+    if refs.is_empty() {
+        return Err(reject::not_found());
+    }
+    let groups = group_by_artifact(refs);
+
+    let html = hbs.render(
+        "compare.html.hbs",
+        &json!({
+            "package": package,
+            "version": version,
+            "groups": groups,
+        }),
+    )?;
     Ok(Box::new(warp::reply::html(html)))
 }
 
@@ -592,6 +2419,18 @@ pub async fn rejection(err: warp::Rejection) -> result::Result<impl warp::Reply,
     if err.is_not_found() {
         code = StatusCode::NOT_FOUND;
         message = "404 - file not found\n";
+    } else if let Some(Error::InvalidTimestamp(_)) = err.find::<Error>() {
+        code = StatusCode::BAD_REQUEST;
+        message = "400 - invalid point in time\n";
+    } else if let Some(Error::InvalidPurl(_)) = err.find::<Error>() {
+        code = StatusCode::BAD_REQUEST;
+        message = "400 - invalid package url\n";
+    } else if let Some(Error::MissingQueryParameter(_)) = err.find::<Error>() {
+        code = StatusCode::BAD_REQUEST;
+        message = "400 - missing query parameter\n";
+    } else if let Some(Error::InvalidSubscription(_)) = err.find::<Error>() {
+        code = StatusCode::BAD_REQUEST;
+        message = "400 - invalid subscription\n";
     } else {
         error!("unhandled rejection: {:?}", err);
         code = StatusCode::INTERNAL_SERVER_ERROR;
@@ -605,10 +2444,13 @@ pub async fn run(args: &args::Web) -> Result<()> {
     let hbs = Arc::new(Handlebars::new()?);
     let hbs = warp::any().map(move || hbs.clone());
 
-    let db = db::Client::create().await?;
+    let db = db::Client::create().await?.with_read_replica().await?;
     let db = Arc::new(db);
     let db = warp::any().map(move || db.clone());
 
+    let http = Arc::new(utils::http_client(None)?);
+    let http = warp::any().map(move || http.clone());
+
     let index = warp::get()
         .and(hbs.clone())
         .and(warp::path::end())
@@ -620,16 +2462,68 @@ pub async fn run(args: &args::Web) -> Result<()> {
         .and(warp::path("artifact"))
         .and(warp::path::param())
         .and(warp::path::end())
+        .and(warp::query::<ArtifactQuery>())
         .and_then(artifact)
         .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let artifact_vsa = warp::get()
+        .and(db.clone())
+        .and(warp::path("artifact"))
+        .and(warp::path::param())
+        .and(warp::path("vsa.json"))
+        .and(warp::path::end())
+        .and_then(artifact_vsa)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let artifact_file = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(http.clone())
+        .and(warp::path("artifact"))
+        .and(warp::path::param())
+        .and(warp::path("file"))
+        .and(warp::path::tail())
+        .and(warp::query::<FileQuery>())
+        .and_then(artifact_file)
+        .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let file_digest = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("file"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(file_digest)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let random = warp::get()
+        .and(db.clone())
+        .and(warp::path("random"))
+        .and(warp::path::end())
+        .and_then(random)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
     let sbom = warp::get()
         .and(hbs.clone())
         .and(db.clone())
         .and(warp::path("sbom"))
         .and(warp::path::param())
         .and(warp::path::end())
+        .and(warp::query::<SbomQuery>())
         .and_then(sbom)
         .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let sbom_diff = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("sbom-diff"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(sbom_diff)
+        .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let patch = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("patch"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(patch)
+        .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
     let search = warp::get()
         .and(hbs.clone())
         .and(db.clone())
@@ -638,6 +2532,110 @@ pub async fn run(args: &args::Web) -> Result<()> {
         .and(warp::query::<SearchQuery>())
         .and_then(search)
         .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let watchlist_create = warp::post()
+        .and(db.clone())
+        .and(warp::path("watchlist"))
+        .and(warp::path::end())
+        .and_then(watchlist_create)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let watchlist = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("watchlist"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(watchlist)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let watchlist_edit = warp::post()
+        .and(db.clone())
+        .and(warp::path("watchlist"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(4096))
+        .and(warp::body::form::<WatchlistForm>())
+        .and_then(watchlist_edit)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let feed_artifacts = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("feed"))
+        .and(warp::path("artifacts.xml"))
+        .and(warp::path::end())
+        .and_then(feed_artifacts)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let feed_reports = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("feed"))
+        .and(warp::path("reports.xml"))
+        .and(warp::path::end())
+        .and_then(feed_reports)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let subscribe = warp::get()
+        .and(hbs.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path::end())
+        .and_then(subscribe)
+        .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let subscribe_create = warp::post()
+        .and(db.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(4096))
+        .and(warp::body::form::<SubscribeForm>())
+        .and_then(subscribe_create)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let subscription = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(subscription)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let subscription_edit = warp::post()
+        .and(db.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(4096))
+        .and(warp::body::form::<SubscriptionForm>())
+        .and_then(subscription_edit)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let subscription_confirm_page = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path("confirm"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(subscription_confirm_page)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let subscription_confirm = warp::post()
+        .and(db.clone())
+        .and(warp::path("subscribe"))
+        .and(warp::path("confirm"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(4096))
+        .and_then(subscription_confirm)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let unsubscribe_page = warp::get()
+        .and(hbs.clone())
+        .and(warp::path("unsubscribe"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(unsubscribe_page)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let unsubscribe = warp::post()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("unsubscribe"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(4096))
+        .and_then(unsubscribe)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
     let stats = warp::get()
         .and(hbs.clone())
         .and(db.clone())
@@ -646,6 +2644,49 @@ pub async fn run(args: &args::Web) -> Result<()> {
         .and(warp::query::<StatsQuery>())
         .and_then(stats)
         .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let stats_sboms = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("stats"))
+        .and(warp::path("sboms"))
+        .and(warp::path::end())
+        .and_then(stats_sboms)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let package = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("package"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(package)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let upstream = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("upstream"))
+        .and(warp::path::end())
+        .and(warp::query::<UpstreamQuery>())
+        .and_then(upstream)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let package_patches = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("patches"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(package_patches)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let compare = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("compare"))
+        .and(warp::path::param())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(compare)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
     let diff_redirect = warp::get()
         .and(warp::path("diff"))
         .and(warp::path::end())
@@ -661,6 +2702,66 @@ pub async fn run(args: &args::Web) -> Result<()> {
         .and(warp::path::end())
         .and_then(diff)
         .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
+    let api_artifact = warp::get()
+        .and(db.clone())
+        .and(warp::path!("api" / "v1" / "artifact" / String))
+        .and(warp::query::<ArtifactQuery>())
+        .and_then(api_artifact)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let reports = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("reports"))
+        .and(warp::path::end())
+        .and(warp::query::<ReportsQuery>())
+        .and_then(reports)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let stats_tasks = warp::get()
+        .and(hbs.clone())
+        .and(db.clone())
+        .and(warp::path("stats"))
+        .and(warp::path("tasks"))
+        .and(warp::path::end())
+        .and_then(stats_tasks)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let healthz = warp::get()
+        .and(warp::path("healthz"))
+        .and(warp::path::end())
+        .and_then(healthz)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let readyz = warp::get()
+        .and(db.clone())
+        .and(warp::path("readyz"))
+        .and(warp::path::end())
+        .and_then(readyz)
+        .map(|r| cache_control(r, CACHE_CONTROL_NONE));
+    let status = warp::get()
+        .and(db.clone())
+        .and(warp::path("status"))
+        .and(warp::path::end())
+        .and_then(status)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let api_refs = warp::get()
+        .and(db.clone())
+        .and(warp::path!("api" / "v1" / "refs"))
+        .and(warp::query::<ApiRefsQuery>())
+        .and_then(api_refs)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let api_file_digest = warp::get()
+        .and(db.clone())
+        .and(warp::path!("api" / "v1" / "file" / String))
+        .and_then(api_file_digest)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let api_failed_tasks = warp::get()
+        .and(db.clone())
+        .and(warp::path!("api" / "v1" / "tasks" / "failed"))
+        .and_then(api_failed_tasks)
+        .map(|r| cache_control(r, CACHE_CONTROL_SHORT));
+    let api_sbom = warp::get()
+        .and(db.clone())
+        .and(warp::path!("api" / "v1" / "sbom" / String))
+        .and_then(api_sbom)
+        .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
     let style = warp::get()
         .and(warp::path("assets"))
         .and(warp::path(asset_name_css()))
@@ -668,20 +2769,58 @@ pub async fn run(args: &args::Web) -> Result<()> {
         .and(warp_embed::embed_one(&Assets, "style.css"))
         .map(|r| cache_control(r, CACHE_CONTROL_DEFAULT));
 
-    let routes = warp::any()
+    let routes = warp::header::optional::<String>("accept-encoding")
         .and(
             index
                 .or(artifact)
+                .or(artifact_vsa)
+                .or(artifact_file)
+                .or(file_digest)
+                .or(random)
                 .or(sbom)
+                .or(sbom_diff)
+                .or(patch)
                 .or(search)
+                .or(package)
+                .or(package_patches)
+                .or(upstream)
+                .or(compare)
+                .or(watchlist_create)
+                .or(watchlist)
+                .or(watchlist_edit)
+                .or(feed_artifacts)
+                .or(feed_reports)
+                .or(subscribe)
+                .or(subscribe_create)
+                .or(subscription)
+                .or(subscription_edit)
+                .or(subscription_confirm_page)
+                .or(subscription_confirm)
+                .or(unsubscribe_page)
+                .or(unsubscribe)
                 .or(stats)
+                .or(stats_sboms)
+                .or(stats_tasks)
+                .or(reports)
+                .or(healthz)
+                .or(readyz)
+                .or(status)
+                .or(api_artifact)
+                .or(api_refs)
+                .or(api_sbom)
+                .or(api_file_digest)
+                .or(api_failed_tasks)
                 .or(diff_redirect)
                 .or(diff)
-                .or(style),
+                .or(style)
+                .recover(rejection),
         )
-        .recover(rejection);
+        .and_then(compress_reply);
 
-    warp::serve(routes).run(args.bind_addr).await;
+    // finish the requests that are in flight, but stop accepting new connections
+    let shutdown = utils::shutdown_signal()?;
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(args.bind_addr, shutdown);
+    server.await;
 
     Ok(())
 }
@@ -893,6 +3032,137 @@ sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e  foo-1.0
         );
     }
 
+    #[test]
+    fn test_parse_named_ref() {
+        assert_eq!(
+            parse_named_ref("debian:xz-utils:5.6.1-1"),
+            Some(("debian", "xz-utils", "5.6.1-1"))
+        );
+        assert_eq!(
+            parse_named_ref("debian:git:1:2.45.2-1"),
+            Some(("debian", "git", "1:2.45.2-1"))
+        );
+        assert_eq!(
+            parse_named_ref(
+                "sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e"
+            ),
+            None
+        );
+        assert_eq!(parse_named_ref("fedora::5.6.1-2"), None);
+    }
+
+    #[test]
+    fn test_detect_filename_search() {
+        assert_eq!(
+            detect_filename_search("xz-5.6.1.tar.xz"),
+            Some(("xz", "5.6.1"))
+        );
+        assert_eq!(
+            detect_filename_search("xz-5.6.1.tar.*"),
+            Some(("xz", "5.6.1"))
+        );
+        assert_eq!(detect_filename_search("xz-5.6.1"), Some(("xz", "5.6.1")));
+        assert_eq!(
+            detect_filename_search("rust-openssl-sys-0.9.103.crate"),
+            Some(("rust-openssl-sys", "0.9.103"))
+        );
+        assert_eq!(detect_filename_search("xz"), None);
+        assert_eq!(detect_filename_search("xz-utils"), None);
+        assert_eq!(detect_filename_search("-5.6.1"), None);
+    }
+
+    #[test]
+    fn test_upstream_version() {
+        assert_eq!(upstream_version("5.6.1"), "5.6.1");
+        assert_eq!(upstream_version("5.6.1-1"), "5.6.1");
+        assert_eq!(upstream_version("5.6.1-r0"), "5.6.1");
+        assert_eq!(upstream_version("5.6.1-1.fc40"), "5.6.1");
+        assert_eq!(upstream_version("1:5.6.1-2"), "5.6.1");
+    }
+
+    #[test]
+    fn test_patch_history() {
+        let patch = |version: &str, filename: &str, chksum: &str| db::PatchRef {
+            vendor: "debian".to_string(),
+            package: "xz-utils".to_string(),
+            version: version.to_string(),
+            filename: filename.to_string(),
+            chksum: chksum.to_string(),
+            size: 123,
+        };
+        let history = patch_history(vec![
+            patch(
+                "5.6.2-2",
+                "debian/patches/0001-fix-build.patch",
+                "sha256:bbbb",
+            ),
+            patch("5.6.2-2", "debian/patches/0003-new.patch", "sha256:dddd"),
+            patch(
+                "5.6.2-1",
+                "debian/patches/0001-fix-build.patch",
+                "sha256:aaaa",
+            ),
+            patch("5.6.2-1", "debian/patches/0002-old.patch", "sha256:cccc"),
+        ]);
+        let summary = history
+            .iter()
+            .map(|v| {
+                let statuses = v.patches.iter().map(|p| p.status).collect::<Vec<_>>();
+                (v.version.as_str(), statuses, v.removed.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "5.6.2-2",
+                    vec![Some("changed"), Some("added")],
+                    vec!["debian/patches/0002-old.patch".to_string()]
+                ),
+                ("5.6.2-1", vec![None, None], vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_artifact() {
+        let resolved = |vendor: &str, artifact: &str| db::ResolvedRef {
+            r: db::Ref {
+                chksum: artifact.to_string(),
+                vendor: vendor.to_string(),
+                package: "xz".to_string(),
+                version: "5.6.1-1".to_string(),
+                filename: None,
+            },
+            artifact: artifact.to_string(),
+        };
+        let groups = group_by_artifact(vec![
+            resolved("alpine", "sha256:aaaa"),
+            resolved("archlinux", "sha256:bbbb"),
+            resolved("debian", "sha256:aaaa"),
+            resolved("fedora", "sha256:aaaa"),
+        ]);
+        let summary = groups
+            .iter()
+            .map(|g| (g.artifact.as_str(), g.vendors.clone(), g.differs))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "sha256:aaaa",
+                    vec![
+                        "alpine".to_string(),
+                        "debian".to_string(),
+                        "fedora".to_string()
+                    ],
+                    false
+                ),
+                ("sha256:bbbb", vec!["archlinux".to_string()], true),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_diff_paths() {
         let diff = "diff".parse::<Diff>().unwrap();
@@ -986,6 +3256,23 @@ sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e  foo-1.0
             search.as_deref(),
             Some("git:7747534db4576db43eced4356859ef400351ca28")
         );
+
+        // test go.sum
+        let search = detect_hash_search("h1:r+8e+loiHxRqhXVl6ML1nO3l1+oFoWbnlu2Ehimmi34=");
+        assert_eq!(
+            search.as_deref(),
+            Some("h1:r+8e+loiHxRqhXVl6ML1nO3l1+oFoWbnlu2Ehimmi34=")
+        );
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items = (0..5).collect::<Vec<_>>();
+        assert_eq!(paginate(items.clone(), 0, 2), (vec![0, 1], Some(2)));
+        assert_eq!(paginate(items.clone(), 2, 2), (vec![2, 3], Some(4)));
+        assert_eq!(paginate(items.clone(), 4, 2), (vec![4], None));
+        assert_eq!(paginate(items.clone(), 3, 2), (vec![3, 4], None));
+        assert_eq!(paginate(items, 10, 2), (vec![], None));
     }
 
     #[test]
@@ -1028,9 +3315,75 @@ sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e  foo-1.0
         );
     }
 
+    #[test]
+    fn test_parse_file_digest() {
+        let digest = "sha256:56d9fc4585da4f39bbc5c8ec953fb7962188fa5ed70b2dd5a19dc82df997ba5e";
+        assert_eq!(parse_file_digest(digest).as_deref(), Some(digest));
+        assert_eq!(
+            parse_file_digest("56D9FC4585DA4F39BBC5C8EC953FB7962188FA5ED70B2DD5A19DC82DF997BA5E")
+                .as_deref(),
+            Some(digest)
+        );
+        assert_eq!(parse_file_digest("56d9fc4585da4f39"), None);
+        assert_eq!(parse_file_digest("md5:56d9fc4585da4f39"), None);
+    }
+
+    #[test]
+    fn test_parse_point_in_time() {
+        let at = parse_point_in_time("2024-03-29").unwrap();
+        assert_eq!(at.to_rfc3339(), "2024-03-29T23:59:59+00:00");
+        let at = parse_point_in_time("2024-03-29T16:00:00+02:00").unwrap();
+        assert_eq!(at.to_rfc3339(), "2024-03-29T14:00:00+00:00");
+        assert!(parse_point_in_time("yesterday").is_err());
+    }
+
     #[test]
     fn test_url_encode_artifact() {
         let encoded = url_encode_artifact("sha256:abc/$<>&#xyz");
         assert_eq!(encoded, "sha256:abc%2F%24%3C%3E%26%23xyz");
     }
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip("gzip, deflate, br"));
+        assert!(accepts_gzip("br;q=1.0, GZIP;q=0.8"));
+        assert!(accepts_gzip("*"));
+        assert!(!accepts_gzip("gzip;q=0, br"));
+        assert!(!accepts_gzip("identity"));
+        assert!(!accepts_gzip("x-gzip-foo"));
+    }
+
+    #[test]
+    fn test_listing_pages() {
+        assert_eq!(listing_pages(10, 1, 10, None), None);
+        assert_eq!(
+            listing_pages(25, 2, 10, Some("2024-01-01")),
+            Some(json!({
+                "page": 2,
+                "pages": 3,
+                "first": 11,
+                "last": 20,
+                "prev": "?page=1&limit=10&at=2024-01-01",
+                "next": "?page=3&limit=10&at=2024-01-01",
+            }))
+        );
+        assert_eq!(
+            listing_pages(25, 3, 10, None),
+            Some(json!({
+                "page": 3,
+                "pages": 3,
+                "first": 21,
+                "last": 25,
+                "prev": "?page=2&limit=10",
+                "next": null,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejection_missing_query_parameter() {
+        let err = reject::custom(Error::MissingQueryParameter("package or purl"));
+        let resp = warp::Reply::into_response(rejection(err).await.unwrap());
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,11 +1,37 @@
 use crate::args;
+use crate::compression;
 use crate::db::{self, Task, TaskData};
 use crate::errors::*;
 use crate::ingest;
+use crate::notify;
+use crate::quarantine;
+use crate::reports;
 use crate::sbom;
+use crate::signature;
+use crate::sync;
+use crate::tasks;
+use crate::upstream;
 use crate::utils;
+use std::collections::HashMap;
+use std::env;
 use std::sync::Arc;
-use tokio::time::{self, Duration};
+use tokio::io;
+use tokio::sync::Mutex;
+use tokio::task::{self, JoinError, JoinHandle, JoinSet};
+use tokio::time::{self, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How often the worker reports that it's still alive
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often release tarballs are compared with new VCS snapshots
+const COMPARE_VCS_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const COMPARE_VCS_LIMIT: i64 = 250;
+/// How often upstream urls are checked for vanished or replaced artifacts
+const VERIFY_UPSTREAM_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const VERIFY_UPSTREAM_LIMIT: i64 = 50;
+/// How often superseded tasks are dropped from the queue
+const PRUNE_TASKS_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 fn normalize_archlinux_gitlab_names(package: &str) -> String {
     if package == "tree" {
@@ -30,9 +56,38 @@ pub struct Worker {
     db: Arc<db::Client>,
     http: utils::HttpClient,
     git_tmp: String,
+    git_clone: args::GitClone,
+    /// The git workdir is shared, only one snapshot can be taken at a time
+    git_lock: Mutex<()>,
+    quarantine_urls: Vec<String>,
+    pgp_keyring: Option<String>,
 }
 
 impl Worker {
+    /// Run a task and record the outcome in the database
+    #[tracing::instrument(skip_all, fields(id = task.id, key = %task.key))]
+    pub async fn process(&self, task: &Task) -> Result<()> {
+        info!("task={task:?}");
+        if let Err(err) = self.do_task(task).await {
+            error!("Failed to process task: {err:#}");
+            self.db
+                .bump_task_error_counter(task, &format!("{err:#}"))
+                .await?;
+        } else {
+            self.db.delete_task(task).await?;
+        }
+        Ok(())
+    }
+
+    /// Artifacts above the size limit are recorded as skipped, retrying wouldn't help
+    async fn skip_oversized(&self, url: &str, size: Option<u64>, limit: u64) -> Result<()> {
+        warn!("Skipping artifact above size limit of {limit} bytes: url={url:?} size={size:?}");
+        let reason = format!("exceeds size limit of {limit} bytes");
+        self.db
+            .insert_skipped_artifact(url, &reason, size.and_then(|size| size.try_into().ok()))
+            .await
+    }
+
     pub async fn do_task(&self, task: &Task) -> Result<()> {
         let data = task.data()?;
 
@@ -62,9 +117,15 @@ impl Worker {
                 }
 
                 info!("Fetching tar: {url:?}");
-                let reader = self.http.fetch(&url).await?;
+                let (reader, guard) = match self.http.fetch_artifact(&url).await {
+                    Ok(download) => download,
+                    Err(Error::DownloadTooLarge { size, limit }) => {
+                        return self.skip_oversized(&url, Some(size), limit).await;
+                    }
+                    Err(err) => return Err(err),
+                };
+                let mut reader = io::BufReader::new(reader);
 
-                // TODO: do this stuff on the fly
                 let compression = if let Some(compression) = &compression {
                     Some(compression.as_str())
                 } else if url.ends_with(".gz") || url.ends_with(".tgz") {
@@ -73,20 +134,45 @@ impl Worker {
                     Some("xz")
                 } else if url.ends_with(".bz2") {
                     Some("bz2")
+                } else if url.ends_with(".zst") || url.ends_with(".tzst") {
+                    Some("zst")
                 } else {
-                    None
+                    // no known extension, e.g. `/download?id=123`
+                    compression::sniff(&mut reader).await?
                 };
 
                 // If there's an "on success" hook, insert it
-                let summary = ingest::tar::stream_data(Some(&self.db), reader, compression).await?;
-                if let Some(pkg) = success_ref {
-                    let r = db::Ref {
-                        chksum: summary.outer_digests.sha256,
-                        vendor: pkg.vendor,
-                        package: pkg.package,
-                        version: pkg.version,
-                        filename: Some(url),
-                    };
+                let summary = ingest::tar::stream_data(Some(&self.db), reader, compression).await;
+                if let Some(limit) = guard.exceeded() {
+                    return self.skip_oversized(&url, None, limit).await;
+                }
+                let summary = summary?;
+                let success_ref = success_ref.map(|pkg| db::Ref {
+                    chksum: summary.outer_digests.sha256.clone(),
+                    vendor: pkg.vendor,
+                    package: pkg.package,
+                    version: pkg.version,
+                    filename: Some(url.clone()),
+                });
+
+                if quarantine::is_untrusted(&self.quarantine_urls, &url) {
+                    let chksum = &summary.inner_digests.sha256;
+                    let declared = !self.db.get_all_refs_for(chksum).await?.is_empty();
+                    if let Some(reason) = quarantine::check(&summary.files, declared) {
+                        warn!("Holding back artifact: chksum={chksum:?} reason={reason:?}");
+                        self.db
+                            .insert_quarantine(&db::NewQuarantine {
+                                chksum: chksum.clone(),
+                                url,
+                                success_ref,
+                                reason,
+                            })
+                            .await?;
+                        return Ok(());
+                    }
+                }
+
+                if let Some(r) = success_ref {
                     info!("insert: {r:?}");
                     self.db.insert_ref(&r).await?;
                 }
@@ -120,16 +206,26 @@ impl Worker {
                 url,
             } => {
                 info!("Downloading source rpm: {url:?}");
-                let reader = self.http.fetch(&url).await?;
+                let (reader, guard) = match self.http.fetch_artifact(&url).await {
+                    Ok(download) => download,
+                    Err(Error::DownloadTooLarge { size, limit }) => {
+                        return self.skip_oversized(&url, Some(size), limit).await;
+                    }
+                    Err(err) => return Err(err),
+                };
 
-                ingest::rpm::stream_data(
+                let result = ingest::rpm::stream_data(
                     self.db.clone(),
                     reader,
                     vendor.to_string(),
                     package.to_string(),
                     version.to_string(),
                 )
-                .await?;
+                .await;
+                if let Some(limit) = guard.exceeded() {
+                    return self.skip_oversized(&url, None, limit).await;
+                }
+                result?;
 
                 self.db
                     .insert_package(&db::Package {
@@ -179,9 +275,20 @@ impl Worker {
                     })
                     .await?;
             }
-            TaskData::GitSnapshot { url } => {
-                let git = url.parse::<ingest::git::GitUrl>()?;
-                ingest::git::take_snapshot(&self.db, &git, &self.git_tmp).await?;
+            TaskData::GitSnapshot { url, tag, commit } => {
+                let git = url.parse::<ingest::git::GitUrl>()?.pinned(tag, commit);
+                let _lock = self.git_lock.lock().await;
+                ingest::git::take_snapshot(&self.db, &git, &self.git_tmp, &self.git_clone).await?;
+            }
+            TaskData::HgSnapshot { url, rev } => {
+                let hg = url.parse::<ingest::hg::HgUrl>()?.pinned(rev);
+                let _lock = self.git_lock.lock().await;
+                ingest::hg::take_snapshot(&self.db, &hg, &self.git_tmp).await?;
+            }
+            TaskData::FossilSnapshot { url, rev } => {
+                let fossil = url.parse::<ingest::fossil::FossilUrl>()?.pinned(rev);
+                let _lock = self.git_lock.lock().await;
+                ingest::fossil::take_snapshot(&self.db, &fossil, &self.git_tmp).await?;
             }
             TaskData::IndexSbom { strain, chksum } => {
                 // Support old sbom task format
@@ -197,6 +304,79 @@ impl Worker {
                     sbom::index(&self.db, &sbom).await?;
                 }
             }
+            TaskData::VerifySignature { url, signature_url } => {
+                let tmp = env::temp_dir();
+                let verifier = signature::Verifier {
+                    db: &self.db,
+                    http: &self.http,
+                    tmp: &tmp,
+                    pgp_keyring: self.pgp_keyring.as_deref(),
+                };
+                verifier.verify(&url, &signature_url).await?;
+            }
+            TaskData::DebianSource {
+                vendor,
+                package,
+                version,
+                url,
+            } => {
+                info!("Importing Debian source package: {url:?}");
+                ingest::dsc::ingest(&self.db, Some(&self.http), &vendor, &url).await?;
+
+                self.db
+                    .insert_package(&db::Package {
+                        vendor,
+                        package,
+                        version,
+                    })
+                    .await?;
+            }
+            TaskData::ObsPackage {
+                vendor,
+                url,
+                project,
+                package,
+                spec,
+                srcmd5,
+            } => {
+                let project_url = sync::obs::project_url(&url, &project);
+                sync::obs::import_package(
+                    &self.db,
+                    &self.http,
+                    &project_url,
+                    &vendor,
+                    &package,
+                    &spec,
+                    &srcmd5,
+                )
+                .await?;
+
+                self.db
+                    .insert_package(&db::Package {
+                        vendor,
+                        package,
+                        version: srcmd5,
+                    })
+                    .await?;
+            }
+            TaskData::GoModule { path, version } => {
+                let url = sync::goproxy::module_zip_url(sync::goproxy::PROXY_URL, &path, &version);
+                info!("Fetching module zip: {url:?}");
+                let (reader, guard) = match self.http.fetch_artifact(&url).await {
+                    Ok(download) => download,
+                    Err(Error::DownloadTooLarge { size, limit }) => {
+                        return self.skip_oversized(&url, Some(size), limit).await;
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                let result =
+                    sync::goproxy::import_module(&self.db, reader, &url, &path, &version).await;
+                if let Some(limit) = guard.exceeded() {
+                    return self.skip_oversized(&url, None, limit).await;
+                }
+                result?;
+            }
             TaskData::VoidLinuxGit {
                 vendor,
                 srcpkg,
@@ -229,31 +409,185 @@ impl Worker {
 
 pub async fn run(args: &args::Worker) -> Result<()> {
     let db = db::Client::create().await?;
-    let http = utils::http_client(args.socks5.as_ref())?;
+    let mut http = utils::http_client(args.socks5.as_ref())?;
+    http.rate_limit(Duration::from_millis(args.host_delay));
 
-    let worker = Worker {
+    let worker = Arc::new(Worker {
         db: Arc::new(db),
         http,
         git_tmp: args.git_tmp.to_string(),
+        git_clone: args.git_clone.clone(),
+        git_lock: Mutex::new(()),
+        quarantine_urls: args.quarantine_urls.clone(),
+        pgp_keyring: args.pgp_keyring.clone(),
+    });
+    let concurrency = args.concurrency.max(1);
+    let filter = db::TaskFilter {
+        only: args.only.clone(),
+        exclude: args.exclude.clone(),
     };
 
-    loop {
-        if let Some(task) = worker.db.get_random_task().await? {
-            info!("task={task:?}");
-            if let Err(err) = worker.do_task(&task).await {
-                error!("Failed to process task: {err:#}");
-                worker
-                    .db
-                    .bump_task_error_counter(&task, &format!("{err:#}"))
-                    .await?;
-            } else {
-                worker.db.delete_task(&task).await?;
+    // stop picking up new tasks once we've been asked to shut down
+    let shutdown = CancellationToken::new();
+    let signal = utils::shutdown_signal()?;
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            signal.await;
+            shutdown.cancel();
+        }
+    });
+
+    let mut running = JoinSet::new();
+    // maps the tokio task id to the id of the queued task, so panics can be attributed
+    let mut running_ids = HashMap::new();
+    let mut last_heartbeat = None;
+    let mut last_notify = None;
+    let mut last_compare_vcs = None;
+    let mut last_verify_upstream = None;
+    let mut verify_upstream = None;
+    let mut last_prune_tasks = None;
+    while !shutdown.is_cancelled() {
+        if last_heartbeat.is_none_or(|at: Instant| at.elapsed() >= HEARTBEAT_INTERVAL) {
+            worker.db.heartbeat_worker(&args.name).await?;
+            last_heartbeat = Some(Instant::now());
+        }
+
+        if args.notify && last_notify.is_none_or(|at: Instant| at.elapsed() >= NOTIFY_INTERVAL) {
+            if let Err(err) = notify::run_once(&worker.db).await {
+                error!("Failed to deliver notifications: {err:#}");
+            }
+            last_notify = Some(Instant::now());
+        }
+
+        if args.compare_vcs
+            && last_compare_vcs.is_none_or(|at: Instant| at.elapsed() >= COMPARE_VCS_INTERVAL)
+        {
+            match reports::compare_vcs(&worker.db, None, true, COMPARE_VCS_LIMIT).await {
+                Ok(n) => info!("Compared {n} release tarballs with their VCS snapshot"),
+                Err(err) => error!("Failed to compare release tarballs: {err:#}"),
+            }
+            last_compare_vcs = Some(Instant::now());
+        }
+
+        // this downloads full artifacts, run it next to the tasks instead of blocking the loop
+        if args.verify_upstream
+            && verify_upstream
+                .as_ref()
+                .is_none_or(|handle: &JoinHandle<()>| handle.is_finished())
+            && last_verify_upstream
+                .is_none_or(|at: Instant| at.elapsed() >= VERIFY_UPSTREAM_INTERVAL)
+        {
+            let worker = worker.clone();
+            let shutdown = shutdown.clone();
+            verify_upstream = Some(tokio::spawn(async move {
+                match upstream::verify_once(
+                    &worker.db,
+                    &worker.http,
+                    VERIFY_UPSTREAM_LIMIT,
+                    &shutdown,
+                )
+                .await
+                {
+                    Ok(n) => info!("Verified {n} upstream urls"),
+                    Err(err) => error!("Failed to verify upstream urls: {err:#}"),
+                }
+            }));
+            last_verify_upstream = Some(Instant::now());
+        }
+
+        if args.prune_tasks
+            && last_prune_tasks.is_none_or(|at: Instant| at.elapsed() >= PRUNE_TASKS_INTERVAL)
+        {
+            match tasks::prune(&worker.db, None).await {
+                Ok(n) => info!("Deleted {n} superseded tasks"),
+                Err(err) => error!("Failed to prune tasks: {err:#}"),
+            }
+            last_prune_tasks = Some(Instant::now());
+        }
+
+        while let Some(joined) = running.try_join_next_with_id() {
+            task_finished(&mut running_ids, joined);
+        }
+
+        if running.len() >= concurrency {
+            tokio::select! {
+                joined = running.join_next_with_id() => {
+                    if let Some(joined) = joined {
+                        task_finished(&mut running_ids, joined);
+                    }
+                }
+                _ = shutdown.cancelled() => (),
+            }
+            continue;
+        }
+
+        let running_list = running_ids.values().copied().collect::<Vec<_>>();
+        if let Some(task) = worker.db.get_random_task(&filter, &running_list).await? {
+            let id = task.id;
+            let worker = worker.clone();
+            let handle = running.spawn(async move { worker.process(&task).await });
+            running_ids.insert(handle.id(), id);
+        } else if running.is_empty() {
+            tokio::select! {
+                _ = time::sleep(Duration::from_secs(60)) => (),
+                _ = shutdown.cancelled() => (),
             }
         } else {
-            time::sleep(Duration::from_secs(60)).await;
+            tokio::select! {
+                joined = time::timeout(Duration::from_secs(60), running.join_next_with_id()) => {
+                    if let Ok(Some(joined)) = joined {
+                        task_finished(&mut running_ids, joined);
+                    }
+                }
+                _ = shutdown.cancelled() => (),
+            }
         }
         time::sleep(Duration::from_millis(50)).await;
     }
+
+    // tasks are only deleted from the queue once they're done, aborted ones are retried later
+    if let Some(handle) = verify_upstream {
+        // an interrupted check isn't recorded, the url is verified again after the restart
+        handle.abort();
+    }
+    info!("Waiting for {} running tasks to finish", running.len());
+    let deadline = Duration::from_secs(args.shutdown_timeout);
+    let drain = async {
+        while let Some(joined) = running.join_next_with_id().await {
+            task_finished(&mut running_ids, joined);
+        }
+    };
+    match time::timeout(deadline, drain).await {
+        Ok(()) => Ok(()),
+        Err(_) => {
+            warn!(
+                "Shutdown timeout reached, aborting {} running tasks",
+                running.len()
+            );
+            running.abort_all();
+            Ok(())
+        }
+    }
+}
+
+/// Forget about a finished task, a failed or panicked task must not take down the other ones
+fn task_finished(
+    running_ids: &mut HashMap<task::Id, i64>,
+    joined: std::result::Result<(task::Id, Result<()>), JoinError>,
+) {
+    match joined {
+        Ok((id, result)) => {
+            let task = running_ids.remove(&id);
+            if let Err(err) = result {
+                error!("Failed to finish task: task={task:?} err={err:#}");
+            }
+        }
+        Err(err) => {
+            let task = running_ids.remove(&err.id());
+            error!("Task crashed: task={task:?} err={err}");
+        }
+    }
 }
 
 #[cfg(test)]
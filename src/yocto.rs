@@ -68,6 +68,13 @@ impl Value {
         };
     }
 
+    pub fn insert_str(&mut self, idx: usize, other: &str) {
+        match self {
+            Self::Valid(s) => s.insert_str(idx, other),
+            Self::Poisoned(s) => s.insert_str(idx, other),
+        }
+    }
+
     pub fn to_string(&self) -> Result<String> {
         match self {
             Self::Valid(s) => Ok(s.to_string()),
@@ -104,6 +111,37 @@ impl BitBake {
         self.vars.insert(key.into(), value);
     }
 
+    fn append(&mut self, key: &str, value: &Value) {
+        self.vars.entry(key.into()).or_default().push_value(value);
+    }
+
+    fn prepend(&mut self, key: &str, mut value: Value) {
+        if let Some(old) = self.vars.get(key) {
+            value.push_value(old);
+        }
+        self.assign_value(key, value);
+    }
+
+    /// Apply `:append` and `:prepend` onto their variables, overrides with a condition are kept as-is
+    fn apply_overrides(&mut self) {
+        let keys = self
+            .vars
+            .keys()
+            .filter(|key| key.ends_with(":append") || key.ends_with(":prepend"))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            let Some(value) = self.vars.remove(&key) else {
+                continue;
+            };
+            if let Some(var) = key.strip_suffix(":append") {
+                self.append(var, &value);
+            } else if let Some(var) = key.strip_suffix(":prepend") {
+                self.prepend(var, value);
+            }
+        }
+    }
+
     fn get_var<I: Into<String>>(&self, key: I) -> Result<Option<String>> {
         let key = key.into();
         let Some(value) = self.vars.get(&key) else {
@@ -112,6 +150,11 @@ impl BitBake {
         Ok(Some(value.to_string()?))
     }
 
+    /// The homepage of the upstream project, if it could be fully resolved
+    pub fn homepage(&self) -> Option<String> {
+        self.get_var("HOMEPAGE").ok().flatten()
+    }
+
     pub fn artifacts(&self) -> Result<Vec<Artifact>> {
         let mut out = Vec::new();
 
@@ -119,10 +162,16 @@ impl BitBake {
             return Ok(out);
         };
 
-        for line in value.lines() {
-            let mut iter = line.trim().split(';');
+        for uri in value.split_whitespace() {
+            let mut iter = uri.split(';');
             let Some(src) = iter.next() else { continue };
-            let name = iter.flat_map(|x| x.strip_prefix("name=")).next();
+            let params = iter.collect::<Vec<_>>();
+            let param = |key: &str| {
+                params
+                    .iter()
+                    .find_map(|x| x.strip_prefix(key)?.strip_prefix('='))
+            };
+            let name = param("name");
 
             match src.split_once("://") {
                 Some(("http" | "https", _)) => {
@@ -151,6 +200,28 @@ impl BitBake {
                         sha256: None,
                     });
                 }
+                Some(("hg", tail)) => {
+                    let commit = match param("rev") {
+                        Some(rev) => Some(rev.to_string()),
+                        None => self.get_var(if let Some(name) = name {
+                            format!("SRCREV_{name}")
+                        } else {
+                            "SRCREV".to_string()
+                        })?,
+                    };
+                    let protocol = param("protocol").unwrap_or("http");
+                    let tail = tail.trim_end_matches('/');
+                    let src = match param("module") {
+                        Some(module) => format!("hg+{protocol}://{tail}/{module}"),
+                        None => format!("hg+{protocol}://{tail}"),
+                    };
+
+                    out.push(Artifact {
+                        src,
+                        commit,
+                        sha256: None,
+                    });
+                }
                 _ => (),
             }
         }
@@ -223,74 +294,165 @@ impl BitBake {
     }
 }
 
-pub fn parse(script: &str, package: Option<String>, version: Option<String>) -> Result<BitBake> {
-    let mut bb = BitBake::default();
-    bb.populate();
-    if let Some(package) = package {
-        bb.assign("PN", package.clone());
-
-        let mut bpn = package.as_str();
-        for suffix in [
-            "-native",
-            "-cross",
-            "-initial",
-            "-intermediate",
-            "-crosssdk",
-            "-cross-canadian",
-        ] {
-            bpn = bpn.strip_suffix(suffix).unwrap_or(bpn);
-        }
+/// Looks up a file referenced by `require` or `include`, given the path of the file that
+/// contains the directive. Returns the path it was found at and its content.
+pub type Resolver<'a> = &'a dyn Fn(&str, &str) -> Option<(String, String)>;
 
-        bb.assign("BPN", bpn);
-        bb.assign(
-            "GITHUB_BASE_URI",
-            format!("https://github.com/{bpn}/{bpn}/releases"),
-        );
+/// Nested includes beyond this depth are assumed to be a loop
+const MAX_INCLUDE_DEPTH: usize = 16;
 
-        if let Some(version) = &version {
-            bb.assign("BP", format!("{bpn}-{version}"));
+impl BitBake {
+    fn init(package: Option<String>, version: Option<String>) -> Self {
+        let mut bb = BitBake::default();
+        bb.populate();
+        if let Some(package) = package {
+            bb.assign("PN", package.clone());
+
+            let mut bpn = package.as_str();
+            for suffix in [
+                "-native",
+                "-cross",
+                "-initial",
+                "-intermediate",
+                "-crosssdk",
+                "-cross-canadian",
+            ] {
+                bpn = bpn.strip_suffix(suffix).unwrap_or(bpn);
+            }
+
+            bb.assign("BPN", bpn);
+            bb.assign(
+                "GITHUB_BASE_URI",
+                format!("https://github.com/{bpn}/{bpn}/releases"),
+            );
+
+            if let Some(version) = &version {
+                bb.assign("BP", format!("{bpn}-{version}"));
+            }
+        }
+        if let Some(version) = version {
+            bb.assign("PV", version);
         }
+        bb
     }
-    if let Some(version) = version {
-        bb.assign("PV", version);
+
+    fn include(
+        &mut self,
+        from: &str,
+        path: &Value,
+        required: bool,
+        resolve: Resolver,
+        depth: usize,
+    ) -> Result<()> {
+        let path = match path.to_string() {
+            Ok(path) => path,
+            Err(err) if required => return Err(err),
+            Err(_) => return Ok(()),
+        };
+        let Some((found, script)) = resolve(from, &path) else {
+            if required {
+                // the file may be provided by a layer we don't have
+                warn!("Failed to find required file {path:?} (from {from:?})");
+            }
+            return Ok(());
+        };
+        if depth >= MAX_INCLUDE_DEPTH {
+            return Err(Error::YoctoIncludeDepth(found));
+        }
+        debug!("Including {found:?} (from {from:?})");
+        self.parse_script(&found, &script, Some(resolve), depth + 1)
     }
 
-    let mut in_function = false;
-    let mut lines = script.lines();
-    while let Some(line) = bb.tokenize(&mut lines) {
-        let mut iter = line.into_iter().peekable();
-        iter.next_if(|x| x.maybe_poisoned() == "export");
+    fn parse_script(
+        &mut self,
+        from: &str,
+        script: &str,
+        resolve: Option<Resolver>,
+        depth: usize,
+    ) -> Result<()> {
+        let mut in_function = false;
+        let mut lines = script.lines();
+        while let Some(line) = self.tokenize(&mut lines) {
+            let mut iter = line.into_iter().peekable();
+            iter.next_if(|x| x.maybe_poisoned() == "export");
+
+            let value = iter.next();
+            let var = match value.as_ref().map(|x| x.maybe_poisoned()) {
+                Some(var) if var.starts_with('#') => continue,
+                Some(var) if var.ends_with("()") => {
+                    in_function = true;
+                    continue;
+                }
+                Some("}") if in_function => {
+                    in_function = false;
+                    continue;
+                }
+                Some(directive @ ("require" | "include")) if !in_function => {
+                    let (Some(resolve), Some(path)) = (resolve, iter.next()) else {
+                        continue;
+                    };
+                    let required = directive == "require";
+                    self.include(from, &path, required, resolve, depth)?;
+                    continue;
+                }
+                Some(var) if !in_function => var,
+                _ => continue,
+            };
+
+            let Some(op) = iter.next() else { continue };
+            let Some(value) = iter.next() else { continue };
 
-        let value = iter.next();
-        let var = match value.as_ref().map(|x| x.maybe_poisoned()) {
-            Some(var) if var.starts_with('#') => continue,
-            Some(var) if var.ends_with("()") => {
-                in_function = true;
+            // overrides are collected and applied once the recipe is fully parsed
+            if var.ends_with(":append") {
+                self.append(var, &value);
                 continue;
-            }
-            Some("}") if in_function => {
-                in_function = false;
+            } else if var.ends_with(":prepend") {
+                self.prepend(var, value);
                 continue;
             }
-            Some(var) if !in_function => var,
-            _ => continue,
-        };
-
-        let Some(op) = iter.next() else { continue };
-        let Some(value) = iter.next() else { continue };
 
-        match op.maybe_poisoned() {
-            "=" => bb.assign_value(var, value),
-            "?=" => bb.assign_value(var, value),
-            ".=" => {
-                bb.vars.entry(var.into()).or_default().push_value(&value);
+            match op.maybe_poisoned() {
+                "=" => self.assign_value(var, value),
+                "?=" => self.assign_value(var, value),
+                ".=" => self.append(var, &value),
+                "=." => self.prepend(var, value),
+                "+=" => {
+                    let mut value = value;
+                    value.insert_str(0, " ");
+                    self.append(var, &value);
+                }
+                "=+" => {
+                    let mut value = value;
+                    value.push(' ');
+                    self.prepend(var, value);
+                }
+                op => debug!("Unsupported bitbake operator for {var:?}: {op:?}"),
             }
-            // we don't need this operation
-            // "+=" => (),
-            _ => continue,
         }
+        Ok(())
     }
+}
 
+/// Parse a recipe on its own, `require` and `include` are ignored
+pub fn parse(script: &str, package: Option<String>, version: Option<String>) -> Result<BitBake> {
+    let mut bb = BitBake::init(package, version);
+    bb.parse_script("", script, None, 0)?;
+    bb.apply_overrides();
+    bb.depopulate();
+    Ok(bb)
+}
+
+/// Parse a recipe located at `path` within a layer, merging the variables of included files
+pub fn parse_with_includes(
+    path: &str,
+    script: &str,
+    package: Option<String>,
+    version: Option<String>,
+    resolve: Resolver,
+) -> Result<BitBake> {
+    let mut bb = BitBake::init(package, version);
+    bb.parse_script(path, script, Some(resolve), 0)?;
+    bb.apply_overrides();
     bb.depopulate();
     Ok(bb)
 }
@@ -394,6 +556,7 @@ KERNEL_DEVICETREE:qemuarmv5 = "arm/versatile-pb.dtb"
                     "BP".to_string() => Value::Valid("linux-yocto-tiny-6.6".to_string()),
                     "BPN".to_string() => Value::Valid("linux-yocto-tiny".to_string()),
                     "COMPATIBLE_MACHINE".to_string() => Value::Valid("^(qemux86|qemux86-64|qemuarm64|qemuarm|qemuarmv5)$".to_string()),
+                    "DEPENDS".to_string() => Value::Poisoned(" ${@bb.utils.contains('ARCH', 'x86', 'elfutils-native', '', d)} openssl-native util-linux-native".to_string()),
                     "KBRANCH".to_string() => Value::Valid("v6.6/standard/tiny/base".to_string()),
                     "KCONFIG_MODE".to_string() => Value::Valid("--allnoconfig".to_string()),
                     "KCONF_BSP_AUDIT_LEVEL".to_string() => Value::Valid("2".to_string()),
@@ -430,6 +593,21 @@ KERNEL_DEVICETREE:qemuarmv5 = "arm/versatile-pb.dtb"
         );
     }
 
+    #[test]
+    fn test_hg_src_uri() {
+        let data = r#"SRC_URI = "hg://hg.example.com/repos;module=libfoo;protocol=https;rev=0123456789abcdef0123456789abcdef01234567"
+"#;
+        let bb = parse(data, Some("libfoo".to_string()), Some("1.0".to_string())).unwrap();
+        assert_eq!(
+            bb.artifacts().unwrap(),
+            &[Artifact {
+                src: "hg+https://hg.example.com/repos/libfoo".to_string(),
+                commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+                sha256: None
+            }]
+        );
+    }
+
     #[test]
     fn test_efivar() {
         let data = r#"SUMMARY = "Tools to manipulate UEFI variables"
@@ -659,4 +837,105 @@ do_install() {
             ]
         );
     }
+
+    #[test]
+    fn test_libmpc_require() {
+        let recipe = r#"require libmpc.inc
+include ${BPN}-missing.inc
+
+DEPENDS = "gmp mpfr"
+SRC_URI[sha256sum] = "ab642492f5cf882b74aa0cb730cd410a81edcdbec895183ce930e706c1c759b8"
+"#;
+        let resolve = |from: &str, path: &str| {
+            assert_eq!(from, "meta/recipes-support/libmpc/libmpc_1.3.1.bb");
+            (path == "libmpc.inc").then(|| {
+                (
+                    "meta/recipes-support/libmpc/libmpc.inc".to_string(),
+                    r#"SUMMARY = "C library for complex number arithmetic"
+SRC_URI = "${GNU_MIRROR}/mpc/mpc-${PV}.tar.gz"
+S = "${WORKDIR}/mpc-${PV}"
+"#
+                    .to_string(),
+                )
+            })
+        };
+        let bb = parse_with_includes(
+            "meta/recipes-support/libmpc/libmpc_1.3.1.bb",
+            recipe,
+            Some("libmpc".to_string()),
+            Some("1.3.1".to_string()),
+            &resolve,
+        )
+        .unwrap();
+        assert_eq!(
+            bb.artifacts().unwrap(),
+            &[Artifact {
+                src: "https://ftp.gnu.org/gnu/mpc/mpc-1.3.1.tar.gz".to_string(),
+                commit: None,
+                sha256: Some(
+                    "ab642492f5cf882b74aa0cb730cd410a81edcdbec895183ce930e706c1c759b8".to_string()
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_include_loop() {
+        let resolve =
+            |_: &str, path: &str| Some((path.to_string(), "require loop.inc".to_string()));
+        let err = parse_with_includes("foo_1.0.bb", "require loop.inc", None, None, &resolve);
+        assert!(matches!(err, Err(Error::YoctoIncludeDepth(_))));
+    }
+
+    #[test]
+    fn test_append_prepend() {
+        let data = r#"SRC_URI = "https://example.com/foo-${PV}.tar.gz"
+SRC_URI:append = " git://example.com/bar.git;protocol=https;name=bar"
+SRC_URI += "file://0001-fix-build.patch"
+SRC_URI:append:class-native = " file://native.patch"
+SRCREV_bar = "0123456789abcdef0123456789abcdef01234567"
+SRC_URI[sha256sum] = "0000000000000000000000000000000000000000000000000000000000000000"
+
+EXTRA_OECONF = "--enable-foo"
+EXTRA_OECONF =+ "--disable-bar"
+EXTRA_OECONF:prepend = "--a "
+EXTRA_OECONF:prepend = "--b "
+EXTRA_OECONF =. "--c "
+EXTRA_OECONF ??= "--ignored"
+"#;
+        let bb = parse(data, Some("foo".to_string()), Some("1.0".to_string())).unwrap();
+        assert_eq!(
+            bb.get_var("SRC_URI").unwrap().as_deref(),
+            Some("https://example.com/foo-1.0.tar.gz file://0001-fix-build.patch git://example.com/bar.git;protocol=https;name=bar")
+        );
+        assert_eq!(
+            bb.get_var("SRC_URI:append:class-native")
+                .unwrap()
+                .as_deref(),
+            Some(" file://native.patch")
+        );
+        assert_eq!(
+            bb.get_var("EXTRA_OECONF").unwrap().as_deref(),
+            Some("--b --a --c --disable-bar --enable-foo")
+        );
+        assert_eq!(bb.get_var("SRC_URI:append").unwrap(), None);
+        assert_eq!(
+            bb.artifacts().unwrap(),
+            &[
+                Artifact {
+                    src: "https://example.com/foo-1.0.tar.gz".to_string(),
+                    commit: None,
+                    sha256: Some(
+                        "0000000000000000000000000000000000000000000000000000000000000000"
+                            .to_string()
+                    ),
+                },
+                Artifact {
+                    src: "git+https://example.com/bar.git".to_string(),
+                    commit: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+                    sha256: None,
+                },
+            ]
+        );
+    }
 }